@@ -0,0 +1,236 @@
+//! `--anchors-index`: the anchors of each document, kept between runs so that the ids of documents
+//! that did not change are not collected again.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use anyhow::{anyhow, Context, Error};
+use serde_json::{json, Map, Value};
+
+use crate::html::is_html_path;
+
+/// Ids defined by a document, and the elements defining them.
+pub type Anchors = BTreeMap<String, Option<String>>;
+
+/// The size and modification time of a file, in nanoseconds since the Unix epoch.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Fingerprint {
+    size: u64,
+    modified: u64,
+}
+
+impl Fingerprint {
+    fn of(path: &Path) -> io::Result<Self> {
+        let metadata = fs::metadata(path)?;
+        let modified = metadata
+            .modified()?
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_nanos() as u64);
+        Ok(Fingerprint {
+            size: metadata.len(),
+            modified,
+        })
+    }
+}
+
+/// The contents of an `--anchors-index` file.
+#[derive(Debug, Default)]
+pub struct AnchorsIndex {
+    /// Path relative to the folder being checked, with forward slashes, to the fingerprint the
+    /// document had when its anchors were collected.
+    documents: BTreeMap<String, (Fingerprint, Anchors)>,
+}
+
+impl AnchorsIndex {
+    /// Read the index written by `write`. A missing file is an empty index, so that the first run
+    /// creates it. The index is also empty if it was written with different `ignored_ids`, which
+    /// change what counts as an anchor.
+    pub fn read(path: &Path, ignored_ids: &[String]) -> Result<Self, Error> {
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(error) => {
+                return Err(error)
+                    .with_context(|| format!("Failed to read anchors index {}", path.display()))
+            }
+        };
+        Self::parse(&contents, ignored_ids)
+            .with_context(|| format!("Failed to parse anchors index {}", path.display()))
+    }
+
+    fn parse(contents: &str, ignored_ids: &[String]) -> Result<Self, Error> {
+        let value: Value = serde_json::from_str(contents)?;
+        if value.get("ignore_id") != Some(&json!(ignored_ids)) {
+            return Ok(Self::default());
+        }
+
+        let mut documents = BTreeMap::new();
+        let entries = value
+            .get("documents")
+            .and_then(Value::as_object)
+            .ok_or_else(|| anyhow!("expected an object of documents"))?;
+        for (document, entry) in entries {
+            let number = |name: &str| {
+                entry
+                    .get(name)
+                    .and_then(Value::as_u64)
+                    .ok_or_else(|| anyhow!("{document} has no {name}"))
+            };
+            let fingerprint = Fingerprint {
+                size: number("size")?,
+                modified: number("modified")?,
+            };
+            let anchors = entry
+                .get("anchors")
+                .and_then(Value::as_object)
+                .ok_or_else(|| anyhow!("{document} has no anchors"))?
+                .iter()
+                .map(|(id, element)| (id.clone(), element.as_str().map(str::to_owned)))
+                .collect();
+            documents.insert(document.clone(), (fingerprint, anchors));
+        }
+
+        Ok(AnchorsIndex { documents })
+    }
+
+    /// The anchors of the document at `path`, which is `relative_path` below the folder being
+    /// checked, if it did not change since they were collected.
+    pub fn lookup(&self, relative_path: &str, path: &Path) -> Option<&Anchors> {
+        let (fingerprint, anchors) = self.documents.get(relative_path)?;
+        let current = Fingerprint::of(path).ok()?;
+        (current == *fingerprint).then_some(anchors)
+    }
+
+    /// Write the anchors of every document in `anchors`, as returned by
+    /// `BrokenLinkCollector::get_defined_anchors`. Documents without anchors are left out, they
+    /// cost nothing to parse again.
+    pub fn write(
+        path: &Path,
+        base_path: &Path,
+        ignored_ids: &[String],
+        anchors: &BTreeMap<Arc<PathBuf>, Anchors>,
+    ) -> Result<(), Error> {
+        let mut documents = Map::new();
+        for (document, anchors) in anchors {
+            let fingerprint = match Fingerprint::of(document) {
+                Ok(fingerprint) => fingerprint,
+                // deleted while checking, collected again next time
+                Err(_) => continue,
+            };
+            let relative_path = document.strip_prefix(base_path).unwrap_or(document);
+            documents.insert(
+                relative_path.to_string_lossy().replace('\\', "/"),
+                json!({
+                    "size": fingerprint.size,
+                    "modified": fingerprint.modified,
+                    "anchors": anchors,
+                }),
+            );
+        }
+
+        let index = json!({
+            "ignore_id": ignored_ids,
+            "documents": documents,
+        });
+        fs::write(path, index.to_string())
+            .with_context(|| format!("Failed to write anchors index {}", path.display()))
+    }
+}
+
+/// The href and anchors of every HTML document in `documents`, as returned by
+/// `BrokenLinkCollector::get_documents`, in the format of `hyperlink export-anchors`. Documents are
+/// keyed by their path relative to `base_path`, like in the index.
+pub fn export(base_path: &Path, documents: &BTreeMap<Arc<PathBuf>, (String, Anchors)>) -> Value {
+    let documents: Map<_, _> = documents
+        .iter()
+        .filter(|(document, _)| is_html_path(document))
+        .map(|(document, (href, anchors))| {
+            let relative_path = document.strip_prefix(base_path).unwrap_or(document);
+            (
+                relative_path.to_string_lossy().replace('\\', "/"),
+                json!({
+                    "href": format!("/{href}"),
+                    "anchors": anchors,
+                }),
+            )
+        })
+        .collect();
+
+    json!({ "documents": documents })
+}
+
+#[test]
+fn test_anchors_index() {
+    let dir = assert_fs::TempDir::new().unwrap();
+    let base_path = dir.path();
+    let index_path = base_path.join("anchors.json");
+    let page = Arc::new(base_path.join("page.html"));
+    fs::write(&*page, "<h2 id=intro>").unwrap();
+
+    let mut anchors = Anchors::new();
+    anchors.insert("intro".to_owned(), Some("h2".to_owned()));
+    anchors.insert("page-1".to_owned(), None);
+    let mut documents = BTreeMap::new();
+    documents.insert(page.clone(), anchors.clone());
+    documents.insert(Arc::new(base_path.join("deleted.html")), Anchors::new());
+    AnchorsIndex::write(&index_path, base_path, &[], &documents).unwrap();
+
+    let index = AnchorsIndex::read(&index_path, &[]).unwrap();
+    assert_eq!(index.lookup("page.html", &page), Some(&anchors));
+    assert_eq!(index.documents.len(), 1);
+
+    // a different --ignore-id invalidates the whole index
+    let index = AnchorsIndex::read(&index_path, &["^fn".to_owned()]).unwrap();
+    assert_eq!(index.lookup("page.html", &page), None);
+
+    fs::write(&*page, "<h2 id=introduction>").unwrap();
+    let index = AnchorsIndex::read(&index_path, &[]).unwrap();
+    assert_eq!(index.lookup("page.html", &page), None);
+
+    let missing = AnchorsIndex::read(&base_path.join("missing.json"), &[]).unwrap();
+    assert!(missing.documents.is_empty());
+    assert!(AnchorsIndex::parse("{\"ignore_id\": []}", &[]).is_err());
+}
+
+#[test]
+fn test_export() {
+    let base_path = Path::new("public");
+    let mut anchors = Anchors::new();
+    anchors.insert("install".to_owned(), Some("h2".to_owned()));
+    let mut documents = BTreeMap::new();
+    documents.insert(
+        Arc::new(base_path.join("docs/index.html")),
+        ("docs".to_owned(), anchors),
+    );
+    documents.insert(
+        Arc::new(base_path.join("about.html")),
+        ("about.html".to_owned(), Anchors::new()),
+    );
+    documents.insert(
+        Arc::new(base_path.join("LEGACY.HTM")),
+        ("LEGACY.HTM".to_owned(), Anchors::new()),
+    );
+    documents.insert(
+        Arc::new(base_path.join("about.html.bak")),
+        ("about.html.bak".to_owned(), Anchors::new()),
+    );
+    documents.insert(
+        Arc::new(base_path.join("logo.png")),
+        ("logo.png".to_owned(), Anchors::new()),
+    );
+
+    assert_eq!(
+        export(base_path, &documents),
+        json!({
+            "documents": {
+                "LEGACY.HTM": {"href": "/LEGACY.HTM", "anchors": {}},
+                "about.html": {"href": "/about.html", "anchors": {}},
+                "docs/index.html": {"href": "/docs", "anchors": {"install": "h2"}},
+            }
+        })
+    );
+}
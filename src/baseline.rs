@@ -0,0 +1,235 @@
+//! `--baseline`: accept known broken links, each with a reason and optionally until a date.
+
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+use std::time::SystemTime;
+
+use anyhow::{anyhow, Context, Error};
+use bumpalo::collections::String as BumpString;
+use bumpalo::Bump;
+use serde_json::Value;
+
+use crate::html::{push_and_canonicalize, FragmentEncoding};
+
+/// Entries expiring within this many days are listed in the report.
+pub const EXPIRY_WARNING_DAYS: i64 = 14;
+
+/// A calendar date, as days since 1970-01-01.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Date(i64);
+
+impl Date {
+    pub fn today() -> Self {
+        let secs = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_secs());
+        Date::from_unix_secs(secs)
+    }
+
+    /// The UTC date of a Unix timestamp.
+    pub fn from_unix_secs(secs: u64) -> Self {
+        Date((secs / 86400) as i64)
+    }
+
+    fn from_ymd(year: i64, month: i64, day: i64) -> Self {
+        // http://howardhinnant.github.io/date_algorithms.html#days_from_civil
+        let year = if month <= 2 { year - 1 } else { year };
+        let era = year.div_euclid(400);
+        let year_of_era = year - era * 400;
+        let day_of_year = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day - 1;
+        let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+        Date(era * 146097 + day_of_era - 719468)
+    }
+
+    fn to_ymd(self) -> (i64, i64, i64) {
+        // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+        let days = self.0 + 719468;
+        let era = days.div_euclid(146097);
+        let day_of_era = days - era * 146097;
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let mp = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+        (year, month, day)
+    }
+
+    /// Days from `self` until `other`, negative if `other` is earlier.
+    pub fn days_until(self, other: Date) -> i64 {
+        other.0 - self.0
+    }
+}
+
+impl FromStr for Date {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid date {s:?}, expected YYYY-MM-DD");
+        let mut parts = s.splitn(3, '-').map(|part| part.parse::<i64>());
+        let (year, month, day) = match (parts.next(), parts.next(), parts.next()) {
+            (Some(Ok(year)), Some(Ok(month)), Some(Ok(day))) => (year, month, day),
+            _ => return Err(invalid()),
+        };
+
+        let date = Date::from_ymd(year, month, day);
+        // rejects e.g. 2025-02-30, which would roll over into March
+        if date.to_ymd() != (year, month, day) {
+            return Err(invalid());
+        }
+        Ok(date)
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        let (year, month, day) = self.to_ymd();
+        write!(fmt, "{year:04}-{month:02}-{day:02}")
+    }
+}
+
+/// A broken link accepted by the baseline.
+#[derive(Debug, PartialEq, Eq)]
+pub struct BaselineEntry {
+    /// Canonicalized href, without leading slash, like `BrokenLink::link`.
+    pub href: String,
+    pub reason: String,
+    /// The last day the link is accepted. Afterwards it is reported again.
+    pub expires: Option<Date>,
+}
+
+/// The contents of a `--baseline` file.
+#[derive(Debug, Default)]
+pub struct Baseline {
+    pub entries: Vec<BaselineEntry>,
+}
+
+impl Baseline {
+    /// Read a JSON array of entries like
+    /// `{"href": "/old/", "reason": "removed in v2", "expires": "2026-12-31"}`. `reason` is
+    /// required, `expires` is optional. Fragments are normalized with `fragment_encoding`, like
+    /// the links they are compared with.
+    pub fn read(path: &Path, fragment_encoding: FragmentEncoding) -> Result<Self, Error> {
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read baseline file {}", path.display()))?;
+        Self::parse(&contents, fragment_encoding)
+            .with_context(|| format!("Failed to parse baseline file {}", path.display()))
+    }
+
+    fn parse(contents: &str, fragment_encoding: FragmentEncoding) -> Result<Self, Error> {
+        let arena = Bump::new();
+        let values: Vec<Value> = serde_json::from_str(contents)?;
+        let mut entries = Vec::new();
+
+        for (i, value) in values.iter().enumerate() {
+            let field = |name: &str| value.get(name).and_then(Value::as_str);
+            let raw_href = field("href").ok_or_else(|| anyhow!("entry {i} has no href"))?;
+            let reason = field("reason").ok_or_else(|| anyhow!("entry {i} has no reason"))?;
+            let expires = field("expires")
+                .map(|expires| {
+                    expires
+                        .parse()
+                        .map_err(|e: String| anyhow!("entry {i}: {e}"))
+                })
+                .transpose()?;
+
+            let (page, fragment) = match raw_href.split_once('#') {
+                Some((page, fragment)) => (page, Some(fragment)),
+                None => (raw_href, None),
+            };
+            let mut href = BumpString::new_in(&arena);
+            push_and_canonicalize(&mut href, &format!("/{}", page.trim_start_matches('/')));
+            if let Some(fragment) = fragment {
+                href.push('#');
+                href.push_str(&fragment_encoding.normalize(fragment));
+            }
+            entries.push(BaselineEntry {
+                href: href.as_str().to_owned(),
+                reason: reason.to_owned(),
+                expires,
+            });
+        }
+
+        Ok(Baseline { entries })
+    }
+
+    /// Whether a broken link to `href` is accepted on `today`.
+    pub fn accepts(&self, href: &str, today: Date) -> bool {
+        self.entries
+            .iter()
+            .any(|entry| entry.href == href && entry.expires.is_none_or(|expires| today <= expires))
+    }
+
+    /// Entries that expired before `today`, and so no longer accept their link.
+    pub fn expired(&self, today: Date) -> impl Iterator<Item = &BaselineEntry> {
+        self.entries
+            .iter()
+            .filter(move |entry| entry.expires.is_some_and(|expires| expires < today))
+    }
+
+    /// Entries that still accept their link, but expire within `EXPIRY_WARNING_DAYS`.
+    pub fn expiring(&self, today: Date) -> impl Iterator<Item = &BaselineEntry> {
+        self.entries.iter().filter(move |entry| {
+            entry.expires.is_some_and(|expires| {
+                (0..=EXPIRY_WARNING_DAYS).contains(&today.days_until(expires))
+            })
+        })
+    }
+}
+
+#[test]
+fn test_dates() {
+    for date in ["1970-01-01", "2000-02-29", "2026-10-16", "2399-12-31"] {
+        assert_eq!(date.parse::<Date>().unwrap().to_string(), date);
+    }
+    assert_eq!("1970-01-02".parse::<Date>().unwrap(), Date(1));
+    assert!("2025-02-29".parse::<Date>().is_err());
+    assert!("2025-13-01".parse::<Date>().is_err());
+    assert!("next week".parse::<Date>().is_err());
+}
+
+#[test]
+fn test_baseline() {
+    let baseline = Baseline::parse(
+        r#"[
+            {"href": "/old/", "reason": "removed in v2"},
+            {"href": "docs/../blog/", "reason": "migration", "expires": "2026-10-20"},
+            {"href": "/gone", "reason": "gone", "expires": "2026-10-01"},
+            {"href": "/index.html#top", "reason": "generated by a script"},
+            {"href": "/caf%C3%A9/#men%C3%BC", "reason": "renamed"}
+        ]"#,
+        FragmentEncoding::Decoded,
+    )
+    .unwrap();
+    let today: Date = "2026-10-16".parse().unwrap();
+
+    assert!(baseline.accepts("old", today));
+    assert!(baseline.accepts("blog", today));
+    assert!(!baseline.accepts("gone", today));
+    assert!(!baseline.accepts("new", today));
+    assert!(baseline.accepts("#top", today));
+    assert!(baseline.accepts("caf%C3%A9#menü", today));
+    let hrefs = |entries: Vec<&BaselineEntry>| -> Vec<String> {
+        entries.iter().map(|entry| entry.href.clone()).collect()
+    };
+    assert_eq!(hrefs(baseline.expired(today).collect()), ["gone"]);
+    assert_eq!(hrefs(baseline.expiring(today).collect()), ["blog"]);
+
+    let raw = Baseline::parse(
+        r#"[{"href": "/#men%C3%BC", "reason": "renamed"}]"#,
+        FragmentEncoding::Raw,
+    )
+    .unwrap();
+    assert!(raw.accepts("#men%C3%BC", today));
+    assert!(!raw.accepts("#menü", today));
+
+    assert!(Baseline::parse(r#"[{"href": "/old/"}]"#, FragmentEncoding::Decoded).is_err());
+    assert!(Baseline::parse(
+        r#"[{"href": "/old/", "reason": "", "expires": "soon"}]"#,
+        FragmentEncoding::Decoded
+    )
+    .is_err());
+}
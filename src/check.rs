@@ -0,0 +1,914 @@
+//! The link check of `hyperlink BASE-PATH`: parse the site, find its broken links and the source
+//! files they come from, and stream the problems into report sinks.
+//!
+//! ```no_run
+//! use std::path::Path;
+//!
+//! use hyperlink::check::{check, CheckOptions};
+//! use hyperlink::github::Level;
+//! use hyperlink::html::{LinkCallback, LinkDecision};
+//!
+//! // the API is served by another service, and the changelog is allowed to rot
+//! let hooks: Vec<Box<LinkCallback>> = vec![Box::new(|link| {
+//!     if link.href.0.starts_with("api/") {
+//!         LinkDecision::Skip
+//!     } else if link.path.ends_with("changelog.html") {
+//!         LinkDecision::Reclassify(Level::Warning)
+//!     } else {
+//!         LinkDecision::Check
+//!     }
+//! })];
+//! let result = check(Path::new("public/"), CheckOptions::default(), hooks, &mut [])?;
+//! println!("{} bad links", result.bad_links_count);
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+use std::fs;
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use anyhow::{Context, Error};
+use rayon::prelude::*;
+
+use crate::anchors::AnchorsIndex;
+use crate::baseline::{Baseline, Date};
+use crate::collector::{
+    AnchorDefinition, BrokenLink, BrokenLinkCollector, LinkCollector, LocalLinksOnly, PageError,
+    UsedLinkCollector,
+};
+use crate::deploy::DeployIgnore;
+use crate::downloads::Downloads;
+use crate::html::{
+    Document, DocumentBuffers, HookOutcome, Href, LinkCallback, LinkOrigin, LinkText, ParseOptions,
+    Placeholder, SkipReason,
+};
+use crate::lints::Lint;
+use crate::paragraph::{NoopParagraphWalker, Paragraph, ParagraphHasher, VoidParagraph};
+use crate::presets::Preset;
+use crate::redirects::Redirects;
+use crate::report::{page_error_level, BadLink, ReportSink};
+use crate::shards::Shard;
+use crate::sisters::SisterSites;
+use crate::sources::{SourceMapping, SourcePrefix};
+use crate::targets::ExtraTargets;
+use crate::timings::Timings;
+use crate::walk::{
+    extract_html_links, extract_markdown_paragraphs, relative_path, run_in, stage_pools, HtmlResult,
+};
+
+/// Line in the source file, href, and details of a broken link, as collected for reporting.
+pub type BadHref = (Option<usize>, String, LinkOrigin, Option<Arc<LinkText>>);
+/// Bad links, bad anchors and other problems of a file.
+pub type FileProblems = (BTreeSet<BadHref>, BTreeSet<BadHref>, Vec<PageError>);
+/// What the documents of a site are collected into for checking.
+pub type SiteCollector = LocalLinksOnly<BrokenLinkCollector<VoidParagraph>>;
+
+/// What to check besides what `parse` extracts from documents, and where to look for the sources
+/// of broken links.
+///
+/// `redirects`, `downloads` and `sister_sites` only know what they are told by their hooks, so
+/// these need to be in `parse.link_hooks`.
+#[derive(Default)]
+pub struct CheckOptions {
+    pub parse: ParseOptions,
+    /// Folders with the Markdown sources of the site, from `--sources`.
+    pub sources_paths: Vec<PathBuf>,
+    /// From `--map-sources`.
+    pub map_sources: Vec<SourcePrefix>,
+    /// From `--build-manifest`.
+    pub build_manifest: Option<PathBuf>,
+    /// Only report broken links coming from these files, from `--git-range`. Canonical paths.
+    pub changed_files: Option<BTreeSet<PathBuf>>,
+    pub preset: Option<Preset>,
+    /// Replay the links of byte-identical documents instead of parsing them again, see
+    /// `ParseOptions::can_replay_links`.
+    pub dedup_documents: bool,
+    /// Only parse the files of this shard.
+    pub shard: Option<Shard>,
+    pub deploy_ignore: Option<DeployIgnore>,
+    pub anchors_index: Option<AnchorsIndex>,
+    pub baseline: Option<Arc<Baseline>>,
+    pub extra_targets: ExtraTargets,
+    /// Report unused and shadowed rules in `_redirects`, and anchors on redirected pages.
+    pub check_redirects: bool,
+    pub redirects: Option<Arc<Redirects>>,
+    pub downloads: Option<Arc<Downloads>>,
+    pub sister_sites: Option<Arc<SisterSites>>,
+    /// Require every page to be its own canonical page, from `--self-canonical`.
+    pub self_canonical: bool,
+    /// Count broken links by top-level directory in `Check::bad_links_by_directory`.
+    pub summary_by_directory: bool,
+}
+
+/// What `check` found.
+pub struct Check {
+    /// The parsed site, e.g. for `Summary::site`.
+    pub site: HtmlResult<SiteCollector>,
+    /// Problems by file, in the order they are reported. Keyed by whether the file is an HTML file
+    /// rather than a source file, to report source files first.
+    pub files: BTreeMap<(bool, Arc<PathBuf>), FileProblems>,
+    pub used_links_count: usize,
+    pub skipped_links: BTreeMap<SkipReason, usize>,
+    /// Whether there were broken links to look up in the sources.
+    pub read_sources: bool,
+    pub bad_links_count: usize,
+    pub bad_anchors_count: usize,
+    pub bad_links_by_directory: BTreeMap<String, usize>,
+    pub warned_links_count: usize,
+    pub placeholder_links_count: usize,
+    pub soft_404_links_count: usize,
+    pub accepted_links_count: usize,
+    pub bad_hreflang_count: usize,
+    pub bad_canonical_count: usize,
+    pub bad_redirects_count: usize,
+    pub redirected_anchors_count: usize,
+    pub bad_integrity_count: usize,
+    pub bad_downloads_count: usize,
+    pub bad_sister_links_count: usize,
+    pub lint_warnings_count: usize,
+    pub lint_counts: BTreeMap<Lint, usize>,
+    pub unparseable_count: usize,
+    /// Broken hrefs to files that exist, but are not deployed, without anchor.
+    pub undeployed_hrefs: BTreeSet<String>,
+    /// Broken hrefs to copies of the 404 page, without anchor.
+    pub soft_404s: BTreeMap<String, Placeholder>,
+    /// Broken hrefs to anchors, and the id the `--preset` generator gives the heading they spell
+    /// out.
+    pub heading_anchors: BTreeMap<String, String>,
+    /// Pages that define the anchors of broken hrefs, by anchor.
+    pub anchor_definitions: BTreeMap<String, Vec<AnchorDefinition>>,
+    pub timings: Timings,
+}
+
+/// Problems by file, in the order they are reported. Assembled per worker and then merged.
+#[derive(Default)]
+struct Report<'a> {
+    /// Keyed by whether the file is an HTML file rather than a source file, to report source
+    /// files first.
+    files: BTreeMap<(bool, Arc<PathBuf>), FileProblems>,
+    bad_links_count: usize,
+    bad_anchors_count: usize,
+    bad_links_by_directory: BTreeMap<String, usize>,
+    /// Broken links for the report sinks, if any.
+    bad_links: Vec<BadLink<'a>>,
+}
+
+impl<'a> Report<'a> {
+    fn merge(mut self, mut other: Report<'a>) -> Report<'a> {
+        if self.files.len() < other.files.len() {
+            mem::swap(&mut self, &mut other);
+        }
+
+        for (file, (bad_links, bad_anchors, page_errors)) in other.files {
+            let (all_bad_links, all_bad_anchors, all_page_errors) =
+                self.files.entry(file).or_default();
+            all_bad_links.extend(bad_links);
+            all_bad_anchors.extend(bad_anchors);
+            all_page_errors.extend(page_errors);
+        }
+        self.bad_links_count += other.bad_links_count;
+        self.bad_anchors_count += other.bad_anchors_count;
+        for (directory, count) in other.bad_links_by_directory {
+            *self.bad_links_by_directory.entry(directory).or_insert(0) += count;
+        }
+        self.bad_links.extend(other.bad_links);
+
+        self
+    }
+}
+
+/// Check the site in `base_path`.
+///
+/// `hooks` see every used link after those in `options.parse`, see `LinkCallback`. Broken links
+/// and then all other problems are streamed into `sinks`, but their summary and `finish` are left
+/// to the caller.
+pub fn check(
+    base_path: &Path,
+    mut options: CheckOptions,
+    hooks: Vec<Box<LinkCallback>>,
+    sinks: &mut [Box<dyn ReportSink>],
+) -> Result<Check, Error> {
+    for hook in hooks {
+        options
+            .parse
+            .link_callbacks
+            .push(Arc::new(Mutex::new(hook)));
+    }
+    let timings = Timings::default();
+    let site = collect(base_path, &options)?;
+    check_collected(base_path, site, options, timings, sinks)
+}
+
+/// Parse the site in `base_path`, e.g. to check it in shards and `check_collected` the merged
+/// result.
+pub fn collect(
+    base_path: &Path,
+    options: &CheckOptions,
+) -> Result<HtmlResult<SiteCollector>, Error> {
+    run_in(stage_pools().parse.as_ref(), || {
+        extract_html_links::<SiteCollector, NoopParagraphWalker>(
+            base_path,
+            &options.parse,
+            options.preset,
+            options.dedup_documents && options.parse.can_replay_links(),
+            options.deploy_ignore.as_ref(),
+            options.anchors_index.as_ref(),
+            options.shard,
+        )
+    })
+}
+
+/// Check a site that was already parsed, like `check`. `timings` has the phases so far.
+pub fn check_collected(
+    base_path: &Path,
+    mut site: HtmlResult<SiteCollector>,
+    options: CheckOptions,
+    mut timings: Timings,
+    sinks: &mut [Box<dyn ReportSink>],
+) -> Result<Check, Error> {
+    timings.finish_phase("reading files", Some(site.peak_arena_bytes));
+
+    let CheckOptions {
+        parse: parse_options,
+        sources_paths,
+        map_sources,
+        build_manifest,
+        changed_files,
+        preset,
+        deploy_ignore,
+        baseline,
+        extra_targets,
+        check_redirects,
+        redirects,
+        downloads,
+        sister_sites,
+        self_canonical,
+        summary_by_directory,
+        ..
+    } = options;
+    let check_anchors = parse_options.check_anchors;
+    let has_sink = !sinks.is_empty();
+    let today = Date::today();
+
+    for link in extra_targets.defined_links() {
+        site.collector.ingest(link);
+    }
+    let used_links_count = site.collector.collector.used_links_count();
+    let skipped_links = site.collector.collector.get_skipped_links().clone();
+
+    let broken_links: Vec<_> = site
+        .collector
+        .collector
+        .get_broken_links(check_anchors)
+        .collect();
+    // links to placeholders are not broken, they are warned about next to the lint warnings. Soft
+    // 404s are.
+    let (placeholder_links, broken_links): (Vec<_>, Vec<_>) =
+        broken_links.into_iter().partition(|broken_link| {
+            broken_link
+                .placeholder
+                .as_ref()
+                .is_some_and(|placeholder| !placeholder.is_soft_404())
+        });
+    let placeholder_links_count: usize = placeholder_links
+        .iter()
+        .map(|broken_link| broken_link.count)
+        .sum();
+    // reported next to the lint warnings instead, without looking for their sources
+    let (warned_links, broken_links): (Vec<_>, Vec<_>) = broken_links
+        .into_iter()
+        .partition(|broken_link| broken_link.warn_only);
+    let warned_links_count: usize = warned_links
+        .iter()
+        .map(|broken_link| broken_link.count)
+        .sum();
+    let (accepted_links, broken_links): (Vec<_>, Vec<_>) =
+        broken_links.into_iter().partition(|broken_link| {
+            baseline
+                .as_ref()
+                .is_some_and(|baseline| baseline.accepts(&broken_link.link.href, today))
+        });
+    let accepted_links_count: usize = accepted_links
+        .iter()
+        .map(|broken_link| broken_link.count)
+        .sum();
+    let undeployed_hrefs: BTreeSet<String> = match deploy_ignore {
+        Some(ref deploy_ignore) => broken_links
+            .iter()
+            .filter(|broken_link| broken_link.hard_404)
+            .map(|broken_link| Href(&broken_link.link.href).without_anchor().0.to_owned())
+            .filter(|href| is_undeployed_file(base_path, deploy_ignore, href))
+            .collect(),
+        None => BTreeSet::new(),
+    };
+    let soft_404s: BTreeMap<String, Placeholder> = broken_links
+        .iter()
+        .filter_map(|broken_link| {
+            let placeholder = broken_link.placeholder.clone()?;
+            let href = Href(&broken_link.link.href).without_anchor().0.to_owned();
+            Some((href, placeholder))
+        })
+        .collect();
+    let soft_404_links_count: usize = broken_links
+        .iter()
+        .filter(|broken_link| broken_link.placeholder.is_some())
+        .map(|broken_link| broken_link.count)
+        .sum();
+    // broken anchors that spell out a heading the way the --preset generator names it
+    let heading_anchors: BTreeMap<String, String> = match preset {
+        Some(preset) if check_anchors => broken_links
+            .iter()
+            .filter(|broken_link| !broken_link.hard_404)
+            .filter_map(|broken_link| {
+                let (page, anchor) = broken_link.link.href.split_once('#')?;
+                let id = preset.heading_anchor(anchor)?;
+                site.collector
+                    .collector
+                    .is_defined(&format!("{page}#{id}"))
+                    .then(|| (broken_link.link.href.clone(), id))
+            })
+            .collect(),
+        _ => BTreeMap::new(),
+    };
+    let anchor_definitions = if check_anchors && !broken_links.is_empty() {
+        site.collector.collector.get_anchor_definitions()
+    } else {
+        BTreeMap::new()
+    };
+    timings.finish_phase("checking links", None);
+
+    let read_sources =
+        !broken_links.is_empty() && (!sources_paths.is_empty() || build_manifest.is_some());
+    let (broken_link_paragraphs, paragraps_to_sourcefile, source_mapping) = if read_sources {
+        (
+            if sources_paths.is_empty() {
+                vec![Vec::new(); broken_links.len()]
+            } else {
+                let hooked_links = site.collector.collector.get_hooked_links();
+                run_in(stage_pools().sources.as_ref(), || {
+                    hash_broken_link_paragraphs(
+                        base_path,
+                        &parse_options,
+                        &hooked_links,
+                        &broken_links,
+                    )
+                })?
+            },
+            run_in(stage_pools().sources.as_ref(), || {
+                extract_markdown_paragraphs::<ParagraphHasher>(
+                    &sources_paths,
+                    preset,
+                    &parse_options.paragraphs,
+                )
+            })?,
+            Some(SourceMapping::new(
+                base_path,
+                &sources_paths,
+                map_sources,
+                build_manifest.as_deref(),
+            )?),
+        )
+    } else {
+        (vec![Vec::new(); broken_links.len()], BTreeMap::new(), None)
+    };
+    timings.finish_phase("reading sources", None);
+
+    // assembled in parallel, as a broken template can produce millions of broken links
+    let report = broken_links
+        .into_par_iter()
+        .zip(broken_link_paragraphs)
+        .fold(Report::default, |mut report, (broken_link, paragraphs)| {
+            let mut had_sources = false;
+            // source files and lines for the sink of --format
+            let mut sources = Vec::new();
+
+            if let Some(ref changed_files) = changed_files {
+                let is_changed = |path: &Path| {
+                    fs::canonicalize(path).is_ok_and(|path| changed_files.contains(&path))
+                };
+
+                let in_changed_paragraph = paragraphs
+                    .iter()
+                    .filter_map(|paragraph| paragraps_to_sourcefile.get(paragraph))
+                    .flatten()
+                    .filter(|(source, _)| {
+                        source_mapping.as_ref().is_none_or(|mapping| {
+                            mapping.may_produce(&source.path, &broken_link.link.path)
+                        })
+                    })
+                    .any(|(source, _)| is_changed(&source.path));
+                let in_changed_file = source_mapping
+                    .as_ref()
+                    .and_then(|mapping| mapping.exact_source(&broken_link.link.path))
+                    .is_some_and(is_changed);
+
+                if !in_changed_paragraph && !in_changed_file {
+                    return report;
+                }
+            }
+
+            if broken_link.hard_404 {
+                report.bad_links_count += broken_link.count;
+            } else {
+                report.bad_anchors_count += broken_link.count;
+            }
+
+            if summary_by_directory {
+                *report
+                    .bad_links_by_directory
+                    .entry(top_level_directory(base_path, &broken_link.link.path))
+                    .or_insert(0) += broken_link.count;
+            }
+
+            for paragraph in &paragraphs {
+                if let Some(document_sources) = &paragraps_to_sourcefile.get(paragraph) {
+                    debug_assert!(!document_sources.is_empty());
+
+                    for (source, lineno) in document_sources.iter().filter(|(source, _)| {
+                        source_mapping.as_ref().is_none_or(|mapping| {
+                            mapping.may_produce(&source.path, &broken_link.link.path)
+                        })
+                    }) {
+                        had_sources = true;
+                        if has_sink {
+                            sources.push((source.path.as_path(), Some(*lineno)));
+                        }
+
+                        let (bad_links, bad_anchors, _) = report
+                            .files
+                            .entry((!had_sources, source.path.clone()))
+                            .or_default();
+
+                        if broken_link.hard_404 {
+                            bad_links
+                        } else {
+                            bad_anchors
+                        }
+                        .insert((
+                            Some(*lineno),
+                            broken_link.link.href.clone(),
+                            broken_link.link.origin,
+                            broken_link.link.text.clone(),
+                        ));
+                    }
+                }
+            }
+
+            if !had_sources {
+                // the build manifest knows the file, but not the line
+                if let Some(exact_source) = source_mapping
+                    .as_ref()
+                    .and_then(|mapping| mapping.exact_source(&broken_link.link.path))
+                {
+                    had_sources = true;
+                    if has_sink {
+                        sources.push((exact_source, None));
+                    }
+
+                    let (bad_links, bad_anchors, _) = report
+                        .files
+                        .entry((!had_sources, Arc::new(exact_source.to_owned())))
+                        .or_default();
+
+                    if broken_link.hard_404 {
+                        bad_links
+                    } else {
+                        bad_anchors
+                    }
+                    .insert((
+                        None,
+                        broken_link.link.href.clone(),
+                        broken_link.link.origin,
+                        broken_link.link.text.clone(),
+                    ));
+                }
+            }
+
+            if has_sink {
+                let href = Href(&broken_link.link.href);
+                let page = href.without_anchor().0;
+                report.bad_links.push(BadLink {
+                    href: broken_link.link.href.clone(),
+                    file: broken_link.link.path.clone(),
+                    target: base_path.join(page),
+                    origin: broken_link.link.origin,
+                    count: broken_link.count,
+                    hard_404: broken_link.hard_404,
+                    soft_404: broken_link.placeholder.clone(),
+                    sources,
+                });
+            }
+
+            if !had_sources {
+                let (bad_links, bad_anchors, _) = report
+                    .files
+                    .entry((!had_sources, broken_link.link.path))
+                    .or_default();
+
+                if broken_link.hard_404 {
+                    bad_links
+                } else {
+                    bad_anchors
+                }
+                .insert((
+                    None,
+                    broken_link.link.href,
+                    broken_link.link.origin,
+                    broken_link.link.text,
+                ));
+            }
+
+            report
+        })
+        .reduce(Report::default, Report::merge);
+    let Report {
+        files: mut bad_links_and_anchors,
+        bad_links_count,
+        bad_anchors_count,
+        bad_links_by_directory,
+        mut bad_links,
+    } = report;
+    bad_links.sort_by(|a, b| (&a.file, &a.href).cmp(&(&b.file, &b.href)));
+    for sink in sinks.iter_mut() {
+        for bad_link in &bad_links {
+            sink.on_broken_link(bad_link)?;
+        }
+    }
+
+    let hreflang_errors = if parse_options.check_hreflang {
+        site.collector.collector.get_hreflang_errors()
+    } else {
+        Vec::new()
+    };
+    let canonical_errors = if parse_options.check_canonical {
+        site.collector
+            .collector
+            .get_canonical_errors(self_canonical)
+    } else {
+        Vec::new()
+    };
+    let mut redirect_errors = Vec::new();
+    if let Some(ref redirects) = redirects {
+        let path = Arc::new(redirects.path.clone());
+        for rule in run_in(stage_pools().stat.as_ref(), || {
+            redirects.shadowed(base_path)
+        }) {
+            redirect_errors.push((
+                path.clone(),
+                PageError::ShadowedRedirect {
+                    href: rule.source.clone(),
+                    lineno: rule.lineno,
+                },
+            ));
+        }
+        for rule in redirects.unused() {
+            redirect_errors.push((
+                path.clone(),
+                PageError::UnusedRedirect {
+                    href: rule.source.clone(),
+                    lineno: rule.lineno,
+                },
+            ));
+        }
+    }
+
+    let bad_hreflang_count = hreflang_errors.len();
+    let bad_canonical_count = canonical_errors.len();
+    let integrity_errors = if parse_options.check_integrity {
+        site.collector.collector.get_integrity_errors(base_path)
+    } else {
+        Vec::new()
+    };
+    let download_errors = match downloads {
+        Some(ref downloads) => downloads.errors(base_path)?,
+        None => Vec::new(),
+    };
+    let sister_errors = match sister_sites {
+        Some(ref sister_sites) => sister_sites.errors(),
+        None => Vec::new(),
+    };
+    let lint_warnings = site.collector.collector.get_lint_warnings();
+    let mut lint_counts = BTreeMap::new();
+    for (_, error) in &lint_warnings {
+        if let PageError::Lint { lint, .. } = error {
+            *lint_counts.entry(*lint).or_insert(0) += 1;
+        }
+    }
+    let unparseable_errors = site.collector.collector.get_unparseable_errors();
+    let redirected_anchors = if check_redirects && check_anchors {
+        site.collector.collector.get_redirected_anchors()
+    } else {
+        Vec::new()
+    };
+    let redirected_anchors_count = redirected_anchors.len();
+
+    let bad_redirects_count = redirect_errors.len();
+    let bad_integrity_count = integrity_errors.len();
+    let bad_downloads_count = download_errors.len();
+    let bad_sister_links_count = sister_errors.len();
+    let lint_warnings_count = lint_warnings.len();
+    let unparseable_count = unparseable_errors.len();
+
+    for (filepath, error) in hreflang_errors
+        .into_iter()
+        .chain(canonical_errors)
+        .chain(redirect_errors)
+        .chain(integrity_errors)
+        .chain(download_errors)
+        .chain(sister_errors)
+        .chain(lint_warnings)
+        .chain(unparseable_errors)
+        .chain(redirected_anchors)
+        .chain(warned_links.into_iter().map(|broken_link| {
+            (
+                broken_link.link.path,
+                PageError::WarnedLink {
+                    href: broken_link.link.href,
+                    origin: broken_link.link.origin,
+                },
+            )
+        }))
+        .chain(placeholder_links.into_iter().filter_map(|broken_link| {
+            Some((
+                broken_link.link.path,
+                PageError::PlaceholderLink {
+                    href: broken_link.link.href,
+                    origin: broken_link.link.origin,
+                    placeholder: broken_link.placeholder?,
+                },
+            ))
+        }))
+    {
+        let (_, _, page_errors) = bad_links_and_anchors
+            .entry((true, filepath))
+            .or_insert_with(Default::default);
+        page_errors.push(error);
+    }
+
+    // _is_raw_file only controls the order of the keys, see `Check::files`
+    for ((_is_raw_file, filepath), (_, _, page_errors)) in &bad_links_and_anchors {
+        for error in page_errors {
+            for sink in sinks.iter_mut() {
+                sink.on_page_error(filepath, page_error_level(error), error)?;
+            }
+        }
+    }
+
+    Ok(Check {
+        site,
+        files: bad_links_and_anchors,
+        used_links_count,
+        skipped_links,
+        read_sources,
+        bad_links_count,
+        bad_anchors_count,
+        bad_links_by_directory,
+        warned_links_count,
+        placeholder_links_count,
+        soft_404_links_count,
+        accepted_links_count,
+        bad_hreflang_count,
+        bad_canonical_count,
+        bad_redirects_count,
+        redirected_anchors_count,
+        bad_integrity_count,
+        bad_downloads_count,
+        bad_sister_links_count,
+        lint_warnings_count,
+        lint_counts,
+        unparseable_count,
+        undeployed_hrefs,
+        soft_404s,
+        heading_anchors,
+        anchor_definitions,
+        timings,
+    })
+}
+
+/// The first path component of `path` below `base_path`, e.g. `/docs`, or `/` for files at the
+/// root of the site.
+fn top_level_directory(base_path: &Path, path: &Path) -> String {
+    let path = path.strip_prefix(base_path).unwrap_or(path);
+    let mut components = path.components();
+    match (components.next(), components.next()) {
+        (Some(first), Some(_)) => format!("/{}", first.as_os_str().to_string_lossy()),
+        _ => "/".to_owned(),
+    }
+}
+
+/// Whether `href` points at a file in `base_path` that is not deployed. The file is looked up the
+/// way a web server would, including `index.html` for directories.
+fn is_undeployed_file(base_path: &Path, deploy_ignore: &DeployIgnore, href: &str) -> bool {
+    let path = base_path.join(href);
+    [path.join("index.html"), path]
+        .iter()
+        .find(|path| path.is_file())
+        .is_some_and(|path| deploy_ignore.is_excluded(&relative_path(base_path, path)))
+}
+
+/// Hash the paragraphs that `broken_links` appear in, for matching them up with `--sources`.
+///
+/// Paragraphs are not hashed while checking links, so that runs without broken links do not pay
+/// for it. Instead, only the documents containing broken links are parsed again. A broken link
+/// that is used several times in a document gets the paragraph of each use.
+///
+/// The hooks are not run again, so that callbacks see every link once. What they did in the first
+/// parse is replayed from `hooked_links` instead.
+fn hash_broken_link_paragraphs<P: Send + Sync>(
+    base_path: &Path,
+    options: &ParseOptions,
+    hooked_links: &HashMap<Arc<PathBuf>, Vec<(u32, HookOutcome)>>,
+    broken_links: &[BrokenLink<P>],
+) -> Result<Vec<Vec<Paragraph>>, Error> {
+    let options = ParseOptions {
+        link_hooks: Vec::new(),
+        link_callbacks: Vec::new(),
+        ..options.clone()
+    };
+    let mut documents = BTreeMap::<_, Vec<usize>>::new();
+    for (i, broken_link) in broken_links.iter().enumerate() {
+        documents.entry(&broken_link.link.path).or_default().push(i);
+    }
+
+    let results: Vec<Result<Vec<_>, Error>> = documents
+        .into_par_iter()
+        .map(|(path, indices)| {
+            let document = Document::new(base_path, path)
+                .with_hooked_links(hooked_links.get(path).cloned().unwrap_or_default());
+            let mut collector = LocalLinksOnly::<UsedLinkCollector<Paragraph>>::new();
+            document
+                .extract_links::<ParagraphHasher, _>(
+                    &mut DocumentBuffers::default(),
+                    &options,
+                    |link| collector.ingest(link),
+                )
+                .with_context(|| format!("Failed to read file {}", document.path.display()))?;
+
+            // the same broken link may appear in several places, hand them out in order
+            let mut uses = BTreeMap::<_, VecDeque<_>>::new();
+            for used_link in &collector.collector.used_links {
+                uses.entry((
+                    used_link.href.as_str(),
+                    used_link.origin,
+                    used_link.text.as_deref(),
+                ))
+                .or_default()
+                .push_back(used_link.paragraph);
+            }
+            Ok(indices
+                .into_iter()
+                .map(|i| {
+                    let BrokenLink { link, count, .. } = &broken_links[i];
+                    let paragraphs = match uses.get_mut(&(
+                        link.href.as_str(),
+                        link.origin,
+                        link.text.as_deref(),
+                    )) {
+                        Some(queue) => {
+                            let taken = (*count).min(queue.len());
+                            queue.drain(..taken).flatten().collect()
+                        }
+                        None => Vec::new(),
+                    };
+                    (i, paragraphs)
+                })
+                .collect())
+        })
+        .collect();
+
+    let mut paragraphs = vec![Vec::new(); broken_links.len()];
+    for result in results {
+        for (i, paragraph) in result? {
+            paragraphs[i] = paragraph;
+        }
+    }
+
+    Ok(paragraphs)
+}
+
+#[test]
+fn test_check_hooks_and_sinks() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use crate::github::Level;
+    use crate::html::LinkDecision;
+
+    struct HrefSink(Arc<Mutex<Vec<String>>>);
+
+    impl ReportSink for HrefSink {
+        fn on_broken_link(&mut self, link: &BadLink<'_>) -> Result<(), Error> {
+            self.0.lock().unwrap().push(link.href.clone());
+            Ok(())
+        }
+    }
+
+    let dir = assert_fs::TempDir::new().unwrap();
+    fs::write(
+        dir.path().join("index.html"),
+        "<a href='/api/users'>API</a><a href='/missing.html'>Missing</a>",
+    )
+    .unwrap();
+    fs::write(
+        dir.path().join("changelog.html"),
+        "<a href='/old.html'>Old</a><a href='/'>Home</a>",
+    )
+    .unwrap();
+
+    let seen = Arc::new(AtomicUsize::new(0));
+    let hook_seen = seen.clone();
+    let mut count = 0;
+    let hooks: Vec<Box<LinkCallback>> = vec![Box::new(move |link| {
+        count += 1;
+        hook_seen.store(count, Ordering::Relaxed);
+        if link.href.0.starts_with("api/") {
+            LinkDecision::Skip
+        } else if link.path.ends_with("changelog.html") {
+            LinkDecision::Reclassify(Level::Warning)
+        } else {
+            LinkDecision::Check
+        }
+    })];
+    let hrefs = Arc::new(Mutex::new(Vec::new()));
+    let mut sinks: Vec<Box<dyn ReportSink>> = vec![Box::new(HrefSink(hrefs.clone()))];
+
+    let result = check(dir.path(), CheckOptions::default(), hooks, &mut sinks).unwrap();
+
+    assert_eq!(seen.load(Ordering::Relaxed), 4);
+    assert_eq!(result.bad_links_count, 1);
+    assert_eq!(result.warned_links_count, 1);
+    assert_eq!(result.skipped_links.get(&SkipReason::Ignored), Some(&1));
+    assert_eq!(*hrefs.lock().unwrap(), ["missing.html"]);
+
+    dir.close().unwrap();
+}
+
+#[test]
+fn test_check_hooks_with_sources() {
+    use crate::html::LinkDecision;
+
+    /// The href, count and source lines of each broken link.
+    type BrokenLinks = Vec<(String, usize, Vec<Option<usize>>)>;
+
+    struct SourcesSink(Arc<Mutex<BrokenLinks>>);
+
+    impl ReportSink for SourcesSink {
+        fn on_broken_link(&mut self, link: &BadLink<'_>) -> Result<(), Error> {
+            let lines = link.sources.iter().map(|(_, line)| *line).collect();
+            self.0
+                .lock()
+                .unwrap()
+                .push((link.href.clone(), link.count, lines));
+            Ok(())
+        }
+    }
+
+    let dir = assert_fs::TempDir::new().unwrap();
+    fs::create_dir(dir.path().join("public")).unwrap();
+    fs::write(
+        dir.path().join("public/index.html"),
+        "<p>Install the <a href='/v2/missing.html'>tool</a></p>\
+         <p>Skip <a href='/missing.html'>this</a></p>\
+         <p>Run the <a href='/missing.html'>tool</a></p>",
+    )
+    .unwrap();
+    fs::create_dir(dir.path().join("src")).unwrap();
+    fs::write(
+        dir.path().join("src/index.md"),
+        "Install the [tool](/missing.html)\n\n\
+         Skip [this](/missing.html)\n\n\
+         Run the [tool](/missing.html)\n",
+    )
+    .unwrap();
+
+    // skips the second link it sees, which only works if it sees every link once
+    let seen = Arc::new(Mutex::new(0));
+    let hook_seen = seen.clone();
+    let hooks: Vec<Box<LinkCallback>> = vec![Box::new(move |link| {
+        let mut seen = hook_seen.lock().unwrap();
+        *seen += 1;
+        match link.href.0.strip_prefix("v2/") {
+            Some(href) => LinkDecision::Rewrite(href.to_owned()),
+            None if *seen == 2 => LinkDecision::Skip,
+            None => LinkDecision::Check,
+        }
+    })];
+    let broken_links = Arc::new(Mutex::new(Vec::new()));
+    let mut sinks: Vec<Box<dyn ReportSink>> = vec![Box::new(SourcesSink(broken_links.clone()))];
+    let options = CheckOptions {
+        sources_paths: vec![dir.path().join("src")],
+        ..Default::default()
+    };
+
+    let result = check(&dir.path().join("public"), options, hooks, &mut sinks).unwrap();
+
+    assert!(result.read_sources);
+    assert_eq!(*seen.lock().unwrap(), 3);
+    assert_eq!(
+        *broken_links.lock().unwrap(),
+        [("missing.html".to_owned(), 2, vec![Some(1), Some(5)])]
+    );
+
+    dir.close().unwrap();
+}
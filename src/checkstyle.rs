@@ -0,0 +1,131 @@
+//! `--format checkstyle`: the report as Checkstyle XML, for Jenkins Warnings-NG and the editor
+//! plugins that read it.
+
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use crate::github::{Category, Level};
+use crate::junit::escape;
+use crate::sarif::rule_id;
+
+/// One problem in a file.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct Problem {
+    line: Option<usize>,
+    severity: &'static str,
+    message: String,
+    source: &'static str,
+}
+
+/// The problems of every file that has any. Checkstyle has no other element than `<error>`, the
+/// level of a problem goes into its `severity`.
+#[derive(Debug, Default)]
+pub struct CheckstyleReport {
+    files: BTreeMap<Arc<PathBuf>, Vec<Problem>>,
+}
+
+impl CheckstyleReport {
+    /// Add a problem in the file at `path`, at `line` if known. `category` names the check that
+    /// found it, like the rules of `--format sarif`.
+    pub fn add_problem(
+        &mut self,
+        path: Arc<PathBuf>,
+        line: Option<usize>,
+        level: Level,
+        category: Category,
+        message: String,
+    ) {
+        let severity = match level {
+            Level::Error => "error",
+            Level::Warning => "warning",
+            Level::Notice => "info",
+        };
+        self.files.entry(path).or_default().push(Problem {
+            line,
+            severity,
+            message,
+            source: rule_id(category),
+        });
+    }
+
+    /// Write the report, with the problems of each file sorted by line and without duplicates.
+    pub fn write(&self, out: &mut impl Write) -> io::Result<()> {
+        writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(out, r#"<checkstyle version="4.3">"#)?;
+
+        for (path, problems) in &self.files {
+            writeln!(
+                out,
+                r#"  <file name="{}">"#,
+                escape(&path.display().to_string())
+            )?;
+            let mut problems: Vec<_> = problems.iter().collect();
+            problems.sort_unstable();
+            // one source line can produce the same broken link in several HTML files
+            problems.dedup();
+            for problem in problems {
+                write!(out, "    <error")?;
+                if let Some(line) = problem.line {
+                    write!(out, r#" line="{line}""#)?;
+                }
+                writeln!(
+                    out,
+                    r#" severity="{}" message="{}" source="hyperlink.{}"/>"#,
+                    problem.severity,
+                    escape(&problem.message),
+                    problem.source
+                )?;
+            }
+            writeln!(out, "  </file>")?;
+        }
+
+        writeln!(out, "</checkstyle>")
+    }
+}
+
+#[test]
+fn test_checkstyle_report() {
+    let mut report = CheckstyleReport::default();
+    let page = Arc::new(PathBuf::from("public/index.html"));
+    let source = Arc::new(PathBuf::from("src/index.md"));
+    report.add_problem(
+        page,
+        None,
+        Level::Warning,
+        Category::Lints,
+        "javascript-urls: javascript:void(0) is a javascript: URL (a[href])".to_owned(),
+    );
+    report.add_problem(
+        source.clone(),
+        Some(12),
+        Level::Warning,
+        Category::Anchors,
+        "bad link /docs#<install> (a[href])".to_owned(),
+    );
+    report.add_problem(
+        source,
+        Some(3),
+        Level::Error,
+        Category::Links,
+        "bad link /missing.html (a[href])".to_owned(),
+    );
+
+    let mut out = Vec::new();
+    report.write(&mut out).unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<checkstyle version="4.3">
+  <file name="public/index.html">
+    <error severity="warning" message="javascript-urls: javascript:void(0) is a javascript: URL (a[href])" source="hyperlink.lints"/>
+  </file>
+  <file name="src/index.md">
+    <error line="3" severity="error" message="bad link /missing.html (a[href])" source="hyperlink.bad-link"/>
+    <error line="12" severity="warning" message="bad link /docs#&lt;install&gt; (a[href])" source="hyperlink.bad-anchor"/>
+  </file>
+</checkstyle>
+"#
+    );
+}
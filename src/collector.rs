@@ -1,11 +1,23 @@
-use std::collections::BTreeMap;
-use std::path::PathBuf;
+use std::collections::{BTreeMap, HashMap};
+use std::convert::TryFrom;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
+use anyhow::{anyhow, Error};
 use bumpalo::collections::String as BumpString;
 use bumpalo::Bump;
+use rayon::prelude::*;
+use serde_json::{json, Map, Value};
 
-use crate::html::{push_and_canonicalize, try_percent_decode, Href, Link, UsedLink};
+use crate::anchors::Anchors;
+use crate::html::{
+    intern, push_and_canonicalize, try_percent_decode, HookOutcome, Href, Link, LinkOrigin,
+    LinkText, LintWarning, Placeholder, SkipReason, UnparseableDocument, UsedLink,
+};
+use crate::integrity;
+use crate::lints::{is_auto_id, Lint};
 use crate::urls::is_external_link;
 
 pub trait LinkCollector<P>: Send {
@@ -19,6 +31,8 @@ pub struct OwnedUsedLink<P> {
     pub href: String,
     pub path: Arc<PathBuf>,
     pub paragraph: Option<P>,
+    pub origin: LinkOrigin,
+    pub text: Option<Arc<LinkText>>,
 }
 
 /// Collects only used links for match-all-paragraphs command. Discards defined links.
@@ -39,6 +53,8 @@ impl<P: Send> LinkCollector<P> for UsedLinkCollector<P> {
                 href: used_link.href.0.to_owned(),
                 path: used_link.path.to_owned(),
                 paragraph: used_link.paragraph,
+                origin: used_link.origin,
+                text: used_link.text,
             });
         }
     }
@@ -48,31 +64,96 @@ impl<P: Send> LinkCollector<P> for UsedLinkCollector<P> {
     }
 }
 
+/// Where a not-yet-defined href was used. There is one per link on multi-million-link sites, so
+/// the path and origin are ids into `BrokenLinkCollector::paths` and `origins`.
+#[derive(Debug)]
+struct LinkUsage<P> {
+    path: u32,
+    origin: u32,
+    /// How often the document uses the href like this. A link in both the header and the footer
+    /// of a page is stored once.
+    count: u32,
+    paragraph: Option<P>,
+    text: Option<Arc<LinkText>>,
+    warn_only: bool,
+}
+
+/// Where a defined href comes from, see `DefinedLink`. The path and element are ids into
+/// `BrokenLinkCollector::paths` and `elements`.
+#[derive(Debug, Clone, Copy)]
+struct Definition {
+    path: Option<u32>,
+    element: Option<u32>,
+    /// Whether any document uses the href, for `get_checked_links`.
+    used: bool,
+}
+
 #[derive(Debug)]
 enum LinkState<P> {
     /// We have observed a DefinedLink for this href
-    Defined,
+    Defined(Definition),
     /// We have not *yet* observed a DefinedLink and therefore need to keep track of all link
     /// usages for potential error reporting.
-    Undefined(Vec<(Arc<PathBuf>, Option<P>)>),
+    Undefined(Vec<LinkUsage<P>>),
 }
 
-impl<P: Copy> LinkState<P> {
-    fn add_usage(&mut self, link: &UsedLink<P>) {
+impl<P: Copy + PartialEq> LinkState<P> {
+    fn add_usage(&mut self, link: &UsedLink<P>, path: u32, origin: u32) {
+        if let LinkState::Defined(ref mut definition) = self {
+            definition.used = true;
+        }
         if let LinkState::Undefined(ref mut links) = self {
-            links.push((link.path.clone(), link.paragraph));
+            // documents are ingested one at a time, so the usages by the same document are the
+            // last ones, and the only ones a usage can be folded into. that is a handful even for
+            // a link in the header, the footer and the text of a page
+            if let Some(usage) = links
+                .iter_mut()
+                .rev()
+                .take_while(|usage| usage.path == path)
+                .find(|usage| {
+                    usage.origin == origin
+                        && usage.paragraph == link.paragraph
+                        && usage.text == link.text
+                        && usage.warn_only == link.warn_only
+                })
+            {
+                usage.count += 1;
+                return;
+            }
+
+            links.push(LinkUsage {
+                path,
+                origin,
+                count: 1,
+                paragraph: link.paragraph,
+                text: link.text.clone(),
+                warn_only: link.warn_only,
+            });
         }
     }
 
     fn update(&mut self, other: Self) {
         match self {
-            LinkState::Defined => (),
+            LinkState::Defined(definition) => match other {
+                LinkState::Defined(other) => definition.used |= other.used,
+                LinkState::Undefined(links) => definition.used |= !links.is_empty(),
+            },
             LinkState::Undefined(links) => match other {
-                LinkState::Defined => *self = LinkState::Defined,
+                LinkState::Defined(mut definition) => {
+                    definition.used |= !links.is_empty();
+                    *self = LinkState::Defined(definition);
+                }
                 LinkState::Undefined(links2) => links.extend(links2),
             },
         }
     }
+
+    fn is_used(&self) -> bool {
+        match self {
+            LinkState::Defined(definition) => definition.used,
+            LinkState::Undefined(links) => !links.is_empty(),
+        }
+    }
 }
 
 pub struct LocalLinksOnly<C> {
@@ -113,7 +194,13 @@ impl<P, C: LinkCollector<P>> LinkCollector<P> for LocalLinksOnly<C> {
     }
 
     fn ingest(&mut self, link: Link<'_, P>) {
-        if let Some(link) = canonicalize_local_link(&self.arena, link) {
+        let skipped = match link {
+            Link::Uses(ref used_link) => SkipReason::of_external(used_link.href.0),
+            _ => None,
+        };
+        if let Some(reason) = skipped {
+            self.collector.ingest(Link::Skipped(reason));
+        } else if let Some(link) = canonicalize_local_link(&self.arena, link) {
             self.collector.ingest(link);
         }
     }
@@ -123,17 +210,113 @@ impl<P, C: LinkCollector<P>> LinkCollector<P> for LocalLinksOnly<C> {
     }
 }
 
+/// The hreflang alternates declared by one document, as `(hreflang, href)`.
+#[derive(Debug)]
+struct Alternates {
+    path: Arc<PathBuf>,
+    links: Vec<(String, String)>,
+}
+
 /// Link collector used for actual link checking. Keeps track of broken links only.
 pub struct BrokenLinkCollector<P> {
     links: BTreeMap<String, LinkState<P>>,
+    /// Keyed by document href.
+    alternates: BTreeMap<String, Alternates>,
+    /// Document href to the path of the document and the href of its canonical link.
+    canonicals: BTreeMap<String, (Arc<PathBuf>, String)>,
+    /// Hrefs defined by `_redirects`, and their target unless it is external.
+    redirects: BTreeMap<String, Option<String>>,
+    lint_warnings: Vec<LintWarning>,
+    unparseable: Vec<UnparseableDocument>,
+    /// Path of the document, href and `integrity` attribute of each `Link::Integrity`.
+    integrity: Vec<(Arc<PathBuf>, String, String)>,
+    /// Documents that are placeholders, by href. They are not defined, so that the links to them
+    /// end up in `get_broken_links`.
+    placeholders: BTreeMap<String, Placeholder>,
     used_link_count: usize,
+    /// How many used links were not checked, and why.
+    skipped: BTreeMap<SkipReason, usize>,
+    /// Paths of documents that use or define links, indexed by `LinkUsage::path` and
+    /// `Definition::path`.
+    paths: Vec<Arc<PathBuf>>,
+    /// The index of each path in `paths`.
+    path_ids: HashMap<Arc<PathBuf>, u32>,
+    /// Distinct link origins, indexed by `LinkUsage::origin`.
+    origins: Vec<LinkOrigin>,
+    /// Distinct names of elements defining anchors, indexed by `Definition::element`.
+    elements: Vec<String>,
+    /// Path id, index and outcome of each `Link::Hooked`, for `get_hooked_links`.
+    hooked: Vec<(u32, u32, HookOutcome)>,
+}
+
+impl<P> BrokenLinkCollector<P> {
+    fn path_id(&mut self, path: &Arc<PathBuf>) -> u32 {
+        // links are ingested document by document, so the path is usually the last one
+        if self
+            .paths
+            .last()
+            .is_some_and(|last| Arc::ptr_eq(last, path))
+        {
+            return id(self.paths.len() - 1);
+        }
+
+        if let Some(&path_id) = self.path_ids.get(path) {
+            return path_id;
+        }
+        let path_id = id(self.paths.len());
+        self.paths.push(path.clone());
+        self.path_ids.insert(path.clone(), path_id);
+        path_id
+    }
+
+    fn origin_id(&mut self, origin: LinkOrigin) -> u32 {
+        // there are only a few dozen distinct origins
+        match self.origins.iter().position(|known| *known == origin) {
+            Some(i) => id(i),
+            None => {
+                self.origins.push(origin);
+                id(self.origins.len() - 1)
+            }
+        }
+    }
+
+    fn element_id(&mut self, element: &str) -> u32 {
+        // there are only a few distinct elements with ids in practice
+        match self.elements.iter().position(|known| known == element) {
+            Some(i) => id(i),
+            None => {
+                self.elements.push(element.to_owned());
+                id(self.elements.len() - 1)
+            }
+        }
+    }
 }
 
-impl<P: Send + Copy> LinkCollector<P> for BrokenLinkCollector<P> {
+/// How many redirects `BrokenLinkCollector::redirected_anchor` follows, like browsers do.
+const MAX_REDIRECTS: usize = 20;
+
+fn id(index: usize) -> u32 {
+    u32::try_from(index).expect("more than 2^32 documents")
+}
+
+impl<P: Send + Copy + PartialEq> LinkCollector<P> for BrokenLinkCollector<P> {
     fn new() -> Self {
         BrokenLinkCollector {
             links: BTreeMap::new(),
+            alternates: BTreeMap::new(),
+            canonicals: BTreeMap::new(),
+            redirects: BTreeMap::new(),
+            lint_warnings: Vec::new(),
+            unparseable: Vec::new(),
+            integrity: Vec::new(),
+            placeholders: BTreeMap::new(),
             used_link_count: 0,
+            skipped: BTreeMap::new(),
+            paths: Vec::new(),
+            path_ids: HashMap::new(),
+            origins: Vec::new(),
+            elements: Vec::new(),
+            hooked: Vec::new(),
         }
     }
 
@@ -141,40 +324,263 @@ impl<P: Send + Copy> LinkCollector<P> for BrokenLinkCollector<P> {
         match link {
             Link::Uses(used_link) => {
                 self.used_link_count += 1;
+                let path = self.path_id(&used_link.path);
+                let origin = self.origin_id(used_link.origin);
 
                 self.links
                     .entry(used_link.href.0.to_owned())
-                    .and_modify(|state| state.add_usage(&used_link))
+                    .and_modify(|state| state.add_usage(&used_link, path, origin))
                     .or_insert_with(|| {
                         let mut state = LinkState::Undefined(Vec::new());
-                        state.add_usage(&used_link);
+                        state.add_usage(&used_link, path, origin);
                         state
                     });
             }
+            Link::Defines(defined_link)
+                if !defined_link.is_redirect
+                    && defined_link.element.is_none()
+                    && self.placeholders.contains_key(defined_link.href.0) => {}
             Link::Defines(defined_link) => {
-                self.links
-                    .insert(defined_link.href.0.to_owned(), LinkState::Defined);
+                if defined_link.is_redirect {
+                    self.redirects.insert(
+                        defined_link.href.0.to_owned(),
+                        defined_link.redirect_to.map(|target| target.0.to_owned()),
+                    );
+                }
+                let definition = Definition {
+                    path: defined_link.path.as_ref().map(|path| self.path_id(path)),
+                    element: defined_link.element.map(|element| self.element_id(element)),
+                    // the href may have been used before, or defined and used
+                    used: self
+                        .links
+                        .get(defined_link.href.0)
+                        .is_some_and(LinkState::is_used),
+                };
+                self.links.insert(
+                    defined_link.href.0.to_owned(),
+                    LinkState::Defined(definition),
+                );
+            }
+            Link::Canonical(canonical_link) => {
+                self.canonicals.insert(
+                    canonical_link.document.0.to_owned(),
+                    (canonical_link.path, canonical_link.href.0.to_owned()),
+                );
+            }
+            Link::Alternate(alternate_link) => {
+                let path = alternate_link.path;
+                self.alternates
+                    .entry(alternate_link.document.0.to_owned())
+                    .or_insert_with(|| Alternates {
+                        path,
+                        links: Vec::new(),
+                    })
+                    .links
+                    .push((
+                        alternate_link.hreflang.to_owned(),
+                        alternate_link.href.0.to_owned(),
+                    ));
+            }
+            Link::Integrity(integrity_link) => self.integrity.push((
+                integrity_link.path,
+                integrity_link.href.0.to_owned(),
+                integrity_link.integrity.to_owned(),
+            )),
+            Link::Lint(lint_warning) => self.lint_warnings.push(lint_warning),
+            Link::Unparseable(document) => self.unparseable.push(document),
+            Link::Placeholder(document) => {
+                self.placeholders
+                    .insert(document.document.0.to_owned(), document.placeholder);
+            }
+            Link::Skipped(reason) => *self.skipped.entry(reason).or_insert(0) += 1,
+            Link::Hooked(hooked_link) => {
+                let path = self.path_id(&hooked_link.path);
+                self.hooked
+                    .push((path, hooked_link.index, hooked_link.outcome));
             }
         }
     }
 
     fn merge(&mut self, other: Self) {
         self.used_link_count += other.used_link_count;
+        for (reason, count) in other.skipped {
+            *self.skipped.entry(reason).or_insert(0) += count;
+        }
+
+        let path_ids: Vec<u32> = other.paths.iter().map(|path| self.path_id(path)).collect();
+        let origin_ids: Vec<u32> = other
+            .origins
+            .into_iter()
+            .map(|origin| self.origin_id(origin))
+            .collect();
+        let element_ids: Vec<u32> = other
+            .elements
+            .iter()
+            .map(|element| self.element_id(element))
+            .collect();
+
+        for (href, mut other_state) in other.links {
+            match other_state {
+                LinkState::Defined(ref mut definition) => {
+                    if let Some(ref mut path) = definition.path {
+                        *path = path_ids[*path as usize];
+                    }
+                    if let Some(ref mut element) = definition.element {
+                        *element = element_ids[*element as usize];
+                    }
+                }
+                LinkState::Undefined(ref mut usages) => {
+                    for usage in usages {
+                        usage.path = path_ids[usage.path as usize];
+                        usage.origin = origin_ids[usage.origin as usize];
+                    }
+                }
+            }
 
-        for (href, other_state) in other.links {
             if let Some(state) = self.links.get_mut(&href) {
                 state.update(other_state);
             } else {
                 self.links.insert(href, other_state);
             }
         }
+
+        for (document, other_alternates) in other.alternates {
+            match self.alternates.get_mut(&document) {
+                Some(alternates) => alternates.links.extend(other_alternates.links),
+                None => {
+                    self.alternates.insert(document, other_alternates);
+                }
+            }
+        }
+
+        self.canonicals.extend(other.canonicals);
+        self.redirects.extend(other.redirects);
+        self.lint_warnings.extend(other.lint_warnings);
+        self.unparseable.extend(other.unparseable);
+        self.integrity.extend(other.integrity);
+        self.placeholders.extend(other.placeholders);
+        self.hooked.extend(
+            other
+                .hooked
+                .into_iter()
+                .map(|(path, index, outcome)| (path_ids[path as usize], index, outcome)),
+        );
     }
 }
 
 #[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
 pub struct BrokenLink<P> {
     pub hard_404: bool,
+    /// Marked in the markup to be reported as a warning, see `ParseOptions::warn_rels`.
+    pub warn_only: bool,
     pub link: OwnedUsedLink<P>,
+    /// How often the document uses the link with the same origin, paragraph and text.
+    pub count: usize,
+    /// The page exists, but is a placeholder, see `ParseOptions::check_placeholders`.
+    pub placeholder: Option<Placeholder>,
+}
+
+/// An element defining an anchor, see `BrokenLinkCollector::get_anchor_definitions`.
+#[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
+pub struct AnchorDefinition {
+    /// The href of the page, without the anchor.
+    pub page: String,
+    pub path: Option<Arc<PathBuf>>,
+    pub element: Option<String>,
+}
+
+/// A problem with a document that is not a broken link.
+#[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
+pub enum PageError {
+    /// The document declares hreflang alternates, but none with `hreflang=x-default`.
+    MissingHreflangDefault,
+    /// The alternate page exists, but does not declare the document as an alternate in turn.
+    HreflangNotReciprocal { hreflang: String, href: String },
+    /// The canonical link points at a redirect.
+    CanonicalRedirect { href: String },
+    /// The canonical link points at a different page.
+    CanonicalNotSelf { href: String },
+    /// No link points at the source of a `_redirects` rule.
+    UnusedRedirect { href: String, lineno: usize },
+    /// A file exists at the source of a `_redirects` rule.
+    ShadowedRedirect { href: String, lineno: usize },
+    /// The file a `<script>` or `<link>` points at does not match its `integrity` attribute.
+    IntegrityMismatch { href: String, actual: String },
+    /// A linked file does not match the `--downloads-manifest`, e.g. `is empty`.
+    BadDownload { href: String, problem: String },
+    /// An absolute link to a `--sister-site` that its index does not contain.
+    BadSisterLink { url: String },
+    /// A problem found by a lint enabled with `--warn`.
+    Lint { lint: Lint, message: String },
+    /// The document could not be parsed, only reported with `--report-unparseable`.
+    Unparseable { message: String, offset: usize },
+    /// A broken link that the markup marks to be reported as a warning, see
+    /// `ParseOptions::warn_rels`.
+    WarnedLink { href: String, origin: LinkOrigin },
+    /// A link to an anchor on a redirect, which only works if the redirect keeps the fragment.
+    RedirectedAnchor { href: String, target: String },
+    /// A link to a page that exists, but is a placeholder, see
+    /// `ParseOptions::check_placeholders`.
+    PlaceholderLink {
+        href: String,
+        origin: LinkOrigin,
+        placeholder: Placeholder,
+    },
+}
+
+impl fmt::Display for PageError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PageError::MissingHreflangDefault => write!(fmt, "bad hreflang: no hreflang=x-default"),
+            PageError::HreflangNotReciprocal { hreflang, href } => {
+                write!(
+                    fmt,
+                    "bad hreflang: /{href} (hreflang={hreflang}) does not link back"
+                )
+            }
+            PageError::CanonicalRedirect { href } => {
+                write!(fmt, "bad canonical: /{href} is a redirect")
+            }
+            PageError::CanonicalNotSelf { href } => {
+                write!(fmt, "bad canonical: /{href} is not this page")
+            }
+            PageError::UnusedRedirect { href, lineno } => {
+                write!(fmt, "unused redirect /{href} at line {lineno}")
+            }
+            PageError::ShadowedRedirect { href, lineno } => {
+                write!(
+                    fmt,
+                    "shadowed redirect /{href} at line {lineno} (a file exists at this path)"
+                )
+            }
+            PageError::IntegrityMismatch { href, actual } => {
+                write!(
+                    fmt,
+                    "bad integrity: /{href} does not match, its hash is {actual}"
+                )
+            }
+            PageError::BadDownload { href, problem } => {
+                write!(fmt, "bad download: /{href} {problem}")
+            }
+            PageError::BadSisterLink { url } => {
+                write!(fmt, "bad link {url}, not in the index of its site")
+            }
+            PageError::Lint { lint, message } => write!(fmt, "{lint}: {message}"),
+            PageError::Unparseable { message, offset } => {
+                write!(fmt, "unparseable document at byte {offset}: {message}")
+            }
+            PageError::WarnedLink { href, origin } => write!(fmt, "bad link /{href} ({origin})"),
+            PageError::RedirectedAnchor { href, target } => write!(
+                fmt,
+                "anchor through redirect /{href} to /{target}, some redirects drop the fragment"
+            ),
+            PageError::PlaceholderLink {
+                href,
+                origin,
+                placeholder,
+            } => write!(fmt, "link to placeholder /{href} ({origin}), {placeholder}"),
+        }
+    }
 }
 
 impl<P: Copy + PartialEq> BrokenLinkCollector<P> {
@@ -183,22 +589,35 @@ impl<P: Copy + PartialEq> BrokenLinkCollector<P> {
 
         for (href, state) in self.links.iter() {
             if let LinkState::Undefined(links) = state {
+                if check_anchors && self.redirected_anchor_exists(href) {
+                    continue;
+                }
+
+                let placeholder = self
+                    .placeholders
+                    .get(Href(href).without_anchor().0)
+                    .cloned();
                 let hard_404 = if check_anchors {
                     !matches!(
                         self.links.get(Href(href).without_anchor().0),
-                        Some(&LinkState::Defined)
+                        Some(LinkState::Defined(_))
                     )
                 } else {
                     true
                 };
 
-                for (path, paragraph) in links.iter() {
+                for usage in links.iter() {
                     broken_links.push(BrokenLink {
                         hard_404,
+                        warn_only: usage.warn_only,
+                        count: usage.count as usize,
+                        placeholder: placeholder.clone(),
                         link: OwnedUsedLink {
-                            path: path.clone(),
-                            paragraph: *paragraph,
+                            path: self.paths[usage.path as usize].clone(),
+                            paragraph: usage.paragraph,
                             href: href.clone(),
+                            origin: self.origins[usage.origin as usize],
+                            text: usage.text.clone(),
                         },
                     });
                 }
@@ -208,7 +627,1124 @@ impl<P: Copy + PartialEq> BrokenLinkCollector<P> {
         broken_links.into_iter()
     }
 
+    /// Where a link to an anchor on a redirect ends up, e.g. `new#setup` for `old#setup` if
+    /// `_redirects` sends `old` to `new`. Chains of redirects are followed. `None` if `href` is not
+    /// an anchor on a redirect, or the redirect replaces the fragment.
+    pub fn redirected_anchor(&self, href: &str) -> Option<String> {
+        let (mut page, anchor) = href.split_once('#')?;
+        let mut redirected = false;
+        // bounded, as redirects can form a loop
+        for _ in 0..MAX_REDIRECTS {
+            match self.redirects.get(page) {
+                Some(Some(target)) if target.contains('#') => return None,
+                Some(Some(target)) => {
+                    page = target;
+                    redirected = true;
+                }
+                _ => break,
+            }
+        }
+
+        redirected.then(|| format!("{page}#{anchor}"))
+    }
+
+    fn redirected_anchor_exists(&self, href: &str) -> bool {
+        self.redirected_anchor(href)
+            .is_some_and(|target| matches!(self.links.get(&target), Some(LinkState::Defined(_))))
+    }
+
+    /// Links to anchors on redirects that exist on the target of the redirect. They are not broken
+    /// links, but depend on the fragment surviving the redirect, which not every host and client
+    /// gets right.
+    pub fn get_redirected_anchors(&self) -> Vec<(Arc<PathBuf>, PageError)> {
+        let mut warnings = Vec::new();
+
+        for (href, state) in &self.links {
+            if let LinkState::Undefined(links) = state {
+                let target = match self.redirected_anchor(href) {
+                    Some(target)
+                        if matches!(self.links.get(&target), Some(LinkState::Defined(_))) =>
+                    {
+                        target
+                    }
+                    _ => continue,
+                };
+                for usage in links {
+                    warnings.push((
+                        self.paths[usage.path as usize].clone(),
+                        PageError::RedirectedAnchor {
+                            href: href.clone(),
+                            target: target.clone(),
+                        },
+                    ));
+                }
+            }
+        }
+
+        warnings.sort();
+        warnings.dedup();
+        warnings
+    }
+
+    /// The pages defining the anchors of broken links, keyed by the anchor without `#`, so that a
+    /// broken anchor can point at the page that has it. Anchors defined nowhere are left out, and
+    /// so are Sphinx's auto-generated `#id1` and the like, which most pages of a Sphinx site have.
+    pub fn get_anchor_definitions(&self) -> BTreeMap<String, Vec<AnchorDefinition>> {
+        let mut definitions: BTreeMap<String, Vec<AnchorDefinition>> = BTreeMap::new();
+        for (href, state) in &self.links {
+            if let (LinkState::Undefined(_), Some((_, anchor))) = (state, href.split_once('#')) {
+                if !is_auto_id(anchor) {
+                    definitions.entry(anchor.to_owned()).or_default();
+                }
+            }
+        }
+
+        if !definitions.is_empty() {
+            for (href, state) in &self.links {
+                if let (LinkState::Defined(definition), Some((page, anchor))) =
+                    (state, href.split_once('#'))
+                {
+                    if let Some(pages) = definitions.get_mut(anchor) {
+                        pages.push(AnchorDefinition {
+                            page: page.to_owned(),
+                            path: definition
+                                .path
+                                .map(|path| self.paths[path as usize].clone()),
+                            element: definition
+                                .element
+                                .map(|element| self.elements[element as usize].clone()),
+                        });
+                    }
+                }
+            }
+        }
+
+        definitions.retain(|_, pages| !pages.is_empty());
+        definitions
+    }
+
+    /// Every href that is both used and defined, i.e. the links that were checked and are fine,
+    /// e.g. for `--format tap`. Includes anchors if they were collected.
+    pub fn get_checked_links(&self) -> impl Iterator<Item = &str> {
+        self.links.iter().filter_map(|(href, state)| match state {
+            LinkState::Defined(Definition { used: true, .. }) => Some(href.as_str()),
+            _ => None,
+        })
+    }
+
+    /// Whether `href` exists, e.g. `docs/setup.html#install`.
+    pub fn is_defined(&self, href: &str) -> bool {
+        matches!(self.links.get(href), Some(LinkState::Defined(_)))
+    }
+
+    /// Every defined href, e.g. for `hyperlink index`. Includes anchors if they were collected.
+    pub fn get_defined_links(&self) -> impl Iterator<Item = &str> {
+        self.links.iter().filter_map(|(href, state)| match state {
+            LinkState::Defined(_) => Some(href.as_str()),
+            LinkState::Undefined(_) => None,
+        })
+    }
+
+    /// The ids defined by each document, and the elements defining them. Anchors that do not come
+    /// from a document, like those of `--extra-anchors`, are left out.
+    pub fn get_defined_anchors(&self) -> BTreeMap<Arc<PathBuf>, Anchors> {
+        let mut anchors: BTreeMap<Arc<PathBuf>, Anchors> = BTreeMap::new();
+        for (href, state) in &self.links {
+            if let (LinkState::Defined(definition), Some((_, anchor))) =
+                (state, href.split_once('#'))
+            {
+                if let Some(path) = definition.path {
+                    anchors
+                        .entry(self.paths[path as usize].clone())
+                        .or_default()
+                        .insert(
+                            anchor.to_owned(),
+                            definition
+                                .element
+                                .map(|element| self.elements[element as usize].clone()),
+                        );
+                }
+            }
+        }
+        anchors
+    }
+
+    /// The href of every file, and the ids it defines like in `get_defined_anchors`. Redirect
+    /// sources are left out.
+    pub fn get_documents(&self) -> BTreeMap<Arc<PathBuf>, (String, Anchors)> {
+        let mut anchors = self.get_defined_anchors();
+        let mut documents = BTreeMap::new();
+        for (href, state) in &self.links {
+            if let LinkState::Defined(Definition {
+                path: Some(path), ..
+            }) = state
+            {
+                if !href.contains('#') && !self.redirects.contains_key(href) {
+                    let path = self.paths[*path as usize].clone();
+                    let anchors = anchors.remove(&path).unwrap_or_default();
+                    documents.insert(path, (href.clone(), anchors));
+                }
+            }
+        }
+        documents
+    }
+
+    /// Check that every document with hreflang alternates has an `x-default`, and that the
+    /// alternates link back. Missing alternate pages are reported by `get_broken_links` instead.
+    pub fn get_hreflang_errors(&self) -> Vec<(Arc<PathBuf>, PageError)> {
+        let mut errors = Vec::new();
+
+        for (document, alternates) in &self.alternates {
+            if !alternates
+                .links
+                .iter()
+                .any(|(hreflang, _)| hreflang.eq_ignore_ascii_case("x-default"))
+            {
+                errors.push((alternates.path.clone(), PageError::MissingHreflangDefault));
+            }
+
+            for (hreflang, href) in &alternates.links {
+                if href == document || !matches!(self.links.get(href), Some(LinkState::Defined(_)))
+                {
+                    continue;
+                }
+
+                let links_back = self.alternates.get(href).is_some_and(|other| {
+                    other
+                        .links
+                        .iter()
+                        .any(|(_, other_href)| other_href == document)
+                });
+
+                if !links_back {
+                    errors.push((
+                        alternates.path.clone(),
+                        PageError::HreflangNotReciprocal {
+                            hreflang: hreflang.clone(),
+                            href: href.clone(),
+                        },
+                    ));
+                }
+            }
+        }
+
+        errors
+    }
+
+    /// Check that no canonical link points at a redirect, and if `require_self` is set, that
+    /// every canonical link points at its own document. Missing pages are reported by
+    /// `get_broken_links` instead.
+    pub fn get_canonical_errors(&self, require_self: bool) -> Vec<(Arc<PathBuf>, PageError)> {
+        let mut errors = Vec::new();
+
+        for (document, (path, href)) in &self.canonicals {
+            let href = href.as_str();
+            if self.redirects.contains_key(href) {
+                errors.push((
+                    path.clone(),
+                    PageError::CanonicalRedirect {
+                        href: href.to_owned(),
+                    },
+                ));
+            } else if require_self && href != document {
+                errors.push((
+                    path.clone(),
+                    PageError::CanonicalNotSelf {
+                        href: href.to_owned(),
+                    },
+                ));
+            }
+        }
+
+        errors
+    }
+
+    /// Check `integrity` attributes against the files under `base_path` they point to. Each file
+    /// is read once. Missing files are reported by `get_broken_links` instead.
+    pub fn get_integrity_errors(&self, base_path: &Path) -> Vec<(Arc<PathBuf>, PageError)> {
+        let mut by_href: BTreeMap<&str, Vec<(&Arc<PathBuf>, &str)>> = BTreeMap::new();
+        for (path, href, integrity) in &self.integrity {
+            by_href.entry(href).or_default().push((path, integrity));
+        }
+
+        let mut errors: Vec<_> = by_href
+            .into_par_iter()
+            .flat_map_iter(|(href, usages)| {
+                let mut file = base_path.join(href);
+                if file.is_dir() {
+                    file.push("index.html");
+                }
+                let contents = fs::read(&file).ok();
+
+                usages.into_iter().filter_map(move |(path, integrity)| {
+                    let actual = integrity::verify(integrity, contents.as_deref()?).err()?;
+                    Some((
+                        path.clone(),
+                        PageError::IntegrityMismatch {
+                            href: href.to_owned(),
+                            actual,
+                        },
+                    ))
+                })
+            })
+            .collect();
+        errors.sort();
+        errors
+    }
+
+    /// What the hooks did to the used links of each document, see `Document::with_hooked_links`.
+    pub fn get_hooked_links(&self) -> HashMap<Arc<PathBuf>, Vec<(u32, HookOutcome)>> {
+        let mut hooked_links = HashMap::<_, Vec<_>>::new();
+        for (path, index, outcome) in &self.hooked {
+            hooked_links
+                .entry(self.paths[*path as usize].clone())
+                .or_default()
+                .push((*index, outcome.clone()));
+        }
+        for hooked in hooked_links.values_mut() {
+            hooked.sort_unstable_by_key(|(index, _)| *index);
+        }
+        hooked_links
+    }
+
+    /// Problems found by lints, sorted by path.
+    pub fn get_lint_warnings(&self) -> Vec<(Arc<PathBuf>, PageError)> {
+        let mut warnings: Vec<_> = self
+            .lint_warnings
+            .iter()
+            .map(|warning| {
+                (
+                    warning.path.clone(),
+                    PageError::Lint {
+                        lint: warning.lint,
+                        message: warning.message.clone(),
+                    },
+                )
+            })
+            .collect();
+        warnings.sort();
+        warnings
+    }
+
+    /// Documents that could not be parsed, sorted by path.
+    pub fn get_unparseable_errors(&self) -> Vec<(Arc<PathBuf>, PageError)> {
+        let mut errors: Vec<_> = self
+            .unparseable
+            .iter()
+            .map(|document| {
+                (
+                    document.path.clone(),
+                    PageError::Unparseable {
+                        message: document.message.clone(),
+                        offset: document.offset,
+                    },
+                )
+            })
+            .collect();
+        errors.sort();
+        errors
+    }
+
     pub fn used_links_count(&self) -> usize {
         self.used_link_count
     }
+
+    /// How many used links were not checked, by reason. Reasons without skipped links are left
+    /// out.
+    pub fn get_skipped_links(&self) -> &BTreeMap<SkipReason, usize> {
+        &self.skipped
+    }
+}
+
+impl<P> BrokenLinkCollector<P> {
+    /// Everything collected so far, for `--shard-out`. Paragraphs are left out: broken links are
+    /// matched up with `--sources` by parsing their documents again anyway.
+    ///
+    /// Fails for paths that are not valid UTF-8, which JSON cannot hold without changing them.
+    pub fn to_json(&self) -> Result<Value, Error> {
+        let path = |path: &Arc<PathBuf>| -> Result<Value, Error> {
+            path.to_str()
+                .map(Value::from)
+                .ok_or_else(|| anyhow!("{} is not valid UTF-8", path.display()))
+        };
+
+        let links: Map<String, Value> = self
+            .links
+            .iter()
+            .map(|(href, state)| {
+                let state = match state {
+                    LinkState::Defined(definition) => json!({
+                        "path": definition.path,
+                        "element": definition.element,
+                        "used": definition.used,
+                    }),
+                    LinkState::Undefined(usages) => usages
+                        .iter()
+                        .map(|usage| {
+                            let text = usage
+                                .text
+                                .as_ref()
+                                .map(|text| json!([text.text, text.sentence]));
+                            json!([usage.path, usage.origin, usage.count, usage.warn_only, text])
+                        })
+                        .collect(),
+                };
+                (href.clone(), state)
+            })
+            .collect();
+        let alternates: Map<String, Value> = self
+            .alternates
+            .iter()
+            .map(|(document, alternates)| {
+                let value = json!({"path": path(&alternates.path)?, "links": alternates.links});
+                Ok((document.clone(), value))
+            })
+            .collect::<Result<_, Error>>()?;
+        let canonicals: Map<String, Value> = self
+            .canonicals
+            .iter()
+            .map(|(document, (document_path, href))| {
+                Ok((document.clone(), json!([path(document_path)?, href])))
+            })
+            .collect::<Result<_, Error>>()?;
+
+        Ok(json!({
+            "paths": self.paths.iter().map(path).collect::<Result<Vec<_>, _>>()?,
+            "origins": self.origins.iter().map(origin_to_json).collect::<Vec<_>>(),
+            "elements": self.elements,
+            "links": links,
+            "alternates": alternates,
+            "canonicals": canonicals,
+            "redirects": self.redirects,
+            "lint_warnings": self
+                .lint_warnings
+                .iter()
+                .map(|warning| {
+                    Ok(json!([path(&warning.path)?, warning.lint.name(), warning.message]))
+                })
+                .collect::<Result<Vec<_>, Error>>()?,
+            "unparseable": self
+                .unparseable
+                .iter()
+                .map(|document| {
+                    Ok(json!([path(&document.path)?, document.message, document.offset]))
+                })
+                .collect::<Result<Vec<_>, Error>>()?,
+            "integrity": self
+                .integrity
+                .iter()
+                .map(|(document_path, href, integrity)| {
+                    Ok(json!([path(document_path)?, href, integrity]))
+                })
+                .collect::<Result<Vec<_>, Error>>()?,
+            "placeholders": self
+                .placeholders
+                .iter()
+                .map(|(href, placeholder)| (href.clone(), placeholder_to_json(placeholder)))
+                .collect::<Map<_, _>>(),
+            "hooked": self
+                .hooked
+                .iter()
+                .map(|(path, index, outcome)| {
+                    let outcome = match outcome {
+                        HookOutcome::Skip => json!("skip"),
+                        HookOutcome::Check { href, warn_only } => {
+                            json!({"href": href, "warn_only": warn_only})
+                        }
+                    };
+                    json!([path, index, outcome])
+                })
+                .collect::<Vec<_>>(),
+            "used_link_count": self.used_link_count,
+            "skipped": self
+                .skipped
+                .iter()
+                .map(|(reason, count)| (reason.name().to_owned(), Value::from(*count)))
+                .collect::<Map<_, _>>(),
+        }))
+    }
+
+    /// Read what `to_json` wrote.
+    pub fn from_json(value: &Value) -> Result<Self, Error> {
+        Self::try_from_json(value).ok_or_else(|| anyhow!("invalid collector dump"))
+    }
+
+    fn try_from_json(value: &Value) -> Option<Self> {
+        let string = |value: &Value| value.as_str().map(str::to_owned);
+        let path = |value: &Value| Some(Arc::new(PathBuf::from(value.as_str()?)));
+        let id = |value: &Value| value.as_u64().and_then(|id| u32::try_from(id).ok());
+        let paths: Vec<_> = json_array(value.get("paths")?)?
+            .iter()
+            .map(path)
+            .collect::<Option<_>>()?;
+        let origins: Vec<_> = json_array(value.get("origins")?)?
+            .iter()
+            .map(origin_from_json)
+            .collect::<Option<_>>()?;
+        let elements: Vec<_> = json_array(value.get("elements")?)?
+            .iter()
+            .map(string)
+            .collect::<Option<_>>()?;
+        // ids into the tables above, which `merge` and the getters index without checking
+        let id_into = |len: usize| move |value: &Value| id(value).filter(|id| (*id as usize) < len);
+        let path_id = id_into(paths.len());
+        let origin_id = id_into(origins.len());
+        let optional_id = |len: usize| {
+            move |value: &Value| match value {
+                Value::Null => Some(None),
+                value => id_into(len)(value).map(Some),
+            }
+        };
+
+        let mut links = BTreeMap::new();
+        for (href, state) in value.get("links")?.as_object()? {
+            let state = match state {
+                Value::Object(definition) => LinkState::Defined(Definition {
+                    path: optional_id(paths.len())(definition.get("path")?)?,
+                    element: optional_id(elements.len())(definition.get("element")?)?,
+                    used: definition.get("used")?.as_bool()?,
+                }),
+                Value::Array(usages) => LinkState::Undefined(
+                    usages
+                        .iter()
+                        .map(|usage| match json_array(usage)? {
+                            [path, origin, count, warn_only, text] => Some(LinkUsage {
+                                path: path_id(path)?,
+                                origin: origin_id(origin)?,
+                                count: id(count)?,
+                                paragraph: None,
+                                text: match text {
+                                    Value::Null => None,
+                                    text => match json_array(text)? {
+                                        [text, sentence] => Some(Arc::new(LinkText {
+                                            text: string(text)?,
+                                            sentence: sentence.as_str().map(str::to_owned),
+                                        })),
+                                        _ => return None,
+                                    },
+                                },
+                                warn_only: warn_only.as_bool()?,
+                            }),
+                            _ => None,
+                        })
+                        .collect::<Option<_>>()?,
+                ),
+                _ => return None,
+            };
+            links.insert(href.clone(), state);
+        }
+
+        let mut alternates = BTreeMap::new();
+        for (document, value) in value.get("alternates")?.as_object()? {
+            let links = json_array(value.get("links")?)?
+                .iter()
+                .map(|link| match json_array(link)? {
+                    [hreflang, href] => Some((string(hreflang)?, string(href)?)),
+                    _ => None,
+                })
+                .collect::<Option<_>>()?;
+            let path = path(value.get("path")?)?;
+            alternates.insert(document.clone(), Alternates { path, links });
+        }
+
+        let mut canonicals = BTreeMap::new();
+        for (document, value) in value.get("canonicals")?.as_object()? {
+            match json_array(value)? {
+                [document_path, href] => {
+                    canonicals.insert(document.clone(), (path(document_path)?, string(href)?))
+                }
+                _ => return None,
+            };
+        }
+
+        let mut redirects = BTreeMap::new();
+        for (href, target) in value.get("redirects")?.as_object()? {
+            redirects.insert(href.clone(), target.as_str().map(str::to_owned));
+        }
+
+        let triples = |name: &str| -> Option<Vec<(&Value, &Value, &Value)>> {
+            json_array(value.get(name)?)?
+                .iter()
+                .map(|entry| match json_array(entry)? {
+                    [a, b, c] => Some((a, b, c)),
+                    _ => None,
+                })
+                .collect()
+        };
+        let lint_warnings = triples("lint_warnings")?
+            .into_iter()
+            .map(|(document_path, lint, message)| {
+                Some(LintWarning {
+                    path: path(document_path)?,
+                    lint: lint.as_str()?.parse().ok()?,
+                    message: string(message)?,
+                })
+            })
+            .collect::<Option<_>>()?;
+        let unparseable = triples("unparseable")?
+            .into_iter()
+            .map(|(document_path, message, offset)| {
+                Some(UnparseableDocument {
+                    path: path(document_path)?,
+                    message: string(message)?,
+                    offset: usize::try_from(offset.as_u64()?).ok()?,
+                })
+            })
+            .collect::<Option<_>>()?;
+        let integrity = triples("integrity")?
+            .into_iter()
+            .map(|(document_path, href, integrity)| {
+                Some((path(document_path)?, string(href)?, string(integrity)?))
+            })
+            .collect::<Option<_>>()?;
+        let hooked = triples("hooked")?
+            .into_iter()
+            .map(|(path, index, outcome)| {
+                let outcome = match outcome {
+                    Value::String(skip) if skip == "skip" => HookOutcome::Skip,
+                    Value::Object(check) => HookOutcome::Check {
+                        href: match check.get("href")? {
+                            Value::Null => None,
+                            href => Some(string(href)?),
+                        },
+                        warn_only: check.get("warn_only")?.as_bool()?,
+                    },
+                    _ => return None,
+                };
+                Some((path_id(path)?, id(index)?, outcome))
+            })
+            .collect::<Option<_>>()?;
+        let placeholders = value
+            .get("placeholders")?
+            .as_object()?
+            .iter()
+            .map(|(href, placeholder)| Some((href.clone(), placeholder_from_json(placeholder)?)))
+            .collect::<Option<_>>()?;
+
+        let path_ids = paths
+            .iter()
+            .enumerate()
+            .map(|(i, path)| Some((path.clone(), u32::try_from(i).ok()?)))
+            .collect::<Option<_>>()?;
+
+        Some(BrokenLinkCollector {
+            links,
+            alternates,
+            canonicals,
+            redirects,
+            lint_warnings,
+            unparseable,
+            integrity,
+            placeholders,
+            used_link_count: usize::try_from(value.get("used_link_count")?.as_u64()?).ok()?,
+            skipped: value
+                .get("skipped")?
+                .as_object()?
+                .iter()
+                .map(|(name, count)| {
+                    let reason = SkipReason::ALL
+                        .iter()
+                        .find(|reason| reason.name() == name)?;
+                    Some((*reason, usize::try_from(count.as_u64()?).ok()?))
+                })
+                .collect::<Option<_>>()?,
+            paths,
+            path_ids,
+            origins,
+            elements,
+            hooked,
+        })
+    }
+}
+
+fn json_array(value: &Value) -> Option<&[Value]> {
+    value.as_array().map(Vec::as_slice)
+}
+
+fn origin_to_json(origin: &LinkOrigin) -> Value {
+    match *origin {
+        LinkOrigin::Attribute { element, attribute } => {
+            json!({"element": element, "attribute": attribute})
+        }
+        LinkOrigin::LinkRel(rel) => json!({ "rel": rel }),
+        LinkOrigin::LinkMedia { rel, media, title } => {
+            json!({ "rel": rel, "media": media, "title": title })
+        }
+        LinkOrigin::Redirect => json!("redirect"),
+        LinkOrigin::Text => json!("text"),
+        LinkOrigin::TextHeuristic => json!("text_heuristic"),
+        LinkOrigin::DataKey(key) => json!({ "key": key }),
+    }
+}
+
+fn origin_from_json(value: &Value) -> Option<LinkOrigin> {
+    // there are only a few dozen distinct origins, which live as long as the program like the
+    // attributes of --extract rules
+    let leak = |value: &Value| Some(intern(value.as_str()?));
+
+    match value.as_str() {
+        Some("redirect") => return Some(LinkOrigin::Redirect),
+        Some("text") => return Some(LinkOrigin::Text),
+        Some("text_heuristic") => return Some(LinkOrigin::TextHeuristic),
+        Some(_) => return None,
+        None => (),
+    }
+    if let Some(rel) = value.get("rel") {
+        let rel = leak(rel)?;
+        let media = value.get("media").and_then(leak);
+        let title = value.get("title").and_then(leak);
+        if media.is_none() && title.is_none() {
+            return Some(LinkOrigin::LinkRel(rel));
+        }
+        return Some(LinkOrigin::LinkMedia { rel, media, title });
+    }
+    if let Some(key) = value.get("key") {
+        return Some(LinkOrigin::DataKey(leak(key)?));
+    }
+    Some(LinkOrigin::Attribute {
+        element: leak(value.get("element")?)?,
+        attribute: leak(value.get("attribute")?)?,
+    })
+}
+
+fn placeholder_to_json(placeholder: &Placeholder) -> Value {
+    match placeholder {
+        Placeholder::Empty => json!("empty"),
+        Placeholder::Marker(marker) => json!({ "marker": marker }),
+        Placeholder::Soft404(marker) => json!({ "soft_404": marker }),
+    }
+}
+
+fn placeholder_from_json(value: &Value) -> Option<Placeholder> {
+    if value.as_str() == Some("empty") {
+        return Some(Placeholder::Empty);
+    }
+    if let Some(marker) = value.get("soft_404") {
+        return Some(Placeholder::Soft404(marker.as_str()?.to_owned()));
+    }
+    Some(Placeholder::Marker(
+        value.get("marker")?.as_str()?.to_owned(),
+    ))
+}
+
+#[test]
+fn test_merge_remaps_ids() {
+    use crate::paragraph::VoidParagraph;
+
+    fn with_link(path: &str, origin: LinkOrigin) -> BrokenLinkCollector<VoidParagraph> {
+        let mut collector = BrokenLinkCollector::new();
+        collector.ingest(Link::Uses(UsedLink {
+            href: Href("missing"),
+            path: Arc::new(PathBuf::from(path)),
+            paragraph: None,
+            origin,
+            text: None,
+            warn_only: false,
+        }));
+        collector
+    }
+
+    let mut collector = with_link("a.html", LinkOrigin::Redirect);
+    collector.merge(with_link(
+        "b.html",
+        LinkOrigin::Attribute {
+            element: "a",
+            attribute: "href",
+        },
+    ));
+    collector.merge(with_link("c.html", LinkOrigin::Redirect));
+    collector.merge(with_link(
+        "d.html",
+        LinkOrigin::LinkMedia {
+            rel: "stylesheet",
+            media: Some("print"),
+            title: None,
+        },
+    ));
+
+    let broken_links: Vec<_> = collector
+        .get_broken_links(false)
+        .map(|broken_link| (broken_link.link.path, broken_link.link.origin.to_string()))
+        .collect();
+    assert_eq!(
+        broken_links,
+        [
+            (Arc::new(PathBuf::from("a.html")), "_redirects".to_owned()),
+            (Arc::new(PathBuf::from("b.html")), "a[href]".to_owned()),
+            (Arc::new(PathBuf::from("c.html")), "_redirects".to_owned()),
+            (
+                Arc::new(PathBuf::from("d.html")),
+                "link[rel=stylesheet][media=\"print\"]".to_owned()
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_paths_deduplicated() {
+    use crate::paragraph::VoidParagraph;
+
+    fn with_links(paths: &[&str]) -> BrokenLinkCollector<VoidParagraph> {
+        let mut collector = BrokenLinkCollector::new();
+        for path in paths {
+            collector.ingest(Link::Uses(UsedLink {
+                href: Href("missing"),
+                path: Arc::new(PathBuf::from(path)),
+                paragraph: None,
+                origin: LinkOrigin::Redirect,
+                text: None,
+                warn_only: false,
+            }));
+        }
+        collector
+    }
+
+    let mut collector = with_links(&["a.html", "b.html", "a.html"]);
+    assert_eq!(collector.paths.len(), 2);
+    collector.merge(with_links(&["c.html", "b.html"]));
+    assert_eq!(collector.paths.len(), 3);
+
+    let paths: Vec<_> = collector
+        .get_broken_links(false)
+        .map(|broken_link| broken_link.link.path.display().to_string())
+        .collect();
+    assert_eq!(paths, ["a.html", "b.html", "a.html", "c.html", "b.html"]);
+}
+
+#[test]
+fn test_usages_deduplicated_per_document() {
+    use crate::paragraph::VoidParagraph;
+
+    let mut collector = BrokenLinkCollector::<VoidParagraph>::new();
+    // documents share one Arc for all of their links
+    let a = Arc::new(PathBuf::from("a.html"));
+    let b = Arc::new(PathBuf::from("b.html"));
+    let img = LinkOrigin::Attribute {
+        element: "img",
+        attribute: "src",
+    };
+    // the same link in the header and the footer of a, with an image in between
+    for (path, origin) in [
+        (&a, LinkOrigin::Redirect),
+        (&a, img),
+        (&a, LinkOrigin::Redirect),
+        (&b, LinkOrigin::Redirect),
+    ] {
+        collector.ingest(Link::Uses(UsedLink {
+            href: Href("missing"),
+            path: path.clone(),
+            paragraph: None,
+            origin,
+            text: None,
+            warn_only: false,
+        }));
+    }
+
+    let counts: Vec<_> = collector
+        .get_broken_links(false)
+        .map(|broken_link| {
+            (
+                broken_link.link.path,
+                broken_link.link.origin.to_string(),
+                broken_link.count,
+            )
+        })
+        .collect();
+    assert_eq!(
+        counts,
+        [
+            (a.clone(), "_redirects".to_owned(), 2),
+            (a, "img[src]".to_owned(), 1),
+            (b, "_redirects".to_owned(), 1),
+        ]
+    );
+}
+
+#[test]
+fn test_checked_links() {
+    use crate::html::DefinedLink;
+    use crate::paragraph::VoidParagraph;
+
+    let page = Arc::new(PathBuf::from("index.html"));
+    let uses = |href| {
+        Link::Uses(UsedLink {
+            href: Href(href),
+            path: page.clone(),
+            paragraph: None,
+            origin: LinkOrigin::Redirect,
+            text: None,
+            warn_only: false,
+        })
+    };
+    let defines = |href| {
+        Link::Defines(DefinedLink {
+            href: Href(href),
+            is_redirect: false,
+            redirect_to: None,
+            path: Some(page.clone()),
+            element: None,
+        })
+    };
+
+    let mut collector = BrokenLinkCollector::<VoidParagraph>::new();
+    // used before and after being defined, defined and never used, and used in another shard
+    collector.ingest(uses("about.html"));
+    collector.ingest(defines("about.html"));
+    collector.ingest(defines("index.html"));
+    collector.ingest(uses("index.html"));
+    collector.ingest(defines("unused.html"));
+    collector.ingest(defines("pricing.html"));
+    collector.ingest(uses("missing.html"));
+    let mut other = BrokenLinkCollector::<VoidParagraph>::new();
+    other.ingest(uses("pricing.html"));
+    collector.merge(other);
+
+    let checked: Vec<_> = collector.get_checked_links().collect();
+    assert_eq!(checked, ["about.html", "index.html", "pricing.html"]);
+}
+
+#[test]
+fn test_anchor_definitions_survive_merge() {
+    use crate::html::DefinedLink;
+    use crate::paragraph::VoidParagraph;
+
+    let setup = Arc::new(PathBuf::from("docs/setup.html"));
+    let install = Arc::new(PathBuf::from("docs/install.html"));
+    let mut collector = BrokenLinkCollector::<VoidParagraph>::new();
+    collector.ingest(Link::Uses(UsedLink {
+        href: Href("docs/install.html#install"),
+        path: install.clone(),
+        paragraph: None,
+        origin: LinkOrigin::Redirect,
+        text: None,
+        warn_only: false,
+    }));
+
+    let mut other = BrokenLinkCollector::new();
+    other.ingest(Link::Defines(DefinedLink {
+        href: Href("docs/setup.html#install"),
+        is_redirect: false,
+        redirect_to: None,
+        path: Some(setup.clone()),
+        element: Some("h2"),
+    }));
+    collector.merge(other);
+
+    let definitions = collector.get_anchor_definitions();
+    assert_eq!(
+        definitions["install"],
+        [AnchorDefinition {
+            page: "docs/setup.html".to_owned(),
+            path: Some(setup),
+            element: Some("h2".to_owned()),
+        }]
+    );
+}
+
+#[test]
+fn test_redirected_anchor() {
+    use crate::html::DefinedLink;
+    use crate::paragraph::VoidParagraph;
+
+    let mut collector = BrokenLinkCollector::<VoidParagraph>::new();
+    for (source, target) in [
+        ("old", "older"),
+        ("older", "new"),
+        ("loop", "loop"),
+        ("top", "new#top"),
+    ] {
+        collector.ingest(Link::Defines(DefinedLink {
+            href: Href(source),
+            is_redirect: true,
+            redirect_to: Some(Href(target)),
+            path: None,
+            element: None,
+        }));
+    }
+
+    assert_eq!(
+        collector.redirected_anchor("old#setup").unwrap(),
+        "new#setup"
+    );
+    assert_eq!(
+        collector.redirected_anchor("loop#setup").unwrap(),
+        "loop#setup"
+    );
+    assert_eq!(collector.redirected_anchor("top#setup"), None);
+    assert_eq!(collector.redirected_anchor("new#setup"), None);
+    assert_eq!(collector.redirected_anchor("old"), None);
+}
+
+#[test]
+fn test_placeholders() {
+    use crate::html::{DefinedLink, PlaceholderDocument};
+    use crate::paragraph::VoidParagraph;
+
+    let soon = Arc::new(PathBuf::from("soon.html"));
+    let mut collector = BrokenLinkCollector::<VoidParagraph>::new();
+    collector.ingest(Link::Placeholder(PlaceholderDocument {
+        document: Href("soon.html"),
+        placeholder: Placeholder::Marker("Coming soon".to_owned()),
+    }));
+    collector.ingest(Link::Defines(DefinedLink {
+        href: Href("soon.html"),
+        is_redirect: false,
+        redirect_to: None,
+        path: Some(soon),
+        element: None,
+    }));
+
+    let mut other = BrokenLinkCollector::<VoidParagraph>::new();
+    other.ingest(Link::Uses(UsedLink {
+        href: Href("soon.html"),
+        path: Arc::new(PathBuf::from("index.html")),
+        paragraph: None,
+        origin: LinkOrigin::Attribute {
+            element: "a",
+            attribute: "href",
+        },
+        text: None,
+        warn_only: false,
+    }));
+    collector.merge(other);
+    collector.ingest(Link::Placeholder(PlaceholderDocument {
+        document: Href("docs/index.html"),
+        placeholder: Placeholder::Soft404("Page not found".to_owned()),
+    }));
+
+    let broken_links: Vec<_> = collector.get_broken_links(false).collect();
+    assert_eq!(broken_links.len(), 1);
+    assert_eq!(
+        broken_links[0].placeholder,
+        Some(Placeholder::Marker("Coming soon".to_owned()))
+    );
+
+    let restored =
+        BrokenLinkCollector::<VoidParagraph>::from_json(&collector.to_json().unwrap()).unwrap();
+    assert_eq!(restored.placeholders, collector.placeholders);
+}
+
+#[test]
+fn test_json_roundtrip() {
+    use crate::html::DefinedLink;
+    use crate::paragraph::VoidParagraph;
+
+    let page = Arc::new(PathBuf::from("docs/page.html"));
+    let mut collector = BrokenLinkCollector::<VoidParagraph>::new();
+    collector.ingest(Link::Uses(UsedLink {
+        href: Href("docs/missing"),
+        path: page.clone(),
+        paragraph: None,
+        origin: LinkOrigin::Attribute {
+            element: "a",
+            attribute: "href",
+        },
+        text: Some(Arc::new(LinkText {
+            text: "missing".to_owned(),
+            sentence: None,
+        })),
+        warn_only: true,
+    }));
+    collector.ingest(Link::Uses(UsedLink {
+        href: Href("print.css"),
+        path: page.clone(),
+        paragraph: None,
+        origin: LinkOrigin::LinkMedia {
+            rel: "stylesheet",
+            media: Some("print"),
+            title: None,
+        },
+        text: None,
+        warn_only: false,
+    }));
+    collector.ingest(Link::Defines(DefinedLink {
+        href: Href("docs/page.html#intro"),
+        is_redirect: false,
+        redirect_to: None,
+        path: Some(page.clone()),
+        element: Some("h2"),
+    }));
+    collector.ingest(Link::Lint(LintWarning {
+        path: page,
+        lint: Lint::BadIds,
+        message: "empty id".to_owned(),
+    }));
+
+    let json = collector.to_json().unwrap();
+    let restored = BrokenLinkCollector::<VoidParagraph>::from_json(&json).unwrap();
+    assert_eq!(restored.to_json().unwrap(), json);
+    let broken_links = |collector: &BrokenLinkCollector<VoidParagraph>| -> Vec<_> {
+        collector
+            .get_broken_links(false)
+            .map(|broken_link| {
+                (
+                    broken_link.link.href,
+                    broken_link.link.text,
+                    broken_link.warn_only,
+                )
+            })
+            .collect()
+    };
+    assert_eq!(broken_links(&restored), broken_links(&collector));
+    assert!(BrokenLinkCollector::<VoidParagraph>::from_json(&json!({"links": []})).is_err());
+}
+
+#[test]
+fn test_json_ids_out_of_range() {
+    use crate::html::DefinedLink;
+    use crate::paragraph::VoidParagraph;
+
+    let page = Arc::new(PathBuf::from("page.html"));
+    let mut collector = BrokenLinkCollector::<VoidParagraph>::new();
+    collector.ingest(Link::Uses(UsedLink {
+        href: Href("missing.html"),
+        path: page.clone(),
+        paragraph: None,
+        origin: LinkOrigin::Redirect,
+        text: None,
+        warn_only: false,
+    }));
+    collector.ingest(Link::Defines(DefinedLink {
+        href: Href("page.html#intro"),
+        is_redirect: false,
+        redirect_to: None,
+        path: Some(page),
+        element: Some("h2"),
+    }));
+    let json = collector.to_json().unwrap();
+
+    // a truncated or hand-edited dump must not make merge index past the end of a table
+    for table in ["paths", "origins", "elements"] {
+        let mut truncated = json.clone();
+        truncated[table] = json!([]);
+        assert!(
+            BrokenLinkCollector::<VoidParagraph>::from_json(&truncated).is_err(),
+            "{}",
+            table
+        );
+    }
+
+    let mut merged = BrokenLinkCollector::<VoidParagraph>::new();
+    merged.merge(BrokenLinkCollector::from_json(&json).unwrap());
+    assert_eq!(merged.get_broken_links(false).count(), 1);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_json_rejects_non_utf8_paths() {
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+
+    use crate::paragraph::VoidParagraph;
+
+    let mut collector = BrokenLinkCollector::<VoidParagraph>::new();
+    collector.ingest(Link::Uses(UsedLink {
+        href: Href("missing.html"),
+        path: Arc::new(PathBuf::from(OsStr::from_bytes(b"caf\xe9.html"))),
+        paragraph: None,
+        origin: LinkOrigin::Redirect,
+        text: None,
+        warn_only: false,
+    }));
+    assert!(collector.to_json().is_err());
 }
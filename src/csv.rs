@@ -0,0 +1,152 @@
+//! `--format csv` and `tsv`: one row per problem and source file, for spreadsheets and BI tools.
+
+use std::borrow::Cow;
+use std::io::{self, Write};
+
+use crate::github::{Category, Level};
+
+/// The columns of the report, in order.
+const HEADER: [&str; 7] = ["kind", "level", "href", "file", "source", "line", "message"];
+
+/// One problem as found in one file, and the source file it was matched up with, if any.
+#[derive(Debug, Clone)]
+pub struct Row {
+    /// The file the problem was found in, e.g. the HTML file of a broken link.
+    pub file: String,
+    /// The source file of `file`, from `--sources` or `--build-manifest`.
+    pub source: Option<String>,
+    pub line: Option<usize>,
+    /// The kind of problem, see `kind`.
+    pub kind: &'static str,
+    pub level: Level,
+    /// The broken href with leading slash, empty for problems that are not about a link.
+    pub href: String,
+    pub message: String,
+}
+
+/// The `kind` column for problems in `category`. These are part of the format, so they do not
+/// follow renames of categories or SARIF rules.
+pub fn kind(category: Category) -> &'static str {
+    match category {
+        Category::Links => "bad-link",
+        Category::Anchors => "bad-anchor",
+        Category::Hreflang => "bad-hreflang",
+        Category::Canonical => "bad-canonical",
+        Category::Redirects => "bad-redirect",
+        Category::Integrity => "bad-integrity",
+        Category::Downloads => "bad-download",
+        Category::SisterLinks => "bad-sister-link",
+        Category::Lints => "lint",
+        Category::Unparseable => "unparseable",
+    }
+}
+
+impl Row {
+    fn fields(&self) -> [Cow<'_, str>; 7] {
+        [
+            Cow::Borrowed(self.kind),
+            Cow::Owned(self.level.to_string()),
+            Cow::Borrowed(&self.href),
+            Cow::Borrowed(&self.file),
+            Cow::Borrowed(self.source.as_deref().unwrap_or("")),
+            self.line
+                .map_or(Cow::Borrowed(""), |line| Cow::Owned(line.to_string())),
+            Cow::Borrowed(&self.message),
+        ]
+    }
+}
+
+/// Write `rows` with a header, sorted by file, separated by `delimiter`: `,` for CSV, where fields
+/// are quoted as needed, or a tab for TSV, which has no quoting, so tabs and newlines in fields
+/// become spaces.
+pub fn write(out: &mut impl Write, rows: &mut [Row], delimiter: char) -> io::Result<()> {
+    rows.sort_unstable_by(|a, b| {
+        (&a.file, &a.source, a.line, a.kind, &a.href, &a.message)
+            .cmp(&(&b.file, &b.source, b.line, b.kind, &b.href, &b.message))
+    });
+
+    let write_record = |out: &mut dyn Write, fields: &[Cow<'_, str>]| -> io::Result<()> {
+        for (i, field) in fields.iter().enumerate() {
+            if i > 0 {
+                write!(out, "{delimiter}")?;
+            }
+            write!(out, "{}", escape(field, delimiter))?;
+        }
+        // RFC 4180 ends records with CRLF, but spreadsheets accept either
+        writeln!(out)
+    };
+
+    write_record(out, &HEADER.map(Cow::Borrowed))?;
+    for row in rows.iter() {
+        write_record(out, &row.fields())?;
+    }
+    Ok(())
+}
+
+fn escape(field: &str, delimiter: char) -> Cow<'_, str> {
+    if delimiter == '\t' {
+        if field.contains(&['\t', '\n', '\r'][..]) {
+            return Cow::Owned(field.replace(&['\t', '\n', '\r'][..], " "));
+        }
+        return Cow::Borrowed(field);
+    }
+
+    if field.contains(&[delimiter, '"', '\n', '\r'][..]) {
+        Cow::Owned(format!("\"{}\"", field.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(field)
+    }
+}
+
+#[test]
+fn test_csv() {
+    let mut rows = vec![
+        Row {
+            file: "public/index.html".to_owned(),
+            source: None,
+            line: None,
+            kind: "lint",
+            level: Level::Warning,
+            href: String::new(),
+            message: "javascript-urls: javascript:void(0) is a javascript: URL (a[href])"
+                .to_owned(),
+        },
+        Row {
+            file: "public/docs/index.html".to_owned(),
+            source: Some("src/docs.md".to_owned()),
+            line: Some(3),
+            kind: "bad-link",
+            level: Level::Error,
+            href: "/missing.html".to_owned(),
+            message: "bad link /missing.html (a[href])".to_owned(),
+        },
+        Row {
+            file: "public/docs/index.html".to_owned(),
+            source: None,
+            line: None,
+            kind: "bad-anchor",
+            level: Level::Error,
+            href: "/a,b#\"quoted\"".to_owned(),
+            message: "bad link /a,b#\"quoted\"\t(a[href])".to_owned(),
+        },
+    ];
+
+    let mut out = Vec::new();
+    write(&mut out, &mut rows, ',').unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        r#"kind,level,href,file,source,line,message
+bad-anchor,error,"/a,b#""quoted""",public/docs/index.html,,,"bad link /a,b#""quoted""	(a[href])"
+bad-link,error,/missing.html,public/docs/index.html,src/docs.md,3,bad link /missing.html (a[href])
+lint,warning,,public/index.html,,,javascript-urls: javascript:void(0) is a javascript: URL (a[href])
+"#
+    );
+
+    let mut out = Vec::new();
+    write(&mut out, &mut rows, '\t').unwrap();
+    let tsv = String::from_utf8(out).unwrap();
+    assert_eq!(
+        tsv.lines().nth(1).unwrap(),
+        "bad-anchor\terror\t/a,b#\"quoted\"\tpublic/docs/index.html\t\t\tbad link /a,b#\"quoted\" (a[href])"
+    );
+}
@@ -0,0 +1,115 @@
+//! `--data-keys`: URLs in JSON and YAML data files, such as search indexes and nav menus.
+
+use serde_json::Value;
+
+/// String values of `keys` anywhere in a JSON document, as `(key, value)`.
+pub(crate) fn json_urls(
+    contents: &[u8],
+    keys: &[&'static str],
+) -> Result<Vec<(&'static str, String)>, serde_json::Error> {
+    fn walk(value: Value, keys: &[&'static str], urls: &mut Vec<(&'static str, String)>) {
+        match value {
+            Value::Object(object) => {
+                for (name, value) in object {
+                    match (keys.iter().find(|key| **key == name), value) {
+                        (Some(key), Value::String(url)) => urls.push((key, url)),
+                        (_, value) => walk(value, keys, urls),
+                    }
+                }
+            }
+            Value::Array(values) => {
+                for value in values {
+                    walk(value, keys, urls);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    let mut urls = Vec::new();
+    walk(serde_json::from_slice(contents)?, keys, &mut urls);
+    Ok(urls)
+}
+
+/// Byte offset of the error in `contents`, which serde_json only reports as line and column.
+pub(crate) fn json_error_offset(contents: &[u8], error: &serde_json::Error) -> usize {
+    let line_start: usize = contents
+        .split_inclusive(|&byte| byte == b'\n')
+        .take(error.line().saturating_sub(1))
+        .map(<[u8]>::len)
+        .sum();
+    (line_start + error.column().saturating_sub(1)).min(contents.len())
+}
+
+/// Values of `keys` in a YAML document, as `(key, value)`. Only values on the same line as their
+/// key are found, which covers the usual `url: /docs/` and `- href: "/blog/"`.
+pub(crate) fn yaml_urls(contents: &str, keys: &[&'static str]) -> Vec<(&'static str, String)> {
+    let mut urls = Vec::new();
+
+    for line in contents.lines() {
+        let line = line.trim_start();
+        let line = line.strip_prefix("- ").unwrap_or(line).trim_start();
+        let (name, value) = match line.split_once(':') {
+            Some(split) => split,
+            None => continue,
+        };
+        let name = name.trim().trim_matches(&['"', '\''][..]);
+        let key = match keys.iter().find(|key| **key == name) {
+            Some(key) => key,
+            None => continue,
+        };
+
+        let value = value.trim();
+        let value = match value.chars().next() {
+            Some(quote @ ('"' | '\'')) => value[1..].split(quote).next().unwrap_or(""),
+            // a comment needs whitespace before the `#`, `/page#anchor` is not one
+            _ => value.split(" #").next().unwrap_or(value).trim_end(),
+        };
+
+        if !value.is_empty() {
+            urls.push((*key, value.to_owned()));
+        }
+    }
+
+    urls
+}
+
+#[test]
+fn test_json_urls() {
+    let json = br#"{"nav": [{"title": "Docs", "url": "/docs/"}, {"url": 1, "image": "logo.png"}],
+                    "meta": {"href": "/blog/"}}"#;
+    assert_eq!(
+        json_urls(json, &["url", "href"]).unwrap(),
+        [("href", "/blog/".to_owned()), ("url", "/docs/".to_owned())]
+    );
+    assert!(json_urls(b"{", &["url"]).is_err());
+}
+
+#[test]
+fn test_json_error_offset() {
+    let json = b"{\n  \"url\": \"/docs/\"\n  \"href\": \"/blog/\"\n}";
+    let error = json_urls(json, &["url"]).unwrap_err();
+    assert_eq!(json_error_offset(json, &error), 22);
+    assert_eq!(json[22], b'"');
+}
+
+#[test]
+fn test_yaml_urls() {
+    let yaml = "\
+main:
+  - name: Docs
+    url: /docs/ # the manual
+  - url: \"/blog/#latest\"
+    image: 'logo.png'
+  - url:
+    title: url: not a key
+";
+    assert_eq!(
+        yaml_urls(yaml, &["url", "image"]),
+        [
+            ("url", "/docs/".to_owned()),
+            ("url", "/blog/#latest".to_owned()),
+            ("image", "logo.png".to_owned()),
+        ]
+    );
+}
@@ -0,0 +1,81 @@
+//! Paths that exist in the folder being checked, but not when the site is served.
+
+use std::path::Path;
+
+use anyhow::{Context, Error};
+use regex::Regex;
+
+use crate::targets::{glob_to_regex, read_entries};
+
+/// The contents of a `--deploy-ignore` file, like an `.s3ignore` or an rsync exclude file.
+#[derive(Debug, Default)]
+pub struct DeployIgnore {
+    /// Whether the pattern starts with `!`, and the pattern. The last matching pattern wins.
+    patterns: Vec<(bool, Regex)>,
+}
+
+impl DeployIgnore {
+    /// Read a file with one pattern per line, or a JSON array of strings. See `from_entries` for
+    /// the syntax of patterns.
+    pub fn read(path: &Path) -> Result<Self, Error> {
+        Self::from_entries(&read_entries(path, "deploy ignore file")?)
+            .with_context(|| format!("Invalid pattern in {}", path.display()))
+    }
+
+    /// Patterns follow `.gitignore`: they match at any depth unless they contain a slash other
+    /// than a trailing one, a trailing slash only matches directories, and a leading `!`
+    /// re-includes what earlier patterns excluded. Everything inside a matching directory is
+    /// excluded too.
+    fn from_entries(entries: &[String]) -> Result<Self, Error> {
+        let mut patterns = Vec::new();
+
+        for entry in entries {
+            let (negated, pattern) = match entry.strip_prefix('!') {
+                Some(pattern) => (true, pattern),
+                None => (false, entry.as_str()),
+            };
+            let dir_only = pattern.ends_with('/');
+            let pattern = pattern.trim_end_matches('/');
+            let anchored = pattern.contains('/');
+
+            let glob = glob_to_regex(pattern);
+            let glob = &glob[1..glob.len() - 1];
+            let prefix = if anchored { "^" } else { "^(.*/)?" };
+            let suffix = if dir_only { "/.*$" } else { "(/.*)?$" };
+            patterns.push((negated, Regex::new(&format!("{prefix}{glob}{suffix}"))?));
+        }
+
+        Ok(DeployIgnore { patterns })
+    }
+
+    /// Whether the file at `path`, relative to the folder being checked and with forward slashes,
+    /// is not deployed.
+    pub fn is_excluded(&self, path: &str) -> bool {
+        self.patterns
+            .iter()
+            .rev()
+            .find(|(_, pattern)| pattern.is_match(path))
+            .is_some_and(|(negated, _)| !negated)
+    }
+}
+
+#[test]
+fn test_deploy_ignore() {
+    let ignore = DeployIgnore::from_entries(&[
+        "*.map".to_owned(),
+        "drafts/".to_owned(),
+        "/private/*.html".to_owned(),
+        "!/private/press.html".to_owned(),
+    ])
+    .unwrap();
+
+    assert!(ignore.is_excluded("app.js.map"));
+    assert!(ignore.is_excluded("static/app.js.map"));
+    assert!(ignore.is_excluded("drafts/index.html"));
+    assert!(ignore.is_excluded("blog/drafts/post/index.html"));
+    assert!(!ignore.is_excluded("drafts"));
+    assert!(ignore.is_excluded("private/team.html"));
+    assert!(!ignore.is_excluded("private/press.html"));
+    assert!(!ignore.is_excluded("blog/private/team.html"));
+    assert!(!ignore.is_excluded("index.html"));
+}
@@ -0,0 +1,258 @@
+//! `--downloads-manifest`: checking linked release artifacts against their checksums.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use anyhow::{anyhow, Context, Error};
+use bumpalo::collections::String as BumpString;
+use bumpalo::Bump;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use crate::collector::PageError;
+use crate::html::{push_and_canonicalize, LinkDecision, LinkHook};
+
+/// What the manifest says about a file. Either can be missing.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Artifact {
+    /// Lowercase hex.
+    pub sha256: Option<String>,
+    pub size: Option<u64>,
+}
+
+/// A manifest of downloadable files, and which documents link to files next to them.
+#[derive(Debug)]
+pub struct Downloads {
+    /// Canonicalized href, without leading slash, to what the manifest says about it.
+    artifacts: BTreeMap<String, Artifact>,
+    /// Directories containing artifacts, without trailing slash.
+    directories: BTreeSet<String>,
+    /// Hrefs into `directories` and the documents using them, recorded by `usage_hook`.
+    used: Mutex<BTreeSet<(String, Arc<PathBuf>)>>,
+}
+
+impl Downloads {
+    /// Read a `SHA256SUMS` file as written by `sha256sum`, or a JSON object like
+    /// `{"downloads/app.tar.gz": {"sha256": "...", "size": 1234}}`. Paths are relative to the
+    /// root of the site.
+    pub fn read(path: &Path) -> Result<Arc<Self>, Error> {
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read file {}", path.display()))?;
+        let downloads = Self::parse(&contents)
+            .with_context(|| format!("Failed to parse downloads manifest {}", path.display()))?;
+        Ok(Arc::new(downloads))
+    }
+
+    fn parse(contents: &str) -> Result<Self, Error> {
+        let mut entries = Vec::new();
+
+        if contents.trim_start().starts_with('{') {
+            let object: BTreeMap<String, Value> = serde_json::from_str(contents)?;
+            for (file, value) in object {
+                let sha256 = value.get("sha256").and_then(Value::as_str);
+                let size = value.get("size").and_then(Value::as_u64);
+                if sha256.is_none() && size.is_none() {
+                    return Err(anyhow!("{file} has neither sha256 nor size"));
+                }
+                entries.push((
+                    file,
+                    Artifact {
+                        sha256: sha256.map(str::to_ascii_lowercase),
+                        size,
+                    },
+                ));
+            }
+        } else {
+            for line in contents
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty())
+            {
+                let (sha256, file) = line
+                    .split_once(char::is_whitespace)
+                    .ok_or_else(|| anyhow!("Expected a checksum and a file name: {line}"))?;
+                // `sha256sum --binary` marks files with `*`
+                let file = file.trim_start();
+                let file = file.strip_prefix('*').unwrap_or(file);
+                entries.push((
+                    file.to_owned(),
+                    Artifact {
+                        sha256: Some(sha256.to_ascii_lowercase()),
+                        size: None,
+                    },
+                ));
+            }
+        }
+
+        let arena = Bump::new();
+        let mut artifacts = BTreeMap::new();
+        let mut directories = BTreeSet::new();
+        for (file, artifact) in entries {
+            let mut href = BumpString::new_in(&arena);
+            push_and_canonicalize(&mut href, &format!("/{}", file.trim_start_matches('/')));
+            directories.insert(parent(&href).to_owned());
+            artifacts.insert(href.as_str().to_owned(), artifact);
+        }
+
+        Ok(Downloads {
+            artifacts,
+            directories,
+            used: Mutex::new(BTreeSet::new()),
+        })
+    }
+
+    /// A hook that records links to files in the directories of the manifest. It never skips or
+    /// rewrites links.
+    pub fn usage_hook(self: &Arc<Self>) -> Arc<LinkHook> {
+        let downloads = self.clone();
+
+        Arc::new(move |href, document, _| {
+            let href = href.0.split(&['?', '#'][..]).next().unwrap_or("");
+            if downloads.directories.contains(parent(href)) {
+                downloads
+                    .used
+                    .lock()
+                    .unwrap()
+                    .insert((href.to_owned(), document.path.clone()));
+            }
+            LinkDecision::Check
+        })
+    }
+
+    /// Check the linked files in `base_path` against the manifest. Only meaningful after all
+    /// documents have been parsed with `usage_hook` registered.
+    ///
+    /// Linked files next to the artifacts that are not in the manifest are reported as stale,
+    /// except for HTML pages. Missing files are left to `get_broken_links`.
+    pub fn errors(&self, base_path: &Path) -> Result<Vec<(Arc<PathBuf>, PageError)>, Error> {
+        let mut problems = BTreeMap::new();
+        let mut errors = Vec::new();
+
+        for (href, document) in self.used.lock().unwrap().iter() {
+            if !problems.contains_key(href) {
+                let problem = self.verify(base_path, href)?;
+                problems.insert(href.clone(), problem);
+            }
+            if let Some(problem) = &problems[href] {
+                errors.push((
+                    document.clone(),
+                    PageError::BadDownload {
+                        href: href.clone(),
+                        problem: problem.clone(),
+                    },
+                ));
+            }
+        }
+
+        errors.sort();
+        Ok(errors)
+    }
+
+    fn verify(&self, base_path: &Path, href: &str) -> Result<Option<String>, Error> {
+        let path = base_path.join(href);
+        if !path.is_file() {
+            return Ok(None);
+        }
+
+        let artifact = match self.artifacts.get(href) {
+            Some(artifact) => artifact,
+            None if href.ends_with(".html") || href.ends_with(".htm") => return Ok(None),
+            None => return Ok(Some("is not in the manifest".to_owned())),
+        };
+
+        let contents =
+            fs::read(&path).with_context(|| format!("Failed to read file {}", path.display()))?;
+        let size = contents.len() as u64;
+        if size == 0 {
+            return Ok(Some("is empty".to_owned()));
+        }
+        if let Some(expected) = artifact.size.filter(|&expected| expected != size) {
+            return Ok(Some(format!("has {size} bytes, expected {expected}")));
+        }
+        if let Some(ref expected) = artifact.sha256 {
+            let actual: String = Sha256::digest(&contents)
+                .iter()
+                .map(|byte| format!("{byte:02x}"))
+                .collect();
+            if actual != *expected {
+                return Ok(Some(format!("has sha256 {actual}, expected {expected}")));
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+fn parent(href: &str) -> &str {
+    href.rfind('/').map_or("", |i| &href[..i])
+}
+
+#[test]
+fn test_downloads() {
+    use crate::html::{Document, Href, LinkOrigin};
+
+    let dir = assert_fs::TempDir::new().unwrap();
+    let base_path = dir.path();
+    fs::create_dir(base_path.join("downloads")).unwrap();
+    fs::write(base_path.join("downloads/ok.tar.gz"), "ok").unwrap();
+    fs::write(base_path.join("downloads/changed.tar.gz"), "changed").unwrap();
+    fs::write(base_path.join("downloads/empty.zip"), "").unwrap();
+    fs::write(base_path.join("downloads/old.tar.gz"), "old").unwrap();
+    fs::write(base_path.join("downloads/index.html"), "").unwrap();
+
+    let downloads = Arc::new(
+        Downloads::parse(&format!(
+            "{}  downloads/ok.tar.gz\n{}  *downloads/changed.tar.gz\n{}  /downloads/empty.zip\n",
+            "2689367b205c16ce32ed4200942b8b8b1e262dfc70d9bc9fbc77c49699a4f1df",
+            "2689367b205c16ce32ed4200942b8b8b1e262dfc70d9bc9fbc77c49699a4f1df",
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855",
+        ))
+        .unwrap(),
+    );
+
+    let hook = downloads.usage_hook();
+    let document = Document::new(base_path, &base_path.join("index.html"));
+    for href in [
+        "downloads/ok.tar.gz",
+        "downloads/changed.tar.gz?mirror=eu",
+        "downloads/empty.zip",
+        "downloads/old.tar.gz",
+        "downloads/missing.tar.gz",
+        "downloads/index.html",
+        "about/old.tar.gz",
+    ] {
+        assert_eq!(
+            hook(&Href(href), &document, LinkOrigin::Redirect),
+            LinkDecision::Check
+        );
+    }
+
+    let problems: Vec<_> = downloads
+        .errors(base_path)
+        .unwrap()
+        .into_iter()
+        .map(|(_, error)| error.to_string())
+        .collect();
+    assert_eq!(
+        problems,
+        [
+            "bad download: /downloads/changed.tar.gz has sha256 \
+             d67e2e944994496c8d8ec76eed0cf9f09679448d584b532bebf941852a37f5ed, expected \
+             2689367b205c16ce32ed4200942b8b8b1e262dfc70d9bc9fbc77c49699a4f1df",
+            "bad download: /downloads/empty.zip is empty",
+            "bad download: /downloads/old.tar.gz is not in the manifest",
+        ]
+    );
+
+    let json = Downloads::parse(r#"{"/downloads/app.zip": {"size": 12}}"#).unwrap();
+    assert_eq!(
+        json.artifacts["downloads/app.zip"],
+        Artifact {
+            sha256: None,
+            size: Some(12)
+        }
+    );
+    assert!(Downloads::parse(r#"{"app.zip": {}}"#).is_err());
+}
@@ -0,0 +1,202 @@
+//! `--check-feeds`: the HTML of posts embedded in Atom, RSS and JSON feeds.
+
+use std::borrow::Cow;
+use std::sync::OnceLock;
+
+use regex::Regex;
+use serde_json::Value;
+
+/// The HTML embedded in an Atom or RSS feed: Atom `<content>` and `<summary>` of type `html` or
+/// `xhtml`, and RSS `<description>` and `<content:encoded>`. Escaped HTML and CDATA sections are
+/// unwrapped, XHTML is returned as it is.
+pub(crate) fn xml_feed_contents(xml: &str) -> Vec<Cow<'_, str>> {
+    static START_TAG: OnceLock<Regex> = OnceLock::new();
+    static TYPE: OnceLock<Regex> = OnceLock::new();
+    let start_tag = START_TAG.get_or_init(|| {
+        Regex::new(r"<(content:encoded|content|summary|description)(\s[^>]*)?>").unwrap()
+    });
+    let type_attribute =
+        TYPE.get_or_init(|| Regex::new(r#"\btype\s*=\s*["']([^"']*)["']"#).unwrap());
+
+    let mut contents = Vec::new();
+    let mut rest = xml;
+    while let Some(captures) = start_tag.captures(rest) {
+        let name = captures.get(1).unwrap().as_str();
+        let attributes = captures.get(2).map_or("", |attributes| attributes.as_str());
+        let inner = &rest[captures.get(0).unwrap().end()..];
+        if attributes.ends_with('/') {
+            rest = inner;
+            continue;
+        }
+        let end = inner.find(&format!("</{name}")).unwrap_or(inner.len());
+        rest = &inner[end..];
+
+        let is_atom = name == "content" || name == "summary";
+        let kind = if is_atom {
+            let kind = type_attribute
+                .captures(attributes)
+                .map_or("text", |captures| captures.get(1).unwrap().as_str());
+            match kind {
+                "html" | "text/html" => "html",
+                "xhtml" | "application/xhtml+xml" => "xhtml",
+                _ => continue,
+            }
+        } else {
+            "html"
+        };
+
+        let inner = &inner[..end];
+        if kind == "xhtml" {
+            contents.push(Cow::Borrowed(inner));
+        } else {
+            contents.push(unwrap_text(inner));
+        }
+    }
+
+    contents
+}
+
+/// The `content_html` of every item of a JSON Feed, or `None` if `json` is not a JSON Feed.
+pub(crate) fn json_feed_contents(json: &[u8]) -> Option<Vec<String>> {
+    let feed: Value = serde_json::from_slice(json).ok()?;
+    let version = feed.get("version")?.as_str()?;
+    if !version.starts_with("https://jsonfeed.org/version/") {
+        return None;
+    }
+
+    Some(
+        feed.get("items")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(|item| item.get("content_html")?.as_str().map(str::to_owned))
+            .collect(),
+    )
+}
+
+/// The text content of an XML element, with CDATA sections taken as they are and entities
+/// elsewhere decoded.
+fn unwrap_text(inner: &str) -> Cow<'_, str> {
+    if !inner.contains(&['&', '<'][..]) {
+        return Cow::Borrowed(inner);
+    }
+
+    let mut text = String::with_capacity(inner.len());
+    let mut rest = inner;
+    while !rest.is_empty() {
+        match rest.find("<![CDATA[") {
+            Some(0) => {
+                let cdata = &rest["<![CDATA[".len()..];
+                let end = cdata.find("]]>").unwrap_or(cdata.len());
+                text.push_str(&cdata[..end]);
+                rest = cdata.get(end + "]]>".len()..).unwrap_or("");
+            }
+            Some(start) => {
+                decode_entities(&rest[..start], &mut text);
+                rest = &rest[start..];
+            }
+            None => {
+                decode_entities(rest, &mut text);
+                rest = "";
+            }
+        }
+    }
+    Cow::Owned(text)
+}
+
+/// Push `escaped` to `text` with the predefined XML entities and character references decoded.
+/// Anything else that looks like an entity is kept as it is.
+fn decode_entities(escaped: &str, text: &mut String) {
+    let mut rest = escaped;
+    while let Some(start) = rest.find('&') {
+        text.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let decoded = rest.find(';').and_then(|end| {
+            let c = match &rest[1..end] {
+                "lt" => '<',
+                "gt" => '>',
+                "amp" => '&',
+                "quot" => '"',
+                "apos" => '\'',
+                entity => {
+                    let number = entity.strip_prefix('#')?;
+                    let code = match number.strip_prefix(['x', 'X']) {
+                        Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                        None => number.parse().ok()?,
+                    };
+                    char::from_u32(code)?
+                }
+            };
+            Some((c, end))
+        });
+
+        match decoded {
+            Some((c, end)) => {
+                text.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                text.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    text.push_str(rest);
+}
+
+#[test]
+fn test_xml_feed_contents() {
+    let atom = r#"<feed xmlns="http://www.w3.org/2005/Atom">
+        <title type="html">Release &lt;b&gt;notes&lt;/b&gt;</title>
+        <entry>
+            <summary>See /docs/ for more</summary>
+            <content type="html">&lt;a href="/docs/setup?a=1&amp;amp;b=2"&gt;setup&lt;/a&gt; &#60;br&#x3E; &nbsp;</content>
+        </entry>
+        <entry>
+            <content type="xhtml"><div xmlns="http://www.w3.org/1999/xhtml"><a href="/about/">about</a></div></content>
+            <content type="image/png" src="/logo.png"/>
+        </entry>
+    </feed>"#;
+    assert_eq!(
+        xml_feed_contents(atom),
+        [
+            r#"<a href="/docs/setup?a=1&amp;b=2">setup</a> <br> &nbsp;"#,
+            r#"<div xmlns="http://www.w3.org/1999/xhtml"><a href="/about/">about</a></div>"#,
+        ]
+    );
+
+    let rss = r#"<rss><channel>
+        <description>A blog</description>
+        <item>
+            <description>&lt;p&gt;Short&lt;/p&gt;</description>
+            <content:encoded><![CDATA[<a href="/posts/1/">more</a>]]> and <![CDATA[<img src="/a.png">]]></content:encoded>
+        </item>
+    </channel></rss>"#;
+    assert_eq!(
+        xml_feed_contents(rss),
+        [
+            "A blog",
+            "<p>Short</p>",
+            r#"<a href="/posts/1/">more</a> and <img src="/a.png">"#,
+        ]
+    );
+}
+
+#[test]
+fn test_json_feed_contents() {
+    let feed = br#"{
+        "version": "https://jsonfeed.org/version/1.1",
+        "title": "Blog",
+        "items": [
+            {"id": "1", "content_html": "<a href=\"/posts/1/\">one</a>"},
+            {"id": "2", "content_text": "plain"}
+        ]
+    }"#;
+    assert_eq!(
+        json_feed_contents(feed),
+        Some(vec![r#"<a href="/posts/1/">one</a>"#.to_owned()])
+    );
+    assert_eq!(json_feed_contents(br#"{"items": []}"#), None);
+    assert_eq!(json_feed_contents(b"not json"), None);
+}
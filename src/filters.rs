@@ -0,0 +1,164 @@
+//! Link hooks backing command-line options like `--ignore-url`.
+
+use std::sync::Arc;
+
+use anyhow::{anyhow, Context, Error};
+use bumpalo::collections::String as BumpString;
+use bumpalo::Bump;
+use regex::{Regex, RegexSet};
+
+use crate::html::{push_and_canonicalize, FragmentEncoding, LinkDecision, LinkHook};
+use crate::urls::is_external_link;
+
+/// Compile `patterns` given to `option`, with the option in the error message.
+pub fn regex_set(option: &str, patterns: &[String]) -> Result<RegexSet, Error> {
+    RegexSet::new(patterns).with_context(|| format!("Invalid regex for {option}"))
+}
+
+/// Skip links whose href, with a leading slash, matches any of `patterns`.
+pub fn ignore_urls(patterns: &[String]) -> Result<Arc<LinkHook>, Error> {
+    let patterns = regex_set("--ignore-url", patterns)?;
+
+    Ok(Arc::new(move |href, _, _| {
+        if patterns.is_match(&format!("/{href}")) {
+            LinkDecision::Skip
+        } else {
+            LinkDecision::Check
+        }
+    }))
+}
+
+/// Skip links whose href, with a leading slash, matches none of `patterns`. All files are still
+/// valid link targets.
+pub fn check_urls_only(patterns: &[String]) -> Result<Arc<LinkHook>, Error> {
+    let patterns = regex_set("--check-url-only", patterns)?;
+
+    Ok(Arc::new(move |href, _, _| {
+        if patterns.is_match(&format!("/{href}")) {
+            LinkDecision::Check
+        } else {
+            LinkDecision::Skip
+        }
+    }))
+}
+
+/// Remove `;`-delimited parameters from every segment of the path, so that `/doc;jsessionid=abc`
+/// is checked as `/doc`. Servers of Java web applications add and ignore these, static hosts look
+/// for a file with the semicolon in its name.
+pub fn strip_path_params() -> Arc<LinkHook> {
+    Arc::new(|href, _, _| {
+        let (path, fragment) = href.0.split_at(href.0.find('#').unwrap_or(href.0.len()));
+        if !path.contains(';') || is_external_link(href.0.as_bytes()) {
+            return LinkDecision::Check;
+        }
+
+        let stripped: Vec<_> = path
+            .split('/')
+            .map(|segment| segment.split(';').next().unwrap_or(segment))
+            .collect();
+        let arena = Bump::new();
+        let mut href = BumpString::new_in(&arena);
+        push_and_canonicalize(&mut href, &format!("/{}", stripped.join("/")));
+        href.push_str(fragment);
+        LinkDecision::Rewrite(href.as_str().to_owned())
+    })
+}
+
+/// Rewrite hrefs the way a reverse proxy in front of the site does, before they are checked.
+///
+/// Each rule is `REGEX=REPLACEMENT`, split at the last `=`. The regex is matched against the href
+/// with a leading slash, the replacement can refer to groups like `$1`, and only the first
+/// matching rule applies. A fragment in the result is normalized with `fragment_encoding`.
+pub fn rewrite_urls(
+    rules: &[String],
+    fragment_encoding: FragmentEncoding,
+) -> Result<Arc<LinkHook>, Error> {
+    let rules = rules
+        .iter()
+        .map(|rule| {
+            let (pattern, replacement) = rule
+                .rsplit_once('=')
+                .ok_or_else(|| anyhow!("Missing =REPLACEMENT in --rewrite-url {rule}"))?;
+            let regex = Regex::new(pattern).context("Invalid regex for --rewrite-url")?;
+            Ok((regex, replacement.to_owned()))
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+
+    Ok(Arc::new(move |href, _, _| {
+        let href = format!("/{href}");
+        let Some((regex, replacement)) = rules.iter().find(|(regex, _)| regex.is_match(&href))
+        else {
+            return LinkDecision::Check;
+        };
+
+        let rewritten = regex.replace(&href, replacement.as_str());
+        let (page, fragment) = match rewritten.split_once('#') {
+            Some((page, fragment)) => (page, Some(fragment)),
+            None => (&*rewritten, None),
+        };
+        let arena = Bump::new();
+        let mut href = BumpString::new_in(&arena);
+        push_and_canonicalize(&mut href, &format!("/{}", page.trim_start_matches('/')));
+        if let Some(fragment) = fragment {
+            href.push('#');
+            href.push_str(&fragment_encoding.normalize(fragment));
+        }
+        LinkDecision::Rewrite(href.as_str().to_owned())
+    }))
+}
+
+#[test]
+fn test_rewrite_urls() {
+    use std::path::Path;
+
+    use crate::html::{Document, Href, LinkOrigin};
+
+    let hook = rewrite_urls(
+        &[
+            "^/v2/docs/(.*)=/docs/$1".to_owned(),
+            "^/latest/=/v3/".to_owned(),
+        ],
+        FragmentEncoding::Decoded,
+    )
+    .unwrap();
+    let document = Document::new(Path::new("."), Path::new("./index.html"));
+    let rewrite = |href| hook(&Href(href), &document, LinkOrigin::Redirect);
+
+    assert_eq!(
+        rewrite("v2/docs/setup/index.html#install"),
+        LinkDecision::Rewrite("docs/setup#install".to_owned())
+    );
+    assert_eq!(
+        rewrite("latest/api"),
+        LinkDecision::Rewrite("v3/api".to_owned())
+    );
+    assert_eq!(
+        rewrite("v2/docs/setup#%C3%BCbersicht"),
+        LinkDecision::Rewrite("docs/setup#übersicht".to_owned())
+    );
+    assert_eq!(rewrite("docs/setup"), LinkDecision::Check);
+    assert!(rewrite_urls(&["^/old/".to_owned()], FragmentEncoding::Decoded).is_err());
+    assert!(rewrite_urls(&["(=/new/".to_owned()], FragmentEncoding::Decoded).is_err());
+}
+
+#[test]
+fn test_strip_path_params() {
+    use std::path::Path;
+
+    use crate::html::{Document, Href, LinkOrigin};
+
+    let hook = strip_path_params();
+    let document = Document::new(Path::new("."), Path::new("./index.html"));
+    let strip = |href| hook(&Href(href), &document, LinkOrigin::Redirect);
+
+    assert_eq!(
+        strip("doc;jsessionid=abc"),
+        LinkDecision::Rewrite("doc".to_owned())
+    );
+    assert_eq!(
+        strip("docs;v=2/setup/index.html;lang=de#install"),
+        LinkDecision::Rewrite("docs/setup#install".to_owned())
+    );
+    assert_eq!(strip("docs/setup#a;b"), LinkDecision::Check);
+    assert_eq!(strip("https://example.com/a;b"), LinkDecision::Check);
+}
@@ -0,0 +1,209 @@
+//! Asking git about the sources: `--git-blame` and `--git-range`.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use anyhow::{anyhow, Context, Error};
+
+/// Run git in the current directory and return its stdout.
+fn git(args: &[&str]) -> Result<String, Error> {
+    let output = Command::new("git")
+        .args(args)
+        .output()
+        .context("Failed to run git")?;
+
+    if !output.status.success() {
+        return Err(anyhow!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// The commit checked out in the repository containing `dir`, or `None` if it is not in a git
+/// repository or git is not installed.
+pub fn head_commit(dir: &Path) -> Option<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["rev-parse", "HEAD"])
+        .output()
+        .ok()?;
+    output.status.success().then(|| {
+        String::from_utf8_lossy(&output.stdout)
+            .trim_end()
+            .to_owned()
+    })
+}
+
+/// The files changed in `range`, e.g. `origin/main...HEAD`, as canonical paths.
+///
+/// Files that were deleted in `range` are left out.
+pub fn changed_files(range: &str) -> Result<BTreeSet<PathBuf>, Error> {
+    let toplevel = PathBuf::from(git(&["rev-parse", "--show-toplevel"])?.trim_end());
+
+    Ok(git(&["diff", "--name-only", "-z", range, "--"])?
+        .split('\0')
+        .filter(|name| !name.is_empty())
+        .filter_map(|name| fs::canonicalize(toplevel.join(name)).ok())
+        .collect())
+}
+
+/// The commit that last changed a line.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Blame {
+    pub author: String,
+    pub author_mail: String,
+    pub commit: String,
+}
+
+impl fmt::Display for Blame {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        let short_commit = self.commit.get(..7).unwrap_or(&self.commit);
+        write!(
+            fmt,
+            "last changed by {} {} in {}",
+            self.author, self.author_mail, short_commit
+        )
+    }
+}
+
+/// Run `git blame` once for the lines `linenos` of `path`, in the repository containing `path`.
+///
+/// Lines that are not committed yet are left out, and nothing is returned if the file is not
+/// tracked by git.
+pub fn blame(path: &Path, linenos: &BTreeSet<usize>) -> Result<BTreeMap<usize, Blame>, Error> {
+    let (dir, file_name) = match (path.parent(), path.file_name()) {
+        (Some(dir), Some(file_name)) => (dir, file_name),
+        _ => return Ok(BTreeMap::new()),
+    };
+    if linenos.is_empty() {
+        return Ok(BTreeMap::new());
+    }
+
+    let mut command = Command::new("git");
+    command
+        .arg("-C")
+        .arg(if dir.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            dir
+        })
+        .args(["blame", "--porcelain"]);
+    for (start, end) in line_ranges(linenos) {
+        command.arg("-L").arg(format!("{start},{end}"));
+    }
+    let output = command
+        .arg("--")
+        .arg(file_name)
+        .output()
+        .context("Failed to run git")?;
+
+    if !output.status.success() {
+        return Ok(BTreeMap::new());
+    }
+
+    Ok(parse_porcelain(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// `linenos` as ranges of consecutive lines, so that `git blame` gets fewer `-L` arguments.
+fn line_ranges(linenos: &BTreeSet<usize>) -> Vec<(usize, usize)> {
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+    for &lineno in linenos {
+        match ranges.last_mut() {
+            Some((_, end)) if *end + 1 == lineno => *end = lineno,
+            _ => ranges.push((lineno, lineno)),
+        }
+    }
+    ranges
+}
+
+/// The blame of each line in `git blame --porcelain` output, by line number.
+///
+/// Author details are only printed the first time a commit comes up, so they are collected per
+/// commit and looked up once all lines are read.
+fn parse_porcelain(output: &str) -> BTreeMap<usize, Blame> {
+    let mut line_commits = Vec::new();
+    let mut authors: BTreeMap<&str, (Option<&str>, Option<&str>)> = BTreeMap::new();
+    let mut current = None;
+    for line in output.lines() {
+        if line.starts_with('\t') {
+            // the content of the line ends its entry
+            current = None;
+        } else if let Some(commit) = current {
+            let (author, author_mail) = authors.entry(commit).or_default();
+            if let Some(value) = line.strip_prefix("author ") {
+                *author = Some(value);
+            } else if let Some(value) = line.strip_prefix("author-mail ") {
+                *author_mail = Some(value);
+            }
+        } else {
+            let mut fields = line.split(' ');
+            let commit = fields.next();
+            let lineno = fields
+                .nth(1)
+                .and_then(|lineno| lineno.parse::<usize>().ok());
+            if let (Some(commit), Some(lineno)) = (commit, lineno) {
+                line_commits.push((lineno, commit));
+                current = Some(commit);
+            }
+        }
+    }
+
+    line_commits
+        .into_iter()
+        .filter(|(_, commit)| !commit.bytes().all(|b| b == b'0'))
+        .filter_map(|(lineno, commit)| {
+            let (author, author_mail) = authors.get(commit)?;
+            let blame = Blame {
+                author: (*author)?.to_owned(),
+                author_mail: (*author_mail)?.to_owned(),
+                commit: commit.to_owned(),
+            };
+            Some((lineno, blame))
+        })
+        .collect()
+}
+
+#[test]
+fn test_parse_porcelain() {
+    // lines 3 and 7 come from the same commit, whose details are only printed once, line 5 is
+    // not committed yet
+    let output = "\
+8f0c1d2e3a4b5c6d7e8f9a0b1c2d3e4f5a6b7c8d 3 3 1
+author Jane Doe
+author-mail <jane@example.com>
+author-time 1700000000
+summary Rename install page
+filename docs/intro.md
+\tSee the [install guide](/install/).
+0000000000000000000000000000000000000000 5 5 1
+author Not Committed Yet
+author-mail <not.committed.yet>
+filename docs/intro.md
+\tSee the [faq](/faq/).
+8f0c1d2e3a4b5c6d7e8f9a0b1c2d3e4f5a6b7c8d 6 7 1
+filename docs/intro.md
+\tSee the [changelog](/changelog/).
+";
+
+    let blames = parse_porcelain(output);
+    assert_eq!(blames.keys().copied().collect::<Vec<_>>(), [3, 7]);
+    assert_eq!(
+        blames[&3].to_string(),
+        "last changed by Jane Doe <jane@example.com> in 8f0c1d2"
+    );
+    assert_eq!(blames[&7], blames[&3]);
+}
+
+#[test]
+fn test_line_ranges() {
+    let linenos = BTreeSet::from([1, 2, 3, 7, 9, 10]);
+    assert_eq!(line_ranges(&linenos), [(1, 3), (7, 7), (9, 10)]);
+}
@@ -0,0 +1,229 @@
+//! Severities of the annotations emitted with `--github-actions`, and other GitHub Actions
+//! integration.
+
+use std::env;
+use std::fmt;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use anyhow::{Context, Error};
+
+/// Whether hyperlink runs in a GitHub Actions workflow.
+pub fn is_github_actions() -> bool {
+    env::var("GITHUB_ACTIONS").is_ok_and(|value| value == "true")
+}
+
+/// Append `markdown` to the summary of the current job, if there is one. See
+/// <https://docs.github.com/en/actions/reference/workflows-and-actions/workflow-commands#adding-a-job-summary>.
+pub fn append_step_summary(markdown: &str) -> Result<(), Error> {
+    let Some(path) = env::var_os("GITHUB_STEP_SUMMARY") else {
+        return Ok(());
+    };
+
+    OpenOptions::new()
+        .append(true)
+        .create(true)
+        .open(&path)
+        .and_then(|mut file| file.write_all(markdown.as_bytes()))
+        .with_context(|| format!("Failed to write job summary to {}", path.to_string_lossy()))
+}
+
+/// The absolute path annotations need for `filepath`, with symlinks resolved.
+///
+/// Every file with problems is annotated once, so it is canonicalized once. If that fails, e.g.
+/// because the file was removed in the meantime, the path is made absolute without resolving
+/// symlinks instead of failing the run.
+pub fn annotation_path(filepath: &Path) -> PathBuf {
+    filepath
+        .canonicalize()
+        .or_else(|_| env::current_dir().map(|cwd| cwd.join(filepath)))
+        .unwrap_or_else(|_| filepath.to_owned())
+}
+
+/// A GitHub workflow command that creates an annotation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Level {
+    Error,
+    Warning,
+    Notice,
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Level::Error => write!(fmt, "error"),
+            Level::Warning => write!(fmt, "warning"),
+            Level::Notice => write!(fmt, "notice"),
+        }
+    }
+}
+
+impl FromStr for Level {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "error" => Ok(Level::Error),
+            "warning" => Ok(Level::Warning),
+            "notice" => Ok(Level::Notice),
+            _ => Err(format!(
+                "unknown level {s:?}, expected error, warning or notice"
+            )),
+        }
+    }
+}
+
+/// The kinds of problems that are reported.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Category {
+    Links,
+    Anchors,
+    Hreflang,
+    Canonical,
+    Redirects,
+    Integrity,
+    Downloads,
+    SisterLinks,
+    Lints,
+    Unparseable,
+}
+
+impl FromStr for Category {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "links" => Ok(Category::Links),
+            "anchors" => Ok(Category::Anchors),
+            "hreflang" => Ok(Category::Hreflang),
+            "canonical" => Ok(Category::Canonical),
+            "redirects" => Ok(Category::Redirects),
+            "integrity" => Ok(Category::Integrity),
+            "downloads" => Ok(Category::Downloads),
+            "sister-links" => Ok(Category::SisterLinks),
+            "lints" => Ok(Category::Lints),
+            "unparseable" => Ok(Category::Unparseable),
+            _ => Err(format!(
+                "unknown category {s:?}, expected links, anchors, hreflang, canonical, redirects, \
+                 integrity, downloads, sister-links, lints or unparseable"
+            )),
+        }
+    }
+}
+
+impl Category {
+    /// The name used by `--deny`, `--allow` and `--github-severity`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Category::Links => "links",
+            Category::Anchors => "anchors",
+            Category::Hreflang => "hreflang",
+            Category::Canonical => "canonical",
+            Category::Redirects => "redirects",
+            Category::Integrity => "integrity",
+            Category::Downloads => "downloads",
+            Category::SisterLinks => "sister-links",
+            Category::Lints => "lints",
+            Category::Unparseable => "unparseable",
+        }
+    }
+
+    /// Problems that make `hyperlink` exit with `exit 2` rather than `exit 1`, or that do not
+    /// affect the exit code at all, are warnings by default.
+    fn default_level(self) -> Level {
+        match self {
+            Category::Links
+            | Category::Hreflang
+            | Category::Canonical
+            | Category::Integrity
+            | Category::Downloads
+            | Category::SisterLinks
+            | Category::Unparseable => Level::Error,
+            Category::Anchors | Category::Redirects | Category::Lints => Level::Warning,
+        }
+    }
+}
+
+/// A `--github-severity` argument, e.g. `anchors=error`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SeverityOverride {
+    category: Category,
+    level: Level,
+}
+
+impl FromStr for SeverityOverride {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (category, level) = s
+            .split_once('=')
+            .ok_or_else(|| format!("expected CATEGORY=LEVEL, got {s:?}"))?;
+
+        Ok(SeverityOverride {
+            category: category.parse()?,
+            level: level.parse()?,
+        })
+    }
+}
+
+/// The level to annotate each category with, the last override for a category wins.
+pub fn level(overrides: &[SeverityOverride], category: Category) -> Level {
+    overrides
+        .iter()
+        .rev()
+        .find(|o| o.category == category)
+        .map_or_else(|| category.default_level(), |o| o.level)
+}
+
+#[test]
+fn test_level() {
+    let overrides: Vec<SeverityOverride> = vec![
+        "anchors=notice".parse().unwrap(),
+        "links=warning".parse().unwrap(),
+        "anchors=error".parse().unwrap(),
+    ];
+
+    assert_eq!(level(&overrides, Category::Anchors), Level::Error);
+    assert_eq!(level(&overrides, Category::Links), Level::Warning);
+    assert_eq!(level(&overrides, Category::Redirects), Level::Warning);
+    assert_eq!(level(&[], Category::Anchors), Level::Warning);
+    assert!("anchors".parse::<SeverityOverride>().is_err());
+    assert!("anchors=fatal".parse::<SeverityOverride>().is_err());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_annotation_path_resolves_symlinks() {
+    use assert_fs::prelude::*;
+
+    let dir = assert_fs::TempDir::new().unwrap();
+    dir.child("real/index.html").touch().unwrap();
+    dir.child("public")
+        .symlink_to_dir(dir.child("real"))
+        .unwrap();
+    dir.child("real/page.html").touch().unwrap();
+    dir.child("public/other.html")
+        .symlink_to_file(dir.child("real/page.html"))
+        .unwrap();
+
+    let real = dir.path().canonicalize().unwrap().join("real");
+    assert_eq!(
+        annotation_path(&dir.path().join("public/index.html")),
+        real.join("index.html")
+    );
+    assert_eq!(
+        annotation_path(&dir.path().join("public/other.html")),
+        real.join("page.html")
+    );
+}
+
+#[test]
+fn test_annotation_path_of_removed_file() {
+    let filepath = Path::new("removed/index.html");
+    assert_eq!(
+        annotation_path(filepath),
+        env::current_dir().unwrap().join(filepath)
+    );
+}
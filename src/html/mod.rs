@@ -1,21 +1,31 @@
 mod parser;
+mod selector;
 
 use std::borrow::Cow;
+use std::cell::Cell;
+use std::collections::BTreeSet;
 use std::fmt;
 use std::fs;
 use std::io::{BufRead, BufReader, Read};
 use std::path::{Path, PathBuf};
 use std::str;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex, OnceLock};
 
 use anyhow::Error;
 use bumpalo::collections::String as BumpString;
 use bumpalo::collections::Vec as BumpVec;
 use html5gum::{IoReader, Tokenizer};
+use regex::{Regex, RegexSet};
 
-use crate::paragraph::ParagraphWalker;
+use crate::data;
+use crate::feeds;
+use crate::github::Level;
+use crate::lints::{self, Lint, PathLimits};
+use crate::paragraph::{ParagraphOptions, ParagraphWalker, VoidParagraph};
 use crate::urls::is_external_link;
 
+pub use selector::ExtractRule;
+
 #[cfg(test)]
 use pretty_assertions::assert_eq;
 
@@ -159,6 +169,51 @@ pub fn try_percent_decode(input: &str) -> Cow<'_, str> {
         .unwrap_or(Cow::Borrowed(input))
 }
 
+/// How the fragments of links and the ids they point to are compared, see
+/// `ParseOptions::fragment_encoding`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FragmentEncoding {
+    /// Percent-decode fragments and ids, so that `#%C3%BCbersicht` points to `id="übersicht"`
+    /// like in browsers.
+    #[default]
+    Decoded,
+    /// Compare fragments and ids as written, e.g. for scripts that read `location.hash` without
+    /// decoding it.
+    Raw,
+}
+
+impl FragmentEncoding {
+    pub const ALL: &'static [FragmentEncoding] =
+        &[FragmentEncoding::Decoded, FragmentEncoding::Raw];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            FragmentEncoding::Decoded => "decoded",
+            FragmentEncoding::Raw => "raw",
+        }
+    }
+
+    /// `fragment`, with or without leading `#`, as it is compared.
+    pub fn normalize(self, fragment: &str) -> Cow<'_, str> {
+        match self {
+            FragmentEncoding::Decoded => try_percent_decode(fragment),
+            FragmentEncoding::Raw => Cow::Borrowed(fragment),
+        }
+    }
+}
+
+impl str::FromStr for FragmentEncoding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        FragmentEncoding::ALL
+            .iter()
+            .copied()
+            .find(|encoding| encoding.name() == s)
+            .ok_or_else(|| format!("unknown fragment encoding {s:?}, expected decoded or raw"))
+    }
+}
+
 #[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd)]
 pub struct Href<'a>(pub &'a str);
 
@@ -180,29 +235,577 @@ impl fmt::Display for Href<'_> {
     }
 }
 
+/// Which part of a document a used link was found in.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd)]
+pub enum LinkOrigin {
+    /// An attribute of an element, e.g. `a[href]`.
+    Attribute {
+        element: &'static str,
+        attribute: &'static str,
+    },
+    /// The `href` of a `<link>` element with a well-known `rel`, e.g. `link[rel=stylesheet]`.
+    LinkRel(&'static str),
+    /// The `href` of a `<link>` element with a well-known `rel` that only applies to some media,
+    /// or of an alternate stylesheet, e.g. `link[rel=stylesheet][media="print"]`. The `title` is
+    /// only kept for alternate stylesheets, where it names the style in the browser's menu.
+    LinkMedia {
+        rel: &'static str,
+        media: Option<&'static str>,
+        title: Option<&'static str>,
+    },
+    /// A redirect target in a `_redirects` file.
+    Redirect,
+    /// A URL found in plain text.
+    Text,
+    /// A URL found in the visible text of an HTML page, see `ParseOptions::check_page_text`.
+    TextHeuristic,
+    /// The value of a key in a JSON or YAML file.
+    DataKey(&'static str),
+}
+
+impl fmt::Display for LinkOrigin {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LinkOrigin::Attribute { element, attribute } => write!(fmt, "{element}[{attribute}]"),
+            LinkOrigin::LinkRel(rel) => write!(fmt, "link[rel={}]", Quoted(rel)),
+            LinkOrigin::LinkMedia { rel, media, title } => {
+                write!(fmt, "link[rel={}]", Quoted(rel))?;
+                if let Some(media) = media {
+                    write!(fmt, "[media={media:?}]")?;
+                }
+                if let Some(title) = title {
+                    write!(fmt, "[title={title:?}]")?;
+                }
+                Ok(())
+            }
+            LinkOrigin::Redirect => write!(fmt, "_redirects"),
+            LinkOrigin::Text => write!(fmt, "text"),
+            LinkOrigin::TextHeuristic => write!(fmt, "heuristic: text"),
+            LinkOrigin::DataKey(key) => write!(fmt, "key {key}"),
+        }
+    }
+}
+
+/// An attribute value in a selector, quoted only if it is not a single word, like the `rel` of an
+/// alternate stylesheet.
+struct Quoted<'a>(&'a str);
+
+impl fmt::Display for Quoted<'_> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        if self.0.contains(char::is_whitespace) {
+            write!(fmt, "{:?}", self.0)
+        } else {
+            fmt.write_str(self.0)
+        }
+    }
+}
+
+/// A string that lives as long as the program. Attribute values that end up in a `LinkOrigin`
+/// are interned, there are only a few distinct ones per site.
+pub fn intern(value: &str) -> &'static str {
+    static INTERNED: Mutex<BTreeSet<&'static str>> = Mutex::new(BTreeSet::new());
+
+    let mut interned = INTERNED.lock().unwrap();
+    match interned.get(value) {
+        Some(value) => value,
+        None => {
+            let value = Box::leak(value.to_owned().into_boxed_str());
+            interned.insert(value);
+            value
+        }
+    }
+}
+
+/// Absolute and site-relative URLs in plain text, at the start of the text or after whitespace or
+/// an opening bracket or quote, without trailing punctuation.
+fn text_urls(text: &str) -> impl Iterator<Item = &str> {
+    static TEXT_URL: OnceLock<Regex> = OnceLock::new();
+    let text_url = TEXT_URL.get_or_init(|| {
+        Regex::new(r#"(?:^|[\s(<\["'])((?:https?://|/[^/\s])[^\s<>"'`()\[\]]*)"#).unwrap()
+    });
+
+    text_url.captures_iter(text).map(|captures| {
+        captures
+            .get(1)
+            .unwrap()
+            .as_str()
+            .trim_end_matches(&['.', ',', ';', ':', '!', '?'][..])
+    })
+}
+
+/// What to do with a used link, as decided by a `LinkHook`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LinkDecision {
+    /// Check the link as usual.
+    Check,
+    /// Drop the link. It is neither checked nor counted.
+    Skip,
+    /// Check this href instead. Like the href passed to the hook, it is relative to the site root,
+    /// without a leading slash.
+    Rewrite(String),
+    /// Check the link, but report it at this level if it is broken. `Level::Warning` and
+    /// `Level::Notice` report it next to the lint warnings, like a link on an element with a
+    /// `--warn-rel`, and `Level::Error` as a broken link, even if the markup says otherwise.
+    Reclassify(Level),
+}
+
+impl LinkDecision {
+    /// Apply the decision to the href and level of a used link. `false` if the link is skipped.
+    fn apply(self, rewritten: &mut Option<String>, warn_only: &mut bool) -> bool {
+        match self {
+            LinkDecision::Check => (),
+            LinkDecision::Skip => return false,
+            LinkDecision::Rewrite(href) => *rewritten = Some(href),
+            LinkDecision::Reclassify(level) => *warn_only = level != Level::Error,
+        }
+        true
+    }
+}
+
+/// Why a used link was not checked, see `Link::Skipped`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SkipReason {
+    /// An http(s) or protocol-relative URL to another site.
+    External,
+    Mailto,
+    /// A `data:` URI.
+    Data,
+    /// Any other URL scheme, e.g. `tel:` or `javascript:`.
+    OtherScheme,
+    /// On an element with a `--skip-rel` or `data-hyperlink="ignore"`.
+    Markup,
+    /// Skipped by a `LinkHook`, i.e. `--ignore-url`, `--check-url-only` or a glob in
+    /// `--extra-targets`.
+    Ignored,
+}
+
+impl SkipReason {
+    pub const ALL: [SkipReason; 6] = [
+        SkipReason::External,
+        SkipReason::Mailto,
+        SkipReason::Data,
+        SkipReason::OtherScheme,
+        SkipReason::Markup,
+        SkipReason::Ignored,
+    ];
+
+    /// The name in reports and machine output.
+    pub fn name(self) -> &'static str {
+        match self {
+            SkipReason::External => "external",
+            SkipReason::Mailto => "mailto",
+            SkipReason::Data => "data",
+            SkipReason::OtherScheme => "other_schemes",
+            SkipReason::Markup => "markup",
+            SkipReason::Ignored => "ignored",
+        }
+    }
+
+    /// Why `href` is not checked as a link on the site, if it has a URL scheme.
+    pub fn of_external(href: &str) -> Option<Self> {
+        if !is_external_link(href.as_bytes()) {
+            return None;
+        }
+
+        let scheme = href.split(':').next().unwrap_or("");
+        Some(
+            if href.starts_with("//")
+                || ["http", "https"]
+                    .iter()
+                    .any(|s| s.eq_ignore_ascii_case(scheme))
+            {
+                SkipReason::External
+            } else if scheme.eq_ignore_ascii_case("mailto") {
+                SkipReason::Mailto
+            } else if scheme.eq_ignore_ascii_case("data") {
+                SkipReason::Data
+            } else {
+                SkipReason::OtherScheme
+            },
+        )
+    }
+}
+
+/// A callback that is invoked for every used link before it is handed to the collector.
+///
+/// Documents are parsed in parallel, so hooks must be `Sync`. Hooks run in the order they were
+/// registered. A later hook sees the href as rewritten by earlier hooks, and the first `Skip` wins.
+pub type LinkHook = dyn Fn(&Href<'_>, &Document, LinkOrigin) -> LinkDecision + Send + Sync;
+
+/// A callback that sees every used link after the `LinkHook`s, with the href as they left it.
+///
+/// Unlike hooks, callbacks may keep state. Each one is called once per link, one link at a time, in
+/// no particular order, while the parsing threads wait for it, so it should be quick. When a
+/// document is parsed again, e.g. for `--sources`, its decisions are replayed, see `HookedLink`.
+pub type LinkCallback = dyn FnMut(&UsedLink<'_, VoidParagraph>) -> LinkDecision + Send;
+
+/// Settings that control what is extracted from a document.
+#[derive(Clone, Default)]
+pub struct ParseOptions {
+    /// Keep fragments on used links and collect `id`s as defined links.
+    pub check_anchors: bool,
+    /// With `check_anchors`, keep fragments on used links but do not collect `id`s, because they
+    /// are already known from `--anchors-index`.
+    pub skip_ids: bool,
+    /// Record the text of each `<a>` and the sentence around it.
+    pub link_text: bool,
+    /// What goes into paragraph hashes, see `ParagraphOptions`.
+    pub paragraphs: ParagraphOptions,
+    /// Emit `<link rel=alternate hreflang=...>` elements as `Link::Alternate`.
+    pub check_hreflang: bool,
+    /// Emit `<link rel=canonical>` elements as `Link::Canonical`.
+    pub check_canonical: bool,
+    /// Emit `<script>` and `<link>` elements with an `integrity` attribute as `Link::Integrity`.
+    pub check_integrity: bool,
+    /// With `check_anchors`, how fragments and ids are compared.
+    pub fragment_encoding: FragmentEncoding,
+    /// With `check_anchors`, emit the ids in `aria-labelledby` and `aria-describedby` as used
+    /// links to anchors in the same document.
+    pub check_aria: bool,
+    /// Also extract URLs from `.txt` files.
+    pub check_text_files: bool,
+    /// Also extract URLs from the visible text of HTML pages, outside of `<a>`, `<script>` and
+    /// `<style>`. They are emitted with `LinkOrigin::TextHeuristic`.
+    pub check_page_text: bool,
+    /// Keys whose values are extracted from `.json`, `.yaml` and `.yml` files.
+    pub data_keys: Vec<&'static str>,
+    /// Also extract links from the HTML embedded in Atom, RSS and JSON feeds, see `feeds`.
+    pub check_feeds: bool,
+    /// Absolute URLs starting with this are checked like root-relative links. No trailing slash.
+    pub base_url: Option<String>,
+    /// Additional attributes to check, from `--extract`.
+    pub extract_rules: Vec<ExtractRule>,
+    /// Attributes to check in XML files such as OPML blogrolls and feeds, from `--xml-extract`.
+    /// Nothing else is extracted from XML files.
+    pub xml_rules: Vec<ExtractRule>,
+    /// Invoked for every used link, see `LinkHook`.
+    pub link_hooks: Vec<Arc<LinkHook>>,
+    /// Invoked for every used link after `link_hooks`, see `LinkCallback`.
+    pub link_callbacks: Vec<Arc<Mutex<LinkCallback>>>,
+    /// Lints to run, from `--warn`. Problems are emitted as `Link::Lint`.
+    pub lints: Vec<Lint>,
+    /// Limits for `Lint::LongPaths`.
+    pub path_limits: PathLimits,
+    /// Skip links on elements with any of these `rel` values, from `--skip-rel`. Links on
+    /// elements with `data-hyperlink="ignore"` are always skipped.
+    pub skip_rels: Vec<String>,
+    /// Report broken links on elements with any of these `rel` values as warnings, from
+    /// `--warn-rel`. So are links on elements with `data-hyperlink="warn"`.
+    pub warn_rels: Vec<String>,
+    /// With `check_anchors`, ids and `<a name>`s matching any of these are not collected, from
+    /// `--ignore-id`.
+    pub ignored_ids: Option<RegexSet>,
+    /// Emit documents that fail to parse as `Link::Unparseable` instead of returning an error.
+    pub report_unparseable: bool,
+    /// With `report_unparseable`, still extract what can be found in the unparseable document.
+    pub recover_unparseable: bool,
+    /// Emit empty files and HTML documents containing any of `placeholder_markers` as
+    /// `Link::Placeholder`. Non-HTML files are only looked up, not read.
+    pub check_placeholders: bool,
+    /// Text that marks an HTML document as a placeholder, e.g. `Coming soon`, from
+    /// `--placeholder-marker`.
+    pub placeholder_markers: Vec<String>,
+    /// Text that marks an HTML document as a copy of the site's 404 page, from
+    /// `--soft-404-marker`. Such documents are emitted as `Link::Placeholder` even without
+    /// `check_placeholders`.
+    pub soft_404_markers: Vec<String>,
+}
+
+impl ParseOptions {
+    pub(crate) fn lint_enabled(&self, lint: Lint) -> bool {
+        self.lints.contains(&lint)
+    }
+
+    /// Whether HTML documents are looked at as a whole for `Link::Placeholder`.
+    pub(crate) fn detect_placeholders(&self) -> bool {
+        self.check_placeholders || !self.soft_404_markers.is_empty()
+    }
+
+    /// Whether `id`s and `<a name>`s are collected as defined links. If not, they are not even
+    /// looked at, unless `Lint::BadIds` is enabled.
+    pub(crate) fn collects_ids(&self) -> bool {
+        self.check_anchors && !self.skip_ids
+    }
+
+    /// How `Document::join` keeps the fragments of used links: not at all without
+    /// `check_anchors`.
+    pub(crate) fn used_fragments(&self) -> Option<FragmentEncoding> {
+        self.check_anchors.then_some(self.fragment_encoding)
+    }
+
+    /// Whether the links of a document can be replayed for a byte-identical document elsewhere in
+    /// the site, see `Document::record_links`. `Lint::EscapesRoot` depends on where the document
+    /// is.
+    pub fn can_replay_links(&self) -> bool {
+        !self.lint_enabled(Lint::EscapesRoot)
+    }
+
+    /// The `--extract` rules, or the `--xml-extract` rules for XML files.
+    pub(crate) fn extract_rules_for(&self, xml: bool) -> &[ExtractRule] {
+        if xml {
+            &self.xml_rules
+        } else {
+            &self.extract_rules
+        }
+    }
+
+    /// Turn an absolute link to `base_url` into a root-relative one.
+    pub(crate) fn strip_base_url<'v>(&self, href: &'v str) -> &'v str {
+        let base_url = match self.base_url {
+            Some(ref base_url) => base_url,
+            None => return href,
+        };
+
+        match href.strip_prefix(base_url.as_str()) {
+            Some("") => "/",
+            Some(path) if path.starts_with('/') => path,
+            _ => href,
+        }
+    }
+}
+
+/// The visible text of a link and the sentence it appears in.
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd)]
+pub struct LinkText {
+    pub text: String,
+    /// `None` if the link is not part of a paragraph, or the sentence is just the link text.
+    pub sentence: Option<String>,
+}
+
+impl fmt::Display for LinkText {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "{:?}", self.text)?;
+        if let Some(ref sentence) = self.sentence {
+            write!(fmt, " in {sentence:?}")?;
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd)]
 pub struct UsedLink<'a, P> {
     pub href: Href<'a>,
     pub path: Arc<PathBuf>,
     pub paragraph: Option<P>,
+    pub origin: LinkOrigin,
+    pub text: Option<Arc<LinkText>>,
+    /// Marked in the markup to be reported as a warning if broken, see `ParseOptions::warn_rels`.
+    pub warn_only: bool,
 }
 
 #[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd)]
 pub struct DefinedLink<'a> {
     pub href: Href<'a>,
+    /// Defined by a rule in `_redirects` rather than by a file or an anchor.
+    pub is_redirect: bool,
+    /// The target of the rule in `_redirects`, unless it is external.
+    pub redirect_to: Option<Href<'a>>,
+    /// The file defining the href, `None` for hrefs that are only listed, e.g. by
+    /// `--extra-anchors`.
+    pub path: Option<Arc<PathBuf>>,
+    /// The element whose `id` or `name` defines an anchor, e.g. `h2`.
+    pub element: Option<&'a str>,
+}
+
+/// A `<link rel=alternate hreflang=...>` pointing at a local page.
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd)]
+pub struct AlternateLink<'a> {
+    /// The href of the document containing the element.
+    pub document: Href<'a>,
+    pub path: Arc<PathBuf>,
+    pub hreflang: &'a str,
+    pub href: Href<'a>,
+}
+
+/// A `<link rel=canonical>` pointing at a local page.
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd)]
+pub struct CanonicalLink<'a> {
+    /// The href of the document containing the element.
+    pub document: Href<'a>,
+    pub path: Arc<PathBuf>,
+    pub href: Href<'a>,
+}
+
+/// A `<script>` or `<link>` with an `integrity` attribute, pointing at a local file.
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd)]
+pub struct IntegrityLink<'a> {
+    pub path: Arc<PathBuf>,
+    pub href: Href<'a>,
+    pub integrity: &'a str,
+}
+
+/// A document that could not be parsed, see `ParseOptions::report_unparseable`.
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd)]
+pub struct UnparseableDocument {
+    pub path: Arc<PathBuf>,
+    pub message: String,
+    /// Byte offset of the error in the file.
+    pub offset: usize,
+}
+
+/// Why a document is a placeholder rather than the page its links expect, see
+/// `ParseOptions::check_placeholders`.
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd)]
+pub enum Placeholder {
+    /// The file has zero bytes.
+    Empty,
+    /// The document contains this marker from `ParseOptions::placeholder_markers`.
+    Marker(String),
+    /// The document contains this marker from `ParseOptions::soft_404_markers`, so it is the
+    /// site's 404 page served under another name. Links to it are broken, not only warned about.
+    Soft404(String),
+}
+
+impl Placeholder {
+    /// Whether `contents` are a placeholder, see `ParseOptions::check_placeholders`. A soft 404
+    /// wins over the other kinds.
+    fn of(options: &ParseOptions, contents: &[u8]) -> Option<Placeholder> {
+        let text = String::from_utf8_lossy(contents);
+        let find_marker = |markers: &[String]| {
+            markers
+                .iter()
+                .find(|marker| text.contains(marker.as_str()))
+                .cloned()
+        };
+
+        if let Some(marker) = find_marker(&options.soft_404_markers) {
+            return Some(Placeholder::Soft404(marker));
+        }
+        if !options.check_placeholders {
+            return None;
+        }
+        if contents.is_empty() {
+            return Some(Placeholder::Empty);
+        }
+        find_marker(&options.placeholder_markers).map(Placeholder::Marker)
+    }
+
+    pub fn is_soft_404(&self) -> bool {
+        matches!(self, Placeholder::Soft404(_))
+    }
+}
+
+impl fmt::Display for Placeholder {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Placeholder::Empty => write!(fmt, "the file is empty"),
+            Placeholder::Marker(marker) => write!(fmt, "the page contains {marker:?}"),
+            Placeholder::Soft404(marker) => {
+                write!(fmt, "the page is a soft 404, it contains {marker:?}")
+            }
+        }
+    }
+}
+
+/// A document that exists, but is a placeholder, see `ParseOptions::check_placeholders`.
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd)]
+pub struct PlaceholderDocument<'a> {
+    /// The href of the document.
+    pub document: Href<'a>,
+    pub placeholder: Placeholder,
+}
+
+/// What the `link_hooks` and `link_callbacks` made of a used link that they did not leave alone.
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd)]
+pub enum HookOutcome {
+    Skip,
+    /// Check the link, with this href instead if there is one, and at this level.
+    Check {
+        href: Option<String>,
+        warn_only: bool,
+    },
+}
+
+/// A used link that was skipped, rewritten or reclassified by the hooks, see
+/// `Document::with_hooked_links`.
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd)]
+pub struct HookedLink {
+    pub path: Arc<PathBuf>,
+    /// The position of the link among the used links of its document.
+    pub index: u32,
+    pub outcome: HookOutcome,
+}
+
+/// A problem found by one of `ParseOptions::lints`.
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd)]
+pub struct LintWarning {
+    pub path: Arc<PathBuf>,
+    pub lint: Lint,
+    pub message: String,
 }
 
 #[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd)]
 pub enum Link<'a, P> {
     Uses(UsedLink<'a, P>),
     Defines(DefinedLink<'a>),
+    /// Only emitted with `ParseOptions::check_hreflang`. The href is emitted as a used link too.
+    Alternate(AlternateLink<'a>),
+    /// Only emitted with `ParseOptions::check_canonical`. The href is emitted as a used link too.
+    Canonical(CanonicalLink<'a>),
+    /// Only emitted with `ParseOptions::check_integrity`. The href is emitted as a used link too.
+    Integrity(IntegrityLink<'a>),
+    /// Only emitted for lints enabled in `ParseOptions::lints`.
+    Lint(LintWarning),
+    /// Only emitted with `ParseOptions::report_unparseable`.
+    Unparseable(UnparseableDocument),
+    /// Only emitted with `ParseOptions::check_placeholders` or `soft_404_markers`, before the `Link::Defines` of the
+    /// document itself.
+    Placeholder(PlaceholderDocument<'a>),
+    /// A used link that is not checked. Links to other sites are emitted as used links, and turned
+    /// into this by `LocalLinksOnly`.
+    Skipped(SkipReason),
+    /// Only emitted while `ParseOptions::link_hooks` or `link_callbacks` run, before the link that
+    /// they turned a used link into.
+    Hooked(HookedLink),
+}
+
+/// An owned link with its href as written in the document, see `RecordedLinks`.
+enum RecordedLink<P> {
+    Uses {
+        href: String,
+        paragraph: Option<P>,
+        origin: LinkOrigin,
+        text: Option<Arc<LinkText>>,
+        warn_only: bool,
+    },
+    Defines {
+        href: String,
+        element: Option<String>,
+    },
+    Alternate {
+        hreflang: String,
+        href: String,
+    },
+    Canonical(String),
+    Integrity {
+        href: String,
+        integrity: String,
+    },
+    Lint(Lint, String),
+    Unparseable(String, usize),
+    Placeholder(Placeholder),
+    Skipped(SkipReason),
+}
+
+/// The links of an HTML document, recorded by `Document::record_links` so that they can be
+/// replayed for byte-identical documents without parsing them again.
+pub struct RecordedLinks<P> {
+    links: Vec<RecordedLink<P>>,
 }
 
 impl<P> Link<'_, P> {
     pub fn into_paragraph(self) -> Option<P> {
         match self {
             Link::Uses(UsedLink { paragraph, .. }) => paragraph,
-            Link::Defines(_) => None,
+            Link::Defines(_)
+            | Link::Alternate(_)
+            | Link::Canonical(_)
+            | Link::Integrity(_)
+            | Link::Lint(_)
+            | Link::Unparseable(_)
+            | Link::Placeholder(_)
+            | Link::Skipped(_)
+            | Link::Hooked(_) => None,
         }
     }
 }
@@ -229,16 +832,35 @@ impl Default for DocumentBuffers {
 }
 
 impl DocumentBuffers {
+    /// Memory held by the arena, which grows to fit the largest document parsed so far.
+    pub fn arena_bytes(&self) -> usize {
+        self.arena.allocated_bytes()
+    }
+
     pub fn reset(&mut self) {
         self.arena.reset();
         self.parser_buffers.reset();
     }
 }
 
+/// Whether `path` has the extension of an HTML file, like `page.html` or `PAGE.HTM`. Names like
+/// `page.html.bak` are not HTML files.
+pub fn is_html_path(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| {
+            extension.eq_ignore_ascii_case("html") || extension.eq_ignore_ascii_case("htm")
+        })
+}
+
 pub struct Document {
     pub path: Arc<PathBuf>,
     href: String,
     pub is_index_html: bool,
+    /// How many used links have been passed through `run_link_hooks`.
+    used_links: Cell<u32>,
+    /// Decisions of an earlier parse to replay instead of running the hooks, by `HookedLink::index`.
+    hooked_links: Vec<(u32, HookOutcome)>,
 }
 
 impl Document {
@@ -275,9 +897,22 @@ impl Document {
             path: Arc::new(path.to_owned()),
             href,
             is_index_html,
+            used_links: Cell::new(0),
+            hooked_links: Vec::new(),
         }
     }
 
+    /// Apply what the hooks did to the used links of this document in an earlier parse, instead
+    /// of running the hooks again. `hooked_links` are the indices and outcomes of its
+    /// `Link::Hooked`, sorted by index.
+    ///
+    /// Parsing a document again must not call stateful `link_callbacks` a second time, but the
+    /// links need to come out the same way, e.g. to match them up with broken links.
+    pub fn with_hooked_links(mut self, hooked_links: Vec<(u32, HookOutcome)>) -> Self {
+        self.hooked_links = hooked_links;
+        self
+    }
+
     pub fn href(&self) -> Href<'_> {
         Href(&self.href)
     }
@@ -285,7 +920,7 @@ impl Document {
     fn join<'b>(
         &self,
         arena: &'b bumpalo::Bump,
-        preserve_anchor: bool,
+        fragments: Option<FragmentEncoding>,
         rel_href: &str,
     ) -> Href<'b> {
         let qs_start = rel_href.find(&['?', '#'][..]).unwrap_or(rel_href.len());
@@ -298,44 +933,390 @@ impl Document {
 
         push_and_canonicalize(&mut href, &try_percent_decode(&rel_href[..qs_start]));
 
-        if preserve_anchor {
+        if let Some(fragments) = fragments {
             let anchor = &rel_href[anchor_start..];
             if anchor.len() > 1 {
-                href.push_str(&try_percent_decode(anchor));
+                href.push_str(&fragments.normalize(anchor));
             }
         }
 
         Href(href.into_bump_str())
     }
 
-    pub fn extract_links<'b, 'l, P: ParagraphWalker, F>(
+    /// The directory that relative links are resolved against, without leading slash.
+    fn directory(&self) -> &str {
+        if self.is_index_html {
+            &self.href
+        } else {
+            self.href.rfind('/').map_or("", |i| &self.href[..i])
+        }
+    }
+
+    /// Whether this document is parsed as HTML, as opposed to `_redirects` or PDF files.
+    pub fn is_html(&self) -> bool {
+        is_html_path(&self.path)
+    }
+
+    /// Whether this document is an XML file that `ParseOptions::xml_rules` and `check_feeds` apply
+    /// to.
+    fn is_xml(&self) -> bool {
+        self.path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .is_some_and(|extension| {
+                ["xml", "opml", "rss", "atom"]
+                    .iter()
+                    .any(|xml| extension.eq_ignore_ascii_case(xml))
+            })
+    }
+
+    /// Like `extract_links`, but parse `contents` instead of reading the file again. `contents`
+    /// must have been read from an HTML document.
+    pub fn extract_links_from_bytes<P: ParagraphWalker, F>(
         &self,
-        doc_buf: &'b mut DocumentBuffers,
-        check_anchors: bool,
+        doc_buf: &mut DocumentBuffers,
+        options: &ParseOptions,
+        contents: &[u8],
+        mut callback: F,
+    ) -> Result<(), Error>
+    where
+        F: FnMut(Link<'_, P::Paragraph>),
+    {
+        self.lint_path(options, &mut callback);
+        self.check_placeholder(options, contents, &mut callback);
+
+        for link in self.links_from_read::<_, P>(doc_buf, contents, options)? {
+            self.run_link_hooks(options, link, &mut callback);
+        }
+        Ok(())
+    }
+
+    /// Like `extract_links_from_bytes`, but also return the links for `replay_links`.
+    pub fn record_links<P: ParagraphWalker, F>(
+        &self,
+        doc_buf: &mut DocumentBuffers,
+        options: &ParseOptions,
+        contents: &[u8],
+        callback: F,
+    ) -> Result<RecordedLinks<P::Paragraph>, Error>
+    where
+        F: FnMut(Link<'_, P::Paragraph>),
+    {
+        let mut links = self
+            .tokenize::<_, P>(doc_buf, contents, options, false, true)?
+            .map(|link| match link {
+                Link::Uses(used_link) => RecordedLink::Uses {
+                    href: used_link.href.0.to_owned(),
+                    paragraph: used_link.paragraph,
+                    origin: used_link.origin,
+                    text: used_link.text,
+                    warn_only: used_link.warn_only,
+                },
+                Link::Defines(defined_link) => RecordedLink::Defines {
+                    href: defined_link.href.0.to_owned(),
+                    element: defined_link.element.map(str::to_owned),
+                },
+                Link::Alternate(alternate_link) => RecordedLink::Alternate {
+                    hreflang: alternate_link.hreflang.to_owned(),
+                    href: alternate_link.href.0.to_owned(),
+                },
+                Link::Canonical(canonical_link) => {
+                    RecordedLink::Canonical(canonical_link.href.0.to_owned())
+                }
+                Link::Integrity(integrity_link) => RecordedLink::Integrity {
+                    href: integrity_link.href.0.to_owned(),
+                    integrity: integrity_link.integrity.to_owned(),
+                },
+                Link::Lint(warning) => RecordedLink::Lint(warning.lint, warning.message),
+                Link::Unparseable(unparseable) => {
+                    RecordedLink::Unparseable(unparseable.message, unparseable.offset)
+                }
+                Link::Placeholder(document) => RecordedLink::Placeholder(document.placeholder),
+                Link::Skipped(reason) => RecordedLink::Skipped(reason),
+                Link::Hooked(_) => unreachable!("hooks run when the links are replayed"),
+            })
+            .collect::<Vec<_>>();
+        if options.detect_placeholders() {
+            links.extend(Placeholder::of(options, contents).map(RecordedLink::Placeholder));
+        }
+
+        let recorded = RecordedLinks { links };
+        self.replay_links(doc_buf, options, &recorded, callback);
+        Ok(recorded)
+    }
+
+    /// Emit the links that `record_links` found in a byte-identical document, as if this document
+    /// had been parsed. Relative hrefs are joined with this document.
+    pub fn replay_links<P: Clone, F>(
+        &self,
+        doc_buf: &DocumentBuffers,
+        options: &ParseOptions,
+        recorded: &RecordedLinks<P>,
+        mut callback: F,
+    ) where
+        F: FnMut(Link<'_, P>),
+    {
+        let arena = &doc_buf.arena;
+        self.lint_path(options, &mut callback);
+
+        for link in &recorded.links {
+            let link = match link {
+                RecordedLink::Uses {
+                    href,
+                    paragraph,
+                    origin,
+                    text,
+                    warn_only,
+                } => Link::Uses(UsedLink {
+                    href: self.join(arena, options.used_fragments(), href),
+                    path: self.path.clone(),
+                    paragraph: paragraph.clone(),
+                    origin: *origin,
+                    text: text.clone(),
+                    warn_only: *warn_only,
+                }),
+                RecordedLink::Defines { href, element } => Link::Defines(DefinedLink {
+                    href: self.join(arena, options.used_fragments(), href),
+                    is_redirect: false,
+                    redirect_to: None,
+                    path: Some(self.path.clone()),
+                    element: element.as_deref(),
+                }),
+                RecordedLink::Alternate { hreflang, href } => Link::Alternate(AlternateLink {
+                    document: self.href(),
+                    path: self.path.clone(),
+                    hreflang,
+                    href: self.join(arena, None, href),
+                }),
+                RecordedLink::Canonical(href) => Link::Canonical(CanonicalLink {
+                    document: self.href(),
+                    path: self.path.clone(),
+                    href: self.join(arena, None, href),
+                }),
+                RecordedLink::Integrity { href, integrity } => Link::Integrity(IntegrityLink {
+                    path: self.path.clone(),
+                    href: self.join(arena, None, href),
+                    integrity,
+                }),
+                RecordedLink::Lint(lint, message) => Link::Lint(LintWarning {
+                    path: self.path.clone(),
+                    lint: *lint,
+                    message: message.clone(),
+                }),
+                RecordedLink::Unparseable(message, offset) => {
+                    Link::Unparseable(UnparseableDocument {
+                        path: self.path.clone(),
+                        message: message.clone(),
+                        offset: *offset,
+                    })
+                }
+                RecordedLink::Placeholder(placeholder) => Link::Placeholder(PlaceholderDocument {
+                    document: self.href(),
+                    placeholder: placeholder.clone(),
+                }),
+                RecordedLink::Skipped(reason) => Link::Skipped(*reason),
+            };
+            self.run_link_hooks(options, link, &mut callback);
+        }
+    }
+
+    /// Run the lints that look at the value of a used link, before it is joined with the path of
+    /// the document.
+    pub(crate) fn lint_href(
+        &self,
+        options: &ParseOptions,
+        href: &str,
+        origin: LinkOrigin,
+    ) -> Vec<LintWarning> {
+        options
+            .lints
+            .iter()
+            .filter_map(|&lint| {
+                let message =
+                    lints::check_href(lint, self.directory(), href, options.base_url.as_deref())?;
+                Some(LintWarning {
+                    path: self.path.clone(),
+                    lint,
+                    message: format!("{message} ({origin})"),
+                })
+            })
+            .collect()
+    }
+
+    /// Run the lints that only look at the path of the document.
+    fn lint_path<P, F>(&self, options: &ParseOptions, callback: &mut F)
+    where
+        F: FnMut(Link<'_, P>),
+    {
+        let file_name = self
+            .path
+            .file_name()
+            .and_then(|file_name| file_name.to_str())
+            .unwrap_or("");
+        let mut warn = |lint, message| {
+            callback(Link::Lint(LintWarning {
+                path: self.path.clone(),
+                lint,
+                message,
+            }))
+        };
+
+        if options.lint_enabled(Lint::HostileFilenames) {
+            if let Some(message) = lints::hostile_filename(file_name) {
+                warn(Lint::HostileFilenames, message);
+            }
+        }
+
+        if options.lint_enabled(Lint::BackupFiles) {
+            if let Some(message) = lints::backup_file(file_name) {
+                warn(Lint::BackupFiles, message);
+            }
+        }
+
+        if options.lint_enabled(Lint::LongPaths) {
+            // the href of an index.html is its directory, but the file still has to be synced
+            let path = if self.is_index_html && !self.href.is_empty() {
+                Cow::Owned(format!("{}/{}", self.href, file_name))
+            } else if self.is_index_html {
+                Cow::Borrowed(file_name)
+            } else {
+                Cow::Borrowed(self.href.as_str())
+            };
+
+            if let Some(message) = lints::long_path(&path, &options.path_limits) {
+                warn(Lint::LongPaths, message);
+            }
+        }
+    }
+
+    /// Emit this document as `Link::Placeholder` if `contents` are a placeholder.
+    fn check_placeholder<P, F>(&self, options: &ParseOptions, contents: &[u8], callback: &mut F)
+    where
+        F: FnMut(Link<'_, P>),
+    {
+        if !options.detect_placeholders() {
+            return;
+        }
+        if let Some(placeholder) = Placeholder::of(options, contents) {
+            callback(Link::Placeholder(PlaceholderDocument {
+                document: self.href(),
+                placeholder,
+            }));
+        }
+    }
+
+    pub fn extract_links<P: ParagraphWalker, F>(
+        &self,
+        doc_buf: &mut DocumentBuffers,
+        options: &ParseOptions,
         mut callback: F,
     ) -> Result<bool, Error>
     where
-        'b: 'l,
-        F: FnMut(Link<'l, P::Paragraph>),
+        F: FnMut(Link<'_, P::Paragraph>),
     {
+        self.lint_path(options, &mut callback);
+
+        if options.check_placeholders && !self.is_html() && fs::metadata(&*self.path)?.len() == 0 {
+            callback(Link::Placeholder(PlaceholderDocument {
+                document: self.href(),
+                placeholder: Placeholder::Empty,
+            }));
+        }
+
         if self.href == "_redirects" {
-            for link in self.parse_redirects::<P>(doc_buf, check_anchors)? {
-                callback(link);
+            for link in self.parse_redirects::<P>(doc_buf, options)? {
+                self.run_link_hooks(options, link, &mut callback);
+            }
+            return Ok(true);
+        }
+
+        if self.is_html() && options.detect_placeholders() {
+            let contents = fs::read(&*self.path)?;
+            self.check_placeholder(options, &contents, &mut callback);
+            for link in self.links_from_read::<_, P>(doc_buf, &contents[..], options)? {
+                self.run_link_hooks(options, link, &mut callback);
+            }
+            return Ok(true);
+        }
+
+        if self.is_html() {
+            for link in self.links_from_html::<P>(doc_buf, options)? {
+                self.run_link_hooks(options, link, &mut callback);
             }
             return Ok(true);
         }
 
-        if self
+        if options.check_text_files
+            && self
+                .path
+                .extension()
+                .is_some_and(|extension| extension.eq_ignore_ascii_case("txt"))
+        {
+            for link in self.links_from_text::<P>(doc_buf, options)? {
+                self.run_link_hooks(options, link, &mut callback);
+            }
+            return Ok(true);
+        }
+
+        if (!options.xml_rules.is_empty() || options.check_feeds) && self.is_xml() {
+            if !options.xml_rules.is_empty() {
+                for link in self.links_from_xml::<P>(doc_buf, options)? {
+                    self.run_link_hooks(options, link, &mut callback);
+                }
+            }
+            if options.check_feeds {
+                let contents = fs::read(&*self.path)?;
+                for html in feeds::xml_feed_contents(&String::from_utf8_lossy(&contents)) {
+                    self.extract_feed_html::<P, F>(doc_buf, options, &html, &mut callback)?;
+                }
+            }
+            return Ok(true);
+        }
+
+        let extension = self
             .path
             .extension()
-            .and_then(|extension| {
-                let ext = extension.to_str()?;
-                Some(ext == "html" || ext == "htm")
-            })
-            .unwrap_or(false)
+            .and_then(|extension| extension.to_str())
+            .map(str::to_ascii_lowercase);
+
+        if options.check_feeds && extension.as_deref() == Some("json") {
+            if let Some(contents) = feeds::json_feed_contents(&fs::read(&*self.path)?) {
+                for html in contents {
+                    self.extract_feed_html::<P, F>(doc_buf, options, &html, &mut callback)?;
+                }
+                if options.data_keys.is_empty() {
+                    return Ok(true);
+                }
+            }
+        }
+
+        if !options.data_keys.is_empty() {
+            if let Some(extension @ ("json" | "yaml" | "yml")) = extension.as_deref() {
+                for link in self.links_from_data::<P>(doc_buf, options, extension == "json")? {
+                    self.run_link_hooks(options, link, &mut callback);
+                }
+                return Ok(true);
+            }
+        }
+
+        #[cfg(feature = "pdf")]
+        if options.collects_ids()
+            && self
+                .path
+                .extension()
+                .is_some_and(|extension| extension.eq_ignore_ascii_case("pdf"))
         {
-            for link in self.links_from_html::<P>(doc_buf, check_anchors)? {
-                callback(link);
+            for anchor in crate::pdf::pdf_anchors(&self.path)? {
+                let mut href = BumpString::new_in(&doc_buf.arena);
+                href.push('#');
+                href.push_str(&anchor);
+                callback(Link::Defines(DefinedLink {
+                    href: self.join(&doc_buf.arena, options.used_fragments(), &href),
+                    is_redirect: false,
+                    redirect_to: None,
+                    path: Some(self.path.clone()),
+                    element: None,
+                }));
             }
             return Ok(true);
         }
@@ -343,32 +1324,152 @@ impl Document {
         Ok(false)
     }
 
+    /// Extract links from `html`, a post embedded in this feed, as if it was part of an HTML page
+    /// at the feed's location.
+    fn extract_feed_html<P: ParagraphWalker, F>(
+        &self,
+        doc_buf: &mut DocumentBuffers,
+        options: &ParseOptions,
+        html: &str,
+        callback: &mut F,
+    ) -> Result<(), Error>
+    where
+        F: FnMut(Link<'_, P::Paragraph>),
+    {
+        for link in self.links_from_read::<_, P>(doc_buf, html.as_bytes(), options)? {
+            self.run_link_hooks(options, link, callback);
+        }
+        Ok(())
+    }
+
+    fn run_link_hooks<P, F>(&self, options: &ParseOptions, link: Link<'_, P>, callback: &mut F)
+    where
+        F: FnMut(Link<'_, P>),
+    {
+        let used_link = match link {
+            Link::Uses(used_link)
+                if !options.link_hooks.is_empty()
+                    || !options.link_callbacks.is_empty()
+                    || !self.hooked_links.is_empty() =>
+            {
+                used_link
+            }
+            link => return callback(link),
+        };
+        let index = self.used_links.get();
+        self.used_links.set(index + 1);
+
+        let outcome = if self.hooked_links.is_empty() {
+            let outcome = self.hook_outcome(options, &used_link);
+            if let Some(ref outcome) = outcome {
+                callback(Link::Hooked(HookedLink {
+                    path: used_link.path.clone(),
+                    index,
+                    outcome: outcome.clone(),
+                }));
+            }
+            outcome
+        } else {
+            self.hooked_links
+                .binary_search_by_key(&index, |(hooked, _)| *hooked)
+                .ok()
+                .map(|i| self.hooked_links[i].1.clone())
+        };
+
+        match outcome {
+            None => callback(Link::Uses(used_link)),
+            Some(HookOutcome::Skip) => callback(Link::Skipped(SkipReason::Ignored)),
+            Some(HookOutcome::Check {
+                href: Some(ref href),
+                warn_only,
+            }) => callback(Link::Uses(UsedLink {
+                href: Href(href),
+                warn_only,
+                ..used_link
+            })),
+            Some(HookOutcome::Check {
+                href: None,
+                warn_only,
+            }) => callback(Link::Uses(UsedLink {
+                warn_only,
+                ..used_link
+            })),
+        }
+    }
+
+    /// Run the hooks and callbacks on `used_link`. `None` if they left it alone.
+    fn hook_outcome<P>(
+        &self,
+        options: &ParseOptions,
+        used_link: &UsedLink<'_, P>,
+    ) -> Option<HookOutcome> {
+        let mut rewritten = None;
+        let mut warn_only = used_link.warn_only;
+        for hook in &options.link_hooks {
+            let href = Href(rewritten.as_deref().unwrap_or(used_link.href.0));
+            if !hook(&href, self, used_link.origin).apply(&mut rewritten, &mut warn_only) {
+                return Some(HookOutcome::Skip);
+            }
+        }
+
+        for link_callback in &options.link_callbacks {
+            let decision = link_callback.lock().unwrap()(&UsedLink {
+                href: Href(rewritten.as_deref().unwrap_or(used_link.href.0)),
+                path: used_link.path.clone(),
+                paragraph: None,
+                origin: used_link.origin,
+                text: used_link.text.clone(),
+                warn_only,
+            });
+            if !decision.apply(&mut rewritten, &mut warn_only) {
+                return Some(HookOutcome::Skip);
+            }
+        }
+
+        (rewritten.is_some() || warn_only != used_link.warn_only).then_some(HookOutcome::Check {
+            href: rewritten,
+            warn_only,
+        })
+    }
+
     pub fn links<'b, 'l, P: ParagraphWalker>(
         &self,
         doc_buf: &'b mut DocumentBuffers,
-        check_anchors: bool,
+        options: &ParseOptions,
     ) -> Result<impl Iterator<Item = Link<'l, P::Paragraph>>, Error>
     where
         'b: 'l,
     {
-        self.links_from_read::<_, P>(doc_buf, fs::File::open(&*self.path)?, check_anchors)
+        self.links_from_read::<_, P>(doc_buf, fs::File::open(&*self.path)?, options)
     }
 
     fn links_from_html<'b, 'l, P: ParagraphWalker>(
         &self,
         doc_buf: &'b mut DocumentBuffers,
-        check_anchors: bool,
+        options: &ParseOptions,
+    ) -> Result<impl Iterator<Item = Link<'l, P::Paragraph>>, Error>
+    where
+        'b: 'l,
+    {
+        self.links_from_read::<_, P>(doc_buf, fs::File::open(&*self.path)?, options)
+    }
+
+    fn links_from_xml<'b, 'l, P: ParagraphWalker>(
+        &self,
+        doc_buf: &'b mut DocumentBuffers,
+        options: &ParseOptions,
     ) -> Result<impl Iterator<Item = Link<'l, P::Paragraph>>, Error>
     where
         'b: 'l,
     {
-        self.links_from_read::<_, P>(doc_buf, fs::File::open(&*self.path)?, check_anchors)
+        // html5gum copes with XML well enough to read attributes, it only lowercases their names
+        self.tokenize::<_, P>(doc_buf, fs::File::open(&*self.path)?, options, true, false)
     }
 
     fn parse_redirects<'b, 'l, P: ParagraphWalker>(
         &self,
         doc_buf: &'b mut DocumentBuffers,
-        check_anchors: bool,
+        options: &ParseOptions,
     ) -> Result<impl Iterator<Item = Link<'l, P::Paragraph>>, Error>
     where
         'b: 'l,
@@ -393,15 +1494,29 @@ impl Document {
                 let source_str = doc_buf.arena.alloc_str(source);
                 let target_str = doc_buf.arena.alloc_str(target);
 
+                let target_str = options.strip_base_url(target_str);
+                let target = if is_external_link(target_str.as_bytes()) {
+                    None
+                } else {
+                    Some(self.join(&doc_buf.arena, options.used_fragments(), target_str))
+                };
+
                 link_buf.push(Link::Defines(DefinedLink {
-                    href: self.join(&doc_buf.arena, check_anchors, source_str),
+                    href: self.join(&doc_buf.arena, options.used_fragments(), source_str),
+                    is_redirect: true,
+                    redirect_to: target.clone(),
+                    path: Some(self.path.clone()),
+                    element: None,
                 }));
 
-                if !is_external_link(target.as_bytes()) {
+                if let Some(target) = target {
                     link_buf.push(Link::Uses(UsedLink {
-                        href: self.join(&doc_buf.arena, check_anchors, target_str),
+                        href: target,
                         path: self.path.clone(),
                         paragraph: None,
+                        origin: LinkOrigin::Redirect,
+                        text: None,
+                        warn_only: false,
                     }));
                 }
             }
@@ -410,11 +1525,109 @@ impl Document {
         Ok(link_buf.into_iter())
     }
 
+    fn links_from_text<'b, 'l, P: ParagraphWalker>(
+        &self,
+        doc_buf: &'b mut DocumentBuffers,
+        options: &ParseOptions,
+    ) -> Result<impl Iterator<Item = Link<'l, P::Paragraph>>, Error>
+    where
+        'b: 'l,
+    {
+        let mut link_buf = BumpVec::new_in(&doc_buf.arena);
+        let contents = fs::read(&*self.path)?;
+        let contents = String::from_utf8_lossy(&contents);
+
+        for url in text_urls(&contents) {
+            let url = options.strip_base_url(doc_buf.arena.alloc_str(url));
+            if is_external_link(url.as_bytes()) {
+                continue;
+            }
+
+            link_buf.push(Link::Uses(UsedLink {
+                href: self.join(&doc_buf.arena, options.used_fragments(), url),
+                path: self.path.clone(),
+                paragraph: None,
+                origin: LinkOrigin::Text,
+                text: None,
+                warn_only: false,
+            }));
+        }
+
+        Ok(link_buf.into_iter())
+    }
+
+    fn links_from_data<'b, 'l, P: ParagraphWalker>(
+        &self,
+        doc_buf: &'b mut DocumentBuffers,
+        options: &ParseOptions,
+        is_json: bool,
+    ) -> Result<impl Iterator<Item = Link<'l, P::Paragraph>>, Error>
+    where
+        'b: 'l,
+    {
+        let contents = fs::read(&*self.path)?;
+        let mut link_buf = BumpVec::new_in(&doc_buf.arena);
+        let urls = if is_json {
+            match data::json_urls(&contents, &options.data_keys) {
+                Ok(urls) => urls,
+                Err(error) if options.report_unparseable => {
+                    link_buf.push(Link::Unparseable(UnparseableDocument {
+                        path: self.path.clone(),
+                        offset: data::json_error_offset(&contents, &error),
+                        message: error.to_string(),
+                    }));
+                    if options.recover_unparseable {
+                        // pretty-printed JSON has one key per line, which is all the YAML scanner
+                        // needs, and it carries on past the syntax error
+                        data::yaml_urls(&String::from_utf8_lossy(&contents), &options.data_keys)
+                    } else {
+                        Vec::new()
+                    }
+                }
+                Err(error) => return Err(error.into()),
+            }
+        } else {
+            data::yaml_urls(&String::from_utf8_lossy(&contents), &options.data_keys)
+        };
+
+        for (key, url) in urls {
+            let url = options.strip_base_url(doc_buf.arena.alloc_str(url.trim()));
+            if is_external_link(url.as_bytes()) {
+                continue;
+            }
+
+            link_buf.push(Link::Uses(UsedLink {
+                href: self.join(&doc_buf.arena, options.used_fragments(), url),
+                path: self.path.clone(),
+                paragraph: None,
+                origin: LinkOrigin::DataKey(key),
+                text: None,
+                warn_only: false,
+            }));
+        }
+
+        Ok(link_buf.into_iter())
+    }
+
     fn links_from_read<'b, 'l, R: Read, P: ParagraphWalker>(
         &self,
         doc_buf: &'b mut DocumentBuffers,
         read: R,
-        check_anchors: bool,
+        options: &ParseOptions,
+    ) -> Result<impl Iterator<Item = Link<'l, P::Paragraph>>, Error>
+    where
+        'b: 'l,
+    {
+        self.tokenize::<_, P>(doc_buf, read, options, false, false)
+    }
+
+    fn tokenize<'b, 'l, R: Read, P: ParagraphWalker>(
+        &self,
+        doc_buf: &'b mut DocumentBuffers,
+        read: R,
+        options: &ParseOptions,
+        xml: bool,
+        record: bool,
     ) -> Result<impl Iterator<Item = Link<'l, P::Paragraph>>, Error>
     where
         'b: 'l,
@@ -428,10 +1641,22 @@ impl Document {
                 document: self,
                 link_buf: &mut link_buf,
                 in_paragraph: false,
+                in_code_block: false,
                 last_paragraph_i: 0,
                 buffers: &mut doc_buf.parser_buffers,
                 current_tag_is_closing: false,
-                check_anchors,
+                current_tag_is_generated_link: false,
+                in_generated_link: false,
+                in_link: false,
+                current_tag_link_i: 0,
+                current_tag_rel: None,
+                current_tag_media: None,
+                current_tag_title: None,
+                current_tag_hint: parser::LinkHint::Check,
+                current_link_start: None,
+                options,
+                xml,
+                record,
             };
             let ioreader = IoReader::new_with_buffer(read, doc_buf.html_read_buffer.as_mut());
             let reader = Tokenizer::new_with_emitter(ioreader, emitter);
@@ -488,7 +1713,11 @@ fn test_html_parsing_malformed_script() {
     let mut doc_buf = DocumentBuffers::default();
 
     let links = doc
-        .links_from_read::<_, ParagraphHasher>(&mut doc_buf, html.as_bytes(), false)
+        .links_from_read::<_, ParagraphHasher>(
+            &mut doc_buf,
+            html.as_bytes(),
+            &ParseOptions::default(),
+        )
         .unwrap();
 
     let used_link = |x: &'static str| {
@@ -496,6 +1725,12 @@ fn test_html_parsing_malformed_script() {
             href: Href(x),
             path: doc.path.clone(),
             paragraph: None,
+            origin: LinkOrigin::Attribute {
+                element: "a",
+                attribute: "href",
+            },
+            text: None,
+            warn_only: false,
         })
     };
 
@@ -554,17 +1789,21 @@ fn test_document_links() {
     />
     """#
         .as_bytes(),
-        false,
+        &ParseOptions::default(),
     )
     .unwrap();
 
-    let used_link = |x: &'static str| {
+    let link_from = |x: &'static str, element: &'static str, attribute: &'static str| {
         Link::Uses(UsedLink {
             href: Href(x),
             path: doc.path.clone(),
             paragraph: None,
+            origin: LinkOrigin::Attribute { element, attribute },
+            text: None,
+            warn_only: false,
         })
     };
+    let used_link = |x: &'static str| link_from(x, "a", "href");
 
     let arena = Bump::new();
 
@@ -583,9 +1822,124 @@ fn test_document_links() {
             used_link("platforms/python/troubleshooting/[schlug].js"),
             used_link("platforms/python/troubleshooting/case"),
             used_link("platforms/python/troubleshooting/whitespace"),
-            used_link("static/image.png"),
-            used_link("static/image300.png"),
-            used_link("static/image600.png"),
+            link_from("static/image.png", "img", "src"),
+            link_from("static/image300.png", "img", "srcset"),
+            link_from("static/image600.png", "img", "srcset"),
+        ]
+    );
+}
+
+#[test]
+fn test_skip_ids() {
+    use crate::paragraph::NoopParagraphWalker;
+
+    let doc = Document::new(Path::new("public/"), Path::new("public/hello.html"));
+    let html = r#"<h2 id=intro>Intro</h2> <a name=top href="other.html#intro">"#;
+
+    let hrefs = |options: &ParseOptions| {
+        let mut doc_buf = DocumentBuffers::default();
+        doc.links_from_read::<_, NoopParagraphWalker>(&mut doc_buf, html.as_bytes(), options)
+            .unwrap()
+            .map(|link| match link {
+                Link::Uses(used_link) => format!("uses {}", used_link.href),
+                Link::Defines(defined_link) => format!("defines {}", defined_link.href),
+                _ => unreachable!(),
+            })
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(hrefs(&ParseOptions::default()), ["uses other.html"]);
+    let check_anchors = ParseOptions {
+        check_anchors: true,
+        ..Default::default()
+    };
+    assert_eq!(
+        hrefs(&check_anchors),
+        [
+            "defines hello.html#intro",
+            "defines hello.html#top",
+            "uses other.html#intro"
+        ]
+    );
+    let skip_ids = ParseOptions {
+        skip_ids: true,
+        ..check_anchors
+    };
+    assert_eq!(hrefs(&skip_ids), ["uses other.html#intro"]);
+}
+
+#[test]
+fn test_check_aria() {
+    use crate::paragraph::NoopParagraphWalker;
+
+    let doc = Document::new(Path::new("public/"), Path::new("public/hello.html"));
+    let html = r#"<h2 id=title>Title</h2> <dialog aria-labelledby="title" aria-describedby=" help  hint">"#;
+
+    let hrefs = |options: &ParseOptions| {
+        let mut doc_buf = DocumentBuffers::default();
+        doc.links_from_read::<_, NoopParagraphWalker>(&mut doc_buf, html.as_bytes(), options)
+            .unwrap()
+            .filter_map(|link| match link {
+                Link::Uses(used_link) => Some(format!("{} ({})", used_link.href, used_link.origin)),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let check_aria = ParseOptions {
+        check_anchors: true,
+        check_aria: true,
+        ..Default::default()
+    };
+    assert_eq!(
+        hrefs(&check_aria),
+        [
+            "hello.html#title (*[aria-labelledby])",
+            "hello.html#help (*[aria-describedby])",
+            "hello.html#hint (*[aria-describedby])",
+        ]
+    );
+    assert!(hrefs(&ParseOptions {
+        check_anchors: false,
+        ..check_aria
+    })
+    .is_empty());
+}
+
+#[test]
+fn test_check_page_text() {
+    use crate::paragraph::NoopParagraphWalker;
+
+    let doc = Document::new(Path::new("public/"), Path::new("public/changelog.html"));
+    let html = r#"<title>Changelog</title>
+        <style>body { background: url(/bg.png) }</style>
+        <script>fetch("/api/v1/")</script>
+        <p>Moved the guide to /docs/guide/. See <a href="/docs/">/docs/</a> and
+        <code>/docs/install#linux</code> (or https://example.com/blog/, and/or /etc).</p>"#;
+
+    let mut doc_buf = DocumentBuffers::default();
+    let options = ParseOptions {
+        check_page_text: true,
+        base_url: Some("https://example.com".to_owned()),
+        ..Default::default()
+    };
+    let links = doc
+        .links_from_read::<_, NoopParagraphWalker>(&mut doc_buf, html.as_bytes(), &options)
+        .unwrap()
+        .filter_map(|link| match link {
+            Link::Uses(used_link) => Some(format!("{} ({})", used_link.href, used_link.origin)),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        links,
+        [
+            "docs/guide (heuristic: text)",
+            "docs (a[href])",
+            "docs/install (heuristic: text)",
+            "blog (heuristic: text)",
+            "etc (heuristic: text)",
         ]
     );
 }
@@ -600,24 +1954,32 @@ fn test_document_join_index_html() {
     );
 
     assert_eq!(
-        doc.join(&arena, false, "../../ruby#foo"),
+        doc.join(&arena, None, "../../ruby#foo"),
         Href("platforms/ruby")
     );
     assert_eq!(
-        doc.join(&arena, true, "../../ruby#foo"),
+        doc.join(&arena, Some(FragmentEncoding::Decoded), "../../ruby#foo"),
         Href("platforms/ruby#foo")
     );
     assert_eq!(
-        doc.join(&arena, true, "../../ruby?bar=1#foo"),
+        doc.join(
+            &arena,
+            Some(FragmentEncoding::Decoded),
+            "../../ruby?bar=1#foo"
+        ),
         Href("platforms/ruby#foo")
     );
 
     assert_eq!(
-        doc.join(&arena, false, "/platforms/ruby"),
+        doc.join(&arena, None, "/platforms/ruby"),
         Href("platforms/ruby")
     );
     assert_eq!(
-        doc.join(&arena, true, "/platforms/ruby?bar=1#foo"),
+        doc.join(
+            &arena,
+            Some(FragmentEncoding::Decoded),
+            "/platforms/ruby?bar=1#foo"
+        ),
         Href("platforms/ruby#foo")
     );
 }
@@ -632,34 +1994,50 @@ fn test_document_join_bare_html() {
     );
 
     assert_eq!(
-        doc.join(&arena, false, "../ruby#foo"),
+        doc.join(&arena, None, "../ruby#foo"),
         Href("platforms/ruby")
     );
     assert_eq!(
-        doc.join(&arena, true, "../ruby#foo"),
+        doc.join(&arena, Some(FragmentEncoding::Decoded), "../ruby#foo"),
         Href("platforms/ruby#foo")
     );
     assert_eq!(
-        doc.join(&arena, true, "../ruby?bar=1#foo"),
+        doc.join(&arena, Some(FragmentEncoding::Decoded), "../ruby?bar=1#foo"),
         Href("platforms/ruby#foo")
     );
 
     assert_eq!(
-        doc.join(&arena, false, "/platforms/ruby"),
+        doc.join(&arena, None, "/platforms/ruby"),
         Href("platforms/ruby")
     );
     assert_eq!(
-        doc.join(&arena, true, "/platforms/ruby?bar=1#foo"),
+        doc.join(
+            &arena,
+            Some(FragmentEncoding::Decoded),
+            "/platforms/ruby?bar=1#foo"
+        ),
         Href("platforms/ruby#foo")
     );
     assert_eq!(
-        doc.join(&arena, false, "/locations/troms%C3%B8"),
+        doc.join(&arena, None, "/locations/troms%C3%B8"),
         Href("locations/tromsø")
     );
     assert_eq!(
-        doc.join(&arena, true, "/locations/oslo#gr%C3%BCnerl%C3%B8kka"),
+        doc.join(
+            &arena,
+            Some(FragmentEncoding::Decoded),
+            "/locations/oslo#gr%C3%BCnerl%C3%B8kka"
+        ),
         Href("locations/oslo#grünerløkka")
     );
+    assert_eq!(
+        doc.join(
+            &arena,
+            Some(FragmentEncoding::Raw),
+            "/locations/oslo#gr%C3%BCnerl%C3%B8kka"
+        ),
+        Href("locations/oslo#gr%C3%BCnerl%C3%B8kka")
+    );
 }
 
 #[test]
@@ -673,8 +2051,265 @@ fn test_json_script() {
     let mut doc_buf = DocumentBuffers::default();
 
     let links = doc
-        .links_from_read::<_, ParagraphHasher>(&mut doc_buf, html.as_bytes(), false)
+        .links_from_read::<_, ParagraphHasher>(
+            &mut doc_buf,
+            html.as_bytes(),
+            &ParseOptions::default(),
+        )
         .unwrap();
 
     assert_eq!(links.collect::<Vec<_>>(), &[]);
 }
+
+#[test]
+fn test_sphinx_headerlinks_not_in_paragraph() {
+    use crate::paragraph::{DebugParagraphWalker, ParagraphHasher};
+
+    let doc = Document::new(Path::new("public/"), Path::new("public/install.html"));
+
+    let paragraph_of = |html: &str| {
+        let mut doc_buf = DocumentBuffers::default();
+        let paragraph = doc
+            .links_from_read::<_, DebugParagraphWalker<ParagraphHasher>>(
+                &mut doc_buf,
+                html.as_bytes(),
+                &ParseOptions::default(),
+            )
+            .unwrap()
+            .find_map(|link| link.into_paragraph())
+            .unwrap();
+        paragraph
+    };
+
+    assert_eq!(
+        paragraph_of(
+            r##"<dt id="id1">See <a href="/setup/">setup</a><sup><a class="footnote-reference brackets" href="#id3">1</a></sup><a class="headerlink" href="#id1" title="Link to this definition">¶</a></dt>"##
+        ),
+        paragraph_of(r#"<dt>See <a href="/setup/">setup</a></dt>"#),
+    );
+
+    assert_eq!(
+        paragraph_of(
+            r##"<p>See <a href="/setup/">setup</a><sup id="fnref:1"><a href="#fn:1" class="footnote-ref" role="doc-noteref">1</a></sup></p>"##
+        ),
+        paragraph_of(r#"<p>See <a href="/setup/">setup</a></p>"#),
+    );
+    assert_eq!(
+        paragraph_of(
+            r##"<li id="fn:1"><p>From <a href="/faq/">the FAQ</a>.&#160;<a href="#fnref:1" class="footnote-backref" role="doc-backlink">&#x21a9;&#xfe0e;</a></p></li>"##
+        ),
+        paragraph_of(r#"<p>From <a href="/faq/">the FAQ</a>.&#160;</p>"#),
+    );
+}
+
+#[test]
+fn test_link_origin() {
+    use crate::paragraph::NoopParagraphWalker;
+
+    let doc = Document::new(Path::new("public/"), Path::new("public/index.html"));
+
+    let mut doc_buf = DocumentBuffers::default();
+
+    let origins = doc
+        .links_from_read::<_, NoopParagraphWalker>(
+            &mut doc_buf,
+            r#"
+            <link href="/style.css" rel="stylesheet">
+            <link rel="preload" href="/font.woff2">
+            <link rel="shortcut icon" href="/favicon.ico">
+            <link rel="unknown" href="/other">
+            <link rel="stylesheet" media="print" href="/print.css">
+            <link href="/dark.css" media="(prefers-color-scheme: dark)" rel="stylesheet">
+            <link rel="stylesheet" media="all" href="/all.css">
+            <link rel="alternate stylesheet" title="High contrast" href="/contrast.css">
+            <link rel="alternate" title="Blog" href="/feed.xml">
+            <a href="/" media="print">Home</a>
+            "#
+            .as_bytes(),
+            &ParseOptions::default(),
+        )
+        .unwrap()
+        .filter_map(|link| match link {
+            Link::Uses(used_link) => Some(used_link.origin.to_string()),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        origins,
+        &[
+            "link[rel=stylesheet]",
+            "link[rel=preload]",
+            "link[rel=icon]",
+            "link[href]",
+            "link[rel=stylesheet][media=\"print\"]",
+            "link[rel=stylesheet][media=\"(prefers-color-scheme: dark)\"]",
+            "link[rel=stylesheet]",
+            "link[rel=\"alternate stylesheet\"][title=\"High contrast\"]",
+            "link[rel=alternate]",
+            "a[href]"
+        ]
+    );
+}
+
+#[test]
+fn test_link_text() {
+    use crate::paragraph::NoopParagraphWalker;
+
+    let doc = Document::new(Path::new("public/"), Path::new("public/index.html"));
+
+    let mut doc_buf = DocumentBuffers::default();
+
+    let texts = doc
+        .links_from_read::<_, NoopParagraphWalker>(
+            &mut doc_buf,
+            r#"
+            <nav><a href="/">Home</a></nav>
+            <p>Upgrading is easy. Before you start, see the
+            <a href="/old/">migration   guide</a>! Then run the installer.</p>
+            <p><a href="/only/">Only a link</a></p>
+            <img src="/logo.png">
+            "#
+            .as_bytes(),
+            &ParseOptions {
+                link_text: true,
+                ..Default::default()
+            },
+        )
+        .unwrap()
+        .filter_map(|link| match link {
+            Link::Uses(used_link) => Some(used_link.text.map(|text| text.to_string())),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        texts,
+        &[
+            Some(r#""Home""#.to_owned()),
+            Some(r#""migration guide" in "Before you start, see the migration guide!""#.to_owned()),
+            Some(r#""Only a link""#.to_owned()),
+            None,
+        ]
+    );
+}
+
+#[test]
+fn test_link_hooks() {
+    use crate::paragraph::NoopParagraphWalker;
+
+    let dir = assert_fs::TempDir::new().unwrap();
+    let path = dir.path().join("index.html");
+    fs::write(
+        &path,
+        "<a href='/api/users'>API</a><a href='/v2/docs/'>v2</a><a href='/about/'>About</a>",
+    )
+    .unwrap();
+
+    let doc = Document::new(dir.path(), &path);
+    let options = ParseOptions {
+        link_hooks: vec![
+            Arc::new(|href: &Href<'_>, _: &Document, _| {
+                if href.0.starts_with("api/") {
+                    LinkDecision::Skip
+                } else {
+                    LinkDecision::Check
+                }
+            }),
+            Arc::new(
+                |href: &Href<'_>, _: &Document, _| match href.0.strip_prefix("v2/") {
+                    Some(rest) => LinkDecision::Rewrite(rest.to_owned()),
+                    None => LinkDecision::Check,
+                },
+            ),
+        ],
+        ..Default::default()
+    };
+
+    let mut hrefs = Vec::new();
+    doc.extract_links::<NoopParagraphWalker, _>(
+        &mut DocumentBuffers::default(),
+        &options,
+        |link| {
+            if let Link::Uses(used_link) = link {
+                hrefs.push(used_link.href.0.to_owned());
+            }
+        },
+    )
+    .unwrap();
+
+    assert_eq!(hrefs, &["docs", "about"]);
+
+    dir.close().unwrap();
+}
+
+#[test]
+fn test_skip_reasons() {
+    assert_eq!(
+        SkipReason::of_external("HTTPS://example.com/"),
+        Some(SkipReason::External)
+    );
+    assert_eq!(
+        SkipReason::of_external("//cdn.example.com/app.js"),
+        Some(SkipReason::External)
+    );
+    assert_eq!(
+        SkipReason::of_external("mailto:me@example.com"),
+        Some(SkipReason::Mailto)
+    );
+    assert_eq!(
+        SkipReason::of_external("data:image/png;base64,iVBO"),
+        Some(SkipReason::Data)
+    );
+    assert_eq!(
+        SkipReason::of_external("tel:+4930123"),
+        Some(SkipReason::OtherScheme)
+    );
+    assert_eq!(SkipReason::of_external("/docs/"), None);
+}
+
+#[test]
+fn test_strip_base_url() {
+    let options = ParseOptions {
+        base_url: Some("https://example.com".to_owned()),
+        ..Default::default()
+    };
+
+    assert_eq!(options.strip_base_url("https://example.com"), "/");
+    assert_eq!(options.strip_base_url("https://example.com/a/"), "/a/");
+    assert_eq!(
+        options.strip_base_url("https://example.com.evil/"),
+        "https://example.com.evil/"
+    );
+    assert_eq!(options.strip_base_url("/a/"), "/a/");
+    assert_eq!(
+        ParseOptions::default().strip_base_url("https://example.com/a/"),
+        "https://example.com/a/"
+    );
+}
+
+#[test]
+fn test_placeholder_of() {
+    let mut options = ParseOptions {
+        soft_404_markers: vec!["Page not found".to_owned()],
+        ..ParseOptions::default()
+    };
+    assert_eq!(Placeholder::of(&options, b""), None);
+    assert_eq!(
+        Placeholder::of(&options, b"<h1>Page not found</h1>"),
+        Some(Placeholder::Soft404("Page not found".to_owned()))
+    );
+
+    options.check_placeholders = true;
+    options.placeholder_markers = vec!["Coming soon".to_owned()];
+    assert_eq!(Placeholder::of(&options, b""), Some(Placeholder::Empty));
+    assert_eq!(
+        Placeholder::of(&options, b"<h1>Coming soon</h1>"),
+        Some(Placeholder::Marker("Coming soon".to_owned()))
+    );
+    assert_eq!(
+        Placeholder::of(&options, b"Coming soon: Page not found"),
+        Some(Placeholder::Soft404("Page not found".to_owned()))
+    );
+    assert_eq!(Placeholder::of(&options, b"<h1>Done</h1>"), None);
+}
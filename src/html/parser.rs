@@ -1,14 +1,22 @@
 use bumpalo::collections::String as BumpString;
 use bumpalo::collections::Vec as BumpVec;
+use std::sync::Arc;
+
 use bumpalo::Bump;
 use html5gum::{Emitter, Error, State};
 
-use crate::html::{DefinedLink, Document, Link, UsedLink};
+use crate::html::{
+    intern, text_urls, AlternateLink, CanonicalLink, DefinedLink, Document, FragmentEncoding, Href,
+    IntegrityLink, Link, LinkOrigin, LinkText, LintWarning, ParseOptions, SkipReason, UsedLink,
+};
+use crate::lints::{self, Lint};
 use crate::paragraph::ParagraphWalker;
+use crate::urls::is_external_link;
 
+// Note: Keep in sync with markdown.rs
 #[inline]
 fn is_paragraph_tag(tag: &[u8]) -> bool {
-    tag == b"p" || tag == b"li" || tag == b"dt" || tag == b"dd"
+    matches!(tag, b"p" | b"li" | b"dt" | b"dd" | b"td" | b"th")
 }
 
 #[inline]
@@ -16,12 +24,115 @@ fn try_normalize_href_value(input: &str) -> &str {
     input.trim()
 }
 
+/// Sphinx and mkdocs append permalinks like `<a class="headerlink" href="#id1">¶</a>` to
+/// headings and definition terms, and render footnote references as numbered links. Hugo does the
+/// same for footnotes, and links back from each footnote with `<a class="footnote-backref">↩</a>`.
+/// None of these have a counterpart in the source text, so their contents are excluded from
+/// paragraph hashing.
+#[inline]
+fn is_generated_link_class(class: &[u8]) -> bool {
+    class.split(|c| c.is_ascii_whitespace()).any(|token| {
+        matches!(
+            token,
+            b"headerlink" | b"footnote-reference" | b"footnote-ref" | b"footnote-backref"
+        )
+    })
+}
+
+/// Link types from the HTML standard, so that `<link>` origins can be reported without copying
+/// the attribute value out of the document buffer. Unknown link types are reported as `link[href]`.
+///
+/// https://html.spec.whatwg.org/multipage/links.html#linkTypes
+static LINK_TYPES: &[&str] = &[
+    "alternate",
+    "canonical",
+    "dns-prefetch",
+    "icon",
+    "manifest",
+    "modulepreload",
+    "next",
+    "preconnect",
+    "prefetch",
+    "preload",
+    "prev",
+    "search",
+    "stylesheet",
+];
+
+#[inline]
+fn known_link_type(rel: &[u8]) -> Option<&'static str> {
+    let has_token = |link_type: &[u8]| {
+        rel.split(|c| c.is_ascii_whitespace())
+            .any(|token| token.eq_ignore_ascii_case(link_type))
+    };
+    // an alternate stylesheet is not an alternate version of the page
+    if has_token(b"alternate") && has_token(b"stylesheet") {
+        return Some("alternate stylesheet");
+    }
+
+    rel.split(|c| c.is_ascii_whitespace()).find_map(|token| {
+        LINK_TYPES
+            .iter()
+            .find(|link_type| token.eq_ignore_ascii_case(link_type.as_bytes()))
+            .copied()
+    })
+}
+
+#[inline]
+fn find_attribute<'b>(attributes: &'b [(Vec<u8>, Vec<u8>)], name: &[u8]) -> Option<&'b [u8]> {
+    attributes
+        .iter()
+        .find(|(attribute_name, _)| attribute_name == name)
+        .map(|(_, value)| value.as_slice())
+}
+
+const MAX_SENTENCE_LEN: usize = 120;
+
+fn collapse_whitespace(text: &[u8]) -> String {
+    String::from_utf8_lossy(text)
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[inline]
+fn is_sentence_end(text: &[u8], i: usize) -> bool {
+    matches!(text[i], b'.' | b'!' | b'?') && text.get(i + 1).is_none_or(u8::is_ascii_whitespace)
+}
+
+/// Cut the sentence containing `text[start..end]` out of a paragraph, shortened to
+/// `MAX_SENTENCE_LEN` characters.
+fn surrounding_sentence(text: &[u8], start: usize, end: usize) -> String {
+    let sentence_start = (0..start)
+        .rev()
+        .find(|&i| is_sentence_end(text, i))
+        .map_or(0, |i| i + 1);
+    let sentence_end = (end..text.len())
+        .find(|&i| is_sentence_end(text, i))
+        .map_or(text.len(), |i| i + 1);
+
+    let sentence = collapse_whitespace(&text[sentence_start..sentence_end]);
+    match sentence.char_indices().nth(MAX_SENTENCE_LEN) {
+        Some((i, _)) => format!("{}…", &sentence[..i]),
+        None => sentence,
+    }
+}
+
 #[derive(Default)]
 pub struct ParserBuffers {
     current_tag_name: Vec<u8>,
     current_attribute_name: Vec<u8>,
     current_attribute_value: Vec<u8>,
     last_start_tag: Vec<u8>,
+    /// Text of the current paragraph or link, only collected for `ParseOptions::link_text`.
+    text: Vec<u8>,
+    /// Visible text since the last tag, only collected for `ParseOptions::check_page_text`.
+    page_text: Vec<u8>,
+    /// All attributes of the current tag, only collected if `needs_all_attributes`.
+    current_attributes: Vec<(Vec<u8>, Vec<u8>)>,
+    /// Links of the current paragraph waiting for their sentence: range in `link_buf` and range
+    /// of their text in `text`.
+    pending_link_texts: Vec<(usize, usize, usize, usize)>,
 }
 
 impl ParserBuffers {
@@ -30,19 +141,52 @@ impl ParserBuffers {
         self.current_attribute_name.clear();
         self.current_attribute_value.clear();
         self.last_start_tag.clear();
+        self.text.clear();
+        self.page_text.clear();
+        self.current_attributes.clear();
+        self.pending_link_texts.clear();
     }
 }
 
+/// What the markup of an element asks for its links, see `ParseOptions::skip_rels`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LinkHint {
+    Check,
+    Warn,
+    Skip,
+}
+
 pub struct HyperlinkEmitter<'a, 'l, 'd, P: ParagraphWalker> {
     pub paragraph_walker: P,
     pub arena: &'a Bump,
     pub document: &'d Document,
     pub link_buf: &'d mut BumpVec<'a, Link<'l, P::Paragraph>>,
     pub in_paragraph: bool,
+    /// Inside `<pre>` with `ParagraphOptions::skip_code_blocks`.
+    pub in_code_block: bool,
     pub last_paragraph_i: usize,
     pub buffers: &'d mut ParserBuffers,
     pub current_tag_is_closing: bool,
-    pub check_anchors: bool,
+    pub current_tag_is_generated_link: bool,
+    pub in_generated_link: bool,
+    /// Inside `<a>`, whose text is not searched for `ParseOptions::check_page_text`.
+    pub in_link: bool,
+    /// Index into `link_buf` of the first link found in the current tag.
+    pub current_tag_link_i: usize,
+    pub current_tag_rel: Option<&'static str>,
+    /// The `media` and `title` of the current `<link>`, see `LinkOrigin::LinkMedia`.
+    pub current_tag_media: Option<&'static str>,
+    pub current_tag_title: Option<&'static str>,
+    /// From `rel` and `data-hyperlink`. The strongest hint wins if an element has both.
+    pub current_tag_hint: LinkHint,
+    /// Index into `link_buf` and offset into the text buffer at which the open `<a>` started.
+    pub current_link_start: Option<(usize, usize)>,
+    pub options: &'d ParseOptions,
+    /// Parsing an XML file: only `ParseOptions::xml_rules` apply, none of the HTML elements.
+    pub xml: bool,
+    /// Keep hrefs as written instead of joining them with the document, see
+    /// `Document::record_links`.
+    pub record: bool,
 }
 
 impl<'a, 'l, P> HyperlinkEmitter<'a, 'l, '_, P>
@@ -50,48 +194,442 @@ where
     'a: 'l,
     P: ParagraphWalker,
 {
-    fn extract_used_link(&mut self) {
+    fn link_origin(&self, element: &'static str, attribute: &'static str) -> LinkOrigin {
+        match self.current_tag_rel {
+            Some(rel) if element == "link" => LinkOrigin::LinkRel(rel),
+            _ => LinkOrigin::Attribute { element, attribute },
+        }
+    }
+
+    fn join(&self, fragments: Option<FragmentEncoding>, href: &str) -> Href<'a> {
+        if self.record {
+            Href(self.arena.alloc_str(href))
+        } else {
+            self.document.join(self.arena, fragments, href)
+        }
+    }
+
+    fn extract_used_link(&mut self, element: &'static str, attribute: &'static str) {
         let value = try_normalize_href_value(
             std::str::from_utf8(&self.buffers.current_attribute_value).unwrap(),
         );
+        let origin = self.link_origin(element, attribute);
+
+        for warning in self.document.lint_href(self.options, value, origin) {
+            self.link_buf.push(Link::Lint(warning));
+        }
 
+        let value = self.options.strip_base_url(value);
         self.link_buf.push(Link::Uses(UsedLink {
-            href: self.document.join(self.arena, self.check_anchors, value),
+            href: self.join(self.options.used_fragments(), value),
             path: self.document.path.clone(),
             paragraph: None,
+            origin,
+            text: None,
+            warn_only: false,
         }));
     }
 
+    fn set_link_texts(&mut self, links: std::ops::Range<usize>, text: LinkText) {
+        let text = Arc::new(text);
+        for link in &mut self.link_buf[links] {
+            if let Link::Uses(ref mut x) = link {
+                x.text = Some(text.clone());
+            }
+        }
+    }
+
+    fn start_link_text(&mut self) {
+        if !self.in_paragraph {
+            self.buffers.text.clear();
+        }
+        self.current_link_start = Some((self.current_tag_link_i, self.buffers.text.len()));
+    }
+
+    fn finish_link_text(&mut self) {
+        if let Some((link_i, text_start)) = self.current_link_start.take() {
+            let links = link_i..self.link_buf.len();
+            let text_end = self.buffers.text.len();
+            self.set_link_texts(
+                links.clone(),
+                LinkText {
+                    text: collapse_whitespace(&self.buffers.text[text_start..text_end]),
+                    sentence: None,
+                },
+            );
+
+            if self.in_paragraph {
+                self.buffers.pending_link_texts.push((
+                    links.start,
+                    links.end,
+                    text_start,
+                    text_end,
+                ));
+            }
+        }
+    }
+
+    fn finish_paragraph_text(&mut self) {
+        // taken out of the buffers only to satisfy the borrow checker, and put back for reuse
+        let mut pending_link_texts = std::mem::take(&mut self.buffers.pending_link_texts);
+        for &(link_start, link_end, text_start, text_end) in &pending_link_texts {
+            let text = collapse_whitespace(&self.buffers.text[text_start..text_end]);
+            let sentence = surrounding_sentence(&self.buffers.text, text_start, text_end);
+            self.set_link_texts(
+                link_start..link_end,
+                LinkText {
+                    sentence: Some(sentence).filter(|sentence| *sentence != text),
+                    text,
+                },
+            );
+        }
+
+        pending_link_texts.clear();
+        self.buffers.pending_link_texts = pending_link_texts;
+        self.buffers.text.clear();
+    }
+
+    fn extract_custom_attributes(&mut self) {
+        for rule in self.options.extract_rules_for(self.xml) {
+            if !rule.matches(
+                &self.buffers.current_tag_name,
+                &self.buffers.current_attributes,
+            ) {
+                continue;
+            }
+
+            if let Some((_, value)) = self
+                .buffers
+                .current_attributes
+                .iter()
+                .find(|(name, _)| name == rule.attribute.as_bytes())
+            {
+                let value = try_normalize_href_value(std::str::from_utf8(value).unwrap());
+                let origin = LinkOrigin::Attribute {
+                    element: rule.selector,
+                    attribute: rule.attribute,
+                };
+
+                for warning in self.document.lint_href(self.options, value, origin) {
+                    self.link_buf.push(Link::Lint(warning));
+                }
+
+                let value = self.options.strip_base_url(value);
+                self.link_buf.push(Link::Uses(UsedLink {
+                    href: self.join(self.options.used_fragments(), value),
+                    path: self.document.path.clone(),
+                    paragraph: None,
+                    origin,
+                    text: None,
+                    warn_only: false,
+                }));
+            }
+        }
+    }
+
+    fn extract_alternate_link(&mut self) {
+        if self.current_tag_rel != Some("alternate") {
+            return;
+        }
+
+        let attributes = &self.buffers.current_attributes;
+        let (hreflang, href) = match (
+            find_attribute(attributes, b"hreflang"),
+            find_attribute(attributes, b"href"),
+        ) {
+            (Some(hreflang), Some(href)) => (hreflang, href),
+            _ => return,
+        };
+
+        let href = self
+            .options
+            .strip_base_url(try_normalize_href_value(std::str::from_utf8(href).unwrap()));
+        if is_external_link(href.as_bytes()) {
+            return;
+        }
+
+        let alternate_link = AlternateLink {
+            document: Href(self.arena.alloc_str(self.document.href().0)),
+            path: self.document.path.clone(),
+            hreflang: self
+                .arena
+                .alloc_str(String::from_utf8_lossy(hreflang).trim()),
+            href: self.join(None, href),
+        };
+        self.link_buf.push(Link::Alternate(alternate_link));
+    }
+
+    fn extract_canonical_link(&mut self) {
+        if self.current_tag_rel != Some("canonical") {
+            return;
+        }
+
+        let href = match find_attribute(&self.buffers.current_attributes, b"href") {
+            Some(href) => self
+                .options
+                .strip_base_url(try_normalize_href_value(std::str::from_utf8(href).unwrap())),
+            None => return,
+        };
+        if is_external_link(href.as_bytes()) {
+            return;
+        }
+
+        let canonical_link = CanonicalLink {
+            document: Href(self.arena.alloc_str(self.document.href().0)),
+            path: self.document.path.clone(),
+            href: self.join(None, href),
+        };
+        self.link_buf.push(Link::Canonical(canonical_link));
+    }
+
+    fn extract_integrity_link(&mut self, attribute: &[u8]) {
+        let attributes = &self.buffers.current_attributes;
+        let (integrity, href) = match (
+            find_attribute(attributes, b"integrity"),
+            find_attribute(attributes, attribute),
+        ) {
+            (Some(integrity), Some(href)) => (integrity, href),
+            _ => return,
+        };
+
+        let href = self
+            .options
+            .strip_base_url(try_normalize_href_value(std::str::from_utf8(href).unwrap()));
+        if is_external_link(href.as_bytes()) {
+            return;
+        }
+
+        let integrity_link = IntegrityLink {
+            path: self.document.path.clone(),
+            href: self.join(None, href),
+            integrity: self
+                .arena
+                .alloc_str(String::from_utf8_lossy(integrity).trim()),
+        };
+        self.link_buf.push(Link::Integrity(integrity_link));
+    }
+
+    /// Whether `ParserBuffers::current_attributes` needs to be collected.
+    fn needs_all_attributes(&self) -> bool {
+        !self.options.extract_rules_for(self.xml).is_empty()
+            || self.options.check_hreflang
+            || self.options.check_canonical
+            || self.options.check_integrity
+    }
+
+    fn extract_link_rel(&mut self) {
+        self.current_tag_rel = known_link_type(&self.buffers.current_attribute_value);
+
+        // rel may come after href
+        if let Some(rel) = self.current_tag_rel {
+            for link in &mut self.link_buf[self.current_tag_link_i..] {
+                if let Link::Uses(ref mut x) = link {
+                    x.origin = LinkOrigin::LinkRel(rel);
+                }
+            }
+        }
+    }
+
+    fn extract_link_media(&mut self) {
+        let media = String::from_utf8_lossy(&self.buffers.current_attribute_value);
+        let media = media.trim();
+        // `all` is the default
+        if !media.is_empty() && !media.eq_ignore_ascii_case("all") {
+            self.current_tag_media = Some(intern(media));
+        }
+    }
+
+    /// Report the links of a `<link>` that only applies to some media, or of an alternate
+    /// stylesheet, with their `media` and `title`.
+    fn apply_link_media(&mut self) {
+        let rel = match self.current_tag_rel {
+            Some(rel) => rel,
+            None => return,
+        };
+        let title = self
+            .current_tag_title
+            .filter(|_| rel == "alternate stylesheet");
+        if self.current_tag_media.is_none() && title.is_none() {
+            return;
+        }
+
+        for link in &mut self.link_buf[self.current_tag_link_i..] {
+            if let Link::Uses(ref mut x) = link {
+                x.origin = LinkOrigin::LinkMedia {
+                    rel,
+                    media: self.current_tag_media,
+                    title,
+                };
+            }
+        }
+    }
+
+    fn extract_rel_hint(&mut self) {
+        let rel = String::from_utf8_lossy(&self.buffers.current_attribute_value);
+        for value in rel.split_ascii_whitespace() {
+            let matches = |rels: &[String]| rels.iter().any(|rel| rel.eq_ignore_ascii_case(value));
+            if matches(&self.options.skip_rels) {
+                self.current_tag_hint = LinkHint::Skip;
+            } else if matches(&self.options.warn_rels) {
+                self.current_tag_hint = self.current_tag_hint.max(LinkHint::Warn);
+            }
+        }
+    }
+
+    fn extract_data_hint(&mut self) {
+        let hint = match self.buffers.current_attribute_value.as_slice() {
+            b"ignore" => LinkHint::Skip,
+            b"warn" => LinkHint::Warn,
+            _ => LinkHint::Check,
+        };
+        self.current_tag_hint = self.current_tag_hint.max(hint);
+    }
+
+    /// Apply `current_tag_hint` to the used links of the current tag.
+    fn apply_hint(&mut self) {
+        match self.current_tag_hint {
+            LinkHint::Check => (),
+            LinkHint::Warn => {
+                for link in &mut self.link_buf[self.current_tag_link_i..] {
+                    if let Link::Uses(ref mut x) = link {
+                        x.warn_only = true;
+                    }
+                }
+            }
+            LinkHint::Skip => {
+                // keeps the order of the other links, e.g. lint warnings
+                let mut kept = self.current_tag_link_i;
+                for i in self.current_tag_link_i..self.link_buf.len() {
+                    if !matches!(self.link_buf[i], Link::Uses(_)) {
+                        self.link_buf.swap(kept, i);
+                        kept += 1;
+                    }
+                }
+                for i in kept..self.link_buf.len() {
+                    self.link_buf[i] = Link::Skipped(SkipReason::Markup);
+                }
+            }
+        }
+    }
+
+    /// Emit the site-relative URLs in the text since the last tag, see
+    /// `ParseOptions::check_page_text`.
+    fn extract_page_text_links(&mut self) {
+        if self.buffers.page_text.is_empty() {
+            return;
+        }
+
+        let text = String::from_utf8_lossy(&self.buffers.page_text);
+        for url in text_urls(&text) {
+            let url = self.options.strip_base_url(url);
+            if is_external_link(url.as_bytes()) {
+                continue;
+            }
+            self.link_buf.push(Link::Uses(UsedLink {
+                href: self.join(self.options.used_fragments(), url),
+                path: self.document.path.clone(),
+                paragraph: None,
+                origin: LinkOrigin::TextHeuristic,
+                text: None,
+                warn_only: false,
+            }));
+        }
+        self.buffers.page_text.clear();
+    }
+
     fn extract_used_link_srcset(&mut self) {
         let value = try_normalize_href_value(
             std::str::from_utf8(&self.buffers.current_attribute_value).unwrap(),
         );
 
+        let options = self.options;
+
         // https://html.spec.whatwg.org/multipage/images.html#srcset-attribute
         for value in value
             .split(',')
             .filter_map(|candidate: &str| candidate.split_whitespace().next())
             .filter(|value| !value.is_empty())
+            .map(|value| options.strip_base_url(value))
         {
             self.link_buf.push(Link::Uses(UsedLink {
-                href: self.document.join(self.arena, self.check_anchors, value),
+                href: self.join(self.options.used_fragments(), value),
+                path: self.document.path.clone(),
+                paragraph: None,
+                origin: LinkOrigin::Attribute {
+                    element: "img",
+                    attribute: "srcset",
+                },
+                text: None,
+                warn_only: false,
+            }));
+        }
+    }
+
+    /// Emit the space-separated ids of an attribute like `aria-labelledby` as used links to
+    /// anchors in this document.
+    fn extract_id_references(&mut self, attribute: &'static str) {
+        if !self.options.check_aria || !self.options.check_anchors {
+            return;
+        }
+
+        let value = String::from_utf8_lossy(&self.buffers.current_attribute_value).into_owned();
+        for id in value.split_ascii_whitespace() {
+            let href = self.join(Some(self.options.fragment_encoding), &format!("#{id}"));
+            self.link_buf.push(Link::Uses(UsedLink {
+                href,
                 path: self.document.path.clone(),
                 paragraph: None,
+                origin: LinkOrigin::Attribute {
+                    element: "*",
+                    attribute,
+                },
+                text: None,
+                warn_only: false,
             }));
         }
     }
 
     fn extract_anchor_def(&mut self) {
-        if self.check_anchors {
-            let mut href = BumpString::new_in(self.arena);
-            let value = try_normalize_href_value(
-                std::str::from_utf8(&self.buffers.current_attribute_value).unwrap(),
-            );
-            href.push('#');
-            href.push_str(value);
+        let value = try_normalize_href_value(
+            std::str::from_utf8(&self.buffers.current_attribute_value).unwrap(),
+        );
+        if self
+            .options
+            .ignored_ids
+            .as_ref()
+            .is_some_and(|ignored_ids| ignored_ids.is_match(value))
+        {
+            return;
+        }
+
+        let mut href = BumpString::new_in(self.arena);
+        href.push('#');
+        href.push_str(value);
+
+        self.link_buf.push(Link::Defines(DefinedLink {
+            href: self.join(Some(self.options.fragment_encoding), &href),
+            is_redirect: false,
+            redirect_to: None,
+            path: Some(self.document.path.clone()),
+            element: Some(
+                self.arena
+                    .alloc_str(&String::from_utf8_lossy(&self.buffers.current_tag_name)),
+            ),
+        }));
+    }
 
-            self.link_buf.push(Link::Defines(DefinedLink {
-                href: self.document.join(self.arena, self.check_anchors, &href),
+    fn lint_id(&mut self) {
+        if self.current_tag_is_closing || !self.options.lint_enabled(Lint::BadIds) {
+            return;
+        }
+
+        let element = String::from_utf8_lossy(&self.buffers.current_tag_name);
+        let id = String::from_utf8_lossy(&self.buffers.current_attribute_value);
+        if let Some(message) = lints::bad_id(&element, &id) {
+            self.link_buf.push(Link::Lint(LintWarning {
+                path: self.document.path.clone(),
+                lint: Lint::BadIds,
+                message,
             }));
         }
     }
@@ -101,15 +639,53 @@ where
             self.buffers.current_tag_name.as_slice(),
             self.buffers.current_attribute_name.as_slice(),
         ) {
-            (b"link" | b"area" | b"a", b"href") => self.extract_used_link(),
-            (b"a", b"name") => self.extract_anchor_def(),
-            (b"img" | b"script" | b"iframe", b"src") => self.extract_used_link(),
+            // XML files only go through `ParseOptions::xml_rules`
+            _ if self.xml => (),
+            (b"link", b"href") => self.extract_used_link("link", "href"),
+            (b"link", b"rel") => {
+                self.extract_link_rel();
+                self.extract_rel_hint();
+            }
+            (b"link", b"media") => self.extract_link_media(),
+            (b"link", b"title") => {
+                let title = String::from_utf8_lossy(&self.buffers.current_attribute_value);
+                self.current_tag_title = Some(intern(title.trim()));
+            }
+            (_, b"rel") => self.extract_rel_hint(),
+            (_, b"data-hyperlink") => self.extract_data_hint(),
+            (b"area", b"href") => self.extract_used_link("area", "href"),
+            (b"a", b"href") => self.extract_used_link("a", "href"),
+            (b"a", b"name") if self.options.collects_ids() => self.extract_anchor_def(),
+            (b"a", b"class") => {
+                self.current_tag_is_generated_link =
+                    is_generated_link_class(&self.buffers.current_attribute_value);
+            }
+            (b"img", b"src") => self.extract_used_link("img", "src"),
+            (b"script", b"src") => self.extract_used_link("script", "src"),
+            (b"iframe", b"src") => self.extract_used_link("iframe", "src"),
             (b"img", b"srcset") => self.extract_used_link_srcset(),
-            (b"object", b"data") => self.extract_used_link(),
-            (_, b"id") => self.extract_anchor_def(),
+            (b"object", b"data") => self.extract_used_link("object", "data"),
+            (_, b"aria-labelledby") => self.extract_id_references("aria-labelledby"),
+            (_, b"aria-describedby") => self.extract_id_references("aria-describedby"),
+            (_, b"id") => {
+                self.lint_id();
+                if self.options.collects_ids() {
+                    self.extract_anchor_def();
+                }
+            }
             _ => (),
         }
 
+        if self.needs_all_attributes()
+            && !self.current_tag_is_closing
+            && !self.buffers.current_attribute_name.is_empty()
+        {
+            self.buffers.current_attributes.push((
+                self.buffers.current_attribute_name.clone(),
+                self.buffers.current_attribute_value.clone(),
+            ));
+        }
+
         self.buffers.current_attribute_name.clear();
         self.buffers.current_attribute_value.clear();
     }
@@ -134,17 +710,38 @@ where
     }
 
     fn emit_string(&mut self, c: &[u8]) {
-        if !P::is_noop() && self.in_paragraph {
+        if !P::is_noop() && self.in_paragraph && !self.in_generated_link && !self.in_code_block {
             self.paragraph_walker.update(c);
         }
+
+        if self.options.link_text && (self.in_paragraph || self.current_link_start.is_some()) {
+            self.buffers.text.extend(c);
+        }
+
+        if self.options.check_page_text
+            && !self.xml
+            && !self.in_link
+            && !matches!(self.buffers.last_start_tag.as_slice(), b"script" | b"style")
+        {
+            self.buffers.page_text.extend(c);
+        }
     }
 
     fn init_start_tag(&mut self) {
+        self.extract_page_text_links();
         self.buffers.current_tag_name.clear();
         self.current_tag_is_closing = false;
+        self.current_tag_is_generated_link = false;
+        self.current_tag_link_i = self.link_buf.len();
+        self.current_tag_rel = None;
+        self.current_tag_media = None;
+        self.current_tag_title = None;
+        self.current_tag_hint = LinkHint::Check;
+        self.buffers.current_attributes.clear();
     }
 
     fn init_end_tag(&mut self) {
+        self.extract_page_text_links();
         self.buffers.current_tag_name.clear();
         self.current_tag_is_closing = true;
     }
@@ -152,9 +749,54 @@ where
     fn emit_current_tag(&mut self) -> Option<State> {
         self.flush_old_attribute();
 
+        if !self.options.extract_rules_for(self.xml).is_empty() && !self.current_tag_is_closing {
+            self.extract_custom_attributes();
+        }
+
+        if !self.current_tag_is_closing && !self.xml && self.buffers.current_tag_name == b"link" {
+            self.apply_link_media();
+            if self.options.check_hreflang {
+                self.extract_alternate_link();
+            }
+            if self.options.check_canonical {
+                self.extract_canonical_link();
+            }
+        }
+
+        if !self.current_tag_is_closing && !self.xml && self.options.check_integrity {
+            match self.buffers.current_tag_name.as_slice() {
+                b"script" => self.extract_integrity_link(b"src"),
+                b"link" => self.extract_integrity_link(b"href"),
+                _ => (),
+            }
+        }
+
+        if !self.current_tag_is_closing {
+            self.apply_hint();
+        }
+
         self.buffers.last_start_tag.clear();
 
-        let is_paragraph_tag = !P::is_noop() && is_paragraph_tag(&self.buffers.current_tag_name);
+        let is_paragraph_tag = (!P::is_noop() || self.options.link_text)
+            && is_paragraph_tag(&self.buffers.current_tag_name);
+
+        if self.options.paragraphs.skip_code_blocks && self.buffers.current_tag_name == b"pre" {
+            self.in_code_block = !self.current_tag_is_closing;
+        }
+
+        if self.buffers.current_tag_name == b"a" {
+            self.in_link = !self.current_tag_is_closing;
+            self.in_generated_link =
+                !self.current_tag_is_closing && self.current_tag_is_generated_link;
+
+            if self.options.link_text {
+                if self.current_tag_is_closing {
+                    self.finish_link_text();
+                } else {
+                    self.start_link_text();
+                }
+            }
+        }
 
         if !self.current_tag_is_closing {
             self.buffers
@@ -165,16 +807,30 @@ where
                 self.in_paragraph = true;
                 self.last_paragraph_i = self.link_buf.len();
                 self.paragraph_walker.finish_paragraph();
+                self.buffers.text.clear();
+                self.buffers.pending_link_texts.clear();
             }
         } else if is_paragraph_tag {
             let paragraph = self.paragraph_walker.finish_paragraph();
             if self.in_paragraph {
+                if self.options.link_text {
+                    self.finish_paragraph_text();
+                }
+
                 for link in &mut self.link_buf[self.last_paragraph_i..] {
                     match link {
                         Link::Uses(ref mut x) => {
                             x.paragraph = paragraph.clone();
                         }
-                        Link::Defines(_) => (),
+                        Link::Defines(_)
+                        | Link::Alternate(_)
+                        | Link::Canonical(_)
+                        | Link::Integrity(_)
+                        | Link::Lint(_)
+                        | Link::Unparseable(_)
+                        | Link::Placeholder(_)
+                        | Link::Skipped(_)
+                        | Link::Hooked(_) => (),
                     }
                 }
                 self.in_paragraph = false;
@@ -187,7 +843,9 @@ where
     }
 
     fn set_self_closing(&mut self) {
-        if !P::is_noop() && is_paragraph_tag(&self.buffers.current_tag_name) {
+        if (!P::is_noop() || self.options.link_text)
+            && is_paragraph_tag(&self.buffers.current_tag_name)
+        {
             self.in_paragraph = false;
         }
     }
@@ -216,7 +874,9 @@ where
 
     fn emit_current_comment(&mut self) {}
     fn emit_current_doctype(&mut self) {}
-    fn emit_eof(&mut self) {}
+    fn emit_eof(&mut self) {
+        self.extract_page_text_links();
+    }
     fn emit_error(&mut self, _: Error) {}
     #[inline]
     fn should_emit_errors(&mut self) -> bool {
@@ -0,0 +1,144 @@
+use std::str::FromStr;
+
+/// A `--extract SELECTOR@ATTRIBUTE` rule: the value of `attribute` on elements matching
+/// `selector` is checked like any other link.
+///
+/// Only compound selectors are supported (`div.card`, `a[data-kind=modal]`, `*#nav`), as the
+/// parser does not keep track of ancestors.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExtractRule {
+    /// The selector as written, for error messages.
+    pub selector: &'static str,
+    pub attribute: &'static str,
+    element: Option<String>,
+    classes: Vec<String>,
+    id: Option<String>,
+    attributes: Vec<(String, Option<String>)>,
+}
+
+impl FromStr for ExtractRule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (selector, attribute) = s
+            .rsplit_once('@')
+            .filter(|(selector, attribute)| !selector.is_empty() && !attribute.is_empty())
+            .ok_or_else(|| format!("expected SELECTOR@ATTRIBUTE, got {s:?}"))?;
+
+        let mut rule = ExtractRule {
+            // rules live as long as the program, and reports refer to them as &'static str like
+            // to the built-in ones
+            selector: Box::leak(selector.to_owned().into_boxed_str()),
+            attribute: Box::leak(attribute.to_ascii_lowercase().into_boxed_str()),
+            element: None,
+            classes: Vec::new(),
+            id: None,
+            attributes: Vec::new(),
+        };
+
+        let mut rest = selector;
+        let element_end = rest.find(['.', '#', '[']).unwrap_or(rest.len());
+        match &rest[..element_end] {
+            "" | "*" => (),
+            element => rule.element = Some(element.to_ascii_lowercase()),
+        }
+        rest = &rest[element_end..];
+
+        while let Some(c) = rest.chars().next() {
+            rest = &rest[c.len_utf8()..];
+            match c {
+                '.' | '#' => {
+                    let end = rest.find(['.', '#', '[']).unwrap_or(rest.len());
+                    let name = &rest[..end];
+                    if name.is_empty() {
+                        return Err(format!("empty class or id in selector {selector:?}"));
+                    }
+                    if c == '.' {
+                        rule.classes.push(name.to_owned());
+                    } else {
+                        rule.id = Some(name.to_owned());
+                    }
+                    rest = &rest[end..];
+                }
+                '[' => {
+                    let end = rest
+                        .find(']')
+                        .ok_or_else(|| format!("unclosed [ in selector {selector:?}"))?;
+                    let condition = &rest[..end];
+                    rule.attributes.push(match condition.split_once('=') {
+                        Some((name, value)) => (
+                            name.to_ascii_lowercase(),
+                            Some(value.trim_matches(|c| c == '"' || c == '\'').to_owned()),
+                        ),
+                        None => (condition.to_ascii_lowercase(), None),
+                    });
+                    rest = &rest[end + 1..];
+                }
+                _ => return Err(format!("unsupported selector {selector:?}")),
+            }
+        }
+
+        Ok(rule)
+    }
+}
+
+impl ExtractRule {
+    /// Whether a start tag with the given name and attributes matches the selector.
+    pub fn matches(&self, element: &[u8], attributes: &[(Vec<u8>, Vec<u8>)]) -> bool {
+        let get = |name: &[u8]| {
+            attributes
+                .iter()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value.as_slice())
+        };
+
+        self.element
+            .as_ref()
+            .is_none_or(|expected| expected.as_bytes() == element)
+            && self
+                .id
+                .as_ref()
+                .is_none_or(|id| get(b"id") == Some(id.as_bytes()))
+            && self.classes.iter().all(|class| {
+                get(b"class").is_some_and(|classes| {
+                    classes
+                        .split(|c| c.is_ascii_whitespace())
+                        .any(|token| token == class.as_bytes())
+                })
+            })
+            && self.attributes.iter().all(|(name, expected)| {
+                get(name.as_bytes()).is_some_and(|value| {
+                    expected
+                        .as_ref()
+                        .is_none_or(|expected| expected.as_bytes() == value)
+                })
+            })
+    }
+}
+
+#[test]
+fn test_extract_rule() {
+    let rule: ExtractRule = "div.card.wide[data-kind=modal]@data-target"
+        .parse()
+        .unwrap();
+    assert_eq!(rule.selector, "div.card.wide[data-kind=modal]");
+    assert_eq!(rule.attribute, "data-target");
+
+    let attributes = |pairs: &[(&str, &str)]| {
+        pairs
+            .iter()
+            .map(|(key, value)| (key.as_bytes().to_vec(), value.as_bytes().to_vec()))
+            .collect::<Vec<_>>()
+    };
+
+    let card = attributes(&[("class", "wide card"), ("data-kind", "modal")]);
+    assert!(rule.matches(b"div", &card));
+    assert!(!rule.matches(b"span", &card));
+    assert!(!rule.matches(b"div", &attributes(&[("class", "card")])));
+
+    let any: ExtractRule = "*@data-src".parse().unwrap();
+    assert!(any.matches(b"img", &[]));
+
+    assert!("div.card".parse::<ExtractRule>().is_err());
+    assert!("div[data-kind@href".parse::<ExtractRule>().is_err());
+}
@@ -0,0 +1,68 @@
+//! `--check-integrity`: Subresource Integrity hashes of local scripts and stylesheets.
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use sha2::{Digest, Sha256, Sha384, Sha512};
+
+/// Hash algorithms allowed in `integrity` attributes, weakest first.
+const ALGORITHMS: &[&str] = &["sha256", "sha384", "sha512"];
+
+fn hash(algorithm: &str, contents: &[u8]) -> String {
+    match algorithm {
+        "sha256" => STANDARD.encode(Sha256::digest(contents)),
+        "sha384" => STANDARD.encode(Sha384::digest(contents)),
+        "sha512" => STANDARD.encode(Sha512::digest(contents)),
+        _ => unreachable!(),
+    }
+}
+
+/// Check `contents` against the `integrity` attribute, as a browser would: only hashes with the
+/// strongest algorithm in the attribute count, and any of them may match. Attributes without a
+/// known algorithm always match. On mismatch, returns the metadata `contents` would need, e.g.
+/// `sha384-...`.
+pub fn verify(integrity: &str, contents: &[u8]) -> Result<(), String> {
+    let hashes: Vec<(usize, &str)> = integrity
+        .split_ascii_whitespace()
+        .filter_map(|metadata| {
+            let (algorithm, rest) = metadata.split_once('-')?;
+            let strength = ALGORITHMS.iter().position(|a| *a == algorithm)?;
+            // options after `?` are reserved and ignored
+            let digest = rest.split('?').next().unwrap_or(rest);
+            Some((strength, digest))
+        })
+        .collect();
+
+    let strongest = match hashes.iter().map(|(strength, _)| *strength).max() {
+        Some(strongest) => strongest,
+        None => return Ok(()),
+    };
+
+    let actual = hash(ALGORITHMS[strongest], contents);
+    if hashes
+        .iter()
+        .any(|&(strength, digest)| strength == strongest && digest == actual)
+    {
+        Ok(())
+    } else {
+        Err(format!("{}-{}", ALGORITHMS[strongest], actual))
+    }
+}
+
+#[test]
+fn test_verify() {
+    let contents = b"alert('Hello, world.');";
+    let sha256 = "sha256-qznLcsROx4GACP2dm0UCKCzCG+HiZ1guq6ZZDob/Tng=";
+    let sha384 = "sha384-H8BRh8j48O9oYatfu5AZzq6A9RINhZO5H16dQZngK7T62em8MUt1FLm52t+eX6xO";
+
+    assert_eq!(verify(sha256, contents), Ok(()));
+    assert_eq!(verify(sha384, contents), Ok(()));
+    assert_eq!(verify(&format!("{sha256} {sha384}?foo"), contents), Ok(()));
+    assert_eq!(
+        verify(sha256, b"alert('Hello, world!');"),
+        Err("sha256-KoKrVMZQc5aqomKnyu9yTvfYVXbus1LpTaD9zExhv38=".to_owned())
+    );
+    // the weaker hash does not count if a stronger one is present
+    assert!(verify(&format!("{sha256} sha512-AAAA"), contents).is_err());
+    assert_eq!(verify("md5-AAAA", contents), Ok(()));
+    assert_eq!(verify("", contents), Ok(()));
+}
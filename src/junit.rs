@@ -0,0 +1,195 @@
+//! `--format junit`: the report as JUnit XML, for CI systems like Jenkins and Buildkite that show
+//! test results without further setup.
+
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::github::Level;
+
+/// The problems found in one file.
+#[derive(Debug, Default)]
+struct TestCase {
+    failures: Vec<String>,
+    warnings: Vec<String>,
+}
+
+/// Every checked document as a test case, failing if it has errors. Warnings do not fail a test
+/// case, they are added to its output instead.
+#[derive(Debug, Default)]
+pub struct JunitReport {
+    cases: BTreeMap<Arc<PathBuf>, TestCase>,
+}
+
+impl JunitReport {
+    /// Add `path` as a test case, which passes unless problems are added for it.
+    pub fn add_document(&mut self, path: Arc<PathBuf>) {
+        self.cases.entry(path).or_default();
+    }
+
+    /// Add a problem in the file at `path`, which is a failure if `level` is `Level::Error`.
+    pub fn add_problem(&mut self, path: Arc<PathBuf>, level: Level, message: String) {
+        let case = self.cases.entry(path).or_default();
+        match level {
+            Level::Error => case.failures.push(message),
+            Level::Warning | Level::Notice => case.warnings.push(format!("{level}: {message}")),
+        }
+    }
+
+    pub fn merge(&mut self, other: JunitReport) {
+        for (path, case) in other.cases {
+            let all = self.cases.entry(path).or_default();
+            all.failures.extend(case.failures);
+            all.warnings.extend(case.warnings);
+        }
+    }
+
+    /// Write the report as one test suite. Test cases are named by their path relative to
+    /// `base_path`, and their problems are sorted, as they are added from several threads.
+    pub fn write(
+        &self,
+        out: &mut impl Write,
+        base_path: &Path,
+        duration_secs: f64,
+    ) -> io::Result<()> {
+        let tests = self.cases.len();
+        let failures = self
+            .cases
+            .values()
+            .filter(|case| !case.failures.is_empty())
+            .count();
+
+        writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(
+            out,
+            r#"<testsuites name="hyperlink" tests="{tests}" failures="{failures}" time="{duration_secs:.3}">"#
+        )?;
+        writeln!(
+            out,
+            r#"  <testsuite name="hyperlink" tests="{tests}" failures="{failures}" errors="0" skipped="0" time="{duration_secs:.3}">"#
+        )?;
+
+        for (path, case) in &self.cases {
+            let name = path
+                .strip_prefix(base_path)
+                .unwrap_or(path)
+                .to_string_lossy()
+                .replace('\\', "/");
+            write!(
+                out,
+                r#"    <testcase name="{}" classname="hyperlink" file="{}""#,
+                escape(&name),
+                escape(&path.display().to_string())
+            )?;
+            if case.failures.is_empty() && case.warnings.is_empty() {
+                writeln!(out, "/>")?;
+                continue;
+            }
+            writeln!(out, ">")?;
+
+            let mut failures: Vec<_> = case.failures.iter().map(String::as_str).collect();
+            failures.sort_unstable();
+            let mut warnings: Vec<_> = case.warnings.iter().map(String::as_str).collect();
+            warnings.sort_unstable();
+
+            if !failures.is_empty() {
+                let message = match failures[..] {
+                    [failure] => Cow::Borrowed(failure),
+                    _ => Cow::Owned(format!("{} problems", failures.len())),
+                };
+                writeln!(
+                    out,
+                    r#"      <failure message="{}">{}</failure>"#,
+                    escape(&message),
+                    escape(&failures.join("\n"))
+                )?;
+            }
+            if !warnings.is_empty() {
+                writeln!(
+                    out,
+                    "      <system-out>{}</system-out>",
+                    escape(&warnings.join("\n"))
+                )?;
+            }
+            writeln!(out, "    </testcase>")?;
+        }
+
+        writeln!(out, "  </testsuite>")?;
+        writeln!(out, "</testsuites>")
+    }
+}
+
+/// `text` with the characters that are special in XML attributes and text escaped.
+pub(crate) fn escape(text: &str) -> Cow<'_, str> {
+    if !text.contains(&['&', '<', '>', '"', '\''][..]) {
+        return Cow::Borrowed(text);
+    }
+
+    let mut escaped = String::with_capacity(text.len() + 16);
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    Cow::Owned(escaped)
+}
+
+#[test]
+fn test_junit_report() {
+    let base_path = Path::new("public");
+    let page = |path: &str| Arc::new(base_path.join(path));
+
+    let mut report = JunitReport::default();
+    report.add_document(page("index.html"));
+    report.add_document(page("docs/index.html"));
+    report.add_problem(
+        page("docs/index.html"),
+        Level::Error,
+        "bad link /missing.html (a[href])".to_owned(),
+    );
+    let mut other = JunitReport::default();
+    other.add_problem(
+        page("docs/index.html"),
+        Level::Error,
+        "bad link /docs#<install> (a[href])".to_owned(),
+    );
+    other.add_problem(
+        page("about.html"),
+        Level::Warning,
+        "javascript-urls: javascript:void(0) is a javascript: URL (a[href])".to_owned(),
+    );
+    report.merge(other);
+
+    let mut out = Vec::new();
+    report.write(&mut out, base_path, 0.5).unwrap();
+    let path = |path: &str| base_path.join(path).display().to_string();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuites name="hyperlink" tests="3" failures="1" time="0.500">
+  <testsuite name="hyperlink" tests="3" failures="1" errors="0" skipped="0" time="0.500">
+    <testcase name="about.html" classname="hyperlink" file="{}">
+      <system-out>warning: javascript-urls: javascript:void(0) is a javascript: URL (a[href])</system-out>
+    </testcase>
+    <testcase name="docs/index.html" classname="hyperlink" file="{}">
+      <failure message="2 problems">bad link /docs#&lt;install&gt; (a[href])
+bad link /missing.html (a[href])</failure>
+    </testcase>
+    <testcase name="index.html" classname="hyperlink" file="{}"/>
+  </testsuite>
+</testsuites>
+"#,
+            path("about.html"),
+            path("docs/index.html"),
+            path("index.html"),
+        )
+    );
+}
@@ -0,0 +1,113 @@
+//! `--deny` and `--allow`: which problems fail a run, like the lint levels of rustc.
+
+use std::str::FromStr;
+
+use hyperlink::github::Category;
+use hyperlink::lints::Lint;
+
+/// What `--deny` and `--allow` apply to: a category of problems, or a single lint.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Selector {
+    Category(Category),
+    Lint(Lint),
+}
+
+impl FromStr for Selector {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.parse()
+            .map(Selector::Category)
+            .or_else(|_| s.parse().map(Selector::Lint))
+            .map_err(|_| {
+                format!(
+                    "unknown category or lint {s:?}, expected links, anchors, hreflang, \
+                     canonical, redirects, integrity, downloads, sister-links, lints, unparseable \
+                     or the name of a lint"
+                )
+            })
+    }
+}
+
+/// How problems affect the exit code, from least to most severe.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ExitLevel {
+    /// Reported, but the run still succeeds.
+    Allow,
+    /// `exit 2`, like for broken anchors.
+    Warn,
+    /// `exit 1`, like for broken links.
+    Deny,
+}
+
+impl ExitLevel {
+    pub fn exit_code(self) -> i32 {
+        match self {
+            ExitLevel::Allow => 0,
+            ExitLevel::Warn => 2,
+            ExitLevel::Deny => 1,
+        }
+    }
+}
+
+/// The `--deny` and `--allow` arguments. If something is both denied and allowed, it is denied.
+#[derive(Clone, Debug, Default)]
+pub struct ExitLevels {
+    pub deny: Vec<Selector>,
+    pub allow: Vec<Selector>,
+}
+
+impl ExitLevels {
+    fn explicit_level(&self, selector: Selector) -> Option<ExitLevel> {
+        if self.deny.contains(&selector) {
+            Some(ExitLevel::Deny)
+        } else if self.allow.contains(&selector) {
+            Some(ExitLevel::Allow)
+        } else {
+            None
+        }
+    }
+
+    /// The level of a category of problems. Lint warnings are allowed by default, broken anchors
+    /// and bad redirects are warnings, everything else is denied.
+    pub fn category(&self, category: Category) -> ExitLevel {
+        self.explicit_level(Selector::Category(category))
+            .unwrap_or(match category {
+                Category::Links
+                | Category::Hreflang
+                | Category::Canonical
+                | Category::Integrity
+                | Category::Downloads
+                | Category::SisterLinks
+                | Category::Unparseable => ExitLevel::Deny,
+                Category::Anchors | Category::Redirects => ExitLevel::Warn,
+                Category::Lints => ExitLevel::Allow,
+            })
+    }
+
+    /// The level of a single lint, which defaults to the level of `Category::Lints`.
+    pub fn lint(&self, lint: Lint) -> ExitLevel {
+        self.explicit_level(Selector::Lint(lint))
+            .unwrap_or_else(|| self.category(Category::Lints))
+    }
+}
+
+#[test]
+fn test_exit_levels() {
+    let levels = ExitLevels {
+        deny: vec!["lints".parse().unwrap(), "anchors".parse().unwrap()],
+        allow: vec![
+            "bad-ids".parse().unwrap(),
+            "anchors".parse().unwrap(),
+            "redirects".parse().unwrap(),
+        ],
+    };
+
+    assert_eq!(levels.category(Category::Anchors), ExitLevel::Deny);
+    assert_eq!(levels.category(Category::Redirects), ExitLevel::Allow);
+    assert_eq!(levels.category(Category::Links), ExitLevel::Deny);
+    assert_eq!(levels.lint(Lint::Backslashes), ExitLevel::Deny);
+    assert_eq!(levels.lint(Lint::BadIds), ExitLevel::Allow);
+    assert_eq!(ExitLevels::default().lint(Lint::BadIds), ExitLevel::Allow);
+    assert!("everything".parse::<Selector>().is_err());
+}
@@ -0,0 +1,41 @@
+//! The building blocks of the `hyperlink` command-line tool, for programs that need to customize
+//! link checking beyond what the command-line options allow. `check::check` runs the same check
+//! as `hyperlink BASE-PATH`.
+//!
+//! This API is not stable and may change in any release.
+#![allow(clippy::manual_flatten)]
+pub mod anchors;
+pub mod baseline;
+pub mod check;
+pub mod checkstyle;
+pub mod collector;
+pub mod csv;
+mod data;
+pub mod deploy;
+pub mod downloads;
+mod feeds;
+pub mod filters;
+pub mod github;
+pub mod html;
+pub mod integrity;
+pub mod junit;
+pub mod lints;
+pub mod markdown;
+pub mod metrics;
+pub mod paragraph;
+#[cfg(feature = "pdf")]
+mod pdf;
+pub mod presets;
+pub mod rdjson;
+pub mod redirects;
+pub mod report;
+pub mod routes;
+pub mod sarif;
+pub mod shards;
+pub mod sisters;
+pub mod sources;
+pub mod tap;
+pub mod targets;
+pub mod timings;
+pub mod urls;
+pub mod walk;
@@ -0,0 +1,862 @@
+//! Opt-in checks for problems that are not broken links, enabled with `--warn`.
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::urls::{is_external_link, without_scheme};
+
+/// A check that reports problems as warnings when enabled.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Lint {
+    /// File names that deploy fine from Linux, but cannot be created on Windows or by sync tools.
+    HostileFilenames,
+    /// Paths too long for some CDNs, Windows checkouts or S3 sync tools.
+    LongPaths,
+    /// Backup, merge and editor swap files, such as `page.html.bak`, that were published by mistake.
+    BackupFiles,
+    /// Hrefs with spaces, quotes or other characters that must be percent-encoded.
+    UnencodedCharacters,
+    /// Hrefs with malformed or double-encoded percent escapes.
+    PercentEncoding,
+    /// Hrefs using backslashes as path separators.
+    Backslashes,
+    /// Relative hrefs with more `..` than the document is deep.
+    EscapesRoot,
+    /// `javascript:` hrefs, which some security policies ban from generated output.
+    JavascriptUrls,
+    /// Empty `id` attributes, or ones containing whitespace, which no fragment can point at.
+    BadIds,
+    /// Absolute URLs to the site itself, which break when it moves to another domain.
+    AbsoluteUrls,
+    /// Root-relative hrefs, for sites whose links must be absolute, e.g. because they are syndicated.
+    RootRelativeUrls,
+    /// Links to `.html` files, for sites that link to pretty URLs such as `page/` instead.
+    HtmlExtensions,
+    /// Hrefs with `;`-delimited path parameters such as `;jsessionid=abc`.
+    PathParameters,
+    /// Malformed `data:` URIs, e.g. inline images truncated by a template.
+    BadDataUris,
+    /// Links to ids that Sphinx numbers in document order, like `page.html#id1`.
+    AutoIds,
+}
+
+impl Lint {
+    pub const ALL: &'static [Lint] = &[
+        Lint::HostileFilenames,
+        Lint::LongPaths,
+        Lint::BackupFiles,
+        Lint::UnencodedCharacters,
+        Lint::PercentEncoding,
+        Lint::Backslashes,
+        Lint::EscapesRoot,
+        Lint::JavascriptUrls,
+        Lint::BadIds,
+        Lint::AbsoluteUrls,
+        Lint::RootRelativeUrls,
+        Lint::HtmlExtensions,
+        Lint::PathParameters,
+        Lint::BadDataUris,
+        Lint::AutoIds,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Lint::HostileFilenames => "hostile-filenames",
+            Lint::LongPaths => "long-paths",
+            Lint::BackupFiles => "backup-files",
+            Lint::UnencodedCharacters => "unencoded-characters",
+            Lint::PercentEncoding => "percent-encoding",
+            Lint::Backslashes => "backslashes",
+            Lint::EscapesRoot => "escapes-root",
+            Lint::JavascriptUrls => "javascript-urls",
+            Lint::BadIds => "bad-ids",
+            Lint::AbsoluteUrls => "absolute-urls",
+            Lint::RootRelativeUrls => "root-relative-urls",
+            Lint::HtmlExtensions => "html-extensions",
+            Lint::PathParameters => "path-parameters",
+            Lint::BadDataUris => "bad-data-uris",
+            Lint::AutoIds => "auto-ids",
+        }
+    }
+}
+
+impl fmt::Display for Lint {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "{}", self.name())
+    }
+}
+
+impl FromStr for Lint {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Lint::ALL
+            .iter()
+            .copied()
+            .find(|lint| lint.name() == s)
+            .ok_or_else(|| {
+                let names: Vec<_> = Lint::ALL.iter().map(|lint| lint.name()).collect();
+                format!("unknown lint {s:?}, expected one of {}", names.join(", "))
+            })
+    }
+}
+
+/// Names that Windows reserves for devices, with or without an extension.
+const RESERVED_NAMES: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Characters that are not allowed in file names on Windows.
+const RESERVED_CHARS: &[char] = &['<', '>', ':', '"', '\\', '|', '?', '*'];
+
+/// Why `file_name` breaks on some platforms or hosts, if it does.
+pub fn hostile_filename(file_name: &str) -> Option<String> {
+    let stem = file_name.split('.').next().unwrap_or(file_name);
+    if RESERVED_NAMES
+        .iter()
+        .any(|name| name.eq_ignore_ascii_case(stem.trim_end()))
+    {
+        return Some(format!(
+            "{file_name:?} is a reserved device name on Windows"
+        ));
+    }
+
+    if file_name.ends_with('.') {
+        return Some(format!("{file_name:?} ends with a dot"));
+    }
+
+    if file_name.ends_with(' ') {
+        return Some(format!("{file_name:?} ends with a space"));
+    }
+
+    if let Some(c) = file_name
+        .chars()
+        .find(|c| RESERVED_CHARS.contains(c) || c.is_ascii_control())
+    {
+        return Some(format!(
+            "{file_name:?} contains {c:?}, which is not allowed on Windows"
+        ));
+    }
+
+    None
+}
+
+/// Extensions of files left behind by editors, backup scripts and merge tools.
+const BACKUP_EXTENSIONS: &[&str] = &["bak", "orig", "rej", "swp", "swo"];
+
+/// Why `file_name` should not be deployed, if it looks like a backup or swap file. These often
+/// contain drafts or template sources, and servers send them as plain text.
+pub fn backup_file(file_name: &str) -> Option<String> {
+    if file_name.len() > 1 && file_name.ends_with('~') {
+        return Some(format!("{file_name:?} looks like an editor backup file"));
+    }
+
+    let (_, extension) = file_name.rsplit_once('.')?;
+    BACKUP_EXTENSIONS
+        .iter()
+        .any(|backup| extension.eq_ignore_ascii_case(backup))
+        .then(|| {
+            format!("{file_name:?} looks like a .{extension} file that should not be deployed")
+        })
+}
+
+/// Maximum lengths in bytes for `Lint::LongPaths`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PathLimits {
+    /// Length of a single file or directory name.
+    pub segment: usize,
+    /// Length of the whole path below the site root.
+    pub total: usize,
+}
+
+impl Default for PathLimits {
+    fn default() -> Self {
+        PathLimits {
+            segment: 255,
+            total: 2000,
+        }
+    }
+}
+
+/// Why `path`, relative to the site root, is too long, if it is.
+pub fn long_path(path: &str, limits: &PathLimits) -> Option<String> {
+    if path.len() > limits.total {
+        return Some(format!(
+            "path is {} bytes long, more than {}",
+            path.len(),
+            limits.total
+        ));
+    }
+
+    let segment = path
+        .split('/')
+        .find(|segment| segment.len() > limits.segment)?;
+    let start: String = segment.chars().take(20).collect();
+    Some(format!(
+        "\"{start}...\" is {} bytes long, more than {}",
+        segment.len(),
+        limits.segment
+    ))
+}
+
+/// Run `lint` on the value of a used link, if it is a lint on hrefs. `directory` is the
+/// directory of the document containing the link, relative to the site root. `base_url` is the
+/// URL of the site root from `--base-url`, without trailing slash.
+pub fn check_href(
+    lint: Lint,
+    directory: &str,
+    href: &str,
+    base_url: Option<&str>,
+) -> Option<String> {
+    match lint {
+        Lint::EscapesRoot => escapes_root(directory, href),
+        Lint::UnencodedCharacters => unencoded_characters(href),
+        Lint::PercentEncoding => bad_percent_encoding(href),
+        Lint::Backslashes => backslashes(href),
+        Lint::JavascriptUrls => javascript_url(href),
+        Lint::AbsoluteUrls => absolute_url(href, base_url?),
+        Lint::RootRelativeUrls => root_relative_url(href, base_url),
+        Lint::HtmlExtensions => html_extension(href, base_url),
+        Lint::PathParameters => path_parameters(href),
+        Lint::BadDataUris => bad_data_uri(href),
+        Lint::AutoIds => auto_id(href),
+        Lint::HostileFilenames | Lint::LongPaths | Lint::BackupFiles | Lint::BadIds => None,
+    }
+}
+
+/// Whether lints on hrefs apply to `href`. Other schemes such as `mailto:` have their own rules.
+fn is_http_or_local(href: &str) -> bool {
+    let scheme = href.split(':').next().unwrap_or("");
+    !is_external_link(href.as_bytes())
+        || href.starts_with("//")
+        || scheme.eq_ignore_ascii_case("http")
+        || scheme.eq_ignore_ascii_case("https")
+}
+
+/// Characters that browsers tolerate in hrefs, but crawlers and strict proxies do not.
+/// Backslashes are left to their own lint.
+const UNENCODED_CHARS: &[char] = &[' ', '"', '<', '>', '`', '{', '}', '|', '^'];
+
+/// Why `href` needs percent-encoding, if it does.
+pub fn unencoded_characters(href: &str) -> Option<String> {
+    if !is_http_or_local(href) {
+        return None;
+    }
+
+    let c = href
+        .chars()
+        .find(|c| UNENCODED_CHARS.contains(c) || c.is_ascii_control())?;
+    let what = match c {
+        ' ' => "a space".to_owned(),
+        '"' => "a quote".to_owned(),
+        c => format!("{c:?}"),
+    };
+    Some(format!(
+        "{href} contains {what}, which must be percent-encoded"
+    ))
+}
+
+/// Why the percent escapes in `href` are wrong, if they are. These usually point at an encoding
+/// bug in the site generator rather than at a missing file.
+pub fn bad_percent_encoding(href: &str) -> Option<String> {
+    if !is_http_or_local(href) {
+        return None;
+    }
+
+    let is_hex = |bytes: &[u8]| bytes.iter().all(u8::is_ascii_hexdigit);
+
+    for (i, _) in href.match_indices('%') {
+        let bytes = &href.as_bytes()[i + 1..];
+        match bytes.get(..2) {
+            Some(escape) if is_hex(escape) => {
+                if escape == b"25" && bytes.get(2..4).is_some_and(is_hex) {
+                    let double = &href[i..i + 5];
+                    return Some(format!(
+                        "{href} looks double-encoded, {double} decodes to %{}",
+                        &double[3..]
+                    ));
+                }
+            }
+            _ => {
+                let escape: String = href[i..].chars().take(3).collect();
+                return Some(format!("{href} contains malformed escape {escape}"));
+            }
+        }
+    }
+
+    None
+}
+
+/// The href with forward slashes, if `href` uses backslashes. Some browsers on Windows treat
+/// them as path separators, everywhere else they are part of the file name.
+pub fn backslashes(href: &str) -> Option<String> {
+    if !is_http_or_local(href) || !href.contains('\\') {
+        return None;
+    }
+
+    Some(format!(
+        "{href} contains a backslash, did you mean {}?",
+        href.replace('\\', "/")
+    ))
+}
+
+/// Whether resolving `href` against `directory` climbs above the site root. Browsers stop at the
+/// root, so the link may still work, but not if the site is ever served from a subdirectory.
+pub fn escapes_root(directory: &str, href: &str) -> Option<String> {
+    if is_external_link(href.as_bytes()) {
+        return None;
+    }
+
+    let path = &href[..href.find(&['?', '#'][..]).unwrap_or(href.len())];
+    let mut depth = if path.starts_with('/') {
+        0
+    } else {
+        directory.split('/').filter(|c| !c.is_empty()).count()
+    };
+
+    for component in path.split('/') {
+        match component {
+            "" | "." => {}
+            ".." if depth == 0 => {
+                return Some(format!("{href} climbs above the site root"));
+            }
+            ".." => depth -= 1,
+            _ => depth += 1,
+        }
+    }
+
+    None
+}
+
+/// Whether `href` runs JavaScript when followed. Like browsers, this ignores case and tabs or
+/// newlines in the scheme.
+pub fn javascript_url(href: &str) -> Option<String> {
+    let (scheme, _) = href.split_once(':')?;
+    let scheme: String = scheme
+        .chars()
+        .filter(|c| !matches!(c, '\t' | '\n' | '\r'))
+        .collect();
+
+    if scheme.eq_ignore_ascii_case("javascript") {
+        Some(format!("{href} is a javascript: URL"))
+    } else {
+        None
+    }
+}
+
+/// What follows `base_url` in `href`, if `href` is a URL below it with either scheme.
+fn below_base_url<'h>(href: &'h str, base_url: &str) -> Option<&'h str> {
+    let rest = without_scheme(href)?.strip_prefix(without_scheme(base_url)?)?;
+    if rest.is_empty() || rest.starts_with(&['/', '?', '#'][..]) {
+        Some(rest)
+    } else {
+        None
+    }
+}
+
+/// The root-relative href to use instead of `href`, if it is an absolute URL to the site at
+/// `base_url`.
+pub fn absolute_url(href: &str, base_url: &str) -> Option<String> {
+    let rest = below_base_url(href, base_url)?;
+    let path = if rest.starts_with('/') {
+        rest.to_owned()
+    } else {
+        format!("/{rest}")
+    };
+    Some(format!(
+        "{href} is an absolute URL to this site, use {path} instead"
+    ))
+}
+
+/// Whether `href` is root-relative, for sites whose links must be absolute. Suggests the URL
+/// below `base_url` if there is one.
+pub fn root_relative_url(href: &str, base_url: Option<&str>) -> Option<String> {
+    if !href.starts_with('/') || href.starts_with("//") {
+        return None;
+    }
+
+    Some(match base_url {
+        Some(base_url) => format!("{href} is root-relative, use {base_url}{href} instead"),
+        None => format!("{href} is root-relative, but links must be absolute"),
+    })
+}
+
+/// The pretty URL to use instead of `href`, if it links to an `.html` file on the site.
+/// `index.html` becomes its directory, other files lose their extension.
+pub fn html_extension(href: &str, base_url: Option<&str>) -> Option<String> {
+    let local = match base_url.and_then(|base_url| below_base_url(href, base_url)) {
+        Some(rest) => rest,
+        None if is_external_link(href.as_bytes()) => return None,
+        None => href,
+    };
+
+    let (path, suffix) = local.split_at(local.find(&['?', '#'][..]).unwrap_or(local.len()));
+    let pretty = [".html", ".htm"]
+        .iter()
+        .find_map(|extension| path.strip_suffix(extension))
+        .filter(|stem| !stem.is_empty() && !stem.ends_with('/'))?;
+    let pretty = match pretty.strip_suffix("index") {
+        Some("") => ".",
+        Some(directory) if directory.ends_with('/') => directory,
+        _ => pretty,
+    };
+    Some(format!(
+        "{href} links to an .html file, use {pretty}{suffix} instead"
+    ))
+}
+
+/// Whether `fragment` looks like an id that docutils, and so Sphinx, generates when a section or
+/// footnote has no name of its own or its name is taken: `id` followed by a number.
+pub fn is_auto_id(fragment: &str) -> bool {
+    fragment
+        .strip_prefix("id")
+        .is_some_and(|number| !number.is_empty() && number.bytes().all(|b| b.is_ascii_digit()))
+}
+
+/// Why `href` should not point at its fragment, if it links to an auto-generated id on another
+/// page. Sphinx numbers these in document order, so they point at another section as soon as one
+/// is added above. Links within the page, like headerlinks and footnotes, are renumbered with it.
+pub fn auto_id(href: &str) -> Option<String> {
+    if is_external_link(href.as_bytes()) {
+        return None;
+    }
+
+    let (page, fragment) = href.split_once('#')?;
+    if page.is_empty() || !is_auto_id(fragment) {
+        return None;
+    }
+    Some(format!(
+        "{href} points at #{fragment}, which Sphinx numbers automatically, link to a label instead"
+    ))
+}
+
+/// The first path parameter in `href`, like `;jsessionid=abc` in `/doc;jsessionid=abc`, if it has
+/// any. Static hosts treat them as part of the file name, so these are usually copied over from a
+/// dynamic site by accident.
+pub fn path_parameters(href: &str) -> Option<String> {
+    if !is_http_or_local(href) {
+        return None;
+    }
+
+    let path = &href[..href.find(&['?', '#'][..]).unwrap_or(href.len())];
+    // the host of an absolute URL is not part of the path
+    let path = match without_scheme(path) {
+        Some(rest) => rest[2..].find('/').map_or("", |i| &rest[2 + i..]),
+        None => path,
+    };
+    let start = path.find(';')?;
+    let end = path[start..].find('/').map_or(path.len(), |i| start + i);
+    Some(format!(
+        "{href} has the path parameter {}, which static hosts do not strip",
+        &path[start..end]
+    ))
+}
+
+/// Decode base64 like browsers do for `data:` URIs: ASCII whitespace is ignored and padding is
+/// optional. `None` if `data` is not valid base64.
+fn forgiving_base64_decode(data: &[u8]) -> Option<Vec<u8>> {
+    let mut data: Vec<u8> = data
+        .iter()
+        .copied()
+        .filter(|byte| !byte.is_ascii_whitespace())
+        .collect();
+    if data.len().is_multiple_of(4) && data.ends_with(b"=") {
+        data.pop();
+        if data.ends_with(b"=") {
+            data.pop();
+        }
+    }
+    if data.len() % 4 == 1 {
+        return None;
+    }
+
+    let mut decoded = Vec::with_capacity(data.len() * 3 / 4);
+    let (mut buffer, mut bits) = (0u32, 0);
+    for byte in data {
+        let value = match byte {
+            b'A'..=b'Z' => byte - b'A',
+            b'a'..=b'z' => byte - b'a' + 26,
+            b'0'..=b'9' => byte - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            _ => return None,
+        };
+        buffer = buffer << 6 | u32::from(value);
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            decoded.push((buffer >> bits) as u8);
+        }
+    }
+    Some(decoded)
+}
+
+/// The signature image files of `media_type` start with, and the bytes they end with.
+fn image_bounds(media_type: &str) -> Option<(&'static [&'static [u8]], &'static [u8])> {
+    match media_type {
+        "image/png" => Some((&[b"\x89PNG\r\n\x1a\n"], b"IEND\xaeB`\x82")),
+        "image/jpeg" => Some((&[b"\xff\xd8\xff"], b"\xff\xd9")),
+        "image/gif" => Some((&[b"GIF87a", b"GIF89a"], b";")),
+        _ => None,
+    }
+}
+
+/// Whether `href` is a `data:` URI that browsers cannot make sense of: no comma before the data,
+/// an implausible media type, invalid base64, no data at all, or a PNG, JPEG or GIF image that
+/// does not start or end like one, which usually means it was cut off.
+pub fn bad_data_uri(href: &str) -> Option<String> {
+    let (scheme, rest) = href.split_once(':')?;
+    if !scheme.eq_ignore_ascii_case("data") {
+        return None;
+    }
+
+    // inline images can be megabytes long
+    let mut start: String = href.chars().take(30).collect();
+    if start.len() < href.len() {
+        start.push_str("...");
+    }
+    let problem = |problem: &str| Some(format!("\"{start}\" {problem}"));
+    let (metadata, data) = match rest.split_once(',') {
+        Some(parts) => parts,
+        None => return problem("has no comma before its data"),
+    };
+
+    let mut parameters = metadata.split(';').map(str::trim);
+    let media_type = parameters.next().unwrap_or("").to_ascii_lowercase();
+    let is_token = |part: &str| {
+        !part.is_empty()
+            && part
+                .bytes()
+                .all(|byte| byte.is_ascii_alphanumeric() || b"!#$&-^_.+".contains(&byte))
+    };
+    if !media_type.is_empty()
+        && !media_type
+            .split_once('/')
+            .is_some_and(|(kind, subtype)| is_token(kind) && is_token(subtype))
+    {
+        return problem(&format!("has an invalid media type {media_type:?}"));
+    }
+
+    let data: Vec<u8> = percent_encoding::percent_decode_str(data).collect();
+    let data = if parameters.any(|parameter| parameter.eq_ignore_ascii_case("base64")) {
+        match forgiving_base64_decode(&data) {
+            Some(decoded) => decoded,
+            None => return problem("is not valid base64"),
+        }
+    } else {
+        data
+    };
+    if data.is_empty() {
+        return problem("has no data");
+    }
+
+    let (signatures, end) = image_bounds(&media_type)?;
+    if !signatures
+        .iter()
+        .any(|signature| data.starts_with(signature))
+    {
+        problem(&format!("does not look like {media_type}"))
+    } else if !data.ends_with(end) {
+        problem(&format!("is a truncated {media_type}"))
+    } else {
+        None
+    }
+}
+
+/// Why the `id` of `element` cannot be linked to, if it cannot. These are usually produced by
+/// templates that interpolate an unset or unslugified variable.
+pub fn bad_id(element: &str, id: &str) -> Option<String> {
+    if id.is_empty() {
+        return Some(format!("<{element}> has an empty id"));
+    }
+
+    if id.contains(|c: char| c.is_ascii_whitespace()) {
+        return Some(format!(
+            "<{element} id={id:?}> contains whitespace, which no fragment can point at"
+        ));
+    }
+
+    None
+}
+
+#[test]
+fn test_bad_id() {
+    assert_eq!(bad_id("h2", "getting-started"), None);
+    assert_eq!(bad_id("h2", "über"), None);
+    assert_eq!(bad_id("div", "").unwrap(), "<div> has an empty id");
+    assert_eq!(
+        bad_id("h2", "getting started").unwrap(),
+        r#"<h2 id="getting started"> contains whitespace, which no fragment can point at"#
+    );
+    assert!(bad_id("h2", "title\n").is_some());
+    assert_eq!("bad-ids".parse(), Ok(Lint::BadIds));
+}
+
+#[test]
+fn test_absolute_url() {
+    let base_url = "https://example.com/docs";
+    assert_eq!(
+        absolute_url("https://example.com/docs/guide/", base_url).unwrap(),
+        "https://example.com/docs/guide/ is an absolute URL to this site, use /guide/ instead"
+    );
+    assert_eq!(
+        absolute_url("http://example.com/docs", base_url).unwrap(),
+        "http://example.com/docs is an absolute URL to this site, use / instead"
+    );
+    assert!(absolute_url("//example.com/docs#top", base_url).is_some());
+    assert_eq!(
+        absolute_url("https://example.com/docsearch", base_url),
+        None
+    );
+    assert_eq!(absolute_url("https://example.org/docs/", base_url), None);
+    assert_eq!(absolute_url("/guide/", base_url), None);
+    assert_eq!("absolute-urls".parse(), Ok(Lint::AbsoluteUrls));
+}
+
+#[test]
+fn test_root_relative_url() {
+    assert_eq!(
+        root_relative_url("/guide/", Some("https://example.com")).unwrap(),
+        "/guide/ is root-relative, use https://example.com/guide/ instead"
+    );
+    assert_eq!(
+        root_relative_url("/guide/", None).unwrap(),
+        "/guide/ is root-relative, but links must be absolute"
+    );
+    assert_eq!(root_relative_url("guide/", None), None);
+    assert_eq!(root_relative_url("//cdn.example.com/app.js", None), None);
+}
+
+#[test]
+fn test_html_extension() {
+    assert_eq!(
+        html_extension("/guide/install.html#linux", None).unwrap(),
+        "/guide/install.html#linux links to an .html file, use /guide/install#linux instead"
+    );
+    assert_eq!(
+        html_extension("../index.html", None).unwrap(),
+        "../index.html links to an .html file, use ../ instead"
+    );
+    assert_eq!(
+        html_extension("index.htm?lang=de", None).unwrap(),
+        "index.htm?lang=de links to an .html file, use .?lang=de instead"
+    );
+    assert!(html_extension("https://example.com/a.html", Some("https://example.com")).is_some());
+    assert_eq!(html_extension("https://example.org/a.html", None), None);
+    assert_eq!(html_extension("/guide/", None), None);
+    assert_eq!(html_extension("/.html", None), None);
+}
+
+#[test]
+fn test_auto_id() {
+    assert_eq!(
+        auto_id("install.html#id3").unwrap(),
+        "install.html#id3 points at #id3, which Sphinx numbers automatically, link to a label instead"
+    );
+    assert!(auto_id("../api/#id12").is_some());
+    assert_eq!(auto_id("#id1"), None);
+    assert_eq!(auto_id("install.html#id"), None);
+    assert_eq!(auto_id("install.html#idea"), None);
+    assert_eq!(auto_id("install.html#installation"), None);
+    assert_eq!(auto_id("https://example.com/page.html#id1"), None);
+    assert!(is_auto_id("id10"));
+    assert!(!is_auto_id("id1a"));
+    assert_eq!("auto-ids".parse(), Ok(Lint::AutoIds));
+}
+
+#[test]
+fn test_path_parameters() {
+    assert_eq!(
+        path_parameters("/doc;jsessionid=abc").unwrap(),
+        "/doc;jsessionid=abc has the path parameter ;jsessionid=abc, which static hosts do not strip"
+    );
+    assert_eq!(
+        path_parameters("https://example.com/a;v=1/b?q=1").unwrap(),
+        "https://example.com/a;v=1/b?q=1 has the path parameter ;v=1, which static hosts do not strip"
+    );
+    assert_eq!(path_parameters("/search?a=1;b=2"), None);
+    assert_eq!(path_parameters("/guide#a;b"), None);
+    assert_eq!(path_parameters("https://user;x@example.com/"), None);
+    assert_eq!(path_parameters("data:text/plain;base64,aGk="), None);
+    assert_eq!("path-parameters".parse(), Ok(Lint::PathParameters));
+}
+
+#[test]
+fn test_bad_data_uri() {
+    // a 1x1 GIF
+    let gif = "data:image/gif;base64,R0lGODlhAQABAAAAACH5BAEKAAEALAAAAAABAAEAAAICTAEAOw==";
+    assert_eq!(bad_data_uri(gif), None);
+    assert_eq!(bad_data_uri(&gif.replace("==", "")), None);
+    assert_eq!(
+        bad_data_uri(&gif[..60]).unwrap(),
+        "\"data:image/gif;base64,R0lGODlh...\" is a truncated image/gif"
+    );
+    assert_eq!(
+        bad_data_uri("data:image/png;base64,R0lGODlhAQABAAAAACH5BAEKAAEALAAAAAABAAEAAAICTAEAOw==")
+            .unwrap(),
+        "\"data:image/png;base64,R0lGODlh...\" does not look like image/png"
+    );
+    assert_eq!(
+        bad_data_uri("data:image/png;base64,").unwrap(),
+        "\"data:image/png;base64,\" has no data"
+    );
+    assert_eq!(
+        bad_data_uri("data:image/png;base64,iVBO%RW0K").unwrap(),
+        "\"data:image/png;base64,iVBO%RW0...\" is not valid base64"
+    );
+    assert_eq!(
+        bad_data_uri("data:image png;base64,iVBO").unwrap(),
+        "\"data:image png;base64,iVBO\" has an invalid media type \"image png\""
+    );
+    assert_eq!(
+        bad_data_uri("data:image/svg+xml;base64").unwrap(),
+        "\"data:image/svg+xml;base64\" has no comma before its data"
+    );
+    assert_eq!(bad_data_uri("data:,Hello%2C%20World"), None);
+    assert_eq!(
+        bad_data_uri("data:text/plain;charset=utf-8,caf%C3%A9"),
+        None
+    );
+    assert_eq!(bad_data_uri("https://example.com/data:"), None);
+    assert_eq!(forgiving_base64_decode(b"aGk=\n"), Some(b"hi".to_vec()));
+    assert_eq!(forgiving_base64_decode(b"aGk"), Some(b"hi".to_vec()));
+    assert_eq!(forgiving_base64_decode(b"a"), None);
+}
+
+#[test]
+fn test_javascript_url() {
+    assert_eq!(javascript_url("/javascript/"), None);
+    assert_eq!(
+        javascript_url("https://example.com/javascript:void(0)"),
+        None
+    );
+    assert_eq!(
+        javascript_url("javascript:void(0)").unwrap(),
+        "javascript:void(0) is a javascript: URL"
+    );
+    assert!(javascript_url("JavaScript:alert(1)").is_some());
+    assert!(javascript_url("java\tscript:alert(1)").is_some());
+}
+
+#[test]
+fn test_escapes_root() {
+    assert_eq!(escapes_root("docs/guide", "../../index.html"), None);
+    assert_eq!(escapes_root("", "./style.css"), None);
+    assert_eq!(
+        escapes_root("docs", "../../etc/passwd").unwrap(),
+        "../../etc/passwd climbs above the site root"
+    );
+    assert!(escapes_root("docs/guide", "/../index.html").is_some());
+    assert!(escapes_root("", "a/../../b").is_some());
+    assert_eq!(escapes_root("", "https://example.com/../a"), None);
+}
+
+#[test]
+fn test_backslashes() {
+    assert_eq!(backslashes("img/logo.png"), None);
+    assert_eq!(
+        backslashes(r"img\logo.png").unwrap(),
+        r"img\logo.png contains a backslash, did you mean img/logo.png?"
+    );
+    assert_eq!(backslashes(r"file:C:\logo.png"), None);
+}
+
+#[test]
+fn test_bad_percent_encoding() {
+    assert_eq!(bad_percent_encoding("/a%20b/%C3%BC.html"), None);
+    assert_eq!(bad_percent_encoding("/100%25"), None);
+    assert_eq!(
+        bad_percent_encoding("/a%2").unwrap(),
+        "/a%2 contains malformed escape %2"
+    );
+    assert_eq!(
+        bad_percent_encoding("/a%ZZb").unwrap(),
+        "/a%ZZb contains malformed escape %ZZ"
+    );
+    assert_eq!(
+        bad_percent_encoding("/a%2520b").unwrap(),
+        "/a%2520b looks double-encoded, %2520 decodes to %20"
+    );
+    assert_eq!(
+        bad_percent_encoding("/%ü"),
+        Some("/%ü contains malformed escape %ü".to_owned())
+    );
+    assert_eq!(bad_percent_encoding("javascript:alert('100%')"), None);
+}
+
+#[test]
+fn test_unencoded_characters() {
+    assert_eq!(unencoded_characters("/docs/a%20b.html"), None);
+    assert_eq!(unencoded_characters("/über/"), None);
+    assert_eq!(
+        unencoded_characters("/docs/a b.html").unwrap(),
+        "/docs/a b.html contains a space, which must be percent-encoded"
+    );
+    assert_eq!(
+        unencoded_characters("https://example.com/?q={x}").unwrap(),
+        "https://example.com/?q={x} contains '{', which must be percent-encoded"
+    );
+    assert!(unencoded_characters("a\"b").is_some());
+    assert_eq!(
+        unencoded_characters("mailto:me@example.com?subject=Hi there"),
+        None
+    );
+}
+
+#[test]
+fn test_long_path() {
+    let limits = PathLimits {
+        segment: 10,
+        total: 20,
+    };
+    assert_eq!(long_path("docs/index.html", &limits), None);
+    assert_eq!(
+        long_path("docs/installation.html", &limits).unwrap(),
+        "path is 22 bytes long, more than 20"
+    );
+    assert_eq!(
+        long_path("a/installing.html", &limits).unwrap(),
+        r#""installing.html..." is 15 bytes long, more than 10"#
+    );
+    assert_eq!(long_path(&"a/".repeat(10), &PathLimits::default()), None);
+}
+
+#[test]
+fn test_backup_file() {
+    assert_eq!(backup_file("index.html"), None);
+    assert_eq!(backup_file("backup.html"), None);
+    assert_eq!(
+        backup_file("page.html.bak").unwrap(),
+        r#""page.html.bak" looks like a .bak file that should not be deployed"#
+    );
+    assert!(backup_file("style.css.ORIG").is_some());
+    assert!(backup_file(".index.html.swp").is_some());
+    assert_eq!(
+        backup_file("index.html~").unwrap(),
+        r#""index.html~" looks like an editor backup file"#
+    );
+    assert_eq!(backup_file("~"), None);
+    assert_eq!("backup-files".parse(), Ok(Lint::BackupFiles));
+}
+
+#[test]
+fn test_hostile_filename() {
+    assert_eq!(hostile_filename("index.html"), None);
+    assert_eq!(hostile_filename("console.html"), None);
+    assert_eq!(
+        hostile_filename("nul.txt").unwrap(),
+        r#""nul.txt" is a reserved device name on Windows"#
+    );
+    assert!(hostile_filename("CON").is_some());
+    assert!(hostile_filename("com1 .html").is_some());
+    assert!(hostile_filename("draft.").is_some());
+    assert!(hostile_filename("draft ").is_some());
+    assert_eq!(
+        hostile_filename("what?.html").unwrap(),
+        r#""what?.html" contains '?', which is not allowed on Windows"#
+    );
+    assert!(hostile_filename("10:30.html").is_some());
+    assert_eq!("hostile-filenames".parse(), Ok(Lint::HostileFilenames));
+    assert!("hostile".parse::<Lint>().is_err());
+}
@@ -1,45 +1,433 @@
 #![allow(clippy::manual_flatten)]
-mod collector;
-mod html;
-mod markdown;
-mod paragraph;
-mod urls;
+mod git;
+mod levels;
+mod permalinks;
+mod ratchet;
+mod update;
 
 use std::cmp;
 use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io::{self, Write};
+use std::iter;
 use std::mem;
 use std::path::{Path, PathBuf};
 use std::process;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Instant, SystemTime};
 
 use anyhow::{anyhow, Context, Error};
 use bpaf::*;
-use jwalk::WalkDirGeneric;
-use markdown::DocumentSource;
-use rayon::prelude::*;
 
-use collector::{BrokenLinkCollector, LinkCollector, LocalLinksOnly, UsedLinkCollector};
-use html::{DefinedLink, Document, DocumentBuffers, Link};
-use paragraph::{DebugParagraphWalker, NoopParagraphWalker, ParagraphHasher, ParagraphWalker};
-
-use crate::urls::is_external_link;
+use hyperlink::anchors::{self, AnchorsIndex};
+use hyperlink::baseline::{Baseline, Date};
+use hyperlink::check::{self, BadHref, Check, CheckOptions, SiteCollector};
+use hyperlink::collector::{
+    AnchorDefinition, BrokenLinkCollector, LinkCollector, LocalLinksOnly, UsedLinkCollector,
+};
+use hyperlink::deploy::DeployIgnore;
+use hyperlink::downloads::Downloads;
+use hyperlink::filters;
+use hyperlink::github::{self, Category, Level, SeverityOverride};
+use hyperlink::html::{
+    Document, DocumentBuffers, ExtractRule, FragmentEncoding, Href, Link, LinkOrigin, LinkText,
+    ParseOptions, SkipReason, UsedLink,
+};
+use hyperlink::lints::{Lint, PathLimits};
+use hyperlink::markdown::DocumentSource;
+use hyperlink::metrics::{Metrics, RunInfo};
+use hyperlink::paragraph::{
+    DebugParagraphWalker, NoopParagraphWalker, ParagraphHasher, ParagraphOptions, VoidParagraph,
+};
+use hyperlink::presets::Preset;
+use hyperlink::redirects::Redirects;
+use hyperlink::report::{page_error_category, page_error_level, SinkRegistry, Summary};
+use hyperlink::routes::SpaRoutes;
+use hyperlink::shards::{self, Shard, ShardDump};
+use hyperlink::sisters::{LinkIndex, SisterSites};
+use hyperlink::sources::{SourceMapping, SourcePrefix};
+use hyperlink::targets::{ExtraAnchors, ExtraTargets};
+use hyperlink::timings::Timings;
+use hyperlink::urls::is_external_link;
+use hyperlink::walk::{
+    self, extract_html_links, extract_markdown_paragraphs, HtmlResult, StagePools, MARKDOWN_FILES,
+};
+
+use git::Blame;
+use levels::{ExitLevel, ExitLevels, Selector};
+
+/// With any `--format` but `text`, stdout only gets that report, and everything else goes to
+/// stderr.
+static TEXT_TO_STDERR: AtomicBool = AtomicBool::new(false);
+
+/// `println!` for the text report, see `TEXT_TO_STDERR`.
+macro_rules! reportln {
+    ($($arg:tt)*) => {
+        if TEXT_TO_STDERR.load(Ordering::Relaxed) {
+            eprintln!($($arg)*)
+        } else {
+            println!($($arg)*)
+        }
+    };
+}
 
-static MARKDOWN_FILES: &[&str] = &["md", "mdx"];
 static HTML_FILES: &[&str] = &["htm", "html"];
 
-#[derive(Bpaf, PartialEq, Debug)]
+#[derive(Bpaf, Clone, PartialEq, Debug)]
 struct MainCommand {
     /// whether to check for valid anchor references
     #[bpaf(long)]
     check_anchors: bool,
 
-    /// path to directory of markdown files to use for reporting errors
+    /// with --check-anchors, also check that the ids in aria-labelledby and aria-describedby exist
+    /// in the same document
+    #[bpaf(long)]
+    check_aria: bool,
+
+    /// with --check-anchors, compare fragments and ids percent-decoded (the default) or as written
+    #[bpaf(
+        long,
+        argument::<String>("ENCODING"),
+        complete(complete_fragment_encoding),
+        parse(parse_fragment_encoding),
+        optional
+    )]
+    fragment_encoding: Option<FragmentEncoding>,
+
+    /// show the text of broken links and the sentence around them
+    #[bpaf(long)]
+    link_text: bool,
+
+    /// check that hreflang alternates link back to each other and include x-default
+    #[bpaf(long)]
+    check_hreflang: bool,
+
+    /// check that canonical links do not point at redirects
+    #[bpaf(long)]
+    check_canonical: bool,
+
+    /// with --check-canonical, also require canonical links to point at the page itself
+    #[bpaf(long)]
+    self_canonical: bool,
+
+    /// the URL the site is served from, links starting with it are checked like local links
+    #[bpaf(long, argument("URL"))]
+    base_url: Option<String>,
+
+    /// check absolute links to another site against its index, can be repeated
+    ///
+    /// SITE is URL=INDEX, e.g. `https://www.example.com=www-index.json`, where INDEX was printed
+    /// by `hyperlink index` for that site. Fragments are checked with --check-anchors.
+    #[bpaf(long, argument("SITE"))]
+    sister_site: Vec<String>,
+
+    /// report rules in _redirects that nothing links to, or that a file shadows
+    #[bpaf(long)]
+    check_redirects: bool,
+
+    /// check `integrity` attributes of scripts and stylesheets against the local files
+    #[bpaf(long)]
+    check_integrity: bool,
+
+    /// check linked files next to the release artifacts listed in PATH against their checksums
+    ///
+    /// PATH is a SHA256SUMS file, or a JSON object like `{"downloads/app.tar.gz": {"sha256":
+    /// "...", "size": 1234}}`. Paths are relative to BASE-PATH.
+    #[bpaf(long, argument("PATH"))]
+    downloads_manifest: Option<PathBuf>,
+
+    /// also check URLs in .txt files, e.g. llms.txt or security.txt
+    #[bpaf(long)]
+    check_text_files: bool,
+
+    /// also check site-relative paths in the visible text of HTML pages, e.g. changelogs. This is a
+    /// heuristic, findings are marked as such
+    #[bpaf(long)]
+    check_page_text: bool,
+
+    /// comma-separated keys to check in JSON and YAML files, e.g. `url,href,image`
+    #[bpaf(long, argument("KEYS"))]
+    data_keys: Vec<String>,
+
+    /// also check links in the HTML of posts embedded in Atom, RSS and JSON feeds
+    #[bpaf(long)]
+    check_feeds: bool,
+
+    /// warn about links to files that exist, but are empty or contain a --placeholder-marker
+    #[bpaf(long)]
+    check_placeholders: bool,
+
+    /// text that marks an HTML page as a placeholder, e.g. `Coming soon`, can be repeated.
+    /// Implies --check-placeholders
+    #[bpaf(long, argument("TEXT"))]
+    placeholder_marker: Vec<String>,
+
+    /// text from the 404 page, e.g. `Page not found`, that marks HTML pages as soft 404s. Links to
+    /// them are bad links. Can be repeated
+    #[bpaf(long("soft-404-marker"), argument("TEXT"))]
+    soft_404_marker: Vec<String>,
+
+    /// report files that fail to parse, e.g. malformed JSON, as errors instead of aborting
+    #[bpaf(long)]
+    report_unparseable: bool,
+
+    /// with --report-unparseable, still check the links that can be found in those files
+    #[bpaf(long)]
+    recover_unparseable: bool,
+
+    /// report problems found by LINT as warnings, e.g. `hostile-filenames`, can be repeated
+    #[bpaf(
+        argument::<String>("LINT"),
+        complete(complete_lint),
+        parse(parse_lint),
+        many
+    )]
+    warn: Vec<Lint>,
+
+    /// with --warn long-paths, the longest path in bytes not to warn about, default is 2000
+    #[bpaf(argument("N"))]
+    max_path_length: Option<usize>,
+
+    /// with --warn long-paths, the longest file or directory name in bytes, default is 255
+    #[bpaf(argument("N"))]
+    max_segment_length: Option<usize>,
+
+    /// print the number of broken links per top-level directory, e.g. to route fixes to teams
+    #[bpaf(long)]
+    summary_by_directory: bool,
+
+    /// write counts and duration of the run to PATH as JSON, also if no links are broken
+    #[bpaf(long, argument("PATH"))]
+    summary_out: Option<PathBuf>,
+
+    /// write run metrics to PATH in the Prometheus text format, e.g. for node_exporter
+    #[bpaf(long, argument("PATH"))]
+    prometheus_out: Option<PathBuf>,
+
+    /// send run metrics to the StatsD server at ADDRESS over UDP, e.g. `localhost:8125`
+    #[bpaf(long, argument("ADDRESS"))]
+    statsd: Option<String>,
+
+    /// print duration, peak memory usage and arena size of each phase to stderr
+    #[bpaf(long)]
+    timings: bool,
+
+    /// parse byte-identical HTML files only once, e.g. copies kept as locale fallbacks
+    #[bpaf(long)]
+    dedup_documents: bool,
+
+    /// only parse one part of the files, given as K/N, and write what was found to --shard-out
+    ///
+    /// Run once for each K from 1 to N, e.g. on separate CI machines, then report on all of them
+    /// with `hyperlink merge`.
+    #[bpaf(long, argument("SHARD"))]
+    shard: Option<Shard>,
+
+    /// with --shard, file to write the links of the shard to, for `hyperlink merge`
+    #[bpaf(long, argument("PATH"))]
+    shard_out: Option<PathBuf>,
+
+    /// only validate the options and the files they refer to, without checking any links
+    #[bpaf(long)]
+    check_config: bool,
+
+    /// only fail if there are more problems than recorded in PATH, and record fewer
+    ///
+    /// PATH is a JSON file with counts like `{"bad_links": 12}`. It is created if it does not exist,
+    /// and updated whenever a count goes down.
+    #[bpaf(long, argument("PATH"))]
+    ratchet: Option<PathBuf>,
+
+    /// do not fail because of the broken links listed in PATH, each with a reason
+    ///
+    /// PATH is a JSON array like `[{"href": "/old/", "reason": "removed in v2", "expires":
+    /// "2026-12-31"}]`. `expires` is optional, after that date the link is reported again.
+    #[bpaf(long, argument("PATH"))]
+    baseline: Option<PathBuf>,
+
+    /// fail the run if there are problems in CATEGORY, or found by a lint, can be repeated
+    ///
+    /// CATEGORY is links, anchors, hreflang, canonical, redirects, integrity, downloads,
+    /// sister-links, lints, unparseable or the name of a lint, e.g. `--deny anchors` or
+    /// `--deny backslashes`.
+    #[bpaf(long, argument("CATEGORY"))]
+    deny: Vec<Selector>,
+
+    /// do not fail the run because of problems in CATEGORY, e.g. `redirects`, can be repeated
+    #[bpaf(long, argument("CATEGORY"))]
+    allow: Vec<Selector>,
+
+    /// also check ATTRIBUTE on elements matching SELECTOR, can be repeated
+    ///
+    /// RULE is SELECTOR@ATTRIBUTE, e.g. `--extract div.card@data-target`. Only simple selectors
+    /// made of an element name, classes, an id and attribute conditions are supported.
+    #[bpaf(long, argument("RULE"))]
+    extract: Vec<ExtractRule>,
+
+    /// check ATTRIBUTE on elements matching SELECTOR in .xml, .opml, .rss and .atom files
+    ///
+    /// RULE is SELECTOR@ATTRIBUTE like for --extract, e.g. `--xml-extract outline@htmlUrl` for an
+    /// OPML blogroll. Can be repeated. XML files are only checked if this is given.
+    #[bpaf(long, argument("RULE"))]
+    xml_extract: Vec<ExtractRule>,
+
+    /// comma-separated attributes to check on any element, e.g. `data-src,data-href`
+    #[bpaf(long, argument("ATTRIBUTES"))]
+    extra_attributes: Vec<String>,
+
+    /// rewrite hrefs before checking them, like a reverse proxy does, can be repeated
+    ///
+    /// RULE is REGEX=REPLACEMENT, e.g. `--rewrite-url '^/v2/docs/(.*)=/docs/$1'`. The first
+    /// matching rule applies, and other options like --ignore-url see the rewritten href.
+    #[bpaf(long, argument("RULE"))]
+    rewrite_url: Vec<String>,
+
+    /// check hrefs like `/doc;jsessionid=abc` without their `;`-delimited path parameters
+    #[bpaf(long)]
+    strip_path_params: bool,
+
+    /// do not check links whose href matches REGEX, e.g. `^/api/`, can be repeated
+    #[bpaf(long, argument("REGEX"))]
+    ignore_url: Vec<String>,
+
+    /// only check links whose href matches REGEX, e.g. `^/docs/`, can be repeated
+    #[bpaf(long, argument("REGEX"))]
+    check_url_only: Vec<String>,
+
+    /// do not check links on elements with `rel=REL`, e.g. `nofollow`, can be repeated
+    #[bpaf(long, argument("REL"))]
+    skip_rel: Vec<String>,
+
+    /// report broken links on elements with `rel=REL` as warnings, e.g. `external`, can be repeated
+    #[bpaf(long, argument("REL"))]
+    warn_rel: Vec<String>,
+
+    /// with --check-anchors, do not count ids matching REGEX as anchors, e.g. `^fnref-`
+    #[bpaf(long, argument("REGEX"))]
+    ignore_id: Vec<String>,
+
+    /// file listing paths that exist at serve time but not in BASE-PATH
+    ///
+    /// One path or glob per line, or a JSON array. Links to plain paths are valid, links matching
+    /// a glob like `/functions/*` or `/app/**` are not checked.
+    #[bpaf(long, argument("PATH"))]
+    extra_targets: Option<PathBuf>,
+
+    /// file listing anchors that scripts add to pages in the browser, like `/search/#results`
+    ///
+    /// One PAGE#ID per line, or a JSON array. PAGE can be a glob like for --extra-targets, e.g.
+    /// `/**#top` for an id on every page. Links to these anchors only need the page to exist.
+    #[bpaf(long, argument("PATH"))]
+    extra_anchors: Option<PathBuf>,
+
+    /// with --check-anchors, file to keep the anchors of each document in between runs
+    ///
+    /// Documents that did not change since the last run are not searched for ids again. The file
+    /// is created if it does not exist, and rewritten after every run.
+    #[bpaf(long, argument("PATH"))]
+    anchors_index: Option<PathBuf>,
+
+    /// file with patterns of files that are not deployed, like an `.s3ignore` or rsync excludes
+    ///
+    /// One pattern per line, in the syntax of `.gitignore`. Matching files are not checked, and
+    /// links to them are reported as broken, with a note that the file exists.
+    #[bpaf(long, argument("PATH"))]
+    deploy_ignore: Option<PathBuf>,
+
+    /// file listing the client-side routes of a single-page app, like `/users/:id`
+    ///
+    /// With --check-anchors, fragments like `#/users/1` are checked against these routes instead
+    /// of against ids.
+    #[bpaf(long, argument("PATH"))]
+    spa_routes: Option<PathBuf>,
+
+    /// with --spa-routes, fragments matching REGEX are routes, default is `^!?/`, can be repeated
+    #[bpaf(long, argument("REGEX"))]
+    spa_fragment: Vec<String>,
+
+    /// path to directory of markdown files to use for reporting errors, can be repeated
     #[bpaf(long("sources"))]
-    sources_path: Option<PathBuf>,
+    sources_path: Vec<PathBuf>,
+
+    /// only match up HTML under URL-PREFIX with sources under SOURCE-DIR
+    ///
+    /// Takes SOURCE-DIR=URL-PREFIX, e.g. `--map-sources de=/de/`. SOURCE-DIR is relative to the
+    /// sources folder. Can be repeated, the longest matching prefix wins.
+    #[bpaf(long, argument("MAPPING"))]
+    map_sources: Vec<SourcePrefix>,
+
+    /// JSON file mapping output paths to source paths, used before matching paragraphs
+    ///
+    /// Many static site generators can emit such a file. Keys are paths relative to BASE-PATH,
+    /// values are paths to source files, e.g. `{"docs/intro/index.html": "docs/intro.md"}`.
+    #[bpaf(long, argument("PATH"))]
+    build_manifest: Option<PathBuf>,
+
+    /// leave code blocks out when matching paragraphs with --sources, e.g. if they are highlighted
+    #[bpaf(long)]
+    skip_code_blocks: bool,
+
+    /// show who last changed the source line of each broken link, using git blame
+    #[bpaf(long)]
+    git_blame: bool,
+
+    /// only report broken links in source files changed in RANGE, e.g. `origin/main...HEAD`
+    ///
+    /// All files are still checked, but broken links are only reported if they are found in a
+    /// source file that `git diff --name-only RANGE` lists. Requires --sources or
+    /// --build-manifest.
+    #[bpaf(long, argument("RANGE"))]
+    git_range: Option<String>,
+
+    /// print the report in another format: text (default), json, sarif, junit, tap, checkstyle,
+    /// csv, tsv or rdjson
+    ///
+    /// With json, stdout only has one JSON object with every problem found. With sarif, it has a
+    /// SARIF 2.1.0 log for GitHub code scanning, with junit JUnit XML with a test case per
+    /// document, with tap TAP version 13 with a test per checked href, with checkstyle
+    /// Checkstyle XML with the problems of each file, with csv and tsv a table with a row per
+    /// problem and source file, and with rdjson reviewdog's Diagnostic JSON. Either way, the text
+    /// report goes to stderr.
+    #[bpaf(
+        long,
+        argument::<String>("FORMAT"),
+        complete(complete_format),
+        parse(parse_format),
+        optional
+    )]
+    format: Option<OutputFormat>,
 
     /// enable specialized output for GitHub actions
+    ///
+    /// Enabled by default when running on GitHub Actions, see --no-ci-defaults. Also adds the
+    /// counts of problems to the job summary.
     #[bpaf(long)]
     github_actions: bool,
 
+    /// do not enable --github-actions by default when running on GitHub Actions
+    #[bpaf(long)]
+    no_ci_defaults: bool,
+
+    /// annotation level for a category of problems with --github-actions, can be repeated
+    ///
+    /// Takes CATEGORY=LEVEL, e.g. `--github-severity anchors=error`. Categories are links,
+    /// anchors, hreflang, canonical and redirects. Levels are error, warning and notice. By
+    /// default, anchors and redirects are warnings and everything else is an error.
+    #[bpaf(long, argument("MAPPING"))]
+    github_severity: Vec<SeverityOverride>,
+
+    /// apply workarounds for a static site generator (supported: mdbook)
+    #[bpaf(
+        long,
+        argument::<String>("PRESET"),
+        complete(complete_preset),
+        parse(parse_preset),
+        optional
+    )]
+    preset: Option<Preset>,
+
     /// the static file path to check
     ///
     /// This will be assumed to be the root path of your server as well, so
@@ -60,11 +448,41 @@ struct Cli {
     #[bpaf(short('j'), long("jobs"))]
     threads: Option<usize>,
 
+    /// how many threads to use for listing directories, default is --jobs
+    #[bpaf(long, argument("N"))]
+    walk_jobs: Option<usize>,
+
+    /// how many threads to use for parsing HTML, default is --jobs
+    #[bpaf(long, argument("N"))]
+    parse_jobs: Option<usize>,
+
+    /// how many threads to use for reading --sources, default is --jobs
+    #[bpaf(long, argument("N"))]
+    sources_jobs: Option<usize>,
+
+    /// read HTML files on N threads of their own, ahead of parsing, e.g. on network filesystems
+    #[bpaf(long, argument("N"))]
+    read_jobs: Option<usize>,
+
+    /// look up file types on N threads of their own, in batches, e.g. on network filesystems
+    #[bpaf(long, argument("N"))]
+    stat_jobs: Option<usize>,
+
+    /// take file types from directory listings, and leave out symlinks instead of following them
+    #[bpaf(long)]
+    trust_file_types: bool,
+
+    /// hand files to threads N at a time, default 16. Lower it if a few huge files dominate a run
+    #[bpaf(long, argument("N"))]
+    batch_size: Option<usize>,
+
     #[bpaf(external)]
     command: Command,
 }
 
-#[derive(Bpaf, PartialEq, Debug)]
+// parsed once per run, boxing MainCommand is not worth the noise
+#[allow(clippy::large_enum_variant)]
+#[derive(Bpaf, Clone, PartialEq, Debug)]
 enum Command {
     /// Dump out internal data for markdown or html file.
     ///  
@@ -91,6 +509,14 @@ enum Command {
         /// markdown or html file
         #[bpaf(long)]
         file: PathBuf,
+
+        /// also print the URLs each paragraph links to, with reference-style links resolved
+        #[bpaf(long)]
+        links: bool,
+
+        /// leave code blocks out of paragraphs
+        #[bpaf(long)]
+        skip_code_blocks: bool,
     },
 
     /// Attempt to match up all paragraphs from the HTML folder with the Markdown folder and print
@@ -117,13 +543,194 @@ enum Command {
         base_path: PathBuf,
     },
 
+    /// Print the pages and anchors of a site as JSON, for --sister-site.
+    #[bpaf(command("index"))]
+    Index {
+        /// base path
+        #[bpaf(positional("BASE-PATH"))]
+        base_path: PathBuf,
+    },
+
+    /// Print the anchors of every page as JSON, for other tools.
+    ///  
+    ///  Search indexes and table of contents generators can use this instead of parsing the site
+    /// again.
+    #[bpaf(command("export-anchors"))]
+    ExportAnchors {
+        /// write the anchors to PATH instead of standard output
+        #[bpaf(short('o'), long("output"), argument("PATH"))]
+        output: Option<PathBuf>,
+        /// base path
+        #[bpaf(positional("BASE-PATH"))]
+        base_path: PathBuf,
+    },
+
+    /// Report pages of an old build that a new build breaks.
+    ///  
+    ///  These are pages that NEW neither has nor redirects, URLs that would break for visitors
+    /// coming from other sites and search engines. OLD and NEW are build directories, or files
+    /// printed by `hyperlink index`.
+    #[bpaf(command("permalinks"))]
+    Permalinks {
+        /// the build that is currently published
+        #[bpaf(positional("OLD"))]
+        old: PathBuf,
+        /// the build about to be published
+        #[bpaf(positional("NEW"))]
+        new: PathBuf,
+    },
+
+    /// Print a script that sets up tab completion of subcommands, flags and their values for SHELL.
+    #[bpaf(command("completions"))]
+    Completions {
+        /// bash, zsh, fish or elvish
+        #[bpaf(positional("SHELL"))]
+        shell: Shell,
+    },
+
+    /// Print a man page in roff format, generated from the same definitions as --help.
+    #[bpaf(command("man"))]
+    Man,
+
+    /// Report on the files written by every `--shard` of a run, as if it had been a single run.
+    ///  
+    ///  The options are taken from the shards, which must all have been run with the same ones.
+    /// BASE-PATH must exist in the same place as for the shards, some checks read files from it.
+    #[bpaf(command("merge"))]
+    Merge {
+        /// files written with --shard-out, one for each shard
+        #[bpaf(positional("PART"))]
+        parts: Vec<PathBuf>,
+    },
+
+    /// Replace this binary with the latest release from GitHub.
+    ///  
+    ///  Downloads the prebuilt binary for this platform with curl, verifies its checksum and
+    /// unpacks it with tar, or PowerShell on Windows. Installations from a package manager should
+    /// be updated with that instead.
+    #[bpaf(command("self-update"))]
+    SelfUpdate {
+        /// only print whether there is a newer release
+        #[bpaf(long)]
+        check: bool,
+    },
+
     Main(#[bpaf(external(main_command))] MainCommand),
 }
 
+/// The shells `hyperlink completions` supports, which are the ones bpaf can complete for.
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+    Elvish,
+}
+
+impl FromStr for Shell {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "bash" => Ok(Shell::Bash),
+            "zsh" => Ok(Shell::Zsh),
+            "fish" => Ok(Shell::Fish),
+            "elvish" => Ok(Shell::Elvish),
+            _ => Err(format!(
+                "unsupported shell {s:?}, expected bash, zsh, fish or elvish"
+            )),
+        }
+    }
+}
+
+/// The format of the report on stdout, see `--format`: `text` or one of
+/// `SinkRegistry::builtin`.
+#[derive(Clone, PartialEq, Debug)]
+struct OutputFormat(String);
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let registry = SinkRegistry::builtin();
+        if s == "text" || registry.contains(s) {
+            return Ok(OutputFormat(s.to_owned()));
+        }
+
+        let mut names: Vec<_> = iter::once("text").chain(registry.names()).collect();
+        let last = names.pop().unwrap();
+        Err(format!(
+            "unknown format {s:?}, expected {} or {last}",
+            names.join(", ")
+        ))
+    }
+}
+
+/// Values of `names` that start with what has been typed so far.
+fn complete_names<'a>(
+    names: impl Iterator<Item = &'a str>,
+    input: &str,
+) -> Vec<(String, Option<String>)> {
+    names
+        .filter(|name| name.starts_with(input))
+        .map(|name| (name.to_owned(), None))
+        .collect()
+}
+
+// bpaf completes the argument before it is parsed, so it is passed as a `String`
+#[allow(clippy::ptr_arg)]
+fn complete_lint(input: &String) -> Vec<(String, Option<String>)> {
+    complete_names(Lint::ALL.iter().map(|lint| lint.name()), input)
+}
+
+fn parse_lint(name: String) -> Result<Lint, String> {
+    name.parse()
+}
+
+#[allow(clippy::ptr_arg)]
+fn complete_preset(input: &String) -> Vec<(String, Option<String>)> {
+    complete_names(Preset::ALL.iter().map(|preset| preset.name()), input)
+}
+
+fn parse_preset(name: String) -> Result<Preset, String> {
+    name.parse()
+}
+
+#[allow(clippy::ptr_arg)]
+fn complete_fragment_encoding(input: &String) -> Vec<(String, Option<String>)> {
+    complete_names(
+        FragmentEncoding::ALL.iter().map(|encoding| encoding.name()),
+        input,
+    )
+}
+
+fn parse_fragment_encoding(name: String) -> Result<FragmentEncoding, String> {
+    name.parse()
+}
+
+#[allow(clippy::ptr_arg)]
+fn complete_format(input: &String) -> Vec<(String, Option<String>)> {
+    let registry = SinkRegistry::builtin();
+    complete_names(iter::once("text").chain(registry.names()), input)
+}
+
+fn parse_format(name: String) -> Result<OutputFormat, String> {
+    name.parse()
+}
+
 fn main() -> Result<(), Error> {
+    update::remove_old_binary();
+
     let Cli {
         version,
         threads,
+        walk_jobs,
+        parse_jobs,
+        sources_jobs,
+        read_jobs,
+        stat_jobs,
+        trust_file_types,
+        batch_size,
         command,
     } = cli().run();
 
@@ -141,14 +748,34 @@ fn main() -> Result<(), Error> {
         .build_global()
         .unwrap();
 
-    let MainCommand {
-        base_path,
-        check_anchors,
-        sources_path,
-        github_actions,
-    } = match command {
-        Command::DumpParagraphs { file } => {
-            return dump_paragraphs(file);
+    let stage_pool = |threads: Option<usize>| {
+        threads.map(|threads| {
+            rayon::ThreadPoolBuilder::new()
+                .num_threads(cmp::max(2, threads))
+                .build()
+                .unwrap()
+        })
+    };
+    walk::configure(
+        StagePools {
+            walk: stage_pool(walk_jobs).map(Arc::new),
+            parse: stage_pool(parse_jobs),
+            sources: stage_pool(sources_jobs),
+            read: stage_pool(read_jobs),
+            stat: stage_pool(stat_jobs),
+        },
+        trust_file_types,
+        batch_size,
+    );
+
+    let main_command = match command {
+        Command::DumpParagraphs {
+            file,
+            links,
+            skip_code_blocks,
+        } => {
+            let paragraph_options = ParagraphOptions { skip_code_blocks };
+            return dump_paragraphs(file, links, &paragraph_options);
         }
         Command::MatchAllParagraphs {
             base_path,
@@ -159,11 +786,46 @@ fn main() -> Result<(), Error> {
         Command::DumpExternalLinks { base_path } => {
             return dump_external_links(base_path);
         }
-        Command::Main(main_command) => main_command,
+        Command::Index { base_path } => {
+            return print_link_index(&base_path);
+        }
+        Command::ExportAnchors { output, base_path } => {
+            return export_anchors(&base_path, output.as_deref());
+        }
+        Command::Permalinks { old, new } => {
+            return check_permalinks(&old, &new);
+        }
+        Command::Completions { shell } => {
+            let style = match shell {
+                Shell::Bash => "--bpaf-complete-style-bash",
+                Shell::Zsh => "--bpaf-complete-style-zsh",
+                Shell::Fish => "--bpaf-complete-style-fish",
+                Shell::Elvish => "--bpaf-complete-style-elvish",
+            };
+            // bpaf prints the script, which calls back into hyperlink for the actual completions,
+            // and exits
+            let _ = cli().run_inner(Args::from(&[style][..]).set_name("hyperlink"));
+            return Ok(());
+        }
+        Command::Man => {
+            // no date, so that the page only changes when the options do
+            print!(
+                "{}",
+                cli().render_manpage("hyperlink", doc::Section::General, None, None, None)
+            );
+            return Ok(());
+        }
+        Command::Merge { parts } => {
+            return merge_shards(&parts);
+        }
+        Command::SelfUpdate { check } => {
+            return update::self_update(check);
+        }
+        Command::Main(main_command) => with_ci_defaults(main_command),
     };
 
-    let base_path = match base_path {
-        Some(base_path) => base_path,
+    let base_path = match main_command.base_path {
+        Some(ref base_path) => base_path.clone(),
         None => {
             // Invalid invocation. Ultra hack to show help if no arguments are provided.
             let help_message = cli()
@@ -175,182 +837,1047 @@ fn main() -> Result<(), Error> {
         }
     };
 
-    if sources_path.is_some() {
-        check_links::<ParagraphHasher>(base_path, check_anchors, sources_path, github_actions)
-    } else {
-        check_links::<NoopParagraphWalker>(base_path, check_anchors, sources_path, github_actions)
+    if main_command.check_config {
+        return check_config(&base_path, &main_command);
     }
+
+    check_links(base_path, main_command, None)
 }
 
-fn check_links<P: ParagraphWalker>(
-    base_path: PathBuf,
-    check_anchors: bool,
-    sources_path: Option<PathBuf>,
-    github_actions: bool,
-) -> Result<(), Error>
-where
-    P::Paragraph: Copy + PartialEq,
-{
-    println!("Reading files");
+fn with_ci_defaults(mut main_command: MainCommand) -> MainCommand {
+    if !main_command.no_ci_defaults && github::is_github_actions() {
+        main_command.github_actions = true;
+    }
+    main_command
+}
+
+/// `hyperlink merge`: combine the links found by each shard and check them like `check_links`.
+fn merge_shards(parts: &[PathBuf]) -> Result<(), Error> {
+    let dumps = parts
+        .iter()
+        .map(|part| ShardDump::read(part))
+        .collect::<Result<Vec<_>, _>>()?;
+    let shards: Vec<_> = dumps.iter().map(|dump| dump.shard).collect();
+    shards::check_complete(&shards)?;
+
+    let mut main_command: Option<MainCommand> = None;
+    let mut html_result: HtmlResult<LocalLinksOnly<BrokenLinkCollector<VoidParagraph>>> =
+        HtmlResult {
+            collector: LocalLinksOnly::new(),
+            documents_count: 0,
+            file_count: 0,
+            duplicates_count: 0,
+            indexed_count: 0,
+            peak_arena_bytes: 0,
+        };
+    for dump in dumps {
+        let args: Vec<&str> = dump.args.iter().map(String::as_str).collect();
+        let mut shard_command = match cli().run_inner(Args::from(&args[..])) {
+            Ok(Cli {
+                command: Command::Main(shard_command),
+                ..
+            }) => shard_command,
+            _ => {
+                return Err(anyhow!(
+                    "Failed to parse the options of shard {}: {}",
+                    dump.shard,
+                    dump.args.join(" ")
+                ))
+            }
+        };
+        // the anchors index of each shard only has the anchors of its own files
+        shard_command.shard = None;
+        shard_command.shard_out = None;
+        shard_command.anchors_index = None;
+        match main_command {
+            Some(ref main_command) if *main_command != shard_command => {
+                return Err(anyhow!(
+                    "shard {} was run with different options than the others",
+                    dump.shard
+                ));
+            }
+            Some(_) => (),
+            None => main_command = Some(shard_command),
+        }
 
-    let html_result =
-        extract_html_links::<LocalLinksOnly<BrokenLinkCollector<_>>, P>(&base_path, check_anchors)?;
+        html_result.collector.collector.merge(dump.collector);
+        html_result.documents_count += dump.documents_count;
+        html_result.file_count += dump.file_count;
+        html_result.duplicates_count += dump.duplicates_count;
+    }
 
-    let used_links_len = html_result.collector.collector.used_links_count();
-    println!(
-        "Checking {} links from {} files ({} documents)",
-        used_links_len, html_result.file_count, html_result.documents_count,
+    // check_complete made sure that there is at least one shard
+    let main_command = with_ci_defaults(main_command.unwrap());
+    let base_path = main_command
+        .base_path
+        .clone()
+        .ok_or_else(|| anyhow!("The shards were run without BASE-PATH"))?;
+    check_links(base_path, main_command, Some(html_result))
+}
+
+/// `--check-config`: report options that have no effect and referenced files that cannot be
+/// read, e.g. in CI on changes that only touch the configuration.
+fn check_config(base_path: &Path, main_command: &MainCommand) -> Result<(), Error> {
+    let mut problems = Vec::new();
+
+    let mut requires = |given: bool, option: &str, required: bool, requirement: &str| {
+        if given && !required {
+            problems.push(format!("{option} has no effect without {requirement}"));
+        }
+    };
+    let has_sources = !main_command.sources_path.is_empty();
+    requires(
+        main_command.max_path_length.is_some() || main_command.max_segment_length.is_some(),
+        "--max-path-length or --max-segment-length",
+        main_command.warn.contains(&Lint::LongPaths),
+        "--warn long-paths",
+    );
+    requires(
+        !main_command.spa_fragment.is_empty(),
+        "--spa-fragment",
+        main_command.spa_routes.is_some(),
+        "--spa-routes",
+    );
+    requires(
+        !main_command.github_severity.is_empty(),
+        "--github-severity",
+        main_command.github_actions,
+        "--github-actions",
+    );
+    requires(
+        main_command.report_unparseable || main_command.recover_unparseable,
+        "--report-unparseable or --recover-unparseable",
+        !main_command.data_keys.is_empty(),
+        "--data-keys",
+    );
+    requires(
+        !main_command.map_sources.is_empty() || main_command.skip_code_blocks,
+        "--map-sources or --skip-code-blocks",
+        has_sources,
+        "--sources",
     );
+    requires(
+        main_command.git_blame || main_command.git_range.is_some(),
+        "--git-blame or --git-range",
+        has_sources || main_command.build_manifest.is_some(),
+        "--sources or --build-manifest",
+    );
+    requires(
+        !main_command.ignore_id.is_empty()
+            || main_command.extra_anchors.is_some()
+            || main_command.anchors_index.is_some()
+            || main_command.check_aria
+            || main_command.fragment_encoding.is_some(),
+        "--ignore-id, --extra-anchors, --anchors-index, --check-aria or --fragment-encoding",
+        main_command.check_anchors,
+        "--check-anchors",
+    );
+    requires(
+        main_command.warn.contains(&Lint::AbsoluteUrls),
+        "--warn absolute-urls",
+        main_command.base_url.is_some(),
+        "--base-url",
+    );
+    requires(
+        main_command.shard_out.is_some(),
+        "--shard-out",
+        main_command.shard.is_some(),
+        "--shard",
+    );
+    if main_command.ratchet.is_some()
+        && (!main_command.deny.is_empty() || !main_command.allow.is_empty())
+    {
+        problems.push("--deny and --allow have no effect with --ratchet".to_owned());
+    }
+    if main_command.dedup_documents && main_command.warn.contains(&Lint::EscapesRoot) {
+        problems.push("--dedup-documents has no effect with --warn escapes-root".to_owned());
+    }
 
-    let mut bad_links_and_anchors = BTreeMap::new();
-    let mut bad_links_count = 0;
-    let mut bad_anchors_count = 0;
+    if !base_path.is_dir() {
+        problems.push(format!("{} is not a directory", base_path.display()));
+    }
+    for sources_path in &main_command.sources_path {
+        if !sources_path.is_dir() {
+            problems.push(format!("{} is not a directory", sources_path.display()));
+        }
+    }
 
-    let mut broken_links = html_result
-        .collector
-        .collector
-        .get_broken_links(check_anchors)
-        .peekable();
+    let fragment_encoding = main_command.fragment_encoding.unwrap_or_default();
+    let mut check = |result: Result<(), Error>| {
+        if let Err(error) = result {
+            problems.push(format!("{error:#}"));
+        }
+    };
+    if main_command.check_redirects {
+        check(Redirects::read(base_path).map(drop));
+    }
+    check(filters::rewrite_urls(&main_command.rewrite_url, fragment_encoding).map(drop));
+    check(filters::ignore_urls(&main_command.ignore_url).map(drop));
+    check(filters::check_urls_only(&main_command.check_url_only).map(drop));
+    check(filters::regex_set("--ignore-id", &main_command.ignore_id).map(drop));
+    if let Some(ref path) = main_command.extra_targets {
+        check(ExtraTargets::read(path).and_then(|targets| targets.glob_hook().map(drop)));
+    }
+    if let Some(ref path) = main_command.extra_anchors {
+        check(
+            ExtraAnchors::read(path, fragment_encoding)
+                .and_then(|anchors| anchors.hook().map(drop)),
+        );
+    }
+    if let Some(ref path) = main_command.anchors_index {
+        check(AnchorsIndex::read(path, &main_command.ignore_id).map(drop));
+    }
+    if let Some(ref path) = main_command.downloads_manifest {
+        check(Downloads::read(path).map(drop));
+    }
+    check(SisterSites::read(&main_command.sister_site).map(drop));
+    if let Some(ref path) = main_command.deploy_ignore {
+        check(DeployIgnore::read(path).map(drop));
+    }
+    if let Some(ref path) = main_command.baseline {
+        check(Baseline::read(path, fragment_encoding).map(drop));
+    }
+    if let Some(ref manifest) = main_command.spa_routes {
+        check(SpaRoutes::read(manifest, &main_command.spa_fragment).map(drop));
+    }
+    if has_sources || main_command.build_manifest.is_some() {
+        check(
+            SourceMapping::new(
+                base_path,
+                &main_command.sources_path,
+                main_command.map_sources.clone(),
+                main_command.build_manifest.as_deref(),
+            )
+            .map(drop),
+        );
+    }
+    if let Some(preset) = main_command.preset {
+        for sources_path in &main_command.sources_path {
+            check(preset.source_files(sources_path).map(drop));
+        }
+    }
+    if let Some(ref path) = main_command.ratchet {
+        if path.exists() {
+            check(
+                fs::read_to_string(path)
+                    .map_err(Error::from)
+                    .and_then(|contents| {
+                        serde_json::from_str::<BTreeMap<String, usize>>(&contents)?;
+                        Ok(())
+                    })
+                    .with_context(|| format!("Failed to read ratchet file {}", path.display())),
+            );
+        }
+    }
 
-    let paragraps_to_sourcefile = if broken_links.peek().is_some() {
-        if let Some(ref sources_path) = sources_path {
-            println!("Found some broken links, reading source files");
-            extract_markdown_paragraphs::<P>(sources_path)?
-        } else {
-            BTreeMap::new()
+    for problem in &problems {
+        println!("error: {problem}");
+    }
+    println!("Found {} problems in the configuration", problems.len());
+
+    if !problems.is_empty() {
+        process::exit(1);
+    }
+
+    Ok(())
+}
+
+/// Check the links of `base_path`, or the ones found by the shards of a run if `merged` is given.
+fn check_links(
+    base_path: PathBuf,
+    main_command: MainCommand,
+    merged: Option<HtmlResult<SiteCollector>>,
+) -> Result<(), Error> {
+    let start = Instant::now();
+    let started = SystemTime::now();
+    let phase_timings = Timings::default();
+
+    let MainCommand {
+        check_anchors,
+        check_aria,
+        fragment_encoding,
+        link_text,
+        check_hreflang,
+        check_canonical,
+        self_canonical,
+        base_url,
+        sister_site,
+        check_redirects,
+        check_integrity,
+        downloads_manifest,
+        check_text_files,
+        check_page_text,
+        data_keys,
+        check_feeds,
+        check_placeholders,
+        placeholder_marker,
+        soft_404_marker,
+        report_unparseable,
+        recover_unparseable,
+        warn,
+        max_path_length,
+        max_segment_length,
+        summary_by_directory,
+        summary_out,
+        prometheus_out,
+        statsd,
+        timings,
+        ratchet,
+        baseline,
+        deny,
+        allow,
+        mut extract,
+        xml_extract,
+        extra_attributes,
+        rewrite_url,
+        strip_path_params,
+        ignore_url,
+        check_url_only,
+        skip_rel,
+        warn_rel,
+        ignore_id,
+        extra_targets,
+        extra_anchors,
+        anchors_index: anchors_index_path,
+        deploy_ignore,
+        spa_routes,
+        spa_fragment,
+        sources_path,
+        map_sources,
+        skip_code_blocks,
+        build_manifest,
+        git_blame,
+        git_range,
+        format,
+        github_actions,
+        no_ci_defaults: _,
+        github_severity,
+        preset,
+        dedup_documents,
+        shard,
+        shard_out,
+        ..
+    } = main_command;
+
+    let shard_out = match (shard, shard_out) {
+        (Some(_), None) => return Err(anyhow!("--shard needs --shard-out to write its links to")),
+        (Some(_), Some(_)) if check_redirects || downloads_manifest.is_some() => {
+            return Err(anyhow!(
+                "--shard does not support --check-redirects or --downloads-manifest, which need \
+                 to see every document"
+            ))
         }
+        (Some(_), Some(_)) if !sister_site.is_empty() => {
+            return Err(anyhow!("--shard does not support --sister-site"))
+        }
+        (_, shard_out) => shard_out,
+    };
+
+    for attribute in extra_attributes
+        .iter()
+        .flat_map(|attributes| attributes.split(','))
+        .map(str::trim)
+        .filter(|attribute| !attribute.is_empty())
+    {
+        extract.push(format!("*@{attribute}").parse().map_err(Error::msg)?);
+    }
+
+    let redirects = if check_redirects {
+        Redirects::read(&base_path)?
+    } else {
+        None
+    };
+
+    let fragment_encoding = fragment_encoding.unwrap_or_default();
+    let mut link_hooks = Vec::new();
+    // before anything else looks at the href, as the proxy sees links before the site does
+    if !rewrite_url.is_empty() {
+        link_hooks.push(filters::rewrite_urls(&rewrite_url, fragment_encoding)?);
+    }
+    if strip_path_params {
+        link_hooks.push(filters::strip_path_params());
+    }
+    // before the other hooks, so that links skipped by them still count as uses
+    if let Some(ref redirects) = redirects {
+        link_hooks.push(redirects.usage_hook());
+    }
+    if !ignore_url.is_empty() {
+        link_hooks.push(filters::ignore_urls(&ignore_url)?);
+    }
+    if !check_url_only.is_empty() {
+        link_hooks.push(filters::check_urls_only(&check_url_only)?);
+    }
+    let downloads = downloads_manifest
+        .map(|path| Downloads::read(&path))
+        .transpose()?;
+    if let Some(ref downloads) = downloads {
+        link_hooks.push(downloads.usage_hook());
+    }
+    let sister_sites = if sister_site.is_empty() {
+        None
     } else {
-        BTreeMap::new()
+        Some(SisterSites::read(&sister_site)?)
     };
+    if let Some(ref sister_sites) = sister_sites {
+        link_hooks.push(sister_sites.hook());
+    }
+
+    let extra_targets = match extra_targets {
+        Some(path) => ExtraTargets::read(&path)?,
+        None => ExtraTargets::default(),
+    };
+    link_hooks.extend(extra_targets.glob_hook()?);
+    if let Some(ref path) = extra_anchors {
+        link_hooks.extend(ExtraAnchors::read(path, fragment_encoding)?.hook()?);
+    }
+    let baseline = baseline
+        .map(|path| Baseline::read(&path, fragment_encoding).map(Arc::new))
+        .transpose()?;
+    let deploy_ignore = deploy_ignore
+        .map(|path| DeployIgnore::read(&path))
+        .transpose()?;
+    let anchors_index = anchors_index_path
+        .as_ref()
+        .map(|path| AnchorsIndex::read(path, &ignore_id))
+        .transpose()?;
+    let today = Date::today();
+    if let Some(ref manifest) = spa_routes {
+        link_hooks.push(SpaRoutes::read(manifest, &spa_fragment)?.hook());
+    }
 
-    for broken_link in broken_links {
-        let mut had_sources = false;
+    let mut sinks: Vec<_> = format
+        .and_then(|OutputFormat(format)| SinkRegistry::builtin().create(&format))
+        .into_iter()
+        .collect();
+    TEXT_TO_STDERR.store(!sinks.is_empty(), Ordering::Relaxed);
 
-        if broken_link.hard_404 {
-            bad_links_count += 1;
+    reportln!("Reading files");
+
+    let paragraph_options = ParagraphOptions { skip_code_blocks };
+    let parse_options = ParseOptions {
+        check_anchors,
+        skip_ids: false,
+        link_text,
+        paragraphs: paragraph_options,
+        check_hreflang,
+        check_canonical: check_canonical || self_canonical,
+        check_integrity,
+        fragment_encoding,
+        check_aria,
+        check_text_files,
+        check_page_text,
+        data_keys: data_keys
+            .iter()
+            .flat_map(|keys| keys.split(','))
+            .map(str::trim)
+            .filter(|key| !key.is_empty())
+            // keys live as long as the program, like the attributes of --extract rules
+            .map(|key| &*Box::leak(key.to_owned().into_boxed_str()))
+            .collect(),
+        check_feeds,
+        base_url: base_url.map(|base_url| base_url.trim_end_matches('/').to_owned()),
+        extract_rules: extract,
+        xml_rules: xml_extract,
+        link_hooks,
+        link_callbacks: Vec::new(),
+        lints: warn,
+        path_limits: PathLimits {
+            segment: max_segment_length.unwrap_or(PathLimits::default().segment),
+            total: max_path_length.unwrap_or(PathLimits::default().total),
+        },
+        skip_rels: skip_rel,
+        warn_rels: warn_rel,
+        ignored_ids: if ignore_id.is_empty() {
+            None
         } else {
-            bad_anchors_count += 1;
-        }
+            Some(filters::regex_set("--ignore-id", &ignore_id)?)
+        },
+        report_unparseable: report_unparseable || recover_unparseable,
+        recover_unparseable,
+        check_placeholders: check_placeholders || !placeholder_marker.is_empty(),
+        placeholder_markers: placeholder_marker,
+        soft_404_markers: soft_404_marker,
+    };
 
-        if let Some(ref paragraph) = broken_link.link.paragraph {
-            if let Some(document_sources) = &paragraps_to_sourcefile.get(paragraph) {
-                debug_assert!(!document_sources.is_empty());
-                had_sources = true;
+    let check_canonical = parse_options.check_canonical;
+    let lints_enabled = !parse_options.lints.is_empty();
+    let report_unparseable = parse_options.report_unparseable;
+    let check_placeholders = parse_options.check_placeholders;
+    let check_soft_404s = !parse_options.soft_404_markers.is_empty();
+    let has_downloads = downloads.is_some();
+    let has_sister_sites = sister_sites.is_some();
+    let options = CheckOptions {
+        parse: parse_options,
+        sources_paths: sources_path,
+        map_sources,
+        build_manifest,
+        changed_files: match git_range {
+            Some(ref range) => Some(git::changed_files(range)?),
+            None => None,
+        },
+        preset,
+        dedup_documents,
+        shard,
+        deploy_ignore,
+        anchors_index,
+        baseline: baseline.clone(),
+        extra_targets,
+        check_redirects,
+        redirects,
+        downloads,
+        sister_sites,
+        self_canonical,
+        summary_by_directory,
+    };
 
-                for (source, lineno) in *document_sources {
-                    let (bad_links, bad_anchors) = bad_links_and_anchors
-                        .entry((!had_sources, source.path.clone()))
-                        .or_insert_with(|| (BTreeSet::new(), BTreeSet::new()));
+    let write_anchors_index = |html_result: &HtmlResult<SiteCollector>| match anchors_index_path {
+        Some(ref path) => AnchorsIndex::write(
+            path,
+            &base_path,
+            &ignore_id,
+            &html_result.collector.collector.get_defined_anchors(),
+        ),
+        None => Ok(()),
+    };
 
-                    if broken_link.hard_404 {
-                        bad_links
-                    } else {
-                        bad_anchors
-                    }
-                    .insert((Some(*lineno), broken_link.link.href.clone()));
-                }
-            }
+    if let (Some(shard), Some(path)) = (shard, shard_out) {
+        let html_result = check::collect(&base_path, &options)?;
+        write_anchors_index(&html_result)?;
+        let used_links_len = html_result.collector.collector.used_links_count();
+        ShardDump {
+            shard,
+            args: std::env::args().skip(1).collect(),
+            file_count: html_result.file_count,
+            documents_count: html_result.documents_count,
+            duplicates_count: html_result.duplicates_count,
+            collector: html_result.collector.collector,
         }
+        .write(&path)?;
+        reportln!(
+            "Wrote {} links from {} files of shard {} to {}",
+            used_links_len,
+            html_result.file_count,
+            shard,
+            path.display()
+        );
+        return Ok(());
+    }
 
-        if !had_sources {
-            let (bad_links, bad_anchors) = bad_links_and_anchors
-                .entry((!had_sources, broken_link.link.path))
-                .or_insert_with(|| (BTreeSet::new(), BTreeSet::new()));
-
-            if broken_link.hard_404 {
-                bad_links
-            } else {
-                bad_anchors
-            }
-            .insert((None, broken_link.link.href));
+    let result = match merged {
+        Some(html_result) => {
+            check::check_collected(&base_path, html_result, options, phase_timings, &mut sinks)?
         }
+        None => check::check(&base_path, options, Vec::new(), &mut sinks)?,
+    };
+    write_anchors_index(&result.site)?;
+    let Check {
+        site: html_result,
+        files: bad_links_and_anchors,
+        used_links_count: used_links_len,
+        skipped_links,
+        read_sources,
+        bad_links_count,
+        bad_anchors_count,
+        bad_links_by_directory,
+        warned_links_count,
+        placeholder_links_count,
+        soft_404_links_count,
+        accepted_links_count,
+        bad_hreflang_count,
+        bad_canonical_count,
+        bad_redirects_count,
+        redirected_anchors_count,
+        bad_integrity_count,
+        bad_downloads_count,
+        bad_sister_links_count,
+        lint_warnings_count,
+        lint_counts,
+        unparseable_count,
+        undeployed_hrefs,
+        soft_404s,
+        heading_anchors,
+        anchor_definitions,
+        timings: mut phase_timings,
+    } = result;
+
+    reportln!(
+        "Checking {} links from {} files ({} documents)",
+        used_links_len,
+        html_result.file_count,
+        html_result.documents_count,
+    );
+    if !skipped_links.is_empty() {
+        let counts: Vec<_> = skipped_links
+            .iter()
+            .map(|(reason, count)| format!("{}: {count}", reason.name()))
+            .collect();
+        reportln!(
+            "Skipped {} links ({})",
+            skipped_links.values().sum::<usize>(),
+            counts.join(", ")
+        );
+    }
+    if dedup_documents {
+        reportln!(
+            "Skipped parsing {} duplicate documents",
+            html_result.duplicates_count
+        );
     }
+    if anchors_index_path.is_some() {
+        reportln!(
+            "Took the anchors of {} unchanged documents from the anchors index",
+            html_result.indexed_count
+        );
+    }
+    if read_sources {
+        reportln!("Found some broken links, reading source files");
+    }
+
+    // one lock and few writes, instead of a lock and a write per line
+    let mut out: Box<dyn Write> = if TEXT_TO_STDERR.load(Ordering::Relaxed) {
+        Box::new(io::BufWriter::new(io::stderr().lock()))
+    } else {
+        Box::new(io::BufWriter::new(io::stdout().lock()))
+    };
 
     // _is_raw_file is an unused parameter that is only there to control iteration order over keys.
     // Sort markdown files to the start since otherwise the less valuable annotations on not
     // checked in files fill up the limit on annotations (tested manually, seems to be 10 right
-    // now).
-    for ((_is_raw_file, filepath), (bad_links, bad_anchors)) in bad_links_and_anchors {
-        println!("{}", filepath.display());
+    // now). Any section can still grow until the broken links of all workers are merged, so
+    // sections cannot be written as soon as a worker is done with them.
+    for ((_is_raw_file, filepath), (bad_links, bad_anchors, page_errors)) in bad_links_and_anchors {
+        writeln!(out, "{}", filepath.display())?;
+
+        // one git blame for all lines of the file
+        let blames = if git_blame {
+            let linenos = bad_links
+                .iter()
+                .chain(&bad_anchors)
+                .filter_map(|(lineno, ..)| *lineno)
+                .collect();
+            git::blame(&filepath, &linenos)?
+        } else {
+            BTreeMap::new()
+        };
 
-        for (lineno, href) in &bad_links {
-            print_href_error("error: bad link", href, *lineno);
+        let hrefs = bad_links.iter().map(|bad_href| (false, bad_href));
+        let anchors = bad_anchors.iter().map(|bad_href| (true, bad_href));
+        for (is_anchor, (lineno, href, origin, text)) in hrefs.chain(anchors) {
+            print_href_error(
+                &mut out,
+                "error: bad link",
+                href,
+                *origin,
+                text.as_deref(),
+                *lineno,
+                lineno.and_then(|lineno| blames.get(&lineno)),
+            )?;
+            if is_anchor {
+                print_anchor_note(&mut out, href, &anchor_definitions)?;
+                if let (Some(preset), Some(id)) = (preset, heading_anchors.get(href)) {
+                    writeln!(out, "    note: {} names this heading #{id}", preset.title())?;
+                }
+                if let Some(target) = html_result.collector.collector.redirected_anchor(href) {
+                    writeln!(
+                        out,
+                        "    note: redirects to /{target}, which does not exist"
+                    )?;
+                }
+            } else if undeployed_hrefs.contains(Href(href).without_anchor().0) {
+                writeln!(
+                    out,
+                    "    note: the file exists, but is excluded from deployment"
+                )?;
+            } else if let Some(placeholder) = soft_404s.get(Href(href).without_anchor().0) {
+                writeln!(out, "    note: {placeholder}")?;
+            }
         }
 
-        for (lineno, href) in &bad_anchors {
-            print_href_error("error: bad link", href, *lineno);
+        for error in &page_errors {
+            writeln!(out, "  {}: {error}", page_error_level(error))?;
         }
 
         if github_actions {
+            let annotation_path = github::annotation_path(&filepath);
+
             if !bad_links.is_empty() {
-                print_github_actions_href_list("bad links", &filepath, &bad_links)?;
+                print_github_actions_href_list(
+                    &mut out,
+                    github::level(&github_severity, Category::Links),
+                    "bad links",
+                    &annotation_path,
+                    &bad_links,
+                )?;
             }
 
             if !bad_anchors.is_empty() {
-                print_github_actions_href_list("bad anchors", &filepath, &bad_anchors)?;
+                print_github_actions_href_list(
+                    &mut out,
+                    github::level(&github_severity, Category::Anchors),
+                    "bad anchors",
+                    &annotation_path,
+                    &bad_anchors,
+                )?;
+            }
+
+            for error in &page_errors {
+                writeln!(
+                    out,
+                    "::{} file={}::{}",
+                    github::level(&github_severity, page_error_category(error)),
+                    annotation_path.display(),
+                    error
+                )?;
             }
         }
 
-        println!();
+        writeln!(out)?;
+    }
+    out.flush()?;
+    drop(out);
+
+    if summary_by_directory && !bad_links_by_directory.is_empty() {
+        let mut directories: Vec<_> = bad_links_by_directory.into_iter().collect();
+        directories.sort_by(|(a_dir, a_count), (b_dir, b_count)| {
+            b_count.cmp(a_count).then_with(|| a_dir.cmp(b_dir))
+        });
+
+        reportln!("Bad links by directory:");
+        for (directory, count) in directories {
+            reportln!("  {directory} {count}");
+        }
+        reportln!();
     }
 
-    println!("Found {bad_links_count} bad links");
+    reportln!("Found {bad_links_count} bad links");
 
     if check_anchors {
-        println!("Found {bad_anchors_count} bad anchors");
+        reportln!("Found {bad_anchors_count} bad anchors");
+    }
+
+    if check_hreflang {
+        reportln!("Found {bad_hreflang_count} bad hreflang links");
+    }
+
+    if check_canonical {
+        reportln!("Found {bad_canonical_count} bad canonical links");
+    }
+
+    if check_redirects {
+        reportln!("Found {bad_redirects_count} bad redirects");
+    }
+
+    if redirected_anchors_count > 0 {
+        reportln!("Found {redirected_anchors_count} links to anchors through redirects");
+    }
+
+    if check_integrity {
+        reportln!("Found {bad_integrity_count} bad integrity hashes");
+    }
+
+    if has_downloads {
+        reportln!("Found {bad_downloads_count} bad downloads");
+    }
+
+    if has_sister_sites {
+        reportln!("Found {bad_sister_links_count} bad links to sister sites");
+    }
+
+    if lints_enabled {
+        if lint_counts.is_empty() {
+            reportln!("Found {lint_warnings_count} lint warnings");
+        } else {
+            let counts: Vec<_> = lint_counts
+                .iter()
+                .map(|(lint, count)| format!("{lint}: {count}"))
+                .collect();
+            reportln!(
+                "Found {lint_warnings_count} lint warnings ({})",
+                counts.join(", ")
+            );
+        }
+    }
+
+    if report_unparseable {
+        reportln!("Found {unparseable_count} unparseable documents");
+    }
+
+    if warned_links_count > 0 {
+        reportln!("Found {warned_links_count} bad links marked as warnings");
+    }
+
+    if check_placeholders {
+        reportln!("Found {placeholder_links_count} links to placeholder pages");
+    }
+
+    if check_soft_404s {
+        reportln!("Found {soft_404_links_count} links to soft 404 pages");
+    }
+
+    if let Some(ref baseline) = baseline {
+        reportln!("Found {accepted_links_count} bad links accepted by the baseline");
+        for entry in baseline.expired(today) {
+            reportln!(
+                "  expired on {}: /{} ({})",
+                entry.expires.unwrap(),
+                entry.href,
+                entry.reason
+            );
+        }
+        for entry in baseline.expiring(today) {
+            reportln!(
+                "  expires on {}: /{} ({})",
+                entry.expires.unwrap(),
+                entry.href,
+                entry.reason
+            );
+        }
+    }
+
+    phase_timings.finish_phase("reporting", None);
+    if timings {
+        phase_timings.print();
+    }
+
+    let metrics = Metrics {
+        duration_secs: start.elapsed().as_secs_f64(),
+        files: html_result.file_count,
+        documents: html_result.documents_count,
+        links: used_links_len,
+        problems: vec![
+            ("bad_links", bad_links_count),
+            ("bad_anchors", bad_anchors_count),
+            ("bad_hreflang", bad_hreflang_count),
+            ("bad_canonical", bad_canonical_count),
+            ("bad_redirects", bad_redirects_count),
+            ("bad_integrity", bad_integrity_count),
+            ("bad_downloads", bad_downloads_count),
+            ("bad_sister_links", bad_sister_links_count),
+            ("lint_warnings", lint_warnings_count),
+            ("unparseable", unparseable_count),
+        ],
+        skipped: SkipReason::ALL
+            .iter()
+            .map(|reason| {
+                let count = skipped_links.get(reason).copied().unwrap_or(0);
+                (reason.name(), count)
+            })
+            .collect(),
+    };
+
+    let wants_run_info = sinks.iter().any(|sink| sink.wants_run_info());
+    let run = (summary_out.is_some() || wants_run_info).then(|| RunInfo {
+        version: env!("CARGO_PKG_VERSION"),
+        started,
+        base_path: base_path.clone(),
+        args: std::env::args().skip(1).collect(),
+        git_commit: git::head_commit(&base_path),
+    });
+
+    if let (Some(summary_out), Some(run)) = (summary_out, &run) {
+        let summary = metrics.summary_json(run);
+        fs::write(&summary_out, format!("{summary:#}\n"))
+            .with_context(|| format!("Failed to write summary to {}", summary_out.display()))?;
+    }
+
+    for sink in &mut sinks {
+        sink.on_summary(&Summary {
+            base_path: &base_path,
+            metrics: &metrics,
+            run: run.as_ref(),
+            site: &html_result.collector.collector,
+        })?;
+        let mut stdout = io::stdout().lock();
+        sink.finish(&mut stdout)?;
+        stdout.flush()?;
+    }
+
+    if let Some(prometheus_out) = prometheus_out {
+        metrics.write_prometheus(&prometheus_out)?;
+    }
+
+    if github_actions {
+        github::append_step_summary(&metrics.markdown_table())?;
+    }
+
+    if let Some(ref statsd) = statsd {
+        metrics.send_statsd(statsd)?;
     }
 
     // We're about to exit the program and leaking the memory is faster than running drop
     mem::forget(html_result);
 
-    if bad_links_count > 0 {
-        process::exit(1);
+    if let Some(ratchet_path) = ratchet {
+        let mut counts = BTreeMap::new();
+        counts.insert("bad_links".to_owned(), bad_links_count);
+        if check_anchors {
+            counts.insert("bad_anchors".to_owned(), bad_anchors_count);
+        }
+        if check_hreflang {
+            counts.insert("bad_hreflang".to_owned(), bad_hreflang_count);
+        }
+        if check_canonical {
+            counts.insert("bad_canonical".to_owned(), bad_canonical_count);
+        }
+        if check_redirects {
+            counts.insert("bad_redirects".to_owned(), bad_redirects_count);
+        }
+        if check_integrity {
+            counts.insert("bad_integrity".to_owned(), bad_integrity_count);
+        }
+        if has_downloads {
+            counts.insert("bad_downloads".to_owned(), bad_downloads_count);
+        }
+        if has_sister_sites {
+            counts.insert("bad_sister_links".to_owned(), bad_sister_links_count);
+        }
+        if lints_enabled {
+            counts.insert("lint_warnings".to_owned(), lint_warnings_count);
+        }
+        if report_unparseable {
+            counts.insert("unparseable".to_owned(), unparseable_count);
+        }
+
+        let regressions = ratchet::ratchet(&ratchet_path, &counts)?;
+        for regression in &regressions {
+            reportln!(
+                "Ratchet: {} went up from {} to {}",
+                regression.name,
+                regression.recorded,
+                regression.current
+            );
+        }
+
+        if !regressions.is_empty() {
+            process::exit(1);
+        }
+
+        return Ok(());
     }
 
-    if bad_anchors_count > 0 {
-        process::exit(2);
+    let exit_levels = ExitLevels { deny, allow };
+    let exit_level = [
+        (Category::Links, bad_links_count),
+        (Category::Anchors, bad_anchors_count),
+        (Category::Hreflang, bad_hreflang_count),
+        (Category::Canonical, bad_canonical_count),
+        (Category::Redirects, bad_redirects_count),
+        (Category::Integrity, bad_integrity_count),
+        (Category::Downloads, bad_downloads_count),
+        (Category::SisterLinks, bad_sister_links_count),
+        (Category::Unparseable, unparseable_count),
+    ]
+    .iter()
+    .filter(|(_, count)| *count > 0)
+    .map(|&(category, _)| exit_levels.category(category))
+    .chain(lint_counts.keys().map(|&lint| exit_levels.lint(lint)))
+    .max()
+    .unwrap_or(ExitLevel::Allow);
+
+    if exit_level != ExitLevel::Allow {
+        process::exit(exit_level.exit_code());
     }
 
     Ok(())
 }
 
-fn print_href_error(message: &'static str, href: &str, lineno: Option<usize>) {
-    if let Some(lineno) = lineno {
-        println!("  {message} /{href} at line {lineno}");
-    } else {
-        println!("  {message} /{href}");
+/// The part of an error message in parentheses, e.g. `a[href], "guide"`.
+fn link_details(origin: LinkOrigin, text: Option<&LinkText>) -> String {
+    match text {
+        Some(text) => format!("{origin}, {text}"),
+        None => origin.to_string(),
+    }
+}
+
+fn print_href_error(
+    out: &mut impl Write,
+    message: &'static str,
+    href: &str,
+    origin: LinkOrigin,
+    text: Option<&LinkText>,
+    lineno: Option<usize>,
+    blame: Option<&Blame>,
+) -> io::Result<()> {
+    let details = link_details(origin, text);
+    match (lineno, blame) {
+        (Some(lineno), Some(blame)) => writeln!(
+            out,
+            "  {message} /{href} ({details}) at line {lineno}, {blame}"
+        ),
+        (Some(lineno), None) => writeln!(out, "  {message} /{href} ({details}) at line {lineno}"),
+        (None, _) => writeln!(out, "  {message} /{href} ({details})"),
+    }
+}
+
+/// Point a broken anchor at the pages that do define it, e.g.
+/// `note: #install exists on /docs/setup/ (h2)`.
+fn print_anchor_note(
+    out: &mut impl Write,
+    href: &str,
+    anchor_definitions: &BTreeMap<String, Vec<AnchorDefinition>>,
+) -> io::Result<()> {
+    let (anchor, definitions) = match href
+        .split_once('#')
+        .and_then(|(_, anchor)| Some((anchor, anchor_definitions.get(anchor)?)))
+    {
+        Some(found) => found,
+        None => return Ok(()),
+    };
+
+    let mut pages: Vec<String> = definitions
+        .iter()
+        .take(3)
+        .map(|definition| match definition.element {
+            Some(ref element) => format!("/{} ({element})", definition.page),
+            None => format!("/{}", definition.page),
+        })
+        .collect();
+    if definitions.len() > 3 {
+        pages.push(format!("{} more pages", definitions.len() - 3));
     }
+    writeln!(out, "    note: #{anchor} exists on {}", pages.join(", "))
 }
 
 fn print_github_actions_href_list(
+    out: &mut impl Write,
+    level: Level,
     message: &'static str,
     filepath: &Path,
-    hrefs: &BTreeSet<(Option<usize>, String)>,
-) -> Result<(), Error> {
+    hrefs: &BTreeSet<BadHref>,
+) -> io::Result<()> {
     let mut prev_lineno = None;
-    for (i, (lineno, href)) in hrefs.iter().enumerate() {
+    for (i, (lineno, href, origin, text)) in hrefs.iter().enumerate() {
         if prev_lineno != *lineno || i == 0 {
-            print!(
-                "\n::error file={},line={}::{}:",
-                filepath.canonicalize()?.display(),
+            write!(
+                out,
+                "\n::{} file={},line={}::{}:",
+                level,
+                filepath.display(),
                 lineno.unwrap_or(1),
                 message,
-            );
+            )?;
         }
         prev_lineno = *lineno;
 
         // %0A -- escaped newline
         //
         // https://github.community/t/what-is-the-correct-character-escaping-for-workflow-command-values-e-g-echo-xxxx/118465/5
-        print!("%0A  {}", href);
+        write!(
+            out,
+            "%0A  {} ({})",
+            href,
+            link_details(*origin, text.as_deref())
+        )?;
     }
 
-    println!();
-
-    Ok(())
+    writeln!(out)
 }
 
-fn dump_paragraphs(path: PathBuf) -> Result<(), Error> {
+fn dump_paragraphs(
+    path: PathBuf,
+    print_links: bool,
+    paragraph_options: &ParagraphOptions,
+) -> Result<(), Error> {
     let extension = match path.extension() {
         Some(x) => x,
         None => return Err(anyhow!("File has no extension, cannot determine type")),
@@ -358,31 +1885,58 @@ fn dump_paragraphs(path: PathBuf) -> Result<(), Error> {
 
     let mut doc_buf = DocumentBuffers::default();
 
-    let paragraphs: BTreeSet<_> = match extension.to_str() {
+    let mut paragraphs = BTreeMap::<_, Vec<String>>::new();
+    match extension.to_str().map(str::to_ascii_lowercase).as_deref() {
         Some(x) if MARKDOWN_FILES.contains(&x) => {
             let source = DocumentSource::new(path);
-            source
-                .paragraphs::<DebugParagraphWalker<ParagraphHasher>>()?
-                .into_iter()
-                .map(|(paragraph, lineno)| (paragraph, Some(lineno)))
-                .collect()
+            for (paragraph, lineno, links) in source
+                .paragraphs_with_links::<DebugParagraphWalker<ParagraphHasher>>(paragraph_options)?
+            {
+                paragraphs
+                    .entry((paragraph, Some(lineno)))
+                    .or_default()
+                    .extend(links);
+            }
         }
         Some(x) if HTML_FILES.contains(&x) => {
             let document = Document::new(Path::new(""), &path);
-            document
-                .links::<DebugParagraphWalker<ParagraphHasher>>(&mut doc_buf, false)?
-                .filter_map(|link| Some((link.into_paragraph()?, None)))
-                .collect()
+            for link in document.links::<DebugParagraphWalker<ParagraphHasher>>(
+                &mut doc_buf,
+                &ParseOptions {
+                    paragraphs: *paragraph_options,
+                    ..Default::default()
+                },
+            )? {
+                if let Link::Uses(UsedLink {
+                    href,
+                    paragraph: Some(paragraph),
+                    ..
+                }) = link
+                {
+                    let href = if is_external_link(href.0.as_bytes()) {
+                        href.to_string()
+                    } else {
+                        format!("/{href}")
+                    };
+                    paragraphs.entry((paragraph, None)).or_default().push(href);
+                }
+            }
         }
         _ => return Err(anyhow!("Unknown file extension")),
     };
 
-    for (paragraph, lineno) in paragraphs {
+    for ((paragraph, lineno), links) in paragraphs {
         if let Some(lineno) = lineno {
             println!("{lineno}: {paragraph}");
         } else {
             println!("{paragraph}");
         }
+
+        if print_links {
+            for link in links {
+                println!("  {link}");
+            }
+        }
     }
 
     Ok(())
@@ -390,8 +1944,19 @@ fn dump_paragraphs(path: PathBuf) -> Result<(), Error> {
 
 fn dump_external_links(base_path: PathBuf) -> Result<(), Error> {
     println!("Reading files");
-    let html_result =
-        extract_html_links::<UsedLinkCollector<_>, NoopParagraphWalker>(&base_path, true)?;
+    let html_result = extract_html_links::<UsedLinkCollector<_>, NoopParagraphWalker>(
+        &base_path,
+        &ParseOptions {
+            check_anchors: true,
+            skip_ids: true,
+            ..Default::default()
+        },
+        None,
+        false,
+        None,
+        None,
+        None,
+    )?;
 
     println!(
         "Checking {} links from {} files ({} documents)",
@@ -413,151 +1978,107 @@ fn dump_external_links(base_path: PathBuf) -> Result<(), Error> {
     Ok(())
 }
 
-struct HtmlResult<C> {
-    collector: C,
-    documents_count: usize,
-    file_count: usize,
+fn print_link_index(base_path: &Path) -> Result<(), Error> {
+    println!("{}", build_link_index(base_path)?.to_json());
+    Ok(())
 }
 
-fn walk_files(
+/// Parse a site for its defined links and anchors only, for `hyperlink index` and
+/// `hyperlink export-anchors`.
+fn collect_defined_links(
     base_path: &Path,
-) -> impl ParallelIterator<Item = Result<jwalk::DirEntry<((), bool)>, jwalk::Error>> {
-    WalkDirGeneric::<((), bool)>::new(base_path)
-        .sort(true) // helps branch predictor (?)
-        .skip_hidden(false)
-        .process_read_dir(|_, _, _, children| {
-            for dir_entry_result in children.iter_mut() {
-                if let Ok(dir_entry) = dir_entry_result {
-                    dir_entry.client_state = dir_entry.file_type().is_file();
-                }
-            }
-        })
-        .into_iter()
-        .par_bridge()
-        .filter_map(|entry_result| {
-            if let Ok(entry) = entry_result {
-                if let Some(err) = entry.read_children_error {
-                    // https://github.com/Byron/jwalk/issues/40
-                    return Some(Err(err));
-                }
-
-                if !entry.client_state {
-                    return None;
-                }
-                Some(Ok(entry))
-            } else {
-                Some(entry_result)
-            }
-        })
+) -> Result<HtmlResult<LocalLinksOnly<BrokenLinkCollector<VoidParagraph>>>, Error> {
+    extract_html_links::<LocalLinksOnly<BrokenLinkCollector<VoidParagraph>>, NoopParagraphWalker>(
+        base_path,
+        &ParseOptions {
+            check_anchors: true,
+            ..Default::default()
+        },
+        None,
+        false,
+        None,
+        None,
+        None,
+    )
 }
 
-fn extract_html_links<C: LinkCollector<P::Paragraph>, P: ParagraphWalker>(
-    base_path: &Path,
-    check_anchors: bool,
-) -> Result<HtmlResult<C>, Error> {
-    let result: Result<_, Error> = walk_files(base_path)
-        .try_fold(
-            || (DocumentBuffers::default(), C::new(), 0, 0),
-            |(mut doc_buf, mut collector, mut documents_count, mut file_count), entry| {
-                let entry = entry?;
-                let path = entry.path();
-                let document = Document::new(base_path, &path);
-
-                collector.ingest(Link::Defines(DefinedLink {
-                    href: document.href(),
-                }));
-                file_count += 1;
-
-                let was_parsed = document
-                    .extract_links::<P, _>(&mut doc_buf, check_anchors, |link| {
-                        collector.ingest(link);
-                    })
-                    .with_context(|| format!("Failed to read file {}", document.path.display()))?;
-
-                if was_parsed {
-                    doc_buf.reset();
-                    documents_count += 1;
-                }
-
-                Ok((doc_buf, collector, documents_count, file_count))
-            },
-        )
-        .map(|result| {
-            result.map(|(_, collector, documents_count, file_count)| {
-                (collector, documents_count, file_count)
-            })
-        })
-        .try_reduce(
-            || (C::new(), 0, 0),
-            |(mut collector, mut documents_count, mut file_count),
-             (collector2, documents_count2, file_count2)| {
-                collector.merge(collector2);
-                documents_count += documents_count2;
-                file_count += file_count2;
-                Ok((collector, documents_count, file_count))
-            },
-        );
+fn build_link_index(base_path: &Path) -> Result<LinkIndex, Error> {
+    let html_result = collect_defined_links(base_path)?;
+    let index = LinkIndex::new(
+        html_result
+            .collector
+            .collector
+            .get_defined_links()
+            .map(str::to_owned),
+    );
 
-    let (collector, documents_count, file_count) = result?;
+    mem::forget(html_result);
 
-    Ok(HtmlResult {
-        collector,
-        documents_count,
-        file_count,
-    })
+    Ok(index)
 }
 
-type MarkdownResult<P> = BTreeMap<P, Vec<(DocumentSource, usize)>>;
+fn export_anchors(base_path: &Path, output: Option<&Path>) -> Result<(), Error> {
+    let html_result = collect_defined_links(base_path)?;
+    let documents = html_result.collector.collector.get_documents();
+    let export = anchors::export(base_path, &documents).to_string();
 
-fn extract_markdown_paragraphs<P: ParagraphWalker>(
-    sources_path: &Path,
-) -> Result<MarkdownResult<P::Paragraph>, Error> {
-    let results: Vec<Result<_, Error>> = walk_files(sources_path)
-        .try_fold(Vec::new, |mut paragraphs, entry| {
-            let entry = entry?;
-            let source = DocumentSource::new(entry.path());
-
-            if !source
-                .path
-                .extension()
-                .and_then(|extension| Some(MARKDOWN_FILES.contains(&extension.to_str()?)))
-                .unwrap_or(false)
-            {
-                return Ok(paragraphs);
-            }
+    match output {
+        Some(output) => fs::write(output, export)
+            .with_context(|| format!("Failed to write anchors to {}", output.display()))?,
+        None => println!("{export}"),
+    }
 
-            for paragraph_and_lineno in source
-                .paragraphs::<P>()
-                .with_context(|| format!("Failed to read file {}", source.path.display()))?
-            {
-                paragraphs.push((source.clone(), paragraph_and_lineno));
-            }
-            Ok(paragraphs)
-        })
-        .collect();
+    mem::forget(html_result);
 
-    let mut paragraps_to_sourcefile = BTreeMap::new();
+    Ok(())
+}
 
-    for result in results {
-        for (source, (paragraph, lineno)) in result? {
-            paragraps_to_sourcefile
-                .entry(paragraph)
-                .or_insert_with(Vec::new)
-                .push((source.clone(), lineno));
+fn check_permalinks(old: &Path, new: &Path) -> Result<(), Error> {
+    let read_index = |path: &Path| {
+        if path.is_dir() {
+            build_link_index(path)
+        } else {
+            LinkIndex::read(path)
         }
+    };
+    let old = read_index(old)?;
+    let new = read_index(new)?;
+
+    let broken = permalinks::broken_permalinks(&old, &new);
+    for href in &broken {
+        println!("error: /{href} is gone and not redirected");
     }
+    println!("Found {} broken permalinks", broken.len());
 
-    Ok(paragraps_to_sourcefile)
+    if !broken.is_empty() {
+        process::exit(1);
+    }
+
+    Ok(())
 }
 
 fn match_all_paragraphs(base_path: PathBuf, sources_path: PathBuf) -> Result<(), Error> {
     println!("Reading files");
     let html_result = extract_html_links::<LocalLinksOnly<UsedLinkCollector<_>>, ParagraphHasher>(
-        &base_path, true,
+        &base_path,
+        &ParseOptions {
+            check_anchors: true,
+            skip_ids: true,
+            ..Default::default()
+        },
+        None,
+        false,
+        None,
+        None,
+        None,
     )?;
 
     println!("Reading source files");
-    let paragraps_to_sourcefile = extract_markdown_paragraphs::<ParagraphHasher>(&sources_path)?;
+    let paragraps_to_sourcefile = extract_markdown_paragraphs::<ParagraphHasher>(
+        &[sources_path],
+        None,
+        &ParagraphOptions::default(),
+    )?;
 
     println!("Calculating");
     let mut total_links = 0;
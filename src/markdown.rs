@@ -4,12 +4,15 @@ use std::path::PathBuf;
 use std::sync::Arc;
 
 use anyhow::Error;
-use pulldown_cmark::{Event, Parser, TagEnd};
+use pulldown_cmark::{Event, Options, Parser, Tag, TagEnd};
 
-use crate::paragraph::ParagraphWalker;
+use crate::paragraph::{ParagraphOptions, ParagraphWalker};
 
 // Note: Keep in sync with html.rs
-static PARAGRAPH_TAGS: &[TagEnd] = &[TagEnd::Paragraph, TagEnd::Item];
+static PARAGRAPH_TAGS: &[TagEnd] = &[TagEnd::Paragraph, TagEnd::Item, TagEnd::TableCell];
+
+/// A paragraph, the line it ends on, and the URLs of the links in it.
+pub type ParagraphWithLinks<P> = (P, usize, Vec<String>);
 
 #[derive(Clone)]
 pub struct DocumentSource {
@@ -23,7 +26,24 @@ impl DocumentSource {
         }
     }
 
-    pub fn paragraphs<P: ParagraphWalker>(&self) -> Result<Vec<(P::Paragraph, usize)>, Error> {
+    pub fn paragraphs<P: ParagraphWalker>(
+        &self,
+        options: &ParagraphOptions,
+    ) -> Result<Vec<(P::Paragraph, usize)>, Error> {
+        Ok(self
+            .paragraphs_with_links::<P>(options)?
+            .into_iter()
+            .map(|(paragraph, lineno, _)| (paragraph, lineno))
+            .collect())
+    }
+
+    /// Like `paragraphs`, but also return the URLs of the links and images in each paragraph.
+    /// Reference-style links (`[text][ref]`, `[ref][]` and `[ref]`) are resolved to the URL of
+    /// their definition.
+    pub fn paragraphs_with_links<P: ParagraphWalker>(
+        &self,
+        options: &ParagraphOptions,
+    ) -> Result<Vec<ParagraphWithLinks<P::Paragraph>>, Error> {
         let mut text = String::new();
         // line_numbers[0] = 32 ... line 0 ends at `text` offset 32
         let mut line_numbers = Vec::new();
@@ -45,13 +65,20 @@ impl DocumentSource {
         }
 
         let mut in_paragraph = false;
+        let mut in_code_block = false;
         let mut walker = P::new();
+        let mut links = Vec::new();
         let mut rv = Vec::new();
 
-        for (event, range) in Parser::new(&text).into_offset_iter() {
+        // Footnote definitions are paragraphs of their own, and references to them are left out of
+        // the referencing paragraph, like the generated links themes render them as.
+        let parser = Parser::new_ext(&text, Options::ENABLE_FOOTNOTES | Options::ENABLE_TABLES);
+
+        for (event, range) in parser.into_offset_iter() {
             match event {
                 Event::Start(tag) if PARAGRAPH_TAGS.contains(&tag.to_end()) => {
                     walker.finish_paragraph();
+                    links.clear();
                     in_paragraph = true;
                 }
                 Event::End(tag) if PARAGRAPH_TAGS.contains(&tag) => {
@@ -62,15 +89,21 @@ impl DocumentSource {
                                 Ok(i) => i + 1,
                                 Err(i) => i + 1,
                             };
-                            rv.push((paragraph, lineno));
+                            rv.push((paragraph, lineno, std::mem::take(&mut links)));
                         }
                     }
                     in_paragraph = false;
                 }
-                Event::Text(text) | Event::Code(text) => {
-                    if in_paragraph {
-                        walker.update(text.as_bytes());
-                    }
+                // pulldown-cmark has already looked up the definition of reference-style links
+                Event::Start(Tag::Link { dest_url, .. } | Tag::Image { dest_url, .. })
+                    if in_paragraph =>
+                {
+                    links.push(dest_url.into_string());
+                }
+                Event::Start(Tag::CodeBlock(_)) => in_code_block = options.skip_code_blocks,
+                Event::End(TagEnd::CodeBlock) => in_code_block = false,
+                Event::Text(text) | Event::Code(text) if in_paragraph && !in_code_block => {
+                    walker.update(text.as_bytes());
                 }
                 _ => {}
             }
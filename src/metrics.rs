@@ -0,0 +1,297 @@
+//! `--prometheus-out` and `--statsd`: metrics of a run, for dashboards and alerting.
+
+use std::fmt::Write;
+use std::fs;
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr, ToSocketAddrs, UdpSocket};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use anyhow::{anyhow, Context, Error};
+use serde_json::{json, Value};
+
+use crate::baseline::Date;
+
+/// What produced a report, so that archived reports describe themselves and runs can be compared
+/// like with like.
+#[derive(Debug)]
+pub struct RunInfo {
+    pub version: &'static str,
+    pub started: SystemTime,
+    pub base_path: PathBuf,
+    /// The command-line arguments, without the program name.
+    pub args: Vec<String>,
+    /// The commit checked out in the repository containing `base_path`, if any.
+    pub git_commit: Option<String>,
+}
+
+impl RunInfo {
+    pub fn to_json(&self) -> Value {
+        json!({
+            "version": self.version,
+            "timestamp": rfc3339(self.started),
+            "base_path": self.base_path.to_string_lossy(),
+            "args": self.args,
+            "git_commit": self.git_commit,
+        })
+    }
+}
+
+/// `time` in UTC, to the second, e.g. `2026-10-16T09:30:00Z`.
+fn rfc3339(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs());
+    let (hours, minutes, seconds) = (secs / 3600 % 24, secs / 60 % 60, secs % 60);
+    format!(
+        "{}T{hours:02}:{minutes:02}:{seconds:02}Z",
+        Date::from_unix_secs(secs)
+    )
+}
+
+/// What a run found, the same numbers as in `--summary-out`.
+#[derive(Debug, Default)]
+pub struct Metrics {
+    pub duration_secs: f64,
+    pub files: usize,
+    pub documents: usize,
+    pub links: usize,
+    /// Problems found, by category, e.g. `("bad_links", 3)`. Categories of checks that were not
+    /// enabled are `0`.
+    pub problems: Vec<(&'static str, usize)>,
+    /// Links that were not checked, by reason, e.g. `("mailto", 2)`.
+    pub skipped: Vec<(&'static str, usize)>,
+}
+
+impl Metrics {
+    /// The summary of `--summary-out` and of `--format json`.
+    pub fn summary_json(&self, run: &RunInfo) -> Value {
+        let mut summary = json!({
+            "run": run.to_json(),
+            "files": self.files,
+            "documents": self.documents,
+            "links": self.links,
+            "duration_secs": self.duration_secs,
+        });
+        for &(category, count) in &self.problems {
+            summary[category] = count.into();
+        }
+        summary["skipped"] = self
+            .skipped
+            .iter()
+            .map(|&(reason, count)| (reason.to_owned(), count.into()))
+            .collect::<serde_json::Map<_, _>>()
+            .into();
+        summary
+    }
+
+    /// The metrics in the Prometheus text exposition format.
+    pub fn prometheus_text(&self) -> String {
+        let mut text = String::new();
+        let mut gauge = |name: &str, help: &str, value: &dyn std::fmt::Display| {
+            writeln!(text, "# HELP hyperlink_{name} {help}").unwrap();
+            writeln!(text, "# TYPE hyperlink_{name} gauge").unwrap();
+            writeln!(text, "hyperlink_{name} {value}").unwrap();
+        };
+        gauge(
+            "duration_seconds",
+            "Duration of the last run.",
+            &self.duration_secs,
+        );
+        gauge("files", "Files found in the last run.", &self.files);
+        gauge(
+            "documents",
+            "Documents parsed in the last run.",
+            &self.documents,
+        );
+        gauge("links", "Links checked in the last run.", &self.links);
+
+        text.push_str("# HELP hyperlink_problems Problems found in the last run, by category.\n");
+        text.push_str("# TYPE hyperlink_problems gauge\n");
+        for (category, count) in &self.problems {
+            writeln!(
+                text,
+                "hyperlink_problems{{category=\"{category}\"}} {count}"
+            )
+            .unwrap();
+        }
+
+        text.push_str(
+            "# HELP hyperlink_skipped_links Links not checked in the last run, by reason.\n",
+        );
+        text.push_str("# TYPE hyperlink_skipped_links gauge\n");
+        for (reason, count) in &self.skipped {
+            writeln!(
+                text,
+                "hyperlink_skipped_links{{reason=\"{reason}\"}} {count}"
+            )
+            .unwrap();
+        }
+
+        text
+    }
+
+    /// The metrics as a Markdown table, e.g. for the job summary on GitHub Actions.
+    pub fn markdown_table(&self) -> String {
+        let mut table = String::from("### hyperlink\n\n| | |\n| --- | --: |\n");
+        let counts = [
+            ("files", self.files),
+            ("documents", self.documents),
+            ("links", self.links),
+        ];
+        for (name, count) in counts.iter().chain(&self.problems) {
+            writeln!(table, "| {} | {count} |", name.replace('_', " ")).unwrap();
+        }
+        writeln!(table, "| duration | {:.2}s |", self.duration_secs).unwrap();
+        table
+    }
+
+    /// Write the metrics to `path` for node_exporter's textfile collector. The file is replaced
+    /// atomically, so that the collector never reads a partial file.
+    pub fn write_prometheus(&self, path: &Path) -> Result<(), Error> {
+        let mut tmp_path = path.as_os_str().to_owned();
+        tmp_path.push(".tmp");
+        fs::write(&tmp_path, self.prometheus_text())
+            .and_then(|()| fs::rename(&tmp_path, path))
+            .with_context(|| format!("Failed to write metrics to {}", path.display()))
+    }
+
+    /// The metrics as StatsD gauges and a timer, one per line.
+    pub fn statsd_lines(&self) -> String {
+        let mut lines = format!(
+            "hyperlink.duration:{}|ms\n",
+            (self.duration_secs * 1000.0).round()
+        );
+        let counts = [
+            ("files", self.files),
+            ("documents", self.documents),
+            ("links", self.links),
+        ];
+        for (name, count) in counts.iter().chain(&self.problems) {
+            writeln!(lines, "hyperlink.{name}:{count}|g").unwrap();
+        }
+        for (reason, count) in &self.skipped {
+            writeln!(lines, "hyperlink.skipped.{reason}:{count}|g").unwrap();
+        }
+        lines
+    }
+
+    /// Send the metrics to the StatsD server at `address`, e.g. `localhost:8125`, in a single UDP
+    /// packet.
+    pub fn send_statsd(&self, address: &str) -> Result<(), Error> {
+        let target = address
+            .to_socket_addrs()
+            .with_context(|| format!("Invalid StatsD address {address}"))?
+            .next()
+            .ok_or_else(|| anyhow!("Invalid StatsD address {address}"))?;
+        let local: SocketAddr = if target.is_ipv4() {
+            (Ipv4Addr::UNSPECIFIED, 0).into()
+        } else {
+            (Ipv6Addr::UNSPECIFIED, 0).into()
+        };
+
+        UdpSocket::bind(local)
+            .and_then(|socket| socket.send_to(self.statsd_lines().as_bytes(), target))
+            .with_context(|| format!("Failed to send metrics to StatsD server {address}"))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+fn example() -> Metrics {
+    Metrics {
+        duration_secs: 0.41,
+        files: 12,
+        documents: 10,
+        links: 250,
+        problems: vec![("bad_links", 3), ("bad_anchors", 0)],
+        skipped: vec![("external", 40), ("mailto", 2)],
+    }
+}
+
+#[test]
+fn test_prometheus_text() {
+    assert_eq!(
+        example().prometheus_text(),
+        "\
+# HELP hyperlink_duration_seconds Duration of the last run.
+# TYPE hyperlink_duration_seconds gauge
+hyperlink_duration_seconds 0.41
+# HELP hyperlink_files Files found in the last run.
+# TYPE hyperlink_files gauge
+hyperlink_files 12
+# HELP hyperlink_documents Documents parsed in the last run.
+# TYPE hyperlink_documents gauge
+hyperlink_documents 10
+# HELP hyperlink_links Links checked in the last run.
+# TYPE hyperlink_links gauge
+hyperlink_links 250
+# HELP hyperlink_problems Problems found in the last run, by category.
+# TYPE hyperlink_problems gauge
+hyperlink_problems{category=\"bad_links\"} 3
+hyperlink_problems{category=\"bad_anchors\"} 0
+# HELP hyperlink_skipped_links Links not checked in the last run, by reason.
+# TYPE hyperlink_skipped_links gauge
+hyperlink_skipped_links{reason=\"external\"} 40
+hyperlink_skipped_links{reason=\"mailto\"} 2
+"
+    );
+}
+
+#[test]
+fn test_statsd_lines() {
+    assert_eq!(
+        example().statsd_lines(),
+        "\
+hyperlink.duration:410|ms
+hyperlink.files:12|g
+hyperlink.documents:10|g
+hyperlink.links:250|g
+hyperlink.bad_links:3|g
+hyperlink.bad_anchors:0|g
+hyperlink.skipped.external:40|g
+hyperlink.skipped.mailto:2|g
+"
+    );
+}
+
+#[test]
+fn test_run_info() {
+    use std::time::Duration;
+
+    let run = RunInfo {
+        version: "0.2.0",
+        started: SystemTime::UNIX_EPOCH + Duration::from_secs(1_792_142_999),
+        base_path: PathBuf::from("public"),
+        args: vec!["public".to_owned(), "--check-anchors".to_owned()],
+        git_commit: None,
+    };
+    assert_eq!(
+        run.to_json(),
+        json!({
+            "version": "0.2.0",
+            "timestamp": "2026-10-16T09:29:59Z",
+            "base_path": "public",
+            "args": ["public", "--check-anchors"],
+            "git_commit": null,
+        })
+    );
+}
+
+#[test]
+fn test_markdown_table() {
+    assert_eq!(
+        example().markdown_table(),
+        "\
+### hyperlink
+
+| | |
+| --- | --: |
+| files | 12 |
+| documents | 10 |
+| links | 250 |
+| bad links | 3 |
+| bad anchors | 0 |
+| duration | 0.41s |
+"
+    );
+}
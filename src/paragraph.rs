@@ -2,17 +2,27 @@ use std::fmt;
 use std::hash::Hash;
 use std::mem;
 
+use unicode_normalization::UnicodeNormalization;
+
 #[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Ord, PartialOrd)]
 pub struct Paragraph {
     hash: [u8; 32],
 }
 
+/// Which parts of a paragraph are hashed. Must be the same for Markdown sources and HTML.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ParagraphOptions {
+    /// Leave out code blocks, which syntax highlighters turn into markup that never matches the
+    /// source. Inline code is still hashed.
+    pub skip_code_blocks: bool,
+}
+
 pub struct ParagraphHasher {
     hasher: blake3::Hasher,
 }
 
 pub trait ParagraphWalker: Send {
-    type Paragraph: Clone + Eq + PartialEq + Hash + Ord + PartialOrd + Send + 'static;
+    type Paragraph: Clone + Eq + PartialEq + Hash + Ord + PartialOrd + Send + Sync + 'static;
 
     fn new() -> Self;
 
@@ -25,14 +35,56 @@ pub trait ParagraphWalker: Send {
     fn finish_paragraph(&mut self) -> Option<Self::Paragraph>;
 
     fn update(&mut self, text: &[u8]) {
-        for c in text {
-            if !c.is_ascii_whitespace() {
-                self.update_raw(&[*c]);
+        if text.is_ascii() {
+            for c in text {
+                if !c.is_ascii_whitespace() {
+                    self.update_raw(&[*c]);
+                }
+            }
+            return;
+        }
+
+        // NFKC folds fullwidth Latin letters and digits, as well as the ideographic space, which
+        // CJK text is often typed with in one place and rendered without in another. Whitespace,
+        // which renderers add and remove around inline elements, is left out entirely.
+        let mut buf = [0; 4];
+        for chunk in text.utf8_chunks() {
+            for c in chunk.valid().nfkc().filter(|c| !c.is_whitespace()) {
+                let c = &*c.encode_utf8(&mut buf);
+                match SMART_PUNCTUATION
+                    .iter()
+                    .find(|(typographic, _)| *typographic == c)
+                {
+                    Some((_, ascii)) => self.update_raw(ascii.as_bytes()),
+                    None => self.update_raw(c.as_bytes()),
+                }
+            }
+
+            // the HTML tokenizer may split a character between two calls, hash its bytes as is
+            if !chunk.invalid().is_empty() {
+                self.update_raw(chunk.invalid());
             }
         }
     }
 }
 
+/// Static site generators replace quotes, dashes and ellipses with their typographic versions
+/// (Hugo's typographer, pulldown-cmark's smart punctuation, SmartyPants). Paragraphs are hashed
+/// with the ASCII they are typed as in Markdown instead, so that both sides match.
+static SMART_PUNCTUATION: &[(&str, &str)] = &[
+    ("\u{201c}", "\""),
+    ("\u{201d}", "\""),
+    ("\u{201e}", "\""),
+    ("\u{2018}", "'"),
+    ("\u{2019}", "'"),
+    ("\u{201a}", "'"),
+    ("\u{2013}", "--"),
+    ("\u{2014}", "---"),
+    ("\u{2026}", "..."),
+    ("\u{ab}", "<<"),
+    ("\u{bb}", ">>"),
+];
+
 impl ParagraphWalker for ParagraphHasher {
     type Paragraph = Paragraph;
 
@@ -125,3 +177,38 @@ impl ParagraphWalker for NoopParagraphWalker {
         None
     }
 }
+
+#[test]
+fn test_smart_punctuation() {
+    fn hash(text: &str) -> DebugParagraph<Paragraph> {
+        let mut walker = DebugParagraphWalker::<ParagraphHasher>::new();
+        walker.update(text.as_bytes());
+        walker.finish_paragraph().unwrap()
+    }
+
+    assert_eq!(
+        hash("\u{201c}It\u{2019}s fine\u{201d} \u{2013} they said\u{2026} \u{2014} \u{ab}ok\u{bb}"),
+        hash("\"It's fine\" -- they said... --- <<ok>>"),
+    );
+    assert_ne!(hash("\u{e9}t\u{e9}"), hash("ete"));
+}
+
+#[test]
+fn test_cjk_normalization() {
+    fn hash(text: &str) -> DebugParagraph<Paragraph> {
+        let mut walker = DebugParagraphWalker::<ParagraphHasher>::new();
+        walker.update(text.as_bytes());
+        walker.finish_paragraph().unwrap()
+    }
+
+    assert_eq!(
+        hash("\u{4e2d}\u{6587} \u{6587}\u{6863}\u{3000}\u{ff21}\u{ff30}\u{ff29}\u{ff11}"),
+        hash("\u{4e2d}\u{6587}\u{6587}\u{6863}API1"),
+    );
+    // halfwidth katakana with a separate voiced sound mark compose into one character
+    assert_eq!(
+        hash("\u{ff76}\u{ff9e}\u{ff72}\u{ff84}\u{ff9e}"),
+        hash("\u{30ac}\u{30a4}\u{30c9}"),
+    );
+    assert_eq!(hash("\u{fb01}le \u{2460}"), hash("file1"));
+}
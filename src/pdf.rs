@@ -0,0 +1,141 @@
+use std::collections::BTreeSet;
+use std::path::Path;
+
+use anyhow::Error;
+use lopdf::{Dictionary, Document, Object};
+
+/// Fragments that are valid in links to a PDF file, per Adobe's "PDF Open Parameters": `page=N`
+/// for every page, and both `name` and `nameddest=name` for every named destination.
+pub fn pdf_anchors(path: &Path) -> Result<Vec<String>, Error> {
+    let document = Document::load(path)?;
+
+    let mut anchors: Vec<String> = (1..=document.get_pages().len())
+        .map(|page| format!("page={page}"))
+        .collect();
+
+    let mut names = BTreeSet::new();
+    if let Ok(catalog) = document.catalog() {
+        // PDF 1.2+: name tree in the document's name dictionary
+        if let Some(tree) = catalog
+            .get(b"Names")
+            .ok()
+            .and_then(|names| as_dict(&document, names))
+            .and_then(|names| names.get(b"Dests").ok())
+            .and_then(|dests| as_dict(&document, dests))
+        {
+            collect_name_tree_keys(&document, tree, &mut names, 0);
+        }
+
+        // PDF 1.1: plain dictionary in the catalog
+        if let Some(dests) = catalog
+            .get(b"Dests")
+            .ok()
+            .and_then(|dests| as_dict(&document, dests))
+        {
+            for (name, _) in dests {
+                names.insert(String::from_utf8_lossy(name).into_owned());
+            }
+        }
+    }
+
+    for name in names {
+        anchors.push(format!("nameddest={name}"));
+        anchors.push(name);
+    }
+
+    Ok(anchors)
+}
+
+fn as_dict<'a>(document: &'a Document, object: &'a Object) -> Option<&'a Dictionary> {
+    document.dereference(object).ok()?.1.as_dict().ok()
+}
+
+/// Name trees can be arbitrarily deep, but a malformed file must not send us into a loop.
+const MAX_NAME_TREE_DEPTH: usize = 32;
+
+fn collect_name_tree_keys(
+    document: &Document,
+    node: &Dictionary,
+    names: &mut BTreeSet<String>,
+    depth: usize,
+) {
+    if depth > MAX_NAME_TREE_DEPTH {
+        return;
+    }
+
+    if let Ok(kids) = node.get(b"Kids").and_then(Object::as_array) {
+        for kid in kids {
+            if let Some(kid) = as_dict(document, kid) {
+                collect_name_tree_keys(document, kid, names, depth + 1);
+            }
+        }
+    }
+
+    if let Ok(entries) = node.get(b"Names").and_then(Object::as_array) {
+        // alternating keys and values
+        for key in entries.iter().step_by(2) {
+            if let Ok(key) = key.as_string() {
+                names.insert(key.into_owned());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+pub fn write_test_pdf(path: &Path, pages: usize, named_destinations: &[&str]) {
+    use lopdf::{dictionary, Stream};
+
+    let mut document = Document::with_version("1.5");
+    let pages_id = document.new_object_id();
+
+    let mut kids = Vec::new();
+    for _ in 0..pages {
+        let content_id = document.add_object(Stream::new(dictionary! {}, Vec::new()));
+        let page_id = document.add_object(dictionary! {
+            "Type" => "Page",
+            "Parent" => pages_id,
+            "Contents" => content_id,
+        });
+        kids.push(Object::from(page_id));
+    }
+
+    let first_page = kids[0].clone();
+    document.objects.insert(
+        pages_id,
+        Object::Dictionary(dictionary! {
+            "Type" => "Pages",
+            "Count" => pages as i64,
+            "Kids" => kids,
+        }),
+    );
+
+    let mut entries = Vec::new();
+    for name in named_destinations {
+        entries.push(Object::string_literal(*name));
+        entries.push(Object::Array(vec![first_page.clone(), "Fit".into()]));
+    }
+
+    let catalog_id = document.add_object(dictionary! {
+        "Type" => "Catalog",
+        "Pages" => pages_id,
+        "Names" => dictionary! {
+            "Dests" => dictionary! { "Names" => entries },
+        },
+    });
+    document.trailer.set("Root", catalog_id);
+    document.save(path).unwrap();
+}
+
+#[test]
+fn test_pdf_anchors() {
+    let dir = assert_fs::TempDir::new().unwrap();
+    let path = dir.path().join("manual.pdf");
+    write_test_pdf(&path, 2, &["section-3"]);
+
+    assert_eq!(
+        pdf_anchors(&path).unwrap(),
+        &["page=1", "page=2", "nameddest=section-3", "section-3"]
+    );
+
+    dir.close().unwrap();
+}
@@ -0,0 +1,47 @@
+//! `hyperlink permalinks`: pages of a published build that the next build would break.
+
+use hyperlink::sisters::LinkIndex;
+
+/// Whether `href` from a `LinkIndex` is a page, as opposed to an anchor, an asset or the
+/// `_redirects` file. Assets like fingerprinted scripts change with every build, and are not what
+/// other sites link to.
+pub fn is_page(href: &str) -> bool {
+    if href.contains('#') {
+        return false;
+    }
+
+    let name = href.rsplit('/').next().unwrap_or(href);
+    match name.rsplit_once('.') {
+        Some((_, extension)) => {
+            extension.eq_ignore_ascii_case("html") || extension.eq_ignore_ascii_case("htm")
+        }
+        None => name != "_redirects",
+    }
+}
+
+/// The pages of `old` that `new` neither has nor redirects, in order.
+pub fn broken_permalinks<'a>(old: &'a LinkIndex, new: &LinkIndex) -> Vec<&'a str> {
+    old.hrefs()
+        .filter(|href| is_page(href) && !new.contains(href))
+        .collect()
+}
+
+#[test]
+fn test_broken_permalinks() {
+    let index = |hrefs: &[&str]| LinkIndex::new(hrefs.iter().map(|href| href.to_string()));
+
+    let old = index(&[
+        "",
+        "_redirects",
+        "about.html",
+        "app.3f2a.js",
+        "blog/hello",
+        "blog/hello#comments",
+        "docs/setup",
+        "feed.xml",
+    ]);
+    let new = index(&["", "about", "about.html", "app.9c1e.js", "docs/setup"]);
+
+    assert_eq!(broken_permalinks(&old, &new), ["blog/hello"]);
+    assert!(broken_permalinks(&new, &new).is_empty());
+}
@@ -0,0 +1,126 @@
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use anyhow::{Context, Error};
+use pulldown_cmark::{Event, Parser, Tag};
+
+use crate::html::{try_percent_decode, Href};
+use crate::urls::is_external_link;
+
+/// Workarounds for the output of a specific static site generator, selected with `--preset`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Preset {
+    Mdbook,
+}
+
+impl FromStr for Preset {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Preset::ALL
+            .iter()
+            .copied()
+            .find(|preset| preset.name() == s)
+            .ok_or_else(|| {
+                let names: Vec<_> = Preset::ALL.iter().map(|preset| preset.name()).collect();
+                format!(
+                    "unknown preset {s:?}, expected one of: {}",
+                    names.join(", ")
+                )
+            })
+    }
+}
+
+impl Preset {
+    pub const ALL: &'static [Preset] = &[Preset::Mdbook];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Preset::Mdbook => "mdbook",
+        }
+    }
+
+    /// The name of the generator, for messages.
+    pub fn title(self) -> &'static str {
+        match self {
+            Preset::Mdbook => "mdBook",
+        }
+    }
+
+    /// Documents that are valid link targets, but whose links should not be checked.
+    pub fn skip_document(self, href: &Href<'_>) -> bool {
+        match self {
+            // print.html concatenates all chapters, so every broken link would be reported twice.
+            Preset::Mdbook => href.0 == "print.html",
+        }
+    }
+
+    /// The id the generator gives a heading whose text is `anchor`, if that is not `anchor` itself.
+    /// Points out links that spell out a heading, like `#Getting Started`, instead of its id.
+    pub fn heading_anchor(self, anchor: &str) -> Option<String> {
+        match self {
+            // mdBook's normalize_id: alphanumerics, `_` and `-` are kept and lowercased, whitespace
+            // becomes `-`, anything else is dropped. Duplicate headings get `-1`, `-2` and so on,
+            // which cannot be told from the text.
+            Preset::Mdbook => {
+                let id: String = try_percent_decode(anchor)
+                    .chars()
+                    .filter_map(|c| {
+                        if c.is_alphanumeric() || c == '_' || c == '-' {
+                            Some(c.to_ascii_lowercase())
+                        } else if c.is_whitespace() {
+                            Some('-')
+                        } else {
+                            None
+                        }
+                    })
+                    .collect();
+                (!id.is_empty() && id != anchor).then_some(id)
+            }
+        }
+    }
+
+    /// The markdown files that actually end up in the output, if the generator declares them
+    /// somewhere. Other files in the sources folder are not considered for error reporting.
+    pub fn source_files(self, sources_path: &Path) -> Result<Option<BTreeSet<PathBuf>>, Error> {
+        match self {
+            Preset::Mdbook => {
+                let summary_path = sources_path.join("SUMMARY.md");
+                let summary = fs::read_to_string(&summary_path)
+                    .with_context(|| format!("Failed to read file {}", summary_path.display()))?;
+
+                let mut chapters = BTreeSet::new();
+
+                for event in Parser::new(&summary) {
+                    if let Event::Start(Tag::Link { dest_url, .. }) = event {
+                        let dest_url = dest_url.split('#').next().unwrap_or("");
+
+                        // draft chapters have an empty link
+                        if !dest_url.is_empty() && !is_external_link(dest_url.as_bytes()) {
+                            chapters.insert(sources_path.join(&*try_percent_decode(dest_url)));
+                        }
+                    }
+                }
+
+                Ok(Some(chapters))
+            }
+        }
+    }
+}
+
+#[test]
+fn test_mdbook_heading_anchor() {
+    let mdbook = Preset::Mdbook;
+    assert_eq!(
+        mdbook.heading_anchor("Getting%20Started").as_deref(),
+        Some("getting-started")
+    );
+    assert_eq!(
+        mdbook.heading_anchor("What's_New?").as_deref(),
+        Some("whats_new")
+    );
+    assert_eq!(mdbook.heading_anchor("getting-started"), None);
+    assert_eq!(mdbook.heading_anchor("???"), None);
+}
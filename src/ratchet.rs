@@ -0,0 +1,87 @@
+//! `--ratchet`: tolerate known broken links, but never more of them.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, Error};
+
+/// A count that went up compared to the ratchet file.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Regression {
+    pub name: String,
+    pub recorded: usize,
+    pub current: usize,
+}
+
+/// Compare `counts` against the counts recorded in the ratchet file at `path`.
+///
+/// Counts that went down are written back to the file, as are counts that were not recorded yet.
+/// If the file does not exist, it is created with `counts`.
+pub fn ratchet(path: &Path, counts: &BTreeMap<String, usize>) -> Result<Vec<Regression>, Error> {
+    let mut recorded: BTreeMap<String, usize> = if path.exists() {
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read file {}", path.display()))?;
+        serde_json::from_str(&contents)
+            .with_context(|| format!("Failed to parse ratchet file {}", path.display()))?
+    } else {
+        BTreeMap::new()
+    };
+
+    let mut regressions = Vec::new();
+    let mut changed = !path.exists();
+
+    for (name, &current) in counts {
+        match recorded.get(name) {
+            Some(&recorded) if current > recorded => regressions.push(Regression {
+                name: name.clone(),
+                recorded,
+                current,
+            }),
+            Some(&recorded) if current == recorded => (),
+            _ => {
+                recorded.insert(name.clone(), current);
+                changed = true;
+            }
+        }
+    }
+
+    if changed {
+        let contents = serde_json::to_string_pretty(&recorded)?;
+        fs::write(path, format!("{contents}\n"))
+            .with_context(|| format!("Failed to write ratchet file {}", path.display()))?;
+    }
+
+    Ok(regressions)
+}
+
+#[test]
+fn test_ratchet() {
+    let dir = assert_fs::TempDir::new().unwrap();
+    let path = dir.path().join("ratchet.json");
+    let counts = |links: usize, anchors: usize| -> BTreeMap<String, usize> {
+        [("bad_links", links), ("bad_anchors", anchors)]
+            .iter()
+            .map(|(name, count)| (name.to_string(), *count))
+            .collect()
+    };
+    let read = || -> BTreeMap<String, usize> {
+        serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap()
+    };
+
+    assert_eq!(ratchet(&path, &counts(5, 2)).unwrap(), []);
+    assert_eq!(read(), counts(5, 2));
+
+    assert_eq!(ratchet(&path, &counts(3, 2)).unwrap(), []);
+    assert_eq!(read(), counts(3, 2));
+
+    assert_eq!(
+        ratchet(&path, &counts(4, 1)).unwrap(),
+        [Regression {
+            name: "bad_links".to_owned(),
+            recorded: 3,
+            current: 4,
+        }]
+    );
+    assert_eq!(read(), counts(3, 1));
+}
@@ -0,0 +1,107 @@
+//! `--format rdjson`: the report in reviewdog's Diagnostic JSON format, so that reviewdog can
+//! comment on the lines of a pull request that introduce broken links, on any forge it supports.
+
+use std::path::Path;
+
+use serde_json::{json, Value};
+
+use crate::github::{Category, Level};
+use crate::sarif::{relative_path, rule_id};
+
+fn severity(level: Level) -> &'static str {
+    match level {
+        Level::Error => "ERROR",
+        Level::Warning => "WARNING",
+        Level::Notice => "INFO",
+    }
+}
+
+/// One problem in the file at `path`, at `line` if known. reviewdog only comments on problems
+/// with a line, the others only show up in its other reporters.
+pub fn diagnostic(
+    category: Category,
+    level: Level,
+    message: &str,
+    path: &Path,
+    line: Option<usize>,
+) -> Value {
+    let mut location = json!({ "path": relative_path(path) });
+    if let Some(line) = line {
+        location["range"] = json!({ "start": { "line": line } });
+    }
+
+    json!({
+        "message": message,
+        "location": location,
+        "severity": severity(level),
+        "code": { "value": rule_id(category) },
+    })
+}
+
+/// A `DiagnosticResult` with `diagnostics` as returned by `diagnostic`, sorted by location, so
+/// that the results of two runs can be diffed.
+pub fn result(mut diagnostics: Vec<Value>) -> Value {
+    diagnostics.sort_by_cached_key(|diagnostic| {
+        let location = &diagnostic["location"];
+        (
+            location["path"].as_str().map(str::to_owned),
+            location["range"]["start"]["line"].as_u64(),
+            diagnostic["message"].as_str().map(str::to_owned),
+        )
+    });
+
+    json!({
+        "source": {
+            "name": "hyperlink",
+            "url": "https://github.com/untitaker/hyperlink",
+        },
+        "diagnostics": diagnostics,
+    })
+}
+
+#[test]
+fn test_rdjson_result() {
+    let result = result(vec![
+        diagnostic(
+            Category::Lints,
+            Level::Warning,
+            "javascript-urls: javascript:void(0) is a javascript: URL (a[href])",
+            Path::new("public/index.html"),
+            None,
+        ),
+        diagnostic(
+            Category::Links,
+            Level::Error,
+            "bad link /missing.html (a[href])",
+            Path::new("./src/index.md"),
+            Some(3),
+        ),
+    ]);
+
+    assert_eq!(
+        result,
+        json!({
+            "source": {
+                "name": "hyperlink",
+                "url": "https://github.com/untitaker/hyperlink",
+            },
+            "diagnostics": [
+                {
+                    "message": "javascript-urls: javascript:void(0) is a javascript: URL (a[href])",
+                    "location": { "path": "public/index.html" },
+                    "severity": "WARNING",
+                    "code": { "value": "lints" },
+                },
+                {
+                    "message": "bad link /missing.html (a[href])",
+                    "location": {
+                        "path": "src/index.md",
+                        "range": { "start": { "line": 3 } },
+                    },
+                    "severity": "ERROR",
+                    "code": { "value": "bad-link" },
+                },
+            ],
+        })
+    );
+}
@@ -0,0 +1,116 @@
+//! Finding rules in `_redirects` that can be removed.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use anyhow::{Context, Error};
+use bumpalo::collections::String as BumpString;
+use bumpalo::Bump;
+use rayon::prelude::*;
+
+use crate::html::{push_and_canonicalize, LinkDecision, LinkHook};
+
+/// A rule in `_redirects`.
+#[derive(Debug)]
+pub struct Redirect {
+    /// Canonicalized source href, without leading slash.
+    pub source: String,
+    pub lineno: usize,
+    used: AtomicBool,
+}
+
+/// The rules of a site's `_redirects` file, and whether any link uses them.
+#[derive(Debug)]
+pub struct Redirects {
+    pub path: PathBuf,
+    pub rules: Vec<Redirect>,
+    /// Source href to indices into `rules`.
+    by_source: BTreeMap<String, Vec<usize>>,
+}
+
+impl Redirects {
+    /// Read `_redirects` at the root of `base_path`, or return `None` if there is none.
+    pub fn read(base_path: &Path) -> Result<Option<Arc<Self>>, Error> {
+        let path = base_path.join("_redirects");
+        if !path.is_file() {
+            return Ok(None);
+        }
+
+        let contents = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read file {}", path.display()))?;
+        let arena = Bump::new();
+        let mut rules = Vec::new();
+
+        for (i, line) in contents.lines().enumerate() {
+            let trimmed = line.trim();
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                continue;
+            }
+
+            let mut parts = trimmed.split_whitespace();
+            if let (Some(source), Some(_)) = (parts.next(), parts.next()) {
+                let mut href = BumpString::new_in(&arena);
+                push_and_canonicalize(&mut href, source);
+                rules.push(Redirect {
+                    source: href.as_str().to_owned(),
+                    lineno: i + 1,
+                    used: AtomicBool::new(false),
+                });
+            }
+        }
+
+        let mut by_source = BTreeMap::new();
+        for (i, rule) in rules.iter().enumerate() {
+            by_source
+                .entry(rule.source.clone())
+                .or_insert_with(Vec::new)
+                .push(i);
+        }
+
+        Ok(Some(Arc::new(Redirects {
+            path,
+            rules,
+            by_source,
+        })))
+    }
+
+    /// A hook that records which redirects are linked to. It never skips or rewrites links.
+    pub fn usage_hook(self: &Arc<Self>) -> Arc<LinkHook> {
+        let redirects = self.clone();
+
+        Arc::new(move |href, _, _| {
+            if let Some(indices) = redirects.by_source.get(href.without_anchor().0) {
+                for &i in indices {
+                    redirects.rules[i].used.store(true, Ordering::Relaxed);
+                }
+            }
+            LinkDecision::Check
+        })
+    }
+
+    /// Redirects that no link points to. Only meaningful after all documents have been parsed
+    /// with `usage_hook` registered.
+    pub fn unused(&self) -> impl Iterator<Item = &Redirect> {
+        self.rules
+            .iter()
+            .filter(|rule| !rule.used.load(Ordering::Relaxed))
+    }
+
+    /// Redirects whose source is also a file in `base_path`. Most hosts serve the file and never
+    /// apply the redirect. The files are looked up in parallel on the current thread pool, as each
+    /// lookup is a round trip on network filesystems.
+    pub fn shadowed(&self, base_path: &Path) -> Vec<&Redirect> {
+        self.rules
+            .par_iter()
+            .filter(|rule| {
+                let path = base_path.join(&rule.source);
+                path.is_file()
+                    || path.join("index.html").is_file()
+                    || path.join("index.htm").is_file()
+            })
+            .collect()
+    }
+}
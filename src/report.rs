@@ -0,0 +1,670 @@
+//! Machine-readable reports, see `--format`. Every format is a `ReportSink` that is fed the
+//! problems found one by one, so that programs using this crate can add their own, e.g. to store
+//! results in a database or file them in an issue tracker.
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::Error;
+use serde_json::{json, Value};
+
+use crate::checkstyle::CheckstyleReport;
+use crate::collector::{BrokenLinkCollector, PageError};
+use crate::csv;
+use crate::github::{Category, Level};
+use crate::html::{is_html_path, LinkOrigin, Placeholder};
+use crate::junit::JunitReport;
+use crate::metrics::{Metrics, RunInfo};
+use crate::rdjson;
+use crate::sarif;
+use crate::tap::TapReport;
+
+/// A link to a file or anchor that does not exist, as used by one HTML file.
+#[derive(Debug, Clone)]
+pub struct BadLink<'a> {
+    /// The href without leading slash.
+    pub href: String,
+    /// The HTML file using the link.
+    pub file: Arc<PathBuf>,
+    /// The file the href points at, whether it exists or not.
+    pub target: PathBuf,
+    pub origin: LinkOrigin,
+    /// How often `file` uses the link.
+    pub count: usize,
+    /// Whether the file the href points at is missing, rather than only the anchor.
+    pub hard_404: bool,
+    /// The file the href points at exists, but is a copy of the 404 page, see
+    /// `ParseOptions::soft_404_markers`.
+    pub soft_404: Option<Placeholder>,
+    /// The source files of `file` that contain the link, and the line in them if known. Empty
+    /// without `--sources` and `--build-manifest`.
+    pub sources: Vec<(&'a Path, Option<usize>)>,
+}
+
+impl BadLink<'_> {
+    pub fn category(&self) -> Category {
+        if self.hard_404 {
+            Category::Links
+        } else {
+            Category::Anchors
+        }
+    }
+
+    /// The message of the problem, e.g. `bad link /missing.html (a[href])`.
+    pub fn message(&self) -> String {
+        match self.soft_404 {
+            Some(ref soft_404) => format!("bad link /{} ({}), {soft_404}", self.href, self.origin),
+            None => format!("bad link /{} ({})", self.href, self.origin),
+        }
+    }
+}
+
+/// The category a page error is reported under.
+pub fn page_error_category(error: &PageError) -> Category {
+    match error {
+        PageError::MissingHreflangDefault | PageError::HreflangNotReciprocal { .. } => {
+            Category::Hreflang
+        }
+        PageError::CanonicalRedirect { .. } | PageError::CanonicalNotSelf { .. } => {
+            Category::Canonical
+        }
+        PageError::UnusedRedirect { .. } | PageError::ShadowedRedirect { .. } => {
+            Category::Redirects
+        }
+        PageError::IntegrityMismatch { .. } => Category::Integrity,
+        PageError::BadDownload { .. } => Category::Downloads,
+        PageError::BadSisterLink { .. } => Category::SisterLinks,
+        // warnings on purpose, whatever the level of broken links
+        PageError::Lint { .. }
+        | PageError::WarnedLink { .. }
+        | PageError::RedirectedAnchor { .. }
+        | PageError::PlaceholderLink { .. } => Category::Lints,
+        PageError::Unparseable { .. } => Category::Unparseable,
+    }
+}
+
+/// The level a page error is reported at.
+pub fn page_error_level(error: &PageError) -> Level {
+    match error {
+        PageError::Lint { .. }
+        | PageError::WarnedLink { .. }
+        | PageError::RedirectedAnchor { .. }
+        | PageError::PlaceholderLink { .. } => Level::Warning,
+        _ => Level::Error,
+    }
+}
+
+/// What sinks can look up about the checked site once every problem is in. Only looked up by
+/// the sinks that need it, as it is expensive for large sites.
+pub trait Site {
+    /// Every checked document.
+    fn documents(&self) -> Vec<Arc<PathBuf>>;
+    /// Every href that is defined and used, without leading slash.
+    fn checked_links(&self) -> Vec<&str>;
+}
+
+impl<P: Copy + PartialEq> Site for BrokenLinkCollector<P> {
+    fn documents(&self) -> Vec<Arc<PathBuf>> {
+        self.get_documents().into_keys().collect()
+    }
+
+    fn checked_links(&self) -> Vec<&str> {
+        self.get_checked_links().collect()
+    }
+}
+
+/// The run as a whole, passed to `ReportSink::on_summary`.
+pub struct Summary<'a> {
+    pub base_path: &'a Path,
+    pub metrics: &'a Metrics,
+    /// What produced the report, if any sink asked for it with `ReportSink::wants_run_info`.
+    pub run: Option<&'a RunInfo>,
+    pub site: &'a dyn Site,
+}
+
+/// A report the problems of a run are streamed into.
+///
+/// All broken links come first, sorted by HTML file and href, then every other problem, sorted
+/// by file, then the summary. `finish` is called last, with stdout.
+pub trait ReportSink {
+    fn on_broken_link(&mut self, link: &BadLink<'_>) -> Result<(), Error>;
+
+    /// A problem that is not a broken link, e.g. a lint warning.
+    fn on_page_error(
+        &mut self,
+        _file: &Arc<PathBuf>,
+        _level: Level,
+        _error: &PageError,
+    ) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Whether `Summary::run` is needed, which runs `git` to find the current commit.
+    fn wants_run_info(&self) -> bool {
+        false
+    }
+
+    fn on_summary(&mut self, _summary: &Summary<'_>) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn finish(&mut self, _out: &mut dyn Write) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+type SinkFactory = Box<dyn Fn() -> Box<dyn ReportSink> + Send + Sync>;
+
+/// Report formats by name, as accepted by `--format`.
+pub struct SinkRegistry {
+    formats: Vec<(String, SinkFactory)>,
+}
+
+impl SinkRegistry {
+    /// A registry without any format.
+    pub fn empty() -> Self {
+        SinkRegistry {
+            formats: Vec::new(),
+        }
+    }
+
+    /// The formats that come with hyperlink. `text` is not among them, it is not a sink.
+    pub fn builtin() -> Self {
+        let mut registry = SinkRegistry::empty();
+        registry.register("json", || Box::<JsonSink>::default());
+        registry.register("sarif", || Box::<SarifSink>::default());
+        registry.register("junit", || Box::<JunitSink>::default());
+        registry.register("tap", || Box::<TapSink>::default());
+        registry.register("checkstyle", || Box::<CheckstyleSink>::default());
+        registry.register("csv", || Box::new(CsvSink::new(',')));
+        registry.register("tsv", || Box::new(CsvSink::new('\t')));
+        registry.register("rdjson", || Box::<RdjsonSink>::default());
+        registry
+    }
+
+    /// Add a format, replacing any format with the same name.
+    pub fn register(
+        &mut self,
+        name: &str,
+        factory: impl Fn() -> Box<dyn ReportSink> + Send + Sync + 'static,
+    ) {
+        self.formats.retain(|(known, _)| known != name);
+        self.formats.push((name.to_owned(), Box::new(factory)));
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.formats.iter().any(|(known, _)| known == name)
+    }
+
+    /// A new sink for the format `name`, if there is one.
+    pub fn create(&self, name: &str) -> Option<Box<dyn ReportSink>> {
+        self.formats
+            .iter()
+            .find(|(known, _)| known == name)
+            .map(|(_, factory)| factory())
+    }
+
+    /// The names of all formats, in the order they were registered.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.formats.iter().map(|(name, _)| name.as_str())
+    }
+}
+
+/// `--format json`: one JSON object with every problem and the summary.
+#[derive(Default)]
+struct JsonSink {
+    links: Vec<Value>,
+    problems: Vec<Value>,
+    summary: Option<Value>,
+}
+
+impl ReportSink for JsonSink {
+    fn on_broken_link(&mut self, link: &BadLink<'_>) -> Result<(), Error> {
+        self.links.push(json!({
+            "href": format!("/{}", link.href),
+            "target": link.target.display().to_string(),
+            "file": link.file.display().to_string(),
+            "origin": link.origin.to_string(),
+            "count": link.count,
+            "hard_404": link.hard_404,
+            "soft_404": link.soft_404.is_some(),
+            "sources": link
+                .sources
+                .iter()
+                .map(|(file, line)| {
+                    json!({
+                        "file": file.display().to_string(),
+                        "line": line,
+                    })
+                })
+                .collect::<Vec<_>>(),
+        }));
+        Ok(())
+    }
+
+    fn on_page_error(
+        &mut self,
+        file: &Arc<PathBuf>,
+        level: Level,
+        error: &PageError,
+    ) -> Result<(), Error> {
+        self.problems.push(json!({
+            "file": file.display().to_string(),
+            "level": level.to_string(),
+            "category": page_error_category(error).name(),
+            "message": error.to_string(),
+        }));
+        Ok(())
+    }
+
+    fn wants_run_info(&self) -> bool {
+        true
+    }
+
+    fn on_summary(&mut self, summary: &Summary<'_>) -> Result<(), Error> {
+        self.summary = summary.run.map(|run| summary.metrics.summary_json(run));
+        Ok(())
+    }
+
+    fn finish(&mut self, out: &mut dyn Write) -> Result<(), Error> {
+        let report = json!({
+            "links": self.links,
+            "problems": self.problems,
+            "summary": self.summary,
+        });
+        writeln!(out, "{report}")?;
+        Ok(())
+    }
+}
+
+/// `--format sarif`: a SARIF 2.1.0 log for GitHub code scanning.
+#[derive(Default)]
+struct SarifSink {
+    results: Vec<Value>,
+}
+
+impl ReportSink for SarifSink {
+    fn on_broken_link(&mut self, link: &BadLink<'_>) -> Result<(), Error> {
+        let file_only = [(link.file.as_path(), None)];
+        let locations = if link.sources.is_empty() {
+            &file_only[..]
+        } else {
+            &link.sources
+        };
+        self.results.push(sarif::result(
+            link.category(),
+            Level::Error,
+            &link.message(),
+            locations,
+        ));
+        Ok(())
+    }
+
+    fn on_page_error(
+        &mut self,
+        file: &Arc<PathBuf>,
+        level: Level,
+        error: &PageError,
+    ) -> Result<(), Error> {
+        self.results.push(sarif::result(
+            page_error_category(error),
+            level,
+            &error.to_string(),
+            &[(file, None)],
+        ));
+        Ok(())
+    }
+
+    fn finish(&mut self, out: &mut dyn Write) -> Result<(), Error> {
+        writeln!(out, "{}", sarif::log(std::mem::take(&mut self.results)))?;
+        Ok(())
+    }
+}
+
+/// `--format junit`: JUnit XML with a test case per document.
+#[derive(Default)]
+struct JunitSink {
+    report: JunitReport,
+    base_path: PathBuf,
+    duration_secs: f64,
+}
+
+impl ReportSink for JunitSink {
+    fn on_broken_link(&mut self, link: &BadLink<'_>) -> Result<(), Error> {
+        self.report
+            .add_problem(link.file.clone(), Level::Error, link.message());
+        Ok(())
+    }
+
+    fn on_page_error(
+        &mut self,
+        file: &Arc<PathBuf>,
+        level: Level,
+        error: &PageError,
+    ) -> Result<(), Error> {
+        self.report
+            .add_problem(file.clone(), level, error.to_string());
+        Ok(())
+    }
+
+    fn on_summary(&mut self, summary: &Summary<'_>) -> Result<(), Error> {
+        for document in summary.site.documents() {
+            if is_html_path(&document) {
+                self.report.add_document(document);
+            }
+        }
+        self.base_path = summary.base_path.to_owned();
+        self.duration_secs = summary.metrics.duration_secs;
+        Ok(())
+    }
+
+    fn finish(&mut self, mut out: &mut dyn Write) -> Result<(), Error> {
+        self.report
+            .write(&mut out, &self.base_path, self.duration_secs)?;
+        Ok(())
+    }
+}
+
+/// `--format tap`: TAP version 13 with a test per checked href.
+#[derive(Default)]
+struct TapSink {
+    report: TapReport,
+}
+
+impl ReportSink for TapSink {
+    fn on_broken_link(&mut self, link: &BadLink<'_>) -> Result<(), Error> {
+        let mut locations = link.sources.clone();
+        locations.push((&link.file, None));
+        self.report.add_problem(
+            &link.href,
+            Level::Error,
+            if link.hard_404 {
+                "bad link"
+            } else {
+                "bad anchor"
+            },
+            &locations,
+        );
+        Ok(())
+    }
+
+    fn on_page_error(
+        &mut self,
+        file: &Arc<PathBuf>,
+        level: Level,
+        error: &PageError,
+    ) -> Result<(), Error> {
+        if let PageError::WarnedLink { href, .. } = error {
+            self.report
+                .add_problem(href, level, "bad link", &[(file, None)]);
+        }
+        Ok(())
+    }
+
+    fn on_summary(&mut self, summary: &Summary<'_>) -> Result<(), Error> {
+        for href in summary.site.checked_links() {
+            self.report.add_ok(href);
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self, mut out: &mut dyn Write) -> Result<(), Error> {
+        self.report.write(&mut out)?;
+        Ok(())
+    }
+}
+
+/// `--format checkstyle`: Checkstyle XML with the problems of each file, bad links in source
+/// files where known.
+#[derive(Default)]
+struct CheckstyleSink {
+    report: CheckstyleReport,
+}
+
+impl ReportSink for CheckstyleSink {
+    fn on_broken_link(&mut self, link: &BadLink<'_>) -> Result<(), Error> {
+        // bad anchors are less severe, the page they point at exists
+        let level = if link.hard_404 {
+            Level::Error
+        } else {
+            Level::Warning
+        };
+        if link.sources.is_empty() {
+            self.report.add_problem(
+                link.file.clone(),
+                None,
+                level,
+                link.category(),
+                link.message(),
+            );
+        }
+        for &(source, line) in &link.sources {
+            self.report.add_problem(
+                Arc::new(source.to_owned()),
+                line,
+                level,
+                link.category(),
+                link.message(),
+            );
+        }
+        Ok(())
+    }
+
+    fn on_page_error(
+        &mut self,
+        file: &Arc<PathBuf>,
+        level: Level,
+        error: &PageError,
+    ) -> Result<(), Error> {
+        self.report.add_problem(
+            file.clone(),
+            None,
+            level,
+            page_error_category(error),
+            error.to_string(),
+        );
+        Ok(())
+    }
+
+    fn finish(&mut self, mut out: &mut dyn Write) -> Result<(), Error> {
+        self.report.write(&mut out)?;
+        Ok(())
+    }
+}
+
+/// `--format csv` and `tsv`: a row per problem and source file.
+struct CsvSink {
+    rows: Vec<csv::Row>,
+    delimiter: char,
+}
+
+impl CsvSink {
+    fn new(delimiter: char) -> Self {
+        CsvSink {
+            rows: Vec::new(),
+            delimiter,
+        }
+    }
+}
+
+impl ReportSink for CsvSink {
+    fn on_broken_link(&mut self, link: &BadLink<'_>) -> Result<(), Error> {
+        let row = csv::Row {
+            file: link.file.display().to_string(),
+            source: None,
+            line: None,
+            kind: csv::kind(link.category()),
+            level: Level::Error,
+            href: format!("/{}", link.href),
+            message: link.message(),
+        };
+        for &(source, line) in &link.sources {
+            self.rows.push(csv::Row {
+                source: Some(source.display().to_string()),
+                line,
+                ..row.clone()
+            });
+        }
+        if link.sources.is_empty() {
+            self.rows.push(row);
+        }
+        Ok(())
+    }
+
+    fn on_page_error(
+        &mut self,
+        file: &Arc<PathBuf>,
+        level: Level,
+        error: &PageError,
+    ) -> Result<(), Error> {
+        self.rows.push(csv::Row {
+            file: file.display().to_string(),
+            source: None,
+            line: None,
+            kind: csv::kind(page_error_category(error)),
+            level,
+            href: String::new(),
+            message: error.to_string(),
+        });
+        Ok(())
+    }
+
+    fn finish(&mut self, mut out: &mut dyn Write) -> Result<(), Error> {
+        csv::write(&mut out, &mut self.rows, self.delimiter)?;
+        Ok(())
+    }
+}
+
+/// `--format rdjson`: reviewdog's Diagnostic JSON, a diagnostic per problem and source line.
+#[derive(Default)]
+struct RdjsonSink {
+    diagnostics: Vec<Value>,
+}
+
+impl ReportSink for RdjsonSink {
+    fn on_broken_link(&mut self, link: &BadLink<'_>) -> Result<(), Error> {
+        if link.sources.is_empty() {
+            self.diagnostics.push(rdjson::diagnostic(
+                link.category(),
+                Level::Error,
+                &link.message(),
+                &link.file,
+                None,
+            ));
+        }
+        for &(source, line) in &link.sources {
+            self.diagnostics.push(rdjson::diagnostic(
+                link.category(),
+                Level::Error,
+                &link.message(),
+                source,
+                line,
+            ));
+        }
+        Ok(())
+    }
+
+    fn on_page_error(
+        &mut self,
+        file: &Arc<PathBuf>,
+        level: Level,
+        error: &PageError,
+    ) -> Result<(), Error> {
+        self.diagnostics.push(rdjson::diagnostic(
+            page_error_category(error),
+            level,
+            &error.to_string(),
+            file,
+            None,
+        ));
+        Ok(())
+    }
+
+    fn finish(&mut self, out: &mut dyn Write) -> Result<(), Error> {
+        writeln!(
+            out,
+            "{}",
+            rdjson::result(std::mem::take(&mut self.diagnostics))
+        )?;
+        Ok(())
+    }
+}
+
+#[test]
+fn test_sink_registry() {
+    #[derive(Default)]
+    struct CountingSink {
+        bad_links: usize,
+    }
+
+    impl ReportSink for CountingSink {
+        fn on_broken_link(&mut self, _link: &BadLink<'_>) -> Result<(), Error> {
+            self.bad_links += 1;
+            Ok(())
+        }
+
+        fn finish(&mut self, out: &mut dyn Write) -> Result<(), Error> {
+            writeln!(out, "{} bad links", self.bad_links)?;
+            Ok(())
+        }
+    }
+
+    let mut registry = SinkRegistry::builtin();
+    assert_eq!(
+        registry.names().collect::<Vec<_>>(),
+        [
+            "json",
+            "sarif",
+            "junit",
+            "tap",
+            "checkstyle",
+            "csv",
+            "tsv",
+            "rdjson"
+        ]
+    );
+    assert!(!registry.contains("text"));
+    registry.register("count", || Box::<CountingSink>::default());
+
+    let link = BadLink {
+        href: "missing.html".to_owned(),
+        file: Arc::new(PathBuf::from("public/index.html")),
+        target: PathBuf::from("public/missing.html"),
+        origin: LinkOrigin::Attribute {
+            element: "a",
+            attribute: "href",
+        },
+        count: 1,
+        hard_404: true,
+        soft_404: None,
+        sources: vec![(Path::new("src/index.md"), Some(3))],
+    };
+    assert_eq!(link.message(), "bad link /missing.html (a[href])");
+
+    let mut sink = registry.create("count").unwrap();
+    sink.on_broken_link(&link).unwrap();
+    sink.on_broken_link(&link).unwrap();
+    let mut out = Vec::new();
+    sink.finish(&mut out).unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(), "2 bad links\n");
+
+    let mut sink = registry.create("csv").unwrap();
+    sink.on_broken_link(&link).unwrap();
+    sink.on_page_error(
+        &Arc::new(PathBuf::from("public/index.html")),
+        Level::Error,
+        &PageError::BadSisterLink {
+            url: "https://www.example.com/careers/".to_owned(),
+        },
+    )
+    .unwrap();
+    let mut out = Vec::new();
+    sink.finish(&mut out).unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "kind,level,href,file,source,line,message\n\
+         bad-sister-link,error,,public/index.html,,,\"bad link https://www.example.com/careers/, not in the index of its site\"\n\
+         bad-link,error,/missing.html,public/index.html,src/index.md,3,bad link /missing.html (a[href])\n"
+    );
+}
@@ -0,0 +1,102 @@
+//! `--spa-routes`: fragments that are client-side routes rather than element ids.
+
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::{Context, Error};
+use regex::RegexSet;
+
+use crate::html::{LinkDecision, LinkHook};
+use crate::targets::{glob_to_regex, read_entries};
+
+/// Fragments that single-page apps commonly use for routing, `#/users/1` and `#!/users/1`.
+pub const DEFAULT_FRAGMENT_PATTERN: &str = "^!?/";
+
+/// The routes of a single-page app, and which fragments are routes.
+pub struct SpaRoutes {
+    fragments: RegexSet,
+    routes: RegexSet,
+}
+
+impl SpaRoutes {
+    /// Read a route manifest with one route per line, or a JSON array. Routes may contain globs
+    /// like `/docs/**` and parameters like `/users/:id`, which match a single path segment.
+    pub fn read(manifest: &Path, fragment_patterns: &[String]) -> Result<Self, Error> {
+        let routes = read_entries(manifest, "route manifest")?;
+        Self::new(&routes, fragment_patterns)
+    }
+
+    fn new(routes: &[String], fragment_patterns: &[String]) -> Result<Self, Error> {
+        let fragments = if fragment_patterns.is_empty() {
+            RegexSet::new([DEFAULT_FRAGMENT_PATTERN])
+        } else {
+            RegexSet::new(fragment_patterns)
+        }
+        .context("Invalid regex for --spa-fragment")?;
+
+        let routes = routes.iter().map(|route| {
+            let glob: Vec<_> = route
+                .split('/')
+                .map(|segment| {
+                    if segment.starts_with(':') {
+                        "*"
+                    } else {
+                        segment
+                    }
+                })
+                .collect();
+            glob_to_regex(&glob.join("/"))
+        });
+        let routes = RegexSet::new(routes).context("Invalid route in route manifest")?;
+
+        Ok(SpaRoutes { fragments, routes })
+    }
+
+    /// Whether `fragment`, without `#`, is a route that exists. `None` if it is not a route.
+    fn check(&self, fragment: &str) -> Option<bool> {
+        if !self.fragments.is_match(fragment) {
+            return None;
+        }
+
+        let route = fragment.trim_start_matches('!');
+        let route = &route[..route.find('?').unwrap_or(route.len())];
+        Some(self.routes.is_match(route.trim_start_matches('/')))
+    }
+
+    /// A hook that drops fragments that are known routes, so that only the page is checked.
+    /// Unknown routes are left alone and reported as bad anchors, since no element has them as id.
+    pub fn hook(self) -> Arc<LinkHook> {
+        Arc::new(move |href, _, _| {
+            let (page, fragment) = match href.0.split_once('#') {
+                Some(split) => split,
+                None => return LinkDecision::Check,
+            };
+
+            match self.check(fragment) {
+                Some(true) => LinkDecision::Rewrite(page.to_owned()),
+                _ => LinkDecision::Check,
+            }
+        })
+    }
+}
+
+#[test]
+fn test_spa_routes() {
+    let routes = SpaRoutes::new(
+        &[
+            "/".to_owned(),
+            "/users/:id".to_owned(),
+            "/docs/**".to_owned(),
+        ],
+        &[],
+    )
+    .unwrap();
+
+    assert_eq!(routes.check("/"), Some(true));
+    assert_eq!(routes.check("/users/42"), Some(true));
+    assert_eq!(routes.check("!/users/42?tab=posts"), Some(true));
+    assert_eq!(routes.check("/users/42/posts"), Some(false));
+    assert_eq!(routes.check("/docs/a/b"), Some(true));
+    assert_eq!(routes.check("/settings"), Some(false));
+    assert_eq!(routes.check("installation"), None);
+}
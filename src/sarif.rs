@@ -0,0 +1,213 @@
+//! `--format sarif`: the report as a SARIF 2.1.0 log, for GitHub code scanning and other tools
+//! that show static analysis results next to the code.
+
+use std::env;
+use std::path::Path;
+
+use percent_encoding::{utf8_percent_encode, AsciiSet, CONTROLS};
+use serde_json::{json, Value};
+
+use crate::github::{Category, Level};
+
+/// Characters that cannot appear as they are in the path of a URI reference.
+const URI_PATH: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'%')
+    .add(b'<')
+    .add(b'>')
+    .add(b'?')
+    .add(b'`')
+    .add(b'{')
+    .add(b'}');
+
+/// The categories of problems, in the order of the rules in the log.
+const CATEGORIES: &[Category] = &[
+    Category::Links,
+    Category::Anchors,
+    Category::Hreflang,
+    Category::Canonical,
+    Category::Redirects,
+    Category::Integrity,
+    Category::Downloads,
+    Category::SisterLinks,
+    Category::Lints,
+    Category::Unparseable,
+];
+
+/// The id of the rule for problems in `category`. Broken links and anchors get their own names,
+/// the other rules are named like the category.
+pub fn rule_id(category: Category) -> &'static str {
+    match category {
+        Category::Links => "bad-link",
+        Category::Anchors => "bad-anchor",
+        category => category.name(),
+    }
+}
+
+fn rule_description(category: Category) -> &'static str {
+    match category {
+        Category::Links => "Link to a file that does not exist",
+        Category::Anchors => "Link to an anchor that does not exist",
+        Category::Hreflang => "Missing or one-sided hreflang alternate",
+        Category::Canonical => "Canonical link that redirects or points elsewhere",
+        Category::Redirects => "Unused or shadowed redirect",
+        Category::Integrity => "Script or stylesheet that does not match its integrity hash",
+        Category::Downloads => "Download that is missing or does not match its checksum",
+        Category::SisterLinks => "Link to a page that does not exist on a sister site",
+        Category::Lints => "Lint warning",
+        Category::Unparseable => "File that could not be parsed",
+    }
+}
+
+fn level_name(level: Level) -> &'static str {
+    match level {
+        Level::Error => "error",
+        Level::Warning => "warning",
+        Level::Notice => "note",
+    }
+}
+
+/// `path` with forward slashes. Relative paths stay relative, so that code scanning resolves
+/// them against the checkout, and so do absolute paths below the current directory.
+pub(crate) fn relative_path(path: &Path) -> String {
+    let cwd = env::current_dir().ok();
+    let path = cwd
+        .as_deref()
+        .and_then(|cwd| path.strip_prefix(cwd).ok())
+        .unwrap_or(path);
+    let path = path.to_string_lossy().replace('\\', "/");
+    path.trim_start_matches("./").to_owned()
+}
+
+/// `path` as a URI reference, see `relative_path`.
+fn artifact_uri(path: &Path) -> String {
+    utf8_percent_encode(&relative_path(path), URI_PATH).to_string()
+}
+
+/// One problem as a SARIF result, found at each of `locations`: a file and the line in it, if
+/// known.
+pub fn result(
+    category: Category,
+    level: Level,
+    message: &str,
+    locations: &[(&Path, Option<usize>)],
+) -> Value {
+    let locations: Vec<_> = locations
+        .iter()
+        .map(|&(file, line)| {
+            let mut location = json!({
+                "physicalLocation": {
+                    "artifactLocation": { "uri": artifact_uri(file) },
+                },
+            });
+            if let Some(line) = line {
+                location["physicalLocation"]["region"] = json!({ "startLine": line });
+            }
+            location
+        })
+        .collect();
+
+    json!({
+        "ruleId": rule_id(category),
+        "level": level_name(level),
+        "message": { "text": message },
+        "locations": locations,
+    })
+}
+
+/// A SARIF log with one run of hyperlink, containing `results` as returned by `result`. Results
+/// are sorted by their first location, so that the logs of two runs can be diffed.
+pub fn log(mut results: Vec<Value>) -> Value {
+    results.sort_by_cached_key(|result| {
+        let location = &result["locations"][0]["physicalLocation"];
+        (
+            location["artifactLocation"]["uri"]
+                .as_str()
+                .map(str::to_owned),
+            location["region"]["startLine"].as_u64(),
+            result["message"]["text"].as_str().map(str::to_owned),
+        )
+    });
+
+    let rules: Vec<_> = CATEGORIES
+        .iter()
+        .map(|&category| {
+            json!({
+                "id": rule_id(category),
+                "shortDescription": { "text": rule_description(category) },
+            })
+        })
+        .collect();
+
+    json!({
+        "version": "2.1.0",
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "hyperlink",
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "informationUri": "https://github.com/untitaker/hyperlink",
+                    "rules": rules,
+                },
+            },
+            "results": results,
+        }],
+    })
+}
+
+#[test]
+fn test_sarif() {
+    let anchor = result(
+        Category::Anchors,
+        Level::Error,
+        "bad link /guide#install (a[href])",
+        &[
+            (Path::new("./docs/guide.md"), Some(12)),
+            (Path::new("public/my guide.html"), None),
+        ],
+    );
+    assert_eq!(
+        anchor,
+        json!({
+            "ruleId": "bad-anchor",
+            "level": "error",
+            "message": { "text": "bad link /guide#install (a[href])" },
+            "locations": [
+                {
+                    "physicalLocation": {
+                        "artifactLocation": { "uri": "docs/guide.md" },
+                        "region": { "startLine": 12 },
+                    },
+                },
+                {
+                    "physicalLocation": {
+                        "artifactLocation": { "uri": "public/my%20guide.html" },
+                    },
+                },
+            ],
+        })
+    );
+
+    let lint = result(
+        Category::Lints,
+        Level::Warning,
+        "javascript-urls: javascript:void(0) is a javascript: URL (a[href])",
+        &[(Path::new("public/index.html"), None)],
+    );
+    let log = log(vec![lint, anchor]);
+    assert_eq!(log["version"], "2.1.0");
+    let rules = log["runs"][0]["tool"]["driver"]["rules"]
+        .as_array()
+        .unwrap();
+    assert_eq!(rules[0]["id"], "bad-link");
+    assert_eq!(rules[6]["id"], "downloads");
+    assert_eq!(rules[7]["id"], "sister-links");
+    assert_eq!(rules[8]["id"], "lints");
+    let results = log["runs"][0]["results"].as_array().unwrap();
+    assert_eq!(results[0]["ruleId"], "bad-anchor");
+    assert_eq!(results[1]["ruleId"], "lints");
+    assert_eq!(level_name(Level::Notice), "note");
+}
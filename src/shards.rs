@@ -0,0 +1,212 @@
+//! `--shard` and `hyperlink merge`: checking one site on several machines.
+
+use std::fmt;
+use std::fs;
+use std::io::BufWriter;
+use std::path::Path;
+use std::str::FromStr;
+
+use anyhow::{anyhow, Context, Error};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+use crate::collector::BrokenLinkCollector;
+
+/// One of `count` parts of the files of a site, numbered from 1.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Shard {
+    pub index: usize,
+    pub count: usize,
+}
+
+impl Shard {
+    /// Whether the file at `relative_path`, with forward slashes, belongs to this shard. Files are
+    /// assigned by a hash of their path, so that every machine agrees without coordinating, and
+    /// shards stay balanced as files come and go.
+    pub fn contains(&self, relative_path: &str) -> bool {
+        let hash = Sha256::digest(relative_path.as_bytes());
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(&hash[..8]);
+        (u64::from_be_bytes(bytes) % self.count as u64) as usize == self.index - 1
+    }
+}
+
+impl FromStr for Shard {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid shard {s:?}, expected K/N with 1 <= K <= N");
+        let (index, count) = s.split_once('/').ok_or_else(invalid)?;
+        let index = index.parse().map_err(|_| invalid())?;
+        let count = count.parse().map_err(|_| invalid())?;
+        if index == 0 || index > count {
+            return Err(invalid());
+        }
+        Ok(Shard { index, count })
+    }
+}
+
+impl fmt::Display for Shard {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "{}/{}", self.index, self.count)
+    }
+}
+
+/// What one shard found, written with `--shard-out` and read by `hyperlink merge`.
+pub struct ShardDump<P> {
+    pub shard: Shard,
+    /// The command line of the shard, so that the merged report uses the same options.
+    pub args: Vec<String>,
+    pub file_count: usize,
+    pub documents_count: usize,
+    pub duplicates_count: usize,
+    pub collector: BrokenLinkCollector<P>,
+}
+
+impl<P> ShardDump<P> {
+    pub fn write(&self, path: &Path) -> Result<(), Error> {
+        let dump = json!({
+            "version": env!("CARGO_PKG_VERSION"),
+            "shard": self.shard.to_string(),
+            "args": self.args,
+            "files": self.file_count,
+            "documents": self.documents_count,
+            "duplicates": self.duplicates_count,
+            "collector": self.collector.to_json()?,
+        });
+        let file = fs::File::create(path)
+            .with_context(|| format!("Failed to create shard dump {}", path.display()))?;
+        serde_json::to_writer(BufWriter::new(file), &dump)
+            .with_context(|| format!("Failed to write shard dump {}", path.display()))
+    }
+
+    pub fn read(path: &Path) -> Result<Self, Error> {
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read shard dump {}", path.display()))?;
+        Self::parse(&contents)
+            .with_context(|| format!("Failed to parse shard dump {}", path.display()))
+    }
+
+    fn parse(contents: &str) -> Result<Self, Error> {
+        let value: Value = serde_json::from_str(contents)?;
+        let field = |name: &str| value.get(name).ok_or_else(|| anyhow!("no {name}"));
+        let count = |name: &str| -> Result<usize, Error> {
+            field(name)?
+                .as_u64()
+                .map(|count| count as usize)
+                .ok_or_else(|| anyhow!("{name} is not a number"))
+        };
+
+        let version = field("version")?.as_str().unwrap_or("");
+        if version != env!("CARGO_PKG_VERSION") {
+            return Err(anyhow!(
+                "written by hyperlink {version}, but this is hyperlink {}",
+                env!("CARGO_PKG_VERSION")
+            ));
+        }
+
+        Ok(ShardDump {
+            shard: field("shard")?
+                .as_str()
+                .unwrap_or("")
+                .parse()
+                .map_err(Error::msg)?,
+            args: field("args")?
+                .as_array()
+                .and_then(|args| {
+                    args.iter()
+                        .map(|arg| arg.as_str().map(str::to_owned))
+                        .collect()
+                })
+                .ok_or_else(|| anyhow!("args are not strings"))?,
+            file_count: count("files")?,
+            documents_count: count("documents")?,
+            duplicates_count: count("duplicates")?,
+            collector: BrokenLinkCollector::from_json(field("collector")?)?,
+        })
+    }
+}
+
+/// Check that `shards` are each shard of the same split exactly once.
+pub fn check_complete(shards: &[Shard]) -> Result<(), Error> {
+    let count = match shards.first() {
+        Some(shard) => shard.count,
+        None => return Err(anyhow!("no shards to merge")),
+    };
+
+    let mut seen = vec![false; count];
+    for shard in shards {
+        if shard.count != count {
+            return Err(anyhow!("cannot merge shard {shard} with shards of {count}"));
+        }
+        if std::mem::replace(&mut seen[shard.index - 1], true) {
+            return Err(anyhow!("shard {shard} is given more than once"));
+        }
+    }
+    let missing: Vec<_> = (1..=count)
+        .filter(|&index| !seen[index - 1])
+        .map(|index| Shard { index, count }.to_string())
+        .collect();
+    if !missing.is_empty() {
+        return Err(anyhow!("missing shards {}", missing.join(", ")));
+    }
+    Ok(())
+}
+
+#[test]
+fn test_shards() {
+    assert_eq!("2/3".parse::<Shard>(), Ok(Shard { index: 2, count: 3 }));
+    for invalid in ["0/3", "4/3", "3", "a/b"] {
+        assert!(invalid.parse::<Shard>().is_err());
+    }
+
+    let shards: Vec<Shard> = (1..=3).map(|index| Shard { index, count: 3 }).collect();
+    for path in [
+        "index.html",
+        "docs/index.html",
+        "img/logo.png",
+        "a/b/c.html",
+    ] {
+        assert_eq!(
+            shards.iter().filter(|shard| shard.contains(path)).count(),
+            1
+        );
+    }
+
+    assert!(check_complete(&shards).is_ok());
+    assert_eq!(
+        check_complete(&shards[..2]).unwrap_err().to_string(),
+        "missing shards 3/3"
+    );
+    assert_eq!(
+        check_complete(&[shards[0], shards[0]])
+            .unwrap_err()
+            .to_string(),
+        "shard 1/3 is given more than once"
+    );
+    assert!(check_complete(&[shards[0], Shard { index: 2, count: 2 }]).is_err());
+}
+
+#[test]
+fn test_shard_dump() {
+    use crate::collector::LinkCollector;
+    use crate::paragraph::VoidParagraph;
+
+    let dir = assert_fs::TempDir::new().unwrap();
+    let path = dir.path().join("shard.json");
+    let dump = ShardDump::<VoidParagraph> {
+        shard: Shard { index: 1, count: 2 },
+        args: vec!["hyperlink".to_owned(), "public/".to_owned()],
+        file_count: 3,
+        documents_count: 2,
+        duplicates_count: 0,
+        collector: BrokenLinkCollector::new(),
+    };
+    dump.write(&path).unwrap();
+
+    let read = ShardDump::<VoidParagraph>::read(&path).unwrap();
+    assert_eq!(read.shard, dump.shard);
+    assert_eq!(read.args, dump.args);
+    assert_eq!(read.file_count, 3);
+    assert!(ShardDump::<VoidParagraph>::parse(r#"{"version": "0.0.0"}"#).is_err());
+}
@@ -0,0 +1,209 @@
+//! `--sister-site` and `hyperlink index`: checking absolute links to another site offline, against
+//! the hrefs that site defines.
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use anyhow::{anyhow, Context, Error};
+use bumpalo::collections::String as BumpString;
+use bumpalo::Bump;
+use serde_json::{json, Value};
+
+use crate::collector::PageError;
+use crate::html::{push_and_canonicalize, FragmentEncoding, LinkDecision, LinkHook};
+use crate::urls::without_scheme;
+
+/// The hrefs a site defines, as printed by `hyperlink index`.
+#[derive(Debug, Default)]
+pub struct LinkIndex {
+    /// Canonicalized hrefs without leading slash, like the keys of `BrokenLinkCollector`, and
+    /// `href#id` for every anchor.
+    hrefs: BTreeSet<String>,
+}
+
+impl LinkIndex {
+    pub fn new(hrefs: impl IntoIterator<Item = String>) -> Self {
+        LinkIndex {
+            hrefs: hrefs.into_iter().collect(),
+        }
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({ "hrefs": self.hrefs })
+    }
+
+    pub fn read(path: &Path) -> Result<Self, Error> {
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read link index {}", path.display()))?;
+        Self::parse(&contents)
+            .with_context(|| format!("Failed to parse link index {}", path.display()))
+    }
+
+    fn parse(contents: &str) -> Result<Self, Error> {
+        let value: Value = serde_json::from_str(contents)?;
+        let hrefs = value
+            .get("hrefs")
+            .and_then(Value::as_array)
+            .and_then(|hrefs| {
+                hrefs
+                    .iter()
+                    .map(|href| href.as_str().map(str::to_owned))
+                    .collect()
+            })
+            .ok_or_else(|| anyhow!("expected an array of hrefs"))?;
+        Ok(LinkIndex { hrefs })
+    }
+
+    /// Every href in the index, in order.
+    pub fn hrefs(&self) -> impl Iterator<Item = &str> {
+        self.hrefs.iter().map(String::as_str)
+    }
+
+    /// Whether `href`, canonicalized and without leading slash, exists on the site.
+    pub fn contains(&self, href: &str) -> bool {
+        self.hrefs.contains(href)
+    }
+}
+
+/// The sites given with `--sister-site`, and the links to them that their index does not contain.
+#[derive(Debug)]
+pub struct SisterSites {
+    /// The URL of each site from the `//` on and without trailing slash, and its index.
+    sites: Vec<(String, LinkIndex)>,
+    /// Broken links and the documents using them, recorded by `hook`.
+    broken: Mutex<BTreeSet<(Arc<PathBuf>, String)>>,
+}
+
+impl SisterSites {
+    /// Read `--sister-site` arguments like `https://www.example.com=www-index.json`.
+    pub fn read(sites: &[String]) -> Result<Arc<Self>, Error> {
+        let sites = sites
+            .iter()
+            .map(|site| {
+                let (url, path) = site
+                    .split_once('=')
+                    .ok_or_else(|| anyhow!("Missing =INDEX in --sister-site {site}"))?;
+                let url = without_scheme(url.trim_end_matches('/')).ok_or_else(|| {
+                    anyhow!("--sister-site {site} does not start with an http(s) URL")
+                })?;
+                Ok((url.to_owned(), LinkIndex::read(Path::new(path))?))
+            })
+            .collect::<Result<_, Error>>()?;
+
+        Ok(Arc::new(SisterSites {
+            sites,
+            broken: Mutex::new(BTreeSet::new()),
+        }))
+    }
+
+    /// The index of the site `url` points into, and the href on that site, canonicalized like
+    /// hrefs in the index. `url` is a used link with the query already removed.
+    fn resolve(&self, url: &str) -> Option<(&LinkIndex, String)> {
+        let url = without_scheme(url)?;
+        let (index, rest) = self.sites.iter().find_map(|(site, index)| {
+            let rest = url.strip_prefix(site.as_str())?;
+            (rest.is_empty() || rest.starts_with(&['/', '#'][..])).then_some((index, rest))
+        })?;
+
+        let (page, fragment) = match rest.split_once('#') {
+            Some((page, fragment)) => (page, Some(fragment)),
+            None => (rest, None),
+        };
+        let arena = Bump::new();
+        let mut href = BumpString::new_in(&arena);
+        push_and_canonicalize(&mut href, &format!("/{}", page.trim_start_matches('/')));
+        if let Some(fragment) = fragment.filter(|fragment| !fragment.is_empty()) {
+            href.push('#');
+            // `hyperlink index` writes ids as defined, which is how decoded fragments look
+            href.push_str(&FragmentEncoding::Decoded.normalize(fragment));
+        }
+        Some((index, href.as_str().to_owned()))
+    }
+
+    /// A hook that records links to the sister sites that are not in their index. It never skips
+    /// or rewrites links, links to other sites are not checked anyway.
+    pub fn hook(self: &Arc<Self>) -> Arc<LinkHook> {
+        let sites = self.clone();
+
+        Arc::new(move |href, document, _| {
+            if let Some((index, site_href)) = sites.resolve(href.0) {
+                if !index.contains(&site_href) {
+                    sites
+                        .broken
+                        .lock()
+                        .unwrap()
+                        .insert((document.path.clone(), href.0.to_owned()));
+                }
+            }
+            LinkDecision::Check
+        })
+    }
+
+    /// The broken links recorded by `hook`. Only meaningful after all documents have been parsed.
+    pub fn errors(&self) -> Vec<(Arc<PathBuf>, PageError)> {
+        self.broken
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(document, url)| {
+                (
+                    document.clone(),
+                    PageError::BadSisterLink { url: url.clone() },
+                )
+            })
+            .collect()
+    }
+}
+
+#[test]
+fn test_sister_sites() {
+    use crate::html::{Document, Href, LinkOrigin};
+
+    let dir = assert_fs::TempDir::new().unwrap();
+    let index_path = dir.path().join("www.json");
+    let index = LinkIndex::new(
+        ["", "pricing", "pricing#enterprise", "about.html"]
+            .iter()
+            .map(|href| href.to_string()),
+    );
+    fs::write(&index_path, index.to_json().to_string()).unwrap();
+
+    let sites =
+        SisterSites::read(&[format!("https://www.example.com/={}", index_path.display())]).unwrap();
+    let hook = sites.hook();
+    let document = Document::new(dir.path(), &dir.path().join("index.html"));
+    for href in [
+        "https://www.example.com",
+        "http://www.example.com/pricing/index.html#enterprise",
+        "//www.example.com/about.html",
+        "https://www.example.com/pricing#%65nterprise",
+        "https://www.example.com/pricing#team",
+        "https://www.example.com/careers/",
+        "https://www.example.community/careers/",
+        "https://docs.example.com/careers/",
+    ] {
+        assert_eq!(
+            hook(&Href(href), &document, LinkOrigin::Text),
+            LinkDecision::Check
+        );
+    }
+
+    let errors: Vec<_> = sites
+        .errors()
+        .into_iter()
+        .map(|(_, error)| error.to_string())
+        .collect();
+    assert_eq!(
+        errors,
+        [
+            "bad link https://www.example.com/careers/, not in the index of its site",
+            "bad link https://www.example.com/pricing#team, not in the index of its site",
+        ]
+    );
+
+    assert!(SisterSites::read(&["https://www.example.com".to_owned()]).is_err());
+    assert!(SisterSites::read(&[format!("www.example.com={}", index_path.display())]).is_err());
+    assert!(LinkIndex::parse(r#"{"hrefs": [1]}"#).is_err());
+}
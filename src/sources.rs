@@ -0,0 +1,251 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::str::FromStr;
+
+use anyhow::{anyhow, Context, Error};
+
+/// A `--map-sources` argument: markdown files under `source_dir` produce HTML under `url_prefix`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SourcePrefix {
+    source_dir: PathBuf,
+    url_prefix: PathBuf,
+}
+
+impl FromStr for SourcePrefix {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (source_dir, url_prefix) = s
+            .split_once('=')
+            .ok_or_else(|| format!("expected SOURCE-DIR=URL-PREFIX, got {s:?}"))?;
+
+        Ok(SourcePrefix {
+            source_dir: PathBuf::from(source_dir),
+            url_prefix: PathBuf::from(url_prefix.trim_start_matches('/')),
+        })
+    }
+}
+
+/// Return the index of the most specific prefix that `path` starts with.
+fn longest_prefix<'a>(path: &Path, prefixes: impl Iterator<Item = &'a Path>) -> Option<usize> {
+    prefixes
+        .enumerate()
+        .filter(|(_, prefix)| path.starts_with(prefix))
+        .max_by_key(|(_, prefix)| prefix.components().count())
+        .map(|(i, _)| i)
+}
+
+/// Drop `.` and leading `/` so that paths from a build manifest compare equal to walked paths.
+fn normalize(path: &Path) -> PathBuf {
+    path.components()
+        .filter(|component| !matches!(component, Component::RootDir | Component::CurDir))
+        .collect()
+}
+
+/// Read a `--build-manifest` file: a JSON object mapping output paths (relative to BASE-PATH) to
+/// the source file that produced them.
+fn read_build_manifest(path: &Path) -> Result<BTreeMap<PathBuf, PathBuf>, Error> {
+    let contents = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read file {}", path.display()))?;
+    let value: serde_json::Value = serde_json::from_str(&contents)
+        .with_context(|| format!("Failed to parse build manifest {}", path.display()))?;
+    let entries = value
+        .as_object()
+        .ok_or_else(|| anyhow!("build manifest {} is not a JSON object", path.display()))?;
+
+    let mut manifest = BTreeMap::new();
+    for (output, source) in entries {
+        let source = source.as_str().ok_or_else(|| {
+            anyhow!(
+                "build manifest {}: source for {:?} is not a string",
+                path.display(),
+                output
+            )
+        })?;
+        manifest.insert(normalize(Path::new(output)), normalize(Path::new(source)));
+    }
+
+    Ok(manifest)
+}
+
+/// Narrows down which markdown files may have produced a given HTML file, so that a broken link
+/// is not attributed to an unrelated file that happens to contain the same paragraph.
+pub struct SourceMapping {
+    base_path: PathBuf,
+    sources_paths: Vec<PathBuf>,
+    /// Names of Docusaurus' `versioned_docs/version-*` folders, e.g. `1.0`.
+    docusaurus_versions: BTreeSet<String>,
+    prefixes: Vec<SourcePrefix>,
+    /// Output path (relative to `base_path`) to source path, from `--build-manifest`.
+    manifest: BTreeMap<PathBuf, PathBuf>,
+}
+
+impl SourceMapping {
+    pub fn new(
+        base_path: &Path,
+        sources_paths: &[PathBuf],
+        prefixes: Vec<SourcePrefix>,
+        build_manifest: Option<&Path>,
+    ) -> Result<Self, Error> {
+        let mut docusaurus_versions = BTreeSet::new();
+
+        for sources_path in sources_paths {
+            let versioned_docs = sources_path.join("versioned_docs");
+            if versioned_docs.is_dir() {
+                for entry in fs::read_dir(versioned_docs)? {
+                    if let Some(version) = entry?
+                        .file_name()
+                        .to_str()
+                        .and_then(|name| name.strip_prefix("version-"))
+                    {
+                        docusaurus_versions.insert(version.to_owned());
+                    }
+                }
+            }
+        }
+
+        let manifest = match build_manifest {
+            Some(path) => read_build_manifest(path)?,
+            None => BTreeMap::new(),
+        };
+
+        Ok(SourceMapping {
+            base_path: base_path.to_owned(),
+            sources_paths: sources_paths.to_owned(),
+            docusaurus_versions,
+            prefixes,
+            manifest,
+        })
+    }
+
+    /// The source file that the build manifest declares for `document`, if any.
+    pub fn exact_source(&self, document: &Path) -> Option<&Path> {
+        let document = document.strip_prefix(&self.base_path).unwrap_or(document);
+        self.manifest
+            .get(&normalize(document))
+            .map(PathBuf::as_path)
+    }
+
+    pub fn may_produce(&self, source: &Path, document: &Path) -> bool {
+        if let Some(exact_source) = self.exact_source(document) {
+            return normalize(source) == exact_source;
+        }
+
+        let source = self
+            .sources_paths
+            .iter()
+            .find_map(|sources_path| source.strip_prefix(sources_path).ok())
+            .unwrap_or(source);
+        let document = document.strip_prefix(&self.base_path).unwrap_or(document);
+
+        if !self.docusaurus_versions.is_empty() {
+            let document_version = document.components().find_map(|component| match component {
+                Component::Normal(segment) => self
+                    .docusaurus_versions
+                    .get(segment.to_str()?)
+                    .map(String::as_str),
+                _ => None,
+            });
+
+            let mut source_components = source.components();
+            let source_version = match (source_components.next(), source_components.next()) {
+                (Some(Component::Normal(first)), Some(Component::Normal(second)))
+                    if first == "versioned_docs" =>
+                {
+                    second
+                        .to_str()
+                        .and_then(|name| name.strip_prefix("version-"))
+                }
+                // docs/ is the current version, served from the default route
+                _ => None,
+            };
+
+            if source_version != document_version {
+                return false;
+            }
+        }
+
+        if !self.prefixes.is_empty() {
+            let source_prefix = longest_prefix(
+                source,
+                self.prefixes.iter().map(|prefix| &*prefix.source_dir),
+            );
+            let document_prefix = longest_prefix(
+                document,
+                self.prefixes.iter().map(|prefix| &*prefix.url_prefix),
+            );
+
+            if source_prefix != document_prefix {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+#[test]
+fn test_docusaurus_versions() {
+    let mapping = SourceMapping {
+        base_path: PathBuf::from("build"),
+        sources_paths: vec![PathBuf::from("website")],
+        docusaurus_versions: ["1.0", "2.0"].iter().map(|v| v.to_string()).collect(),
+        prefixes: Vec::new(),
+        manifest: BTreeMap::new(),
+    };
+
+    let current = Path::new("website/docs/intro.md");
+    let v1 = Path::new("website/versioned_docs/version-1.0/intro.md");
+
+    assert!(mapping.may_produce(current, Path::new("build/docs/intro/index.html")));
+    assert!(!mapping.may_produce(v1, Path::new("build/docs/intro/index.html")));
+    assert!(mapping.may_produce(v1, Path::new("build/docs/1.0/intro/index.html")));
+    assert!(!mapping.may_produce(current, Path::new("build/docs/1.0/intro/index.html")));
+    assert!(!mapping.may_produce(v1, Path::new("build/docs/2.0/intro/index.html")));
+}
+
+#[test]
+fn test_locale_prefixes() {
+    let mapping = SourceMapping {
+        base_path: PathBuf::from("public"),
+        sources_paths: vec![PathBuf::from("content")],
+        docusaurus_versions: BTreeSet::new(),
+        prefixes: vec!["en=/".parse().unwrap(), "de=/de/".parse().unwrap()],
+        manifest: BTreeMap::new(),
+    };
+
+    let en = Path::new("content/en/install.md");
+    let de = Path::new("content/de/install.md");
+
+    assert!(mapping.may_produce(en, Path::new("public/install/index.html")));
+    assert!(!mapping.may_produce(de, Path::new("public/install/index.html")));
+    assert!(mapping.may_produce(de, Path::new("public/de/install/index.html")));
+    assert!(!mapping.may_produce(en, Path::new("public/de/install/index.html")));
+}
+
+#[test]
+fn test_build_manifest() {
+    let mapping = SourceMapping {
+        base_path: PathBuf::from("public"),
+        sources_paths: vec![PathBuf::from("content")],
+        docusaurus_versions: BTreeSet::new(),
+        prefixes: Vec::new(),
+        manifest: [("/install/index.html", "./content/setup.md")]
+            .iter()
+            .map(|(output, source)| (normalize(Path::new(output)), normalize(Path::new(source))))
+            .collect(),
+    };
+
+    let setup = Path::new("content/setup.md");
+    let install = Path::new("content/install.md");
+
+    assert_eq!(
+        mapping.exact_source(Path::new("public/install/index.html")),
+        Some(setup)
+    );
+    assert!(mapping.may_produce(setup, Path::new("public/install/index.html")));
+    assert!(!mapping.may_produce(install, Path::new("public/install/index.html")));
+    // documents missing from the manifest fall back to paragraph matching
+    assert!(mapping.may_produce(install, Path::new("public/other/index.html")));
+}
@@ -0,0 +1,183 @@
+//! `--format tap`: the report as TAP (Test Anything Protocol) version 13, with a test per checked
+//! href, for `prove` and other TAP consumers.
+
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{self, Write};
+use std::path::Path;
+
+use crate::github::Level;
+
+/// A checked href, and what is wrong with it, if anything.
+#[derive(Debug, Default)]
+struct TapTest {
+    /// The most severe problem with the href, e.g. `bad link`.
+    problem: Option<(Level, String)>,
+    /// The files using the href, and the line in them if known.
+    files: BTreeSet<(String, Option<usize>)>,
+}
+
+impl TapTest {
+    fn add_problem(&mut self, level: Level, message: String) {
+        if self
+            .problem
+            .as_ref()
+            .is_none_or(|(known, _)| *known != Level::Error && level == Level::Error)
+        {
+            self.problem = Some((level, message));
+        }
+    }
+}
+
+/// Every checked href as a test, in the order of the hrefs. Broken hrefs are `not ok`, and so are
+/// hrefs with warnings, but marked `TODO` so that they do not fail the run.
+#[derive(Debug, Default)]
+pub struct TapReport {
+    /// Keyed by href without leading slash, like in `BrokenLinkCollector`.
+    tests: BTreeMap<String, TapTest>,
+}
+
+impl TapReport {
+    /// Add `href` as a test, which passes unless problems are added for it.
+    pub fn add_ok(&mut self, href: &str) {
+        if !self.tests.contains_key(href) {
+            self.tests.insert(href.to_owned(), TapTest::default());
+        }
+    }
+
+    /// Add a problem with `href`, used by each of `locations`: a file and the line in it, if
+    /// known. Errors win over warnings if an href has both.
+    pub fn add_problem(
+        &mut self,
+        href: &str,
+        level: Level,
+        message: &str,
+        locations: &[(&Path, Option<usize>)],
+    ) {
+        let test = self.tests.entry(href.to_owned()).or_default();
+        test.add_problem(level, message.to_owned());
+        test.files.extend(
+            locations
+                .iter()
+                .map(|&(file, line)| (file.display().to_string(), line)),
+        );
+    }
+
+    pub fn merge(&mut self, other: TapReport) {
+        for (href, other_test) in other.tests {
+            let test = self.tests.entry(href).or_default();
+            if let Some((level, message)) = other_test.problem {
+                test.add_problem(level, message);
+            }
+            test.files.extend(other_test.files);
+        }
+    }
+
+    /// Write the report, with a YAML block listing the files that use the href after each test
+    /// that is not ok.
+    pub fn write(&self, out: &mut impl Write) -> io::Result<()> {
+        writeln!(out, "TAP version 13")?;
+        writeln!(out, "1..{}", self.tests.len())?;
+
+        for (number, (href, test)) in self.tests.iter().enumerate() {
+            let number = number + 1;
+            let description = escape(href);
+            let (level, message) = match test.problem {
+                Some((level, ref message)) => (level, message),
+                None => {
+                    writeln!(out, "ok {number} - /{description}")?;
+                    continue;
+                }
+            };
+
+            if level == Level::Error {
+                writeln!(out, "not ok {number} - /{description}")?;
+            } else {
+                writeln!(out, "not ok {number} - /{description} # TODO {level}")?;
+            }
+            writeln!(out, "  ---")?;
+            writeln!(out, "  message: {}", yaml_string(message))?;
+            writeln!(out, "  severity: {}", yaml_string(&level.to_string()))?;
+            writeln!(out, "  files:")?;
+            for (file, line) in &test.files {
+                let file = match line {
+                    Some(line) => Cow::Owned(format!("{file}:{line}")),
+                    None => Cow::Borrowed(file.as_str()),
+                };
+                writeln!(out, "    - {}", yaml_string(&file))?;
+            }
+            writeln!(out, "  ...")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// `description` with `#` and backslashes escaped, which would otherwise start a directive.
+fn escape(description: &str) -> Cow<'_, str> {
+    if !description.contains(&['#', '\\'][..]) {
+        return Cow::Borrowed(description);
+    }
+    Cow::Owned(description.replace('\\', "\\\\").replace('#', "\\#"))
+}
+
+/// `text` as a double-quoted YAML scalar, which has the same escapes as a JSON string.
+fn yaml_string(text: &str) -> String {
+    serde_json::Value::from(text).to_string()
+}
+
+#[test]
+fn test_tap_report() {
+    let mut report = TapReport::default();
+    report.add_ok("about.html");
+    report.add_problem(
+        "missing.html",
+        Level::Error,
+        "bad link",
+        &[(Path::new("public/index.html"), None)],
+    );
+    let mut other = TapReport::default();
+    other.add_ok("about.html");
+    other.add_problem(
+        "missing.html",
+        Level::Error,
+        "bad link",
+        &[
+            (Path::new("public/docs/index.html"), None),
+            (Path::new("src/docs.md"), Some(3)),
+        ],
+    );
+    other.add_problem(
+        "docs#install",
+        Level::Warning,
+        "bad link",
+        &[(Path::new("public/index.html"), None)],
+    );
+    report.merge(other);
+
+    let mut out = Vec::new();
+    report.write(&mut out).unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        r#"TAP version 13
+1..3
+ok 1 - /about.html
+not ok 2 - /docs\#install # TODO warning
+  ---
+  message: "bad link"
+  severity: "warning"
+  files:
+    - "public/index.html"
+  ...
+not ok 3 - /missing.html
+  ---
+  message: "bad link"
+  severity: "error"
+  files:
+    - "public/docs/index.html"
+    - "public/index.html"
+    - "src/docs.md:3"
+  ...
+"#
+    );
+}
@@ -0,0 +1,254 @@
+//! Paths that exist when the site is served, but not in the folder being checked.
+
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::{anyhow, Context, Error};
+use bumpalo::collections::String as BumpString;
+use bumpalo::Bump;
+use regex::RegexSet;
+
+use crate::html::{
+    push_and_canonicalize, DefinedLink, FragmentEncoding, Href, Link, LinkDecision, LinkHook,
+};
+
+/// The contents of an `--extra-targets` file.
+///
+/// Plain paths become defined links. Paths containing `*` or `?` are globs, and links matching
+/// them are not checked at all.
+#[derive(Debug, Default)]
+pub struct ExtraTargets {
+    /// Canonicalized hrefs, without leading slash.
+    paths: Vec<String>,
+    globs: Vec<String>,
+}
+
+impl ExtraTargets {
+    /// Read a file with one path or glob per line, or a JSON array of strings. In the line-based
+    /// format, empty lines and lines starting with `#` are ignored.
+    pub fn read(path: &Path) -> Result<Self, Error> {
+        Ok(Self::from_entries(&read_entries(path, "extra targets")?))
+    }
+
+    fn from_entries(entries: &[String]) -> Self {
+        let arena = Bump::new();
+        let mut targets = ExtraTargets::default();
+
+        for entry in entries {
+            if entry.contains(&['*', '?'][..]) {
+                targets.globs.push(glob_to_regex(entry));
+            } else {
+                let mut href = BumpString::new_in(&arena);
+                push_and_canonicalize(&mut href, &format!("/{}", entry.trim_start_matches('/')));
+                targets.paths.push(href.as_str().to_owned());
+            }
+        }
+
+        targets
+    }
+
+    /// Defined links for all plain paths, to be fed to a `LinkCollector`.
+    pub fn defined_links<P>(&self) -> impl Iterator<Item = Link<'_, P>> {
+        self.paths.iter().map(|href| {
+            Link::Defines(DefinedLink {
+                href: Href(href),
+                is_redirect: false,
+                redirect_to: None,
+                path: None,
+                element: None,
+            })
+        })
+    }
+
+    /// A hook that skips links matching any of the globs, or `None` if there are no globs.
+    pub fn glob_hook(&self) -> Result<Option<Arc<LinkHook>>, Error> {
+        if self.globs.is_empty() {
+            return Ok(None);
+        }
+
+        let globs = RegexSet::new(&self.globs).context("Invalid glob in extra targets")?;
+
+        Ok(Some(Arc::new(move |href, _, _| {
+            if globs.is_match(href.without_anchor().0) {
+                LinkDecision::Skip
+            } else {
+                LinkDecision::Check
+            }
+        })))
+    }
+}
+
+/// The contents of an `--extra-anchors` file: ids that scripts add to pages in the browser.
+///
+/// Each entry is `PAGE#ID`, where `PAGE` is a path or a glob like in `ExtraTargets`.
+#[derive(Debug, Default)]
+pub struct ExtraAnchors {
+    /// Anchored regexes over document hrefs without leading slash.
+    pages: Vec<String>,
+    /// The id for each of `pages`.
+    ids: Vec<String>,
+}
+
+impl ExtraAnchors {
+    /// Read a file in the same formats as `ExtraTargets::read`. Ids are normalized with
+    /// `fragment_encoding`, like the fragments they are compared with.
+    pub fn read(path: &Path, fragment_encoding: FragmentEncoding) -> Result<Self, Error> {
+        Self::from_entries(&read_entries(path, "extra anchors")?, fragment_encoding)
+    }
+
+    fn from_entries(
+        entries: &[String],
+        fragment_encoding: FragmentEncoding,
+    ) -> Result<Self, Error> {
+        let arena = Bump::new();
+        let mut anchors = ExtraAnchors::default();
+
+        for entry in entries {
+            let (page, id) = entry
+                .split_once('#')
+                .filter(|(_, id)| !id.is_empty())
+                .ok_or_else(|| anyhow!("Missing #ID in extra anchor {entry}"))?;
+
+            if page.contains(&['*', '?'][..]) {
+                anchors.pages.push(glob_to_regex(page));
+            } else {
+                let mut href = BumpString::new_in(&arena);
+                push_and_canonicalize(&mut href, &format!("/{}", page.trim_start_matches('/')));
+                anchors.pages.push(format!("^{}$", regex::escape(&href)));
+            }
+            anchors
+                .ids
+                .push(fragment_encoding.normalize(id).into_owned());
+        }
+
+        Ok(anchors)
+    }
+
+    /// A hook that drops the fragment of links to an extra anchor, so that only the page is
+    /// checked, or `None` if there are no extra anchors.
+    pub fn hook(&self) -> Result<Option<Arc<LinkHook>>, Error> {
+        if self.pages.is_empty() {
+            return Ok(None);
+        }
+
+        let pages = RegexSet::new(&self.pages).context("Invalid glob in extra anchors")?;
+        let ids = self.ids.clone();
+
+        Ok(Some(Arc::new(move |href, _, _| {
+            let Some((page, id)) = href.0.split_once('#') else {
+                return LinkDecision::Check;
+            };
+
+            if pages.matches(page).into_iter().any(|i| ids[i] == id) {
+                LinkDecision::Rewrite(page.to_owned())
+            } else {
+                LinkDecision::Check
+            }
+        })))
+    }
+}
+
+/// Read a file with one entry per line, or a JSON array of strings. In the line-based format,
+/// empty lines and lines starting with `#` are ignored. `what` is used in error messages.
+pub(crate) fn read_entries(path: &Path, what: &str) -> Result<Vec<String>, Error> {
+    let contents = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read file {}", path.display()))?;
+
+    if contents.trim_start().starts_with('[') {
+        serde_json::from_str(&contents)
+            .with_context(|| format!("Failed to parse {what} {}", path.display()))
+    } else {
+        Ok(contents
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(str::to_owned)
+            .collect())
+    }
+}
+
+/// Translate a glob into an anchored regex over hrefs without leading slash. `*` matches within a
+/// path segment, `**` across segments.
+pub(crate) fn glob_to_regex(glob: &str) -> String {
+    let mut regex = String::from("^");
+    let mut chars = glob.trim_start_matches('/').chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                regex.push_str(".*");
+            }
+            '*' => regex.push_str("[^/]*"),
+            '?' => regex.push_str("[^/]"),
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+
+    regex.push('$');
+    regex
+}
+
+#[test]
+fn test_extra_targets() {
+    let targets = ExtraTargets::from_entries(&[
+        "/api/health/".to_owned(),
+        "app/index.html".to_owned(),
+        "/functions/*".to_owned(),
+        "/shop/**".to_owned(),
+    ]);
+
+    let hrefs: Vec<_> = targets
+        .defined_links::<()>()
+        .map(|link| match link {
+            Link::Defines(defined_link) => defined_link.href.0,
+            _ => unreachable!(),
+        })
+        .collect();
+    assert_eq!(hrefs, ["api/health", "app"]);
+
+    let globs = RegexSet::new(&targets.globs).unwrap();
+    assert!(globs.is_match("functions/login"));
+    assert!(!globs.is_match("functions/login/callback"));
+    assert!(globs.is_match("shop/cart/checkout"));
+    assert!(!globs.is_match("shopping"));
+}
+
+#[test]
+fn test_extra_anchors() {
+    let entries = [
+        "/search/#results".to_owned(),
+        "/**#top".to_owned(),
+        "/#caf%C3%A9".to_owned(),
+    ];
+    let anchors = ExtraAnchors::from_entries(&entries, FragmentEncoding::Decoded).unwrap();
+    assert_eq!(anchors.pages, ["^search$", "^.*$", "^$"]);
+    assert_eq!(anchors.ids, ["results", "top", "café"]);
+    let raw = ExtraAnchors::from_entries(&entries, FragmentEncoding::Raw).unwrap();
+    assert_eq!(raw.ids, ["results", "top", "caf%C3%A9"]);
+
+    let hook = anchors.hook().unwrap().unwrap();
+    let document = crate::html::Document::new(Path::new("/site"), Path::new("/site/index.html"));
+    let origin = crate::html::LinkOrigin::Text;
+    assert_eq!(
+        hook(&Href("search#results"), &document, origin),
+        LinkDecision::Rewrite("search".to_owned())
+    );
+    assert_eq!(
+        hook(&Href("blog/post.html#top"), &document, origin),
+        LinkDecision::Rewrite("blog/post.html".to_owned())
+    );
+    assert_eq!(
+        hook(&Href("blog/post.html#results"), &document, origin),
+        LinkDecision::Check
+    );
+    assert_eq!(
+        hook(&Href("search"), &document, origin),
+        LinkDecision::Check
+    );
+
+    assert!(
+        ExtraAnchors::from_entries(&["/search/".to_owned()], FragmentEncoding::Decoded).is_err()
+    );
+}
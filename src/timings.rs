@@ -0,0 +1,81 @@
+//! `--timings`: duration and memory usage of each phase of a run.
+
+use std::fs;
+use std::time::{Duration, Instant};
+
+struct Phase {
+    name: &'static str,
+    duration: Duration,
+    peak_rss: Option<u64>,
+    peak_arena_bytes: Option<usize>,
+}
+
+/// Collects phases as they finish, to be printed at the end of the run.
+pub struct Timings {
+    start: Instant,
+    phase_start: Instant,
+    phases: Vec<Phase>,
+}
+
+impl Default for Timings {
+    fn default() -> Self {
+        let now = Instant::now();
+        Timings {
+            start: now,
+            phase_start: now,
+            phases: Vec::new(),
+        }
+    }
+}
+
+impl Timings {
+    /// End the current phase and start the next one. `peak_arena_bytes` is the largest any
+    /// per-thread arena grew to during the phase, if the phase uses arenas.
+    pub fn finish_phase(&mut self, name: &'static str, peak_arena_bytes: Option<usize>) {
+        let now = Instant::now();
+        self.phases.push(Phase {
+            name,
+            duration: now - self.phase_start,
+            peak_rss: peak_rss(),
+            peak_arena_bytes,
+        });
+        self.phase_start = now;
+    }
+
+    /// Print all finished phases to stderr, so that they do not mix with the report.
+    pub fn print(&self) {
+        eprintln!("Timings:");
+        for phase in &self.phases {
+            let mut line = format!("  {}: {:.2}s", phase.name, phase.duration.as_secs_f64());
+            if let Some(peak_rss) = phase.peak_rss {
+                line.push_str(&format!(", peak RSS {}", format_bytes(peak_rss)));
+            }
+            if let Some(peak_arena_bytes) = phase.peak_arena_bytes {
+                line.push_str(&format!(
+                    ", peak arena {}",
+                    format_bytes(peak_arena_bytes as u64)
+                ));
+            }
+            eprintln!("{line}");
+        }
+        eprintln!("  total: {:.2}s", self.start.elapsed().as_secs_f64());
+    }
+}
+
+/// The peak resident set size of this process so far. Only supported on Linux.
+fn peak_rss() -> Option<u64> {
+    let status = fs::read_to_string("/proc/self/status").ok()?;
+    let kib = status
+        .lines()
+        .find_map(|line| line.strip_prefix("VmHWM:"))?
+        .trim()
+        .strip_suffix("kB")?
+        .trim()
+        .parse::<u64>()
+        .ok()?;
+    Some(kib * 1024)
+}
+
+fn format_bytes(bytes: u64) -> String {
+    format!("{:.1} MiB", bytes as f64 / (1024.0 * 1024.0))
+}
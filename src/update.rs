@@ -0,0 +1,314 @@
+//! `hyperlink self-update`: replacing the running binary with the latest release from GitHub.
+//!
+//! Downloads go through `curl`, `.tar.xz` archives are unpacked with `tar` and `.zip` archives with
+//! PowerShell's `Expand-Archive`. Those come with macOS, Windows 10 and later and practically every
+//! Linux, rather than an HTTP client and archive formats built into every copy of hyperlink.
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use anyhow::{anyhow, Context, Error};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+/// The GitHub repository releases are published to, e.g. `untitaker/hyperlink`.
+fn repository() -> &'static str {
+    env!("CARGO_PKG_REPOSITORY")
+        .trim_start_matches("https://github.com/")
+        .trim_end_matches('/')
+}
+
+/// Where the Windows binary is moved to by `replace_executable`, as it cannot be deleted while it
+/// is running.
+const OLD_BINARY: &str = ".hyperlink-self-update.old";
+
+/// The target triple of the prebuilt binary for this platform, if releases have one.
+pub fn target() -> Option<&'static str> {
+    if cfg!(all(target_os = "linux", target_arch = "x86_64")) {
+        Some("x86_64-unknown-linux-gnu")
+    } else if cfg!(all(target_os = "macos", target_arch = "aarch64")) {
+        Some("aarch64-apple-darwin")
+    } else if cfg!(all(target_os = "macos", target_arch = "x86_64")) {
+        Some("x86_64-apple-darwin")
+    } else if cfg!(all(target_os = "windows", target_arch = "x86_64")) {
+        Some("x86_64-pc-windows-msvc")
+    } else {
+        None
+    }
+}
+
+/// The name of the release archive for `target`, as built by cargo-dist.
+fn archive_name(target: &str) -> String {
+    if target.contains("windows") {
+        format!("hyperlink-{target}.zip")
+    } else {
+        format!("hyperlink-{target}.tar.xz")
+    }
+}
+
+/// The numbers of a version like `0.2.0` or a tag like `v0.2.0`.
+fn parse_version(version: &str) -> Result<Vec<u64>, Error> {
+    version
+        .trim_start_matches('v')
+        .split('.')
+        .map(|number| number.parse().ok())
+        .collect::<Option<_>>()
+        .ok_or_else(|| anyhow!("unexpected version {version:?}"))
+}
+
+/// The checksum of `file_name` in the contents of a `.sha256` file, which are formatted like the
+/// output of `sha256sum`.
+fn parse_checksum(contents: &str, file_name: &str) -> Option<String> {
+    contents.lines().find_map(|line| {
+        let (checksum, name) = line.split_once(char::is_whitespace)?;
+        let name = name.trim_start().trim_start_matches('*');
+        (name == file_name).then(|| checksum.to_ascii_lowercase())
+    })
+}
+
+/// Fetch `url` over https, following redirects.
+fn download(url: &str) -> Result<Vec<u8>, Error> {
+    let output = Command::new("curl")
+        .args(["--fail", "--silent", "--show-error", "--location"])
+        .args(["--proto", "=https", "--user-agent", "hyperlink-self-update"])
+        .arg(url)
+        .output()
+        .context("Failed to run curl, which self-update needs for downloading")?;
+
+    if !output.status.success() {
+        return Err(anyhow!(
+            "Failed to download {url}: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    Ok(output.stdout)
+}
+
+/// The command that unpacks `archive` into `dir`.
+fn unpack_command(archive: &Path, dir: &Path) -> Command {
+    if archive
+        .extension()
+        .is_some_and(|extension| extension == "zip")
+    {
+        let mut command = Command::new("powershell");
+        command
+            .args(["-NoProfile", "-NonInteractive", "-Command"])
+            .arg("Expand-Archive -LiteralPath $args[0] -DestinationPath $args[1]")
+            .arg(archive)
+            .arg(dir);
+        command
+    } else {
+        let mut command = Command::new("tar");
+        command.arg("-xf").arg(archive).arg("-C").arg(dir);
+        command
+    }
+}
+
+/// Unpack `archive` into `dir` and return the path of the binary in it.
+fn unpack(archive: &Path, dir: &Path, target: &str) -> Result<PathBuf, Error> {
+    let mut command = unpack_command(archive, dir);
+    let status = command.status().with_context(|| {
+        format!(
+            "Failed to run {}, which self-update needs for unpacking",
+            command.get_program().to_string_lossy()
+        )
+    })?;
+    if !status.success() {
+        return Err(anyhow!("Failed to unpack {}", archive.display()));
+    }
+    find_binary(dir, target).ok_or_else(|| anyhow!("No hyperlink binary in {}", archive.display()))
+}
+
+/// The binary in an unpacked release archive, which cargo-dist puts in a folder named like the
+/// archive, except on Windows.
+fn find_binary(dir: &Path, target: &str) -> Option<PathBuf> {
+    let name = if target.contains("windows") {
+        "hyperlink.exe"
+    } else {
+        "hyperlink"
+    };
+    let nested = dir.join(format!("hyperlink-{target}")).join(name);
+    if nested.is_file() {
+        return Some(nested);
+    }
+    let flat = dir.join(name);
+    flat.is_file().then_some(flat)
+}
+
+/// Replace the file at `current` with the one at `new`. Windows does not allow replacing a
+/// running executable, but does allow renaming it, so the old binary is moved out of the way
+/// first there.
+fn replace_executable(current: &Path, new: &Path) -> Result<(), Error> {
+    let staged = current.with_file_name(".hyperlink-self-update.new");
+    fs::copy(new, &staged)
+        .with_context(|| format!("Failed to write next to {}", current.display()))?;
+    if let Ok(metadata) = fs::metadata(current) {
+        fs::set_permissions(&staged, metadata.permissions())?;
+    }
+
+    if cfg!(windows) {
+        let old = current.with_file_name(OLD_BINARY);
+        let _ = fs::remove_file(&old);
+        fs::rename(current, &old)
+            .with_context(|| format!("Failed to move {} aside", current.display()))?;
+    }
+    fs::rename(&staged, current).with_context(|| format!("Failed to replace {}", current.display()))
+}
+
+/// Delete the binary that the last update moved aside, which only happens on Windows.
+pub fn remove_old_binary() {
+    if cfg!(windows) {
+        if let Ok(current) = env::current_exe() {
+            let _ = fs::remove_file(current.with_file_name(OLD_BINARY));
+        }
+    }
+}
+
+/// Update the running binary to the latest release. With `check_only`, only print whether there
+/// is one.
+pub fn self_update(check_only: bool) -> Result<(), Error> {
+    let current_version = env!("CARGO_PKG_VERSION");
+    let release: Value = serde_json::from_slice(&download(&format!(
+        "https://api.github.com/repos/{}/releases/latest",
+        repository()
+    ))?)
+    .context("Failed to parse the latest release")?;
+    let tag = release
+        .get("tag_name")
+        .and_then(Value::as_str)
+        .ok_or_else(|| anyhow!("The latest release has no tag"))?;
+
+    if parse_version(tag)? <= parse_version(current_version)? {
+        println!("hyperlink {current_version} is up to date");
+        return Ok(());
+    }
+    if check_only {
+        println!("hyperlink {tag} is available, this is {current_version}");
+        return Ok(());
+    }
+
+    let target = target().ok_or_else(|| {
+        anyhow!("There are no prebuilt binaries for this platform, update from source instead")
+    })?;
+    let archive_name = archive_name(target);
+    let base_url = format!(
+        "https://github.com/{}/releases/download/{tag}",
+        repository()
+    );
+
+    println!("Downloading hyperlink {tag} for {target}");
+    let archive = download(&format!("{base_url}/{archive_name}"))?;
+    let checksums = download(&format!("{base_url}/{archive_name}.sha256"))?;
+    let expected = parse_checksum(&String::from_utf8_lossy(&checksums), &archive_name)
+        .ok_or_else(|| anyhow!("No checksum for {archive_name} in the release"))?;
+    let actual: String = Sha256::digest(&archive)
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect();
+    if actual != expected {
+        return Err(anyhow!(
+            "Checksum mismatch for {archive_name}: expected {expected}, got {actual}"
+        ));
+    }
+
+    let dir = env::temp_dir().join(format!("hyperlink-self-update-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    let result = (|| {
+        let archive_path = dir.join(&archive_name);
+        fs::write(&archive_path, &archive)?;
+        let binary = unpack(&archive_path, &dir, target)?;
+
+        let current = env::current_exe().context("Failed to find the running binary")?;
+        replace_executable(&current, &binary)
+    })();
+    let _ = fs::remove_dir_all(&dir);
+    result?;
+
+    println!("Updated hyperlink from {current_version} to {tag}");
+    Ok(())
+}
+
+#[test]
+fn test_release_assets() {
+    assert_eq!(repository(), "untitaker/hyperlink");
+    assert_eq!(
+        archive_name("x86_64-unknown-linux-gnu"),
+        "hyperlink-x86_64-unknown-linux-gnu.tar.xz"
+    );
+    assert_eq!(
+        archive_name("x86_64-pc-windows-msvc"),
+        "hyperlink-x86_64-pc-windows-msvc.zip"
+    );
+
+    assert!(parse_version("0.2.0").unwrap() < parse_version("v0.10.0").unwrap());
+    assert!(parse_version("1.0.0").unwrap() > parse_version("0.99.1").unwrap());
+    assert!(parse_version("1.0.0-rc.1").is_err());
+
+    let archive = "hyperlink-x86_64-apple-darwin.tar.xz";
+    let checksum = "E3B0C44298FC1C149AFBF4C8996FB92427AE41E4649B934CA495991B7852B855";
+    assert_eq!(
+        parse_checksum(&format!("{checksum} *{archive}\n"), archive),
+        Some(checksum.to_ascii_lowercase())
+    );
+    assert_eq!(
+        parse_checksum(&format!("{checksum}  other.tar.xz\n"), archive),
+        None
+    );
+}
+
+#[test]
+fn test_replace_executable() {
+    let dir = assert_fs::TempDir::new().unwrap();
+    let current = dir.path().join("hyperlink");
+    let new = dir.path().join("new");
+    fs::write(&current, "old").unwrap();
+    fs::write(&new, "new").unwrap();
+
+    replace_executable(&current, &new).unwrap();
+    assert_eq!(fs::read_to_string(&current).unwrap(), "new");
+    assert!(!dir.path().join(".hyperlink-self-update.new").exists());
+    assert!(find_binary(dir.path(), "x86_64-unknown-linux-gnu").is_some());
+}
+
+#[test]
+fn test_unpack_command() {
+    let dir = Path::new("update");
+    let zip = unpack_command(Path::new("hyperlink-x86_64-pc-windows-msvc.zip"), dir);
+    assert_eq!(zip.get_program(), "powershell");
+    let tar = unpack_command(Path::new("hyperlink-x86_64-apple-darwin.tar.xz"), dir);
+    assert_eq!(tar.get_program(), "tar");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_unpack_release_archive() {
+    use assert_fs::prelude::*;
+
+    let target = "x86_64-unknown-linux-gnu";
+    let dir = assert_fs::TempDir::new().unwrap();
+    dir.child("release/hyperlink-x86_64-unknown-linux-gnu/hyperlink")
+        .write_str("new")
+        .unwrap();
+    let archive = dir.path().join(archive_name(target));
+    let status = Command::new("tar")
+        .arg("-cJf")
+        .arg(&archive)
+        .arg("-C")
+        .arg(dir.path().join("release"))
+        .arg("hyperlink-x86_64-unknown-linux-gnu")
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    let unpacked = dir.child("unpacked");
+    unpacked.create_dir_all().unwrap();
+    let binary = unpack(&archive, unpacked.path(), target).unwrap();
+    assert_eq!(fs::read_to_string(binary).unwrap(), "new");
+
+    fs::write(&archive, "not an archive").unwrap();
+    assert!(unpack(&archive, unpacked.path(), target).is_err());
+}
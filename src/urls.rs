@@ -34,6 +34,22 @@ pub fn is_external_link(url: &[u8]) -> bool {
     false
 }
 
+/// `url` from the `//` on, if it is an http(s) or protocol-relative URL.
+pub fn without_scheme(url: &str) -> Option<&str> {
+    if url.starts_with("//") {
+        return Some(url);
+    }
+
+    let (scheme, rest) = url.split_once(':')?;
+    if (scheme.eq_ignore_ascii_case("http") || scheme.eq_ignore_ascii_case("https"))
+        && rest.starts_with("//")
+    {
+        Some(rest)
+    } else {
+        None
+    }
+}
+
 #[test]
 fn test_is_bad_schema() {
     assert!(is_external_link(b"//"));
@@ -43,3 +59,14 @@ fn test_is_bad_schema() {
     assert!(is_external_link(b"http:/"));
     assert!(!is_external_link(b"http/"));
 }
+
+#[test]
+fn test_without_scheme() {
+    assert_eq!(
+        without_scheme("HTTPS://example.com/"),
+        Some("//example.com/")
+    );
+    assert_eq!(without_scheme("//example.com"), Some("//example.com"));
+    assert_eq!(without_scheme("mailto:someone@example.com"), None);
+    assert_eq!(without_scheme("/docs/"), None);
+}
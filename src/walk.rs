@@ -0,0 +1,507 @@
+//! Walking a site and its sources in parallel, and parsing every file in it.
+//!
+//! The stages run on rayon's global pool, unless `configure` gave them pools of their own.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::iter;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc, Mutex, OnceLock};
+use std::thread;
+use std::time::Duration;
+
+use anyhow::{Context, Error};
+use jwalk::WalkDirGeneric;
+use rayon::prelude::*;
+use sha2::{Digest, Sha256};
+
+use crate::anchors::AnchorsIndex;
+use crate::collector::LinkCollector;
+use crate::deploy::DeployIgnore;
+use crate::html::{
+    DefinedLink, Document, DocumentBuffers, Href, Link, ParseOptions, RecordedLinks,
+};
+use crate::markdown::DocumentSource;
+use crate::paragraph::{ParagraphOptions, ParagraphWalker};
+use crate::presets::Preset;
+use crate::shards::Shard;
+
+/// File extensions of Markdown sources.
+pub static MARKDOWN_FILES: &[&str] = &["md", "mdx"];
+
+/// The links of a document, for replaying them for its byte-identical copies.
+type RecordedDocument<P> = Arc<OnceLock<Option<RecordedLinks<P>>>>;
+
+/// Thread pools for individual stages, from `--walk-jobs`, `--parse-jobs`, `--sources-jobs`,
+/// `--read-jobs` and `--stat-jobs`. Stages without their own pool run on the global pool.
+#[derive(Default)]
+pub struct StagePools {
+    pub walk: Option<Arc<rayon::ThreadPool>>,
+    pub parse: Option<rayon::ThreadPool>,
+    pub sources: Option<rayon::ThreadPool>,
+    /// Reads HTML files ahead of parsing, see `extract_html_links`.
+    pub read: Option<rayon::ThreadPool>,
+    /// Looks up file types in batches, see `walk_files`.
+    pub stat: Option<rayon::ThreadPool>,
+}
+
+static STAGE_POOLS: OnceLock<StagePools> = OnceLock::new();
+
+/// With `--trust-file-types`, `walk_files` never looks up where symlinks point.
+static TRUST_FILE_TYPES: AtomicBool = AtomicBool::new(false);
+
+/// How many files `walk_files` hands to a thread at once, from `--batch-size`.
+static BATCH_SIZE: AtomicUsize = AtomicUsize::new(16);
+
+/// Set the pools of the stages, whether `walk_files` trusts the file types of symlinks, and how
+/// many files it hands to a thread at once. The pools can only be set once, before the first
+/// walk.
+pub fn configure(pools: StagePools, trust_file_types: bool, batch_size: Option<usize>) {
+    let _ = STAGE_POOLS.set(pools);
+    TRUST_FILE_TYPES.store(trust_file_types, Ordering::Relaxed);
+    if let Some(batch_size) = batch_size {
+        BATCH_SIZE.store(batch_size.max(1), Ordering::Relaxed);
+    }
+}
+
+pub fn stage_pools() -> &'static StagePools {
+    STAGE_POOLS.get_or_init(StagePools::default)
+}
+
+/// Run `f` on `pool`, or on the current pool if there is none.
+pub fn run_in<T: Send>(pool: Option<&rayon::ThreadPool>, f: impl FnOnce() -> T + Send) -> T {
+    match pool {
+        Some(pool) => pool.install(f),
+        None => f(),
+    }
+}
+
+/// What parsing a site found, see `extract_html_links`.
+pub struct HtmlResult<C> {
+    pub collector: C,
+    pub documents_count: usize,
+    pub file_count: usize,
+    /// Documents whose links were replayed from a byte-identical one, see `parse_html_files`.
+    pub duplicates_count: usize,
+    /// Documents whose anchors were taken from `--anchors-index`, see `parse_html_files`.
+    pub indexed_count: usize,
+    /// The largest size any per-thread arena grew to while parsing a document.
+    pub peak_arena_bytes: usize,
+}
+
+pub fn walk_files(
+    base_path: &Path,
+) -> impl ParallelIterator<Item = Result<jwalk::DirEntry<((), bool)>, jwalk::Error>> {
+    let parallelism = match stage_pools().walk {
+        Some(ref pool) => jwalk::Parallelism::RayonExistingPool {
+            pool: pool.clone(),
+            busy_timeout: None,
+        },
+        None => jwalk::Parallelism::RayonDefaultPool {
+            busy_timeout: Duration::from_secs(1),
+        },
+    };
+
+    let mut files = WalkDirGeneric::<((), bool)>::new(base_path)
+        .parallelism(parallelism)
+        .sort(true) // helps branch predictor (?)
+        .skip_hidden(false)
+        .process_read_dir(|_, _, _, children| {
+            // symlinks are followed as long as they point to a file, which is the common way to
+            // assemble a sources folder out of several directories. the listing already has the
+            // type of everything else
+            let mut symlinks = Vec::new();
+            for dir_entry in children.iter_mut().flatten() {
+                let file_type = dir_entry.file_type();
+                dir_entry.client_state = file_type.is_file();
+                if file_type.is_symlink() && !TRUST_FILE_TYPES.load(Ordering::Relaxed) {
+                    symlinks.push(dir_entry);
+                }
+            }
+
+            // one batch per directory, so that a directory full of symlinks costs one round trip
+            // per --stat-jobs thread rather than one per symlink
+            match stage_pools().stat {
+                Some(ref pool) if symlinks.len() > 1 => pool.install(|| {
+                    symlinks.par_iter_mut().for_each(|dir_entry| {
+                        dir_entry.client_state =
+                            fs::metadata(dir_entry.path()).is_ok_and(|m| m.is_file());
+                    })
+                }),
+                _ => {
+                    for dir_entry in symlinks {
+                        dir_entry.client_state =
+                            fs::metadata(dir_entry.path()).is_ok_and(|m| m.is_file());
+                    }
+                }
+            }
+        })
+        .into_iter()
+        .filter_map(|entry_result| {
+            if let Ok(entry) = entry_result {
+                if let Some(err) = entry.read_children_error {
+                    // https://github.com/Byron/jwalk/issues/40
+                    return Some(Err(err));
+                }
+
+                if !entry.client_state {
+                    return None;
+                }
+                Some(Ok(entry))
+            } else {
+                Some(entry_result)
+            }
+        });
+
+    // on sites with millions of tiny files, handing them out one at a time makes the threads
+    // spend more time taking turns at the walk than parsing. a batch is parsed by one thread,
+    // with the same buffers
+    let batch_size = BATCH_SIZE.load(Ordering::Relaxed);
+    iter::from_fn(move || {
+        let batch: Vec<_> = files.by_ref().take(batch_size).collect();
+        (!batch.is_empty()).then_some(batch)
+    })
+    .par_bridge()
+    .flat_map_iter(|batch| batch)
+}
+
+pub fn extract_html_links<C: LinkCollector<P::Paragraph>, P: ParagraphWalker>(
+    base_path: &Path,
+    options: &ParseOptions,
+    preset: Option<Preset>,
+    dedup: bool,
+    deploy_ignore: Option<&DeployIgnore>,
+    anchors_index: Option<&AnchorsIndex>,
+    shard: Option<Shard>,
+) -> Result<HtmlResult<C>, Error> {
+    // files that are not deployed are left out entirely, so links to them are broken. files of
+    // other shards are left out too, those shards define them
+    let is_deployed = |entry: &Result<jwalk::DirEntry<((), bool)>, jwalk::Error>| {
+        let Ok(entry) = entry else {
+            return true;
+        };
+        if deploy_ignore.is_none() && shard.is_none() {
+            return true;
+        }
+        let path = relative_path(base_path, &entry.path());
+        deploy_ignore.is_none_or(|deploy_ignore| !deploy_ignore.is_excluded(&path))
+            && shard.is_none_or(|shard| shard.contains(&path))
+    };
+
+    let Some(ref read_pool) = stage_pools().read else {
+        let files = walk_files(base_path)
+            .filter(is_deployed)
+            .map(|entry| Ok((entry?.path(), None)));
+        return parse_html_files::<C, P>(base_path, options, preset, dedup, anchors_index, files);
+    };
+
+    // Read HTML files on their own pool and hand the contents over to the parsing threads, so
+    // that slow reads do not leave the CPUs idle. The channel is bounded to limit the number of
+    // files held in memory at once.
+    let (sender, receiver) = mpsc::sync_channel(read_pool.current_num_threads() * 4);
+
+    thread::scope(|scope| {
+        scope.spawn(|| {
+            read_pool.install(|| {
+                walk_files(base_path)
+                    .filter(is_deployed)
+                    .for_each_with(sender, |sender, entry| {
+                        let file = entry.map_err(Error::from).and_then(|entry| {
+                            let path = entry.path();
+                            let contents = if Document::new(base_path, &path).is_html() {
+                                Some(fs::read(&path).with_context(|| {
+                                    format!("Failed to read file {}", path.display())
+                                })?)
+                            } else {
+                                None
+                            };
+                            Ok((path, contents))
+                        });
+                        // the receiver is only dropped early if parsing failed, and that error is
+                        // reported instead
+                        let _ = sender.send(file);
+                    })
+            })
+        });
+
+        parse_html_files::<C, P>(
+            base_path,
+            options,
+            preset,
+            dedup,
+            anchors_index,
+            receiver.into_iter().par_bridge(),
+        )
+    })
+}
+
+/// Parse `files`, using their contents if they have already been read.
+///
+/// With `dedup`, HTML files are hashed, and the links of a file whose contents were seen before
+/// are replayed instead of parsing it again.
+///
+/// With `anchors_index`, the anchors of HTML files that did not change since the index was written
+/// are taken from the index, and the files are parsed without collecting ids.
+fn parse_html_files<C: LinkCollector<P::Paragraph>, P: ParagraphWalker>(
+    base_path: &Path,
+    options: &ParseOptions,
+    preset: Option<Preset>,
+    dedup: bool,
+    anchors_index: Option<&AnchorsIndex>,
+    files: impl ParallelIterator<Item = Result<(PathBuf, Option<Vec<u8>>), Error>>,
+) -> Result<HtmlResult<C>, Error> {
+    let peak_arena_bytes = AtomicUsize::new(0);
+    let duplicates_count = AtomicUsize::new(0);
+    let indexed_count = AtomicUsize::new(0);
+    // keyed by whether ids were skipped too, so that a duplicate of an indexed document that is
+    // not indexed itself still gets its ids
+    //
+    // the first document with some contents records its links, while copies that are read at the
+    // same time wait for it. `None` if it failed to parse, then the copies are parsed themselves
+    let recorded_documents: Mutex<HashMap<_, RecordedDocument<P::Paragraph>>> =
+        Mutex::new(HashMap::new());
+    let options_without_ids = anchors_index.map(|_| ParseOptions {
+        skip_ids: true,
+        ..options.clone()
+    });
+
+    let result: Result<_, Error> = files
+        .try_fold(
+            || (DocumentBuffers::default(), C::new(), 0, 0),
+            |(mut doc_buf, mut collector, mut documents_count, mut file_count), file| {
+                let (path, contents) = file?;
+                let document = Document::new(base_path, &path);
+
+                let file_definition = Link::Defines(DefinedLink {
+                    href: document.href(),
+                    is_redirect: false,
+                    redirect_to: None,
+                    path: Some(document.path.clone()),
+                    element: None,
+                });
+                file_count += 1;
+
+                if preset.is_some_and(|preset| preset.skip_document(&document.href())) {
+                    collector.ingest(file_definition);
+                    return Ok((doc_buf, collector, documents_count, file_count));
+                }
+
+                let indexed_anchors = match (anchors_index, &options_without_ids) {
+                    (Some(anchors_index), Some(options_without_ids)) if document.is_html() => {
+                        anchors_index
+                            .lookup(&relative_path(base_path, &path), &path)
+                            .map(|anchors| (anchors, options_without_ids))
+                    }
+                    _ => None,
+                };
+                let options = match indexed_anchors {
+                    Some((anchors, options_without_ids)) => {
+                        for (id, element) in anchors {
+                            let href = format!("{}#{}", document.href(), id);
+                            collector.ingest(Link::Defines(DefinedLink {
+                                href: Href(&href),
+                                is_redirect: false,
+                                redirect_to: None,
+                                path: Some(document.path.clone()),
+                                element: element.as_deref(),
+                            }));
+                        }
+                        indexed_count.fetch_add(1, Ordering::Relaxed);
+                        options_without_ids
+                    }
+                    None => options,
+                };
+
+                let contents = match contents {
+                    None if dedup && document.is_html() => Some(
+                        fs::read(&*document.path)
+                            .with_context(|| format!("Failed to read file {}", path.display()))?,
+                    ),
+                    contents => contents,
+                };
+
+                let was_parsed = match contents {
+                    Some(contents) if dedup => {
+                        let hash = (Sha256::digest(&contents), options.skip_ids);
+                        let cell = recorded_documents
+                            .lock()
+                            .unwrap()
+                            .entry(hash)
+                            .or_default()
+                            .clone();
+                        let mut recorded_here = None;
+                        let recorded = cell.get_or_init(|| {
+                            let result = document.record_links::<P, _>(
+                                &mut doc_buf,
+                                options,
+                                &contents,
+                                |link| collector.ingest(link),
+                            );
+                            let (result, recorded) = match result {
+                                Ok(recorded) => (Ok(true), Some(recorded)),
+                                Err(error) => (Err(error), None),
+                            };
+                            recorded_here = Some(result);
+                            recorded
+                        });
+                        match (recorded_here, recorded) {
+                            (Some(result), _) => result,
+                            (None, Some(recorded)) => {
+                                document.replay_links(&doc_buf, options, recorded, |link| {
+                                    collector.ingest(link)
+                                });
+                                duplicates_count.fetch_add(1, Ordering::Relaxed);
+                                Ok(true)
+                            }
+                            (None, None) => document
+                                .extract_links_from_bytes::<P, _>(
+                                    &mut doc_buf,
+                                    options,
+                                    &contents,
+                                    |link| collector.ingest(link),
+                                )
+                                .map(|()| true),
+                        }
+                    }
+                    Some(contents) => document
+                        .extract_links_from_bytes::<P, _>(
+                            &mut doc_buf,
+                            options,
+                            &contents,
+                            |link| collector.ingest(link),
+                        )
+                        .map(|()| true),
+                    None => document.extract_links::<P, _>(&mut doc_buf, options, |link| {
+                        collector.ingest(link);
+                    }),
+                }
+                .with_context(|| format!("Failed to read file {}", document.path.display()))?;
+                // after parsing, as the collector does not define placeholders
+                collector.ingest(file_definition);
+
+                if was_parsed {
+                    peak_arena_bytes.fetch_max(doc_buf.arena_bytes(), Ordering::Relaxed);
+                    doc_buf.reset();
+                    documents_count += 1;
+                }
+
+                Ok((doc_buf, collector, documents_count, file_count))
+            },
+        )
+        .map(|result| {
+            result.map(|(_, collector, documents_count, file_count)| {
+                (collector, documents_count, file_count)
+            })
+        })
+        .try_reduce(
+            || (C::new(), 0, 0),
+            |(mut collector, mut documents_count, mut file_count),
+             (collector2, documents_count2, file_count2)| {
+                collector.merge(collector2);
+                documents_count += documents_count2;
+                file_count += file_count2;
+                Ok((collector, documents_count, file_count))
+            },
+        );
+
+    let (collector, documents_count, file_count) = result?;
+
+    Ok(HtmlResult {
+        collector,
+        documents_count,
+        file_count,
+        duplicates_count: duplicates_count.into_inner(),
+        indexed_count: indexed_count.into_inner(),
+        peak_arena_bytes: peak_arena_bytes.into_inner(),
+    })
+}
+
+/// The source files and lines each paragraph appears in.
+pub type MarkdownResult<P> = BTreeMap<P, Vec<(DocumentSource, usize)>>;
+
+/// Hash the paragraphs of the Markdown files in `sources_paths`, for matching them up with the
+/// paragraphs of HTML files.
+pub fn extract_markdown_paragraphs<P: ParagraphWalker>(
+    sources_paths: &[PathBuf],
+    preset: Option<Preset>,
+    options: &ParagraphOptions,
+) -> Result<MarkdownResult<P::Paragraph>, Error> {
+    // walk all folders at once rather than one after another, with many --sources a single small
+    // folder would otherwise leave most threads idle
+    let results: Vec<Result<_, Error>> = sources_paths
+        .par_iter()
+        .map(|sources_path| extract_markdown_paragraphs_from::<P>(sources_path, preset, options))
+        .collect();
+
+    let mut paragraps_to_sourcefile = BTreeMap::new();
+    for result in results {
+        for (source, (paragraph, lineno)) in result? {
+            paragraps_to_sourcefile
+                .entry(paragraph)
+                .or_insert_with(Vec::new)
+                .push((source, lineno));
+        }
+    }
+
+    Ok(paragraps_to_sourcefile)
+}
+
+type SourceParagraphs<P> = Vec<(DocumentSource, (P, usize))>;
+
+fn extract_markdown_paragraphs_from<P: ParagraphWalker>(
+    sources_path: &Path,
+    preset: Option<Preset>,
+    options: &ParagraphOptions,
+) -> Result<SourceParagraphs<P::Paragraph>, Error> {
+    let source_files = match preset {
+        Some(preset) => preset.source_files(sources_path)?,
+        None => None,
+    };
+
+    let results: Vec<Result<_, Error>> = walk_files(sources_path)
+        .try_fold(Vec::new, |mut paragraphs, entry| {
+            let entry = entry?;
+            let source = DocumentSource::new(entry.path());
+
+            if !source
+                .path
+                .extension()
+                .and_then(|extension| {
+                    let extension = extension.to_str()?.to_ascii_lowercase();
+                    Some(MARKDOWN_FILES.contains(&extension.as_str()))
+                })
+                .unwrap_or(false)
+            {
+                return Ok(paragraphs);
+            }
+
+            if let Some(ref source_files) = source_files {
+                if !source_files.contains(&*source.path) {
+                    return Ok(paragraphs);
+                }
+            }
+
+            for paragraph_and_lineno in source
+                .paragraphs::<P>(options)
+                .with_context(|| format!("Failed to read file {}", source.path.display()))?
+            {
+                paragraphs.push((source.clone(), paragraph_and_lineno));
+            }
+            Ok(paragraphs)
+        })
+        .collect();
+
+    let mut paragraphs = Vec::new();
+    for result in results {
+        paragraphs.extend(result?);
+    }
+
+    Ok(paragraphs)
+}
+
+/// `path` below `base_path` with forward slashes, as matched by `DeployIgnore`.
+pub(crate) fn relative_path(base_path: &Path, path: &Path) -> String {
+    let path = path.strip_prefix(base_path).unwrap_or(path);
+    path.to_string_lossy().replace('\\', "/")
+}
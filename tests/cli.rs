@@ -2,13 +2,22 @@ use assert_cmd::Command;
 use assert_fs::prelude::*;
 use predicates::prelude::*;
 
+/// The hyperlink binary, with the environment of GitHub Actions removed so that tests do not
+/// depend on where they run.
+fn hyperlink() -> Command {
+    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    cmd.env_remove("GITHUB_ACTIONS")
+        .env_remove("GITHUB_STEP_SUMMARY");
+    cmd
+}
+
 #[test]
 fn test_dead_link() {
     let site = assert_fs::TempDir::new().unwrap();
     site.child("index.html")
         .write_str("<a href=bar.html>")
         .unwrap();
-    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    let mut cmd = hyperlink();
     cmd.current_dir(site.path()).arg(".");
 
     cmd.assert().failure().code(1).stdout(
@@ -16,7 +25,7 @@ fn test_dead_link() {
             r#"^Reading files
 Checking 1 links from 1 files \(1 documents\)
 \..index\.html
-  error: bad link /bar.html
+  error: bad link /bar.html \(a\[href\]\)
 
 Found 1 bad links
 "#,
@@ -33,7 +42,7 @@ fn test_dead_anchor() {
         .write_str("<a href=bar.html#goo>")
         .unwrap();
     site.child("bar.html").touch().unwrap();
-    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    let mut cmd = hyperlink();
     cmd.current_dir(site.path()).arg(".").arg("--check-anchors");
 
     cmd.assert().failure().code(2).stdout(
@@ -41,7 +50,7 @@ fn test_dead_anchor() {
             r#"^Reading files
 Checking 1 links from 2 files \(2 documents\)
 \..index\.html
-  error: bad link /bar.html#goo
+  error: bad link /bar.html#goo \(a\[href\]\)
 
 Found 0 bad links
 Found 1 bad anchors
@@ -54,7 +63,7 @@ $"#,
 
 #[test]
 fn test_bad_dir() {
-    let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+    let mut cmd = hyperlink();
     cmd.arg("non_existing_dir");
 
     cmd.assert()
@@ -65,3 +74,1748 @@ fn test_bad_dir() {
             "Error: IO error for operation on non_existing_dir:",
         ));
 }
+
+#[test]
+fn test_man() {
+    let mut cmd = hyperlink();
+    cmd.arg("man");
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(".TH hyperlink 1"))
+        .stdout(predicate::str::contains(r"\-\-check\-anchors"))
+        .stdout(predicate::str::contains(r"dump\-external\-links"));
+}
+
+#[test]
+fn test_invalid_ignore_url() {
+    let mut cmd = hyperlink();
+    cmd.arg(".").arg("--ignore-url=(");
+
+    cmd.assert()
+        .failure()
+        .code(1)
+        .stderr(predicate::str::contains(
+            "Error: Invalid regex for --ignore-url",
+        ));
+}
+
+#[test]
+fn test_github_severity() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("index.html")
+        .write_str("<a href=missing.html><a href=bar.html#goo>")
+        .unwrap();
+    site.child("bar.html").touch().unwrap();
+
+    let mut cmd = hyperlink();
+    cmd.current_dir(site.path())
+        .arg(".")
+        .arg("--check-anchors")
+        .arg("--github-actions");
+
+    cmd.assert()
+        .failure()
+        .code(1)
+        .stdout(
+            predicate::str::contains("::error file=").and(predicate::str::contains(
+                "index.html,line=1::bad links:%0A  missing.html (a[href])",
+            )),
+        )
+        .stdout(
+            predicate::str::contains("::warning file=").and(predicate::str::contains(
+                "index.html,line=1::bad anchors:%0A  bar.html#goo (a[href])",
+            )),
+        );
+
+    let mut cmd = hyperlink();
+    cmd.current_dir(site.path())
+        .arg(".")
+        .arg("--check-anchors")
+        .arg("--github-actions")
+        .arg("--github-severity=anchors=error")
+        .arg("--github-severity=links=notice");
+
+    cmd.assert()
+        .failure()
+        .code(1)
+        .stdout(
+            predicate::str::contains("::notice file=")
+                .and(predicate::str::contains("::error file=")),
+        )
+        .stdout(predicate::str::contains("::warning").not());
+
+    site.close().unwrap();
+}
+
+#[test]
+fn test_github_actions_detected() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("index.html")
+        .write_str("<a href=missing.html>")
+        .unwrap();
+    let summary = site.child("summary.md");
+
+    let run = |extra_args: &[&str]| {
+        hyperlink()
+            .current_dir(site.path())
+            .env("GITHUB_ACTIONS", "true")
+            .env("GITHUB_STEP_SUMMARY", summary.path())
+            .arg(".")
+            .args(extra_args)
+            .assert()
+            .failure()
+            .code(1)
+    };
+
+    run(&[]).stdout(predicate::str::contains("::error file="));
+    summary.assert(predicate::str::contains("| bad links | 1 |"));
+
+    run(&["--no-ci-defaults"]).stdout(predicate::str::contains("::error").not());
+    summary.assert(predicate::str::contains("### hyperlink").count(1));
+
+    // other CI systems get no defaults
+    hyperlink()
+        .current_dir(site.path())
+        .env("GITLAB_CI", "true")
+        .env("CI", "true")
+        .arg(".")
+        .assert()
+        .code(1)
+        .stdout(predicate::str::contains("::error").not());
+
+    site.close().unwrap();
+}
+
+#[test]
+fn test_summary_out() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("index.html")
+        .write_str("<a href=bar.html><a href=missing.html><a href=mailto:me@example.com>")
+        .unwrap();
+    site.child("bar.html").touch().unwrap();
+
+    let mut cmd = hyperlink();
+    cmd.current_dir(site.path())
+        .arg(".")
+        .arg("--summary-out=summary.json");
+
+    cmd.assert().failure().code(1);
+
+    let summary: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(site.child("summary.json").path()).unwrap())
+            .unwrap();
+    assert_eq!(summary["files"], 2);
+    assert_eq!(summary["documents"], 2);
+    assert_eq!(summary["links"], 2);
+    assert_eq!(summary["bad_links"], 1);
+    assert_eq!(summary["bad_anchors"], 0);
+    assert_eq!(summary["skipped"]["mailto"], 1);
+    assert_eq!(summary["skipped"]["external"], 0);
+    assert!(summary["duration_secs"].is_f64());
+    assert_eq!(summary["run"]["version"], env!("CARGO_PKG_VERSION"));
+    assert_eq!(summary["run"]["base_path"], ".");
+    assert_eq!(
+        summary["run"]["args"],
+        serde_json::json!([".", "--summary-out=summary.json"])
+    );
+    assert!(summary["run"]["timestamp"].as_str().unwrap().ends_with('Z'));
+
+    site.close().unwrap();
+}
+
+#[test]
+fn test_prometheus_out() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("index.html")
+        .write_str("<a href=bar.html><a href=missing.html>")
+        .unwrap();
+    site.child("bar.html").touch().unwrap();
+
+    let mut cmd = hyperlink();
+    cmd.current_dir(site.path())
+        .arg(".")
+        .arg("--prometheus-out=hyperlink.prom");
+
+    cmd.assert().failure().code(1);
+
+    let metrics = std::fs::read_to_string(site.child("hyperlink.prom").path()).unwrap();
+    assert!(metrics.contains("\nhyperlink_files 2\n"));
+    assert!(metrics.contains("\nhyperlink_links 2\n"));
+    assert!(metrics.contains("\nhyperlink_problems{category=\"bad_links\"} 1\n"));
+    assert!(metrics.contains("\nhyperlink_problems{category=\"bad_anchors\"} 0\n"));
+    assert!(!site.child("hyperlink.prom.tmp").path().exists());
+
+    site.close().unwrap();
+}
+
+#[test]
+fn test_statsd() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("index.html")
+        .write_str("<a href=missing.html>")
+        .unwrap();
+
+    let server = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+
+    let mut cmd = hyperlink();
+    cmd.current_dir(site.path())
+        .arg(".")
+        .arg("--statsd")
+        .arg(server.local_addr().unwrap().to_string());
+
+    cmd.assert().failure().code(1);
+
+    let mut buf = [0; 1024];
+    let len = server.recv(&mut buf).unwrap();
+    let packet = std::str::from_utf8(&buf[..len]).unwrap();
+    assert!(packet.starts_with("hyperlink.duration:"));
+    assert!(packet.contains("\nhyperlink.files:1|g\n"));
+    assert!(packet.contains("\nhyperlink.bad_links:1|g\n"));
+
+    site.close().unwrap();
+}
+
+#[test]
+fn test_ratchet() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("public/index.html")
+        .write_str("<a href=missing.html><a href=missing2.html>")
+        .unwrap();
+
+    let run = || {
+        hyperlink()
+            .current_dir(site.path())
+            .arg("public/")
+            .arg("--ratchet=ratchet.json")
+            .assert()
+    };
+
+    run().success();
+    site.child("ratchet.json")
+        .assert(predicate::str::contains(r#""bad_links": 2"#));
+
+    site.child("public/index.html")
+        .write_str("<a href=missing.html>")
+        .unwrap();
+    run().success();
+    site.child("ratchet.json")
+        .assert(predicate::str::contains(r#""bad_links": 1"#));
+
+    site.child("public/index.html")
+        .write_str("<a href=missing.html><a href=missing2.html>")
+        .unwrap();
+    run().failure().code(1).stdout(predicate::str::contains(
+        "Ratchet: bad_links went up from 1 to 2",
+    ));
+
+    site.close().unwrap();
+}
+
+#[test]
+fn test_git_blame() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("src/index.md")
+        .write_str(
+            "# Hello\n\nSee the [guide](missing.html).\n\nRead the [faq](missing-faq.html).\n",
+        )
+        .unwrap();
+    site.child("public/index.html")
+        .write_str(
+            "<h1>Hello</h1><p>See the <a href=missing.html>guide</a>.</p>\
+             <p>Read the <a href=missing-faq.html>faq</a>.</p>\
+             <p>Ask on the <a href=missing-forum.html>forum</a>.</p>",
+        )
+        .unwrap();
+
+    let git = |args: &[&str]| {
+        let status = std::process::Command::new("git")
+            .current_dir(site.path())
+            .args([
+                "-c",
+                "user.name=Jane Doe",
+                "-c",
+                "user.email=jane@example.com",
+            ])
+            .args(args)
+            .status()
+            .unwrap();
+        assert!(status.success());
+    };
+    git(&["init", "-q"]);
+    git(&["add", "src/index.md"]);
+    git(&["commit", "-q", "-m", "Add index"]);
+    site.child("src/index.md")
+        .write_str(
+            "# Hello\n\nSee the [guide](missing.html).\n\nRead the [faq](missing-faq.html).\n\n\
+             Ask on the [forum](missing-forum.html).\n",
+        )
+        .unwrap();
+
+    let mut cmd = hyperlink();
+    cmd.current_dir(site.path())
+        .arg("public/")
+        .arg("--sources=src/")
+        .arg("--git-blame");
+
+    // all lines of the file are blamed at once, the line that is not committed yet has no blame
+    cmd.assert().failure().code(1).stdout(
+        predicate::str::is_match(
+            r"error: bad link /missing.html \(a\[href\]\) at line 3, last changed by Jane Doe <jane@example.com> in [0-9a-f]{7}\n",
+        )
+        .unwrap()
+        .and(
+            predicate::str::is_match(
+                r"error: bad link /missing-faq.html \(a\[href\]\) at line 5, last changed by Jane Doe <jane@example.com> in [0-9a-f]{7}\n",
+            )
+            .unwrap(),
+        )
+        .and(predicate::str::contains(
+            "error: bad link /missing-forum.html (a[href]) at line 7\n",
+        )),
+    );
+
+    site.close().unwrap();
+}
+
+#[test]
+fn test_git_range() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("src/a.md")
+        .write_str("See the [guide](missing-a.html).\n")
+        .unwrap();
+    site.child("src/b.md").write_str("Nothing here.\n").unwrap();
+    site.child("public/a.html")
+        .write_str("<p>See the <a href=missing-a.html>guide</a>.</p>")
+        .unwrap();
+    site.child("public/b.html")
+        .write_str("<p>See the <a href=missing-b.html>other guide</a>.</p>")
+        .unwrap();
+
+    let git = |args: &[&str]| {
+        let status = std::process::Command::new("git")
+            .current_dir(site.path())
+            .args([
+                "-c",
+                "user.name=Jane Doe",
+                "-c",
+                "user.email=jane@example.com",
+            ])
+            .args(args)
+            .status()
+            .unwrap();
+        assert!(status.success());
+    };
+    git(&["init", "-q"]);
+    git(&["add", "src/"]);
+    git(&["commit", "-q", "-m", "Add sources"]);
+    site.child("src/b.md")
+        .write_str("See the [other guide](missing-b.html).\n")
+        .unwrap();
+    git(&["commit", "-q", "-a", "-m", "Link to other guide"]);
+
+    let mut cmd = hyperlink();
+    cmd.current_dir(site.path())
+        .arg("public/")
+        .arg("--sources=src/")
+        .arg("--git-range=HEAD~1...HEAD");
+
+    cmd.assert()
+        .failure()
+        .code(1)
+        .stdout(predicate::str::contains("error: bad link /missing-b.html"))
+        .stdout(predicate::str::contains("missing-a.html").not())
+        .stdout(predicate::str::contains("Found 1 bad links"));
+
+    site.close().unwrap();
+}
+
+#[test]
+fn test_timings() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("index.html")
+        .write_str("<a href=index.html>")
+        .unwrap();
+
+    let mut cmd = hyperlink();
+    cmd.current_dir(site.path()).arg(".").arg("--timings");
+
+    cmd.assert()
+        .success()
+        .stderr(
+            predicate::str::is_match(r"(?m)^Timings:\n  reading files: \d+\.\d\ds.*, peak arena \d+\.\d MiB\n  checking links: .*\n  reading sources: .*\n  reporting: .*\n  total: \d+\.\d\ds\n$")
+                .unwrap(),
+        );
+
+    site.close().unwrap();
+}
+
+#[test]
+fn test_stage_jobs() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("index.html")
+        .write_str("<a href=missing.html>")
+        .unwrap();
+    site.child("src/index.md").write_str("# Index").unwrap();
+
+    let mut cmd = hyperlink();
+    cmd.current_dir(site.path())
+        .args([
+            "--walk-jobs",
+            "1",
+            "--parse-jobs",
+            "3",
+            "--sources-jobs",
+            "2",
+        ])
+        .args([".", "--sources", "src"]);
+
+    cmd.assert()
+        .failure()
+        .code(1)
+        .stdout(predicate::str::contains("Found 1 bad links"));
+
+    site.close().unwrap();
+}
+
+#[test]
+fn test_read_jobs() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("index.html")
+        .write_str("<a href=missing.html><a href=sub/#frag>")
+        .unwrap();
+    site.child("sub/index.html")
+        .write_str("<p id=frag>")
+        .unwrap();
+
+    let mut cmd = hyperlink();
+    cmd.current_dir(site.path())
+        .args(["--read-jobs", "4", ".", "--check-anchors"]);
+
+    cmd.assert()
+        .failure()
+        .code(1)
+        .stdout(predicate::str::contains(
+            "Checking 2 links from 2 files (2 documents)",
+        ))
+        .stdout(predicate::str::contains("Found 1 bad links"))
+        .stdout(predicate::str::contains("Found 0 bad anchors"));
+
+    site.close().unwrap();
+}
+
+#[test]
+fn test_shards() {
+    let site = assert_fs::TempDir::new().unwrap();
+    for i in 0..10 {
+        site.child(format!("page{i}.html"))
+            .write_str(&format!(
+                "<h2 id=top>Page {i}</h2> <a href=page{}.html#top> <a href=missing{i}.html>",
+                (i + 1) % 10
+            ))
+            .unwrap();
+    }
+    site.child("page3.html")
+        .write_str("<a href=page4.html#bottom>")
+        .unwrap();
+    let state = assert_fs::TempDir::new().unwrap();
+
+    let single = hyperlink()
+        .current_dir(site.path())
+        .args([".", "--check-anchors"])
+        .output()
+        .unwrap();
+
+    for shard in ["1/3", "2/3", "3/3"] {
+        hyperlink()
+            .current_dir(site.path())
+            .args([".", "--check-anchors", "--shard", shard, "--shard-out"])
+            .arg(state.path().join(shard.replace('/', "-")))
+            .assert()
+            .success()
+            .stdout(predicate::str::contains(format!("of shard {shard} to")));
+    }
+
+    hyperlink()
+        .current_dir(site.path())
+        .arg("merge")
+        .args(["1-3", "3-3"].map(|part| state.path().join(part)))
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("missing shards 2/3"));
+
+    let merged = hyperlink()
+        .current_dir(site.path())
+        .arg("merge")
+        .args(["1-3", "2-3", "3-3"].map(|part| state.path().join(part)))
+        .output()
+        .unwrap();
+    assert_eq!(merged.status.code(), Some(1));
+    assert_eq!(
+        String::from_utf8_lossy(&merged.stdout),
+        String::from_utf8_lossy(&single.stdout)
+    );
+
+    // a dump whose ids point past the end of its tables is rejected, not a panic
+    let dump_path = state.path().join("2-3");
+    let mut dump: serde_json::Value =
+        serde_json::from_slice(&std::fs::read(&dump_path).unwrap()).unwrap();
+    dump["collector"]["paths"] = serde_json::json!([]);
+    std::fs::write(&dump_path, dump.to_string()).unwrap();
+    hyperlink()
+        .current_dir(site.path())
+        .arg("merge")
+        .args(["1-3", "2-3", "3-3"].map(|part| state.path().join(part)))
+        .assert()
+        .failure()
+        .code(1)
+        .stderr(predicate::str::contains("invalid collector dump"));
+
+    site.close().unwrap();
+    state.close().unwrap();
+}
+
+#[test]
+fn test_sister_site() {
+    let www = assert_fs::TempDir::new().unwrap();
+    www.child("index.html").touch().unwrap();
+    www.child("pricing/index.html")
+        .write_str("<h2 id=enterprise>Enterprise</h2>")
+        .unwrap();
+
+    let index = hyperlink()
+        .args(["index"])
+        .arg(www.path())
+        .output()
+        .unwrap();
+    assert!(index.status.success());
+    let index_path = www.path().join("www-index.json");
+    std::fs::write(&index_path, &index.stdout).unwrap();
+
+    let docs = assert_fs::TempDir::new().unwrap();
+    docs.child("index.html")
+        .write_str(
+            "<a href='https://www.example.com/pricing/#enterprise'>pricing</a>\
+             <a href='https://www.example.com/pricing/#team'>team</a>\
+             <a href='https://www.example.com/careers/'>careers</a>\
+             <a href='https://example.org/'>elsewhere</a>",
+        )
+        .unwrap();
+
+    let mut cmd = hyperlink();
+    cmd.current_dir(docs.path())
+        .args([".", "--sister-site"])
+        .arg(format!("https://www.example.com={}", index_path.display()));
+    cmd.assert()
+        .failure()
+        .stdout(predicate::str::contains(
+            "error: bad link https://www.example.com/careers/, not in the index of its site",
+        ))
+        .stdout(predicate::str::contains(
+            "Found 1 bad links to sister sites",
+        ));
+
+    let mut cmd = hyperlink();
+    cmd.current_dir(docs.path())
+        .args([".", "--check-anchors", "--sister-site"])
+        .arg(format!("https://www.example.com={}", index_path.display()));
+    cmd.assert()
+        .failure()
+        .stdout(predicate::str::contains(
+            "https://www.example.com/pricing/#team, not in the index",
+        ))
+        .stdout(predicate::str::contains(
+            "Found 2 bad links to sister sites",
+        ));
+
+    // bad links to sister sites are their own category, not broken links
+    let mut cmd = hyperlink();
+    cmd.current_dir(docs.path())
+        .args([".", "--allow", "links", "--sister-site"])
+        .arg(format!("https://www.example.com={}", index_path.display()));
+    cmd.assert().failure();
+
+    let mut cmd = hyperlink();
+    cmd.current_dir(docs.path())
+        .args([".", "--allow", "sister-links", "--sister-site"])
+        .arg(format!("https://www.example.com={}", index_path.display()));
+    cmd.assert().success().stdout(predicate::str::contains(
+        "Found 1 bad links to sister sites",
+    ));
+
+    www.close().unwrap();
+    docs.close().unwrap();
+}
+
+#[test]
+fn test_anchors_index() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("index.html")
+        .write_str("<a href=page.html#intro><a href=page.html#outro>")
+        .unwrap();
+    site.child("page.html")
+        .write_str("<h2 id=intro>Intro</h2>")
+        .unwrap();
+    let state = assert_fs::TempDir::new().unwrap();
+    let index = state.child("anchors.json");
+
+    let run = |indexed: usize| {
+        hyperlink()
+            .current_dir(site.path())
+            .arg(".")
+            .arg("--check-anchors")
+            .arg("--anchors-index")
+            .arg(index.path())
+            .assert()
+            .failure()
+            .code(2)
+            .stdout(predicate::str::contains(format!(
+                "Took the anchors of {indexed} unchanged documents from the anchors index"
+            )))
+            .stdout(predicate::str::contains("bad link /page.html#outro"))
+            .stdout(predicate::str::contains("Found 1 bad anchors"));
+    };
+
+    run(0);
+    index.assert(predicate::str::contains(r#""intro":"h2""#));
+    run(1);
+
+    site.close().unwrap();
+    state.close().unwrap();
+}
+
+#[test]
+fn test_warn_long_paths() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("index.html").touch().unwrap();
+    site.child("docs/getting-started/index.html")
+        .touch()
+        .unwrap();
+    site.child("docs/a.html").touch().unwrap();
+
+    let mut cmd = hyperlink();
+    cmd.current_dir(site.path())
+        .args([".", "--warn", "long-paths"])
+        .args(["--max-segment-length", "12", "--max-path-length", "40"]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(
+            r#"warning: long-paths: "getting-started..." is 15 bytes long, more than 12"#,
+        ))
+        .stdout(predicate::str::contains("Found 1 lint warnings"));
+
+    let mut cmd = hyperlink();
+    cmd.current_dir(site.path())
+        .args([".", "--warn", "long-paths", "--max-path-length", "20"]);
+
+    cmd.assert().success().stdout(predicate::str::contains(
+        "warning: long-paths: path is 31 bytes long, more than 20",
+    ));
+
+    site.close().unwrap();
+}
+
+#[test]
+fn test_warn_percent_encoding() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("index.html")
+        .write_str(
+            "<a href='/a%2520b.html'>a</a><a href='/100%.html'>b</a><a href='/a%20b.html'>c</a>",
+        )
+        .unwrap();
+
+    let mut cmd = hyperlink();
+    cmd.current_dir(site.path())
+        .args([".", "--warn", "percent-encoding"]);
+
+    cmd.assert()
+        .failure()
+        .code(1)
+        .stdout(predicate::str::contains(
+            "warning: percent-encoding: /a%2520b.html looks double-encoded, %2520 decodes to %20 (a[href])",
+        ))
+        .stdout(predicate::str::contains(
+            "warning: percent-encoding: /100%.html contains malformed escape %.h (a[href])",
+        ))
+        .stdout(predicate::str::contains("Found 3 bad links"))
+        .stdout(predicate::str::contains("Found 2 lint warnings"));
+
+    site.close().unwrap();
+}
+
+#[test]
+fn test_warn_backslashes() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("index.html")
+        .write_str(r"<img src='img\logo.png'>")
+        .unwrap();
+    site.child("img/logo.png").touch().unwrap();
+
+    let mut cmd = hyperlink();
+    cmd.current_dir(site.path())
+        .args([".", "--warn", "backslashes"]);
+
+    cmd.assert()
+        .failure()
+        .code(1)
+        .stdout(predicate::str::contains(
+            r"warning: backslashes: img\logo.png contains a backslash, did you mean img/logo.png? (img[src])",
+        ))
+        .stdout(predicate::str::contains("Found 1 lint warnings"));
+
+    site.close().unwrap();
+}
+
+#[test]
+fn test_warn_escapes_root() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("docs/index.html")
+        .write_str("<a href='../../index.html'>home</a><a href='../index.html'>home</a>")
+        .unwrap();
+    site.child("docs/page.html")
+        .write_str("<a href='../../etc/passwd'>x</a>")
+        .unwrap();
+    site.child("index.html").touch().unwrap();
+
+    let mut cmd = hyperlink();
+    cmd.current_dir(site.path())
+        .args([".", "--warn", "escapes-root"]);
+
+    cmd.assert()
+        .failure()
+        .code(1)
+        .stdout(predicate::str::contains(
+            "warning: escapes-root: ../../index.html climbs above the site root (a[href])",
+        ))
+        .stdout(predicate::str::contains(
+            "warning: escapes-root: ../../etc/passwd climbs above the site root (a[href])",
+        ))
+        .stdout(predicate::str::contains("Found 1 bad links"))
+        .stdout(predicate::str::contains("Found 2 lint warnings"));
+
+    site.close().unwrap();
+}
+
+#[test]
+fn test_warn_link_style() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("index.html")
+        .write_str(
+            "<a href='https://example.com/guide/'>guide</a>\
+             <a href='/about.html'>about</a>\
+             <a href='https://example.org/'>elsewhere</a>",
+        )
+        .unwrap();
+    site.child("guide/index.html").touch().unwrap();
+    site.child("about.html").touch().unwrap();
+
+    let mut cmd = hyperlink();
+    cmd.current_dir(site.path()).args([
+        ".",
+        "--base-url",
+        "https://example.com/",
+        "--warn",
+        "absolute-urls",
+        "--warn",
+        "html-extensions",
+    ]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "warning: absolute-urls: https://example.com/guide/ is an absolute URL to this site, \
+             use /guide/ instead (a[href])",
+        ))
+        .stdout(predicate::str::contains(
+            "warning: html-extensions: /about.html links to an .html file, use /about instead \
+             (a[href])",
+        ))
+        .stdout(predicate::str::contains("Found 2 lint warnings"));
+
+    let mut cmd = hyperlink();
+    cmd.current_dir(site.path())
+        .args([".", "--warn", "absolute-urls", "--check-config"]);
+    cmd.assert().failure().stdout(predicate::str::contains(
+        "--warn absolute-urls has no effect without --base-url",
+    ));
+
+    site.close().unwrap();
+}
+
+#[test]
+fn test_warn_bad_data_uris() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("index.html")
+        .write_str(
+            "<img src='data:image/gif;base64,R0lGODlhAQABAAAAACH5BAEKAAEALAAAAAABAAEAAAICTAEAOw=='>\
+             <img src='data:image/gif;base64,R0lGODlhAQABAAAAACH5BAEKAAEA'>\
+             <img src='data:image/png;base64,'>",
+        )
+        .unwrap();
+
+    let mut cmd = hyperlink();
+    cmd.current_dir(site.path())
+        .args([".", "--warn", "bad-data-uris"]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "warning: bad-data-uris: \"data:image/gif;base64,R0lGODlh...\" is a truncated \
+             image/gif (img[src])",
+        ))
+        .stdout(predicate::str::contains(
+            "warning: bad-data-uris: \"data:image/png;base64,\" has no data (img[src])",
+        ))
+        .stdout(predicate::str::contains("Found 2 lint warnings"));
+
+    site.close().unwrap();
+}
+
+#[test]
+fn test_warn_javascript_urls() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("index.html")
+        .write_str("<a href='javascript:void(0)'>menu</a><a href='/'>home</a>")
+        .unwrap();
+
+    let mut cmd = hyperlink();
+    cmd.current_dir(site.path())
+        .args([".", "--warn", "javascript-urls"]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "warning: javascript-urls: javascript:void(0) is a javascript: URL (a[href])",
+        ))
+        .stdout(predicate::str::contains("Found 1 lint warnings"));
+
+    site.close().unwrap();
+}
+
+#[test]
+fn test_sphinx_auto_ids() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("index.html")
+        .write_str(r#"<a href="install.html#id2">install</a> <a href="setup.html#id1">setup</a>"#)
+        .unwrap();
+    site.child("install.html")
+        .write_str(r##"<section id="id2"><h2>Linux<a class="headerlink" href="#id2">¶</a></h2></section>"##)
+        .unwrap();
+    site.child("setup.html")
+        .write_str(r#"<section id="setup"><h1>Setup</h1></section>"#)
+        .unwrap();
+    site.child("usage.html")
+        .write_str(r#"<section id="id1"><h2>Usage</h2></section>"#)
+        .unwrap();
+
+    let mut cmd = hyperlink();
+    cmd.current_dir(site.path())
+        .args([".", "--check-anchors", "--warn", "auto-ids"]);
+    cmd.assert()
+        .failure()
+        .code(2)
+        .stdout(predicate::str::contains(
+            "error: bad link /setup.html#id1 (a[href])",
+        ))
+        // every page of a Sphinx site has an #id1, so there is no point in listing them
+        .stdout(predicate::str::contains("exists on").not())
+        .stdout(predicate::str::contains(
+            "warning: auto-ids: install.html#id2 points at #id2, which Sphinx numbers automatically, link to a label instead (a[href])",
+        ))
+        .stdout(predicate::str::contains("headerlink").not())
+        .stdout(predicate::str::contains("Found 2 lint warnings"));
+
+    site.close().unwrap();
+}
+
+#[test]
+fn test_preset_mdbook_summary() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("book/index.html")
+        .write_str(r#"<p>See <a href="/missing.html">missing</a>.</p>"#)
+        .unwrap();
+    site.child("book/old.html")
+        .write_str(r#"<p>Also <a href="/gone.html">gone</a>.</p>"#)
+        .unwrap();
+    site.child("src/SUMMARY.md")
+        .write_str("# Summary\n\n- [Getting started](getting%20started.md#install)\n- [Draft]()\n")
+        .unwrap();
+    site.child("src/getting started.md")
+        .write_str("See [missing](/missing.html).\n")
+        .unwrap();
+    // not in SUMMARY.md, so not part of the book, even though its paragraphs match
+    site.child("src/unlisted.md")
+        .write_str("# Unlisted\n\nSee [missing](/missing.html).\n")
+        .unwrap();
+    site.child("src/old.md")
+        .write_str("Also [gone](/gone.html).\n")
+        .unwrap();
+
+    let mut cmd = hyperlink();
+    cmd.current_dir(site.path())
+        .args(["book", "--sources", "src", "--preset", "mdbook"]);
+    cmd.assert()
+        .failure()
+        .code(1)
+        .stdout(predicate::str::contains("getting started.md"))
+        .stdout(predicate::str::contains("unlisted.md").not())
+        .stdout(predicate::str::contains("old.md").not())
+        .stdout(predicate::str::contains("old.html"))
+        .stdout(predicate::str::contains("Found 2 bad links"));
+
+    site.child("src/SUMMARY.md")
+        .assert(predicate::path::exists());
+    std::fs::remove_file(site.child("src/SUMMARY.md").path()).unwrap();
+    let mut cmd = hyperlink();
+    cmd.current_dir(site.path())
+        .args(["book", "--sources", "src", "--preset", "mdbook"]);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("SUMMARY.md"));
+
+    site.close().unwrap();
+}
+
+#[test]
+fn test_preset_mdbook_heading_anchors() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("index.html")
+        .write_str(r#"<a href="chapter.html#Getting%20Started">start</a> <a href="chapter.html#nope">nope</a>"#)
+        .unwrap();
+    site.child("chapter.html")
+        .write_str(r##"<h2 id="getting-started"><a class="header" href="#getting-started">Getting Started</a></h2>"##)
+        .unwrap();
+
+    let mut cmd = hyperlink();
+    cmd.current_dir(site.path())
+        .args([".", "--check-anchors", "--preset", "mdbook"]);
+    cmd.assert()
+        .failure()
+        .code(2)
+        .stdout(predicate::str::contains(
+            "note: mdBook names this heading #getting-started",
+        ))
+        .stdout(predicate::str::contains("Found 2 bad anchors"));
+
+    site.close().unwrap();
+}
+
+#[test]
+fn test_media_links() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("index.html")
+        .write_str(
+            r#"<link rel="stylesheet" href="/style.css" media="all">
+            <link rel="stylesheet" href="/print.css" media="print">
+            <link rel="alternate stylesheet" href="/contrast.css" title="High contrast">"#,
+        )
+        .unwrap();
+    site.child("style.css").touch().unwrap();
+
+    let mut cmd = hyperlink();
+    cmd.current_dir(site.path()).arg(".");
+
+    cmd.assert()
+        .failure()
+        .code(1)
+        .stdout(predicate::str::contains(
+            "error: bad link /print.css (link[rel=stylesheet][media=\"print\"])",
+        ))
+        .stdout(predicate::str::contains(
+            "error: bad link /contrast.css (link[rel=\"alternate stylesheet\"][title=\"High \
+             contrast\"])",
+        ))
+        .stdout(predicate::str::contains("Found 2 bad links"));
+
+    site.close().unwrap();
+}
+
+#[test]
+fn test_check_page_text() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("changelog.html")
+        .write_str(
+            "<p>The guide moved from /guide/ to <code>/docs/guide/</code>, \
+             see <a href='/docs/'>/docs/</a>.</p>",
+        )
+        .unwrap();
+    site.child("docs/index.html").touch().unwrap();
+    site.child("docs/guide/index.html").touch().unwrap();
+
+    let mut cmd = hyperlink();
+    cmd.current_dir(site.path()).arg(".");
+    cmd.assert().success();
+
+    let mut cmd = hyperlink();
+    cmd.current_dir(site.path())
+        .args([".", "--check-page-text"]);
+    cmd.assert()
+        .failure()
+        .code(1)
+        .stdout(predicate::str::contains(
+            "error: bad link /guide (heuristic: text)",
+        ))
+        .stdout(predicate::str::contains("Found 1 bad links"));
+
+    site.close().unwrap();
+}
+
+#[test]
+fn test_check_feeds() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("feed.xml")
+        .write_str(
+            r#"<feed xmlns="http://www.w3.org/2005/Atom"><entry>
+            <content type="html">&lt;a href="/posts/1/"&gt;one&lt;/a&gt; &lt;a href="/posts/2/"&gt;two&lt;/a&gt;</content>
+            </entry></feed>"#,
+        )
+        .unwrap();
+    site.child("feed.json")
+        .write_str(
+            r#"{"version": "https://jsonfeed.org/version/1.1",
+            "items": [{"content_html": "<img src='/missing.png'>"}]}"#,
+        )
+        .unwrap();
+    site.child("posts/1/index.html").touch().unwrap();
+
+    let mut cmd = hyperlink();
+    cmd.current_dir(site.path()).arg(".");
+    cmd.assert().success();
+
+    let mut cmd = hyperlink();
+    cmd.current_dir(site.path()).args([".", "--check-feeds"]);
+    cmd.assert()
+        .failure()
+        .code(1)
+        .stdout(predicate::str::contains(
+            "error: bad link /posts/2 (a[href])",
+        ))
+        .stdout(predicate::str::contains(
+            "error: bad link /missing.png (img[src])",
+        ))
+        .stdout(predicate::str::contains("Found 2 bad links"));
+
+    site.close().unwrap();
+}
+
+#[test]
+fn test_check_placeholders() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("index.html")
+        .write_str(
+            r#"<a href="/empty.pdf">pdf</a> <a href="/soon.html">soon</a> <a href="/done.html">done</a>"#,
+        )
+        .unwrap();
+    site.child("empty.pdf").touch().unwrap();
+    site.child("soon.html")
+        .write_str("<h1>Coming soon</h1>")
+        .unwrap();
+    site.child("done.html").write_str("<h1>Done</h1>").unwrap();
+
+    let mut cmd = hyperlink();
+    cmd.current_dir(site.path()).arg(".");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("placeholder").not());
+
+    let mut cmd = hyperlink();
+    cmd.current_dir(site.path())
+        .args([".", "--placeholder-marker", "Coming soon"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "warning: link to placeholder /empty.pdf (a[href]), the file is empty",
+        ))
+        .stdout(predicate::str::contains(
+            r#"warning: link to placeholder /soon.html (a[href]), the page contains "Coming soon""#,
+        ))
+        .stdout(predicate::str::contains("/done.html").not())
+        .stdout(predicate::str::contains(
+            "Found 2 links to placeholder pages",
+        ))
+        .stdout(predicate::str::contains("Found 0 bad links"));
+
+    site.close().unwrap();
+}
+
+#[test]
+fn test_soft_404_marker() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("index.html")
+        .write_str(r#"<p>See <a href="/docs/">docs</a>.</p> <a href="/404.html">404</a>"#)
+        .unwrap();
+    site.child("docs/index.html")
+        .write_str("<h1>Page not found</h1>")
+        .unwrap();
+    site.child("404.html")
+        .write_str("<h1>Page not found</h1>")
+        .unwrap();
+
+    let mut cmd = hyperlink();
+    cmd.current_dir(site.path()).arg(".");
+    cmd.assert().success();
+
+    let mut cmd = hyperlink();
+    cmd.current_dir(site.path())
+        .args([".", "--soft-404-marker", "Page not found"]);
+    cmd.assert()
+        .failure()
+        .code(1)
+        .stdout(predicate::str::contains("error: bad link /docs (a[href])"))
+        .stdout(predicate::str::contains(
+            r#"note: the page is a soft 404, it contains "Page not found""#,
+        ))
+        .stdout(predicate::str::contains("Found 2 bad links"))
+        .stdout(predicate::str::contains("Found 2 links to soft 404 pages"));
+
+    site.close().unwrap();
+}
+
+#[test]
+fn test_permalinks() {
+    let old = assert_fs::TempDir::new().unwrap();
+    old.child("index.html").touch().unwrap();
+    old.child("about.html").touch().unwrap();
+    old.child("blog/hello/index.html").touch().unwrap();
+    old.child("app.3f2a.js").touch().unwrap();
+
+    let new = assert_fs::TempDir::new().unwrap();
+    new.child("index.html").touch().unwrap();
+    new.child("about/index.html").touch().unwrap();
+    new.child("app.9c1e.js").touch().unwrap();
+    new.child("_redirects")
+        .write_str("/about.html /about/ 301\n")
+        .unwrap();
+
+    let mut cmd = hyperlink();
+    cmd.arg("permalinks").arg(old.path()).arg(new.path());
+    cmd.assert()
+        .failure()
+        .code(1)
+        .stdout(predicate::str::contains(
+            "error: /blog/hello is gone and not redirected",
+        ))
+        .stdout(predicate::str::contains("Found 1 broken permalinks"));
+
+    // an index printed by `hyperlink index` works as well
+    let index = hyperlink().arg("index").arg(old.path()).output().unwrap();
+    let index_path = new.path().join("old-index.json");
+    std::fs::write(&index_path, &index.stdout).unwrap();
+    new.child("blog/hello/index.html").touch().unwrap();
+
+    let mut cmd = hyperlink();
+    cmd.arg("permalinks").arg(&index_path).arg(new.path());
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Found 0 broken permalinks"));
+
+    old.close().unwrap();
+    new.close().unwrap();
+}
+
+#[test]
+fn test_export_anchors() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("index.html")
+        .write_str("<h1 id=top>Home</h1>")
+        .unwrap();
+    site.child("docs/index.html")
+        .write_str("<h2 id=install>Install</h2><a name=legacy></a>")
+        .unwrap();
+    site.child("logo.png").touch().unwrap();
+    site.child("_redirects")
+        .write_str("/old/ /docs/\n")
+        .unwrap();
+
+    let output = site.child("anchors.json");
+    let mut cmd = hyperlink();
+    cmd.args(["export-anchors", "-o"])
+        .arg(output.path())
+        .arg(site.path());
+    cmd.assert().success().stdout("");
+
+    let anchors: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(output.path()).unwrap()).unwrap();
+    assert_eq!(
+        anchors,
+        serde_json::json!({
+            "documents": {
+                "index.html": {"href": "/", "anchors": {"top": "h1"}},
+                "docs/index.html": {
+                    "href": "/docs",
+                    "anchors": {"install": "h2", "legacy": "a"},
+                },
+            }
+        })
+    );
+
+    site.close().unwrap();
+}
+
+#[test]
+fn test_format_json() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("public/index.html")
+        .write_str(
+            "<p>Read the <a href='missing.html'>guide</a></p>\
+             <a href='javascript:void(0)'>menu</a>",
+        )
+        .unwrap();
+    site.child("docs/index.md")
+        .write_str("# Docs\n\nRead the [guide](missing.html)\n")
+        .unwrap();
+
+    let mut cmd = hyperlink();
+    cmd.current_dir(site.path()).args([
+        "public",
+        "--sources",
+        "docs",
+        "--warn",
+        "javascript-urls",
+        "--format",
+        "json",
+    ]);
+    let output = cmd.output().unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Found 1 bad links"));
+
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let path = |path: &str| std::path::Path::new(path).display().to_string();
+    assert_eq!(
+        report["links"],
+        serde_json::json!([{
+            "href": "/missing.html",
+            "target": path("public/missing.html"),
+            "file": path("public/index.html"),
+            "origin": "a[href]",
+            "count": 1,
+            "hard_404": true,
+            "soft_404": false,
+            "sources": [{"file": path("docs/index.md"), "line": 3}],
+        }])
+    );
+    assert_eq!(
+        report["problems"],
+        serde_json::json!([{
+            "file": path("public/index.html"),
+            "level": "warning",
+            "category": "lints",
+            "message": "javascript-urls: javascript:void(0) is a javascript: URL (a[href])",
+        }])
+    );
+    assert_eq!(report["summary"]["bad_links"], 1);
+
+    site.close().unwrap();
+}
+
+#[test]
+fn test_sources_keep_command_line_order() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("public/index.html")
+        .write_str("<p>Read the <a href='missing.html'>guide</a></p>")
+        .unwrap();
+    // the same paragraph in both folders, walked at the same time
+    site.child("zeta/index.md")
+        .write_str("# Zeta\n\nRead the [guide](missing.html)\n")
+        .unwrap();
+    site.child("alpha/index.md")
+        .write_str("# Alpha\n\nIntro\n\nRead the [guide](missing.html)\n")
+        .unwrap();
+
+    let mut cmd = hyperlink();
+    cmd.current_dir(site.path()).args([
+        "public",
+        "--sources",
+        "zeta",
+        "--sources",
+        "alpha",
+        "--format",
+        "json",
+    ]);
+    let output = cmd.output().unwrap();
+    assert_eq!(output.status.code(), Some(1));
+
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let path = |path: &str| std::path::Path::new(path).display().to_string();
+    assert_eq!(
+        report["links"][0]["sources"],
+        serde_json::json!([
+            {"file": path("zeta/index.md"), "line": 3},
+            {"file": path("alpha/index.md"), "line": 5},
+        ])
+    );
+
+    site.close().unwrap();
+}
+
+#[test]
+fn test_sources_repeated_broken_link() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("public/index.html")
+        .write_str(
+            "<p>Install the <a href='missing.html'>tool</a></p>\
+             <p>Configure the <a href='missing.html'>tool</a></p>\
+             <p>Run the <a href='missing.html'>tool</a></p>",
+        )
+        .unwrap();
+    site.child("docs/index.md")
+        .write_str(
+            "Install the [tool](missing.html)\n\n\
+             Configure the [tool](missing.html)\n\n\
+             Run the [tool](missing.html)\n",
+        )
+        .unwrap();
+
+    let mut cmd = hyperlink();
+    cmd.current_dir(site.path())
+        .args(["public", "--sources", "docs", "--format", "json"]);
+    let output = cmd.output().unwrap();
+    assert_eq!(output.status.code(), Some(1));
+
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let path = std::path::Path::new("docs/index.md").display().to_string();
+    assert_eq!(report["links"][0]["count"], 3);
+    assert_eq!(
+        report["links"][0]["sources"],
+        serde_json::json!([
+            {"file": path, "line": 1},
+            {"file": path, "line": 3},
+            {"file": path, "line": 5},
+        ])
+    );
+
+    site.close().unwrap();
+}
+
+#[test]
+fn test_report_sections_sorted() {
+    let site = assert_fs::TempDir::new().unwrap();
+    for i in (0..20).rev() {
+        site.child(format!("public/page{i:02}.html"))
+            .write_str(&format!(
+                "<p><a href='missing{i}.html'>other</a></p>\
+                 <p><a href='gone.html'>shared text</a></p>"
+            ))
+            .unwrap();
+    }
+    site.child("docs/zz.md")
+        .write_str("[shared text](gone.html)\n")
+        .unwrap();
+
+    let report = |jobs: &str| {
+        let output = hyperlink()
+            .current_dir(site.path())
+            .args(["-j", jobs, "public", "--sources", "docs"])
+            .output()
+            .unwrap();
+        assert_eq!(output.status.code(), Some(1));
+        String::from_utf8(output.stdout).unwrap()
+    };
+
+    // sections are only written once all broken links are merged, so the order does not depend
+    // on which worker finished first
+    let stdout = report("4");
+    assert_eq!(stdout, report("1"));
+
+    // source files come first, then the other files in path order
+    let positions: Vec<_> = std::iter::once("zz.md".to_owned())
+        .chain((0..20).map(|i| format!("page{i:02}.html")))
+        .map(|name| stdout.find(&name).unwrap())
+        .collect();
+    assert!(positions.windows(2).all(|pair| pair[0] < pair[1]));
+
+    site.close().unwrap();
+}
+
+#[test]
+fn test_format_sarif() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("public/index.html")
+        .write_str(
+            "<p>Read the <a href='missing.html'>guide</a></p>\
+             <a href='#menu'>menu</a>",
+        )
+        .unwrap();
+    site.child("docs/index.md")
+        .write_str("# Docs\n\nRead the [guide](missing.html)\n")
+        .unwrap();
+
+    let mut cmd = hyperlink();
+    cmd.current_dir(site.path()).args([
+        "public",
+        "--sources",
+        "docs",
+        "--check-anchors",
+        "--format",
+        "sarif",
+    ]);
+    let output = cmd.output().unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Found 1 bad anchors"));
+
+    let log: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(log["version"], "2.1.0");
+    assert_eq!(log["runs"][0]["tool"]["driver"]["name"], "hyperlink");
+    assert_eq!(
+        log["runs"][0]["results"],
+        serde_json::json!([
+            {
+                "ruleId": "bad-link",
+                "level": "error",
+                "message": {"text": "bad link /missing.html (a[href])"},
+                "locations": [{
+                    "physicalLocation": {
+                        "artifactLocation": {"uri": "docs/index.md"},
+                        "region": {"startLine": 3},
+                    },
+                }],
+            },
+            {
+                "ruleId": "bad-anchor",
+                "level": "error",
+                "message": {"text": "bad link /#menu (a[href])"},
+                "locations": [{
+                    "physicalLocation": {
+                        "artifactLocation": {"uri": "public/index.html"},
+                    },
+                }],
+            },
+        ])
+    );
+
+    site.close().unwrap();
+}
+
+#[test]
+fn test_format_junit() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("index.html")
+        .write_str("<a href='/about.html'>about</a><a href='/missing.html'>missing</a>")
+        .unwrap();
+    site.child("about.html").touch().unwrap();
+    site.child("logo.png").touch().unwrap();
+
+    let mut cmd = hyperlink();
+    cmd.current_dir(site.path())
+        .args([".", "--format", "junit"]);
+    let output = cmd.output().unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Found 1 bad links"));
+
+    let report = String::from_utf8(output.stdout).unwrap();
+    assert!(report.starts_with(r#"<?xml version="1.0" encoding="UTF-8"?>"#));
+    assert!(report.contains(r#"<testsuites name="hyperlink" tests="2" failures="1""#));
+    assert!(report.contains(r#"<testcase name="about.html" classname="hyperlink""#));
+    assert!(report.contains(
+        r#"<failure message="bad link /missing.html (a[href])">bad link /missing.html (a[href])</failure>"#
+    ));
+    assert!(!report.contains("logo.png"));
+
+    site.close().unwrap();
+}
+
+#[test]
+fn test_format_tap() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("index.html")
+        .write_str("<a href='/about.html'>about</a><a href='/missing.html'>missing</a>")
+        .unwrap();
+    site.child("about.html").touch().unwrap();
+
+    let mut cmd = hyperlink();
+    cmd.current_dir(site.path()).args([".", "--format", "tap"]);
+    let output = cmd.output().unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Found 1 bad links"));
+
+    let index = std::path::Path::new(".")
+        .join("index.html")
+        .display()
+        .to_string();
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        format!(
+            r#"TAP version 13
+1..2
+ok 1 - /about.html
+not ok 2 - /missing.html
+  ---
+  message: "bad link"
+  severity: "error"
+  files:
+    - "{index}"
+  ...
+"#
+        )
+    );
+
+    site.close().unwrap();
+}
+
+#[test]
+fn test_format_checkstyle() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("public/index.html")
+        .write_str("<p>See <a href='/missing.html'>missing</a> and <a href='/about.html#team'>team</a>.</p>")
+        .unwrap();
+    site.child("public/about.html").touch().unwrap();
+    site.child("src/index.md")
+        .write_str("# Home\n\nSee [missing](/missing.html) and [team](/about.html#team).\n")
+        .unwrap();
+
+    let mut cmd = hyperlink();
+    cmd.current_dir(site.path()).args([
+        "public/",
+        "--sources",
+        "src/",
+        "--check-anchors",
+        "--format",
+        "checkstyle",
+    ]);
+    let output = cmd.output().unwrap();
+    assert_eq!(output.status.code(), Some(1));
+
+    let source = std::path::Path::new("src/index.md").display().to_string();
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<checkstyle version="4.3">
+  <file name="{source}">
+    <error line="3" severity="error" message="bad link /missing.html (a[href])" source="hyperlink.bad-link"/>
+    <error line="3" severity="warning" message="bad link /about.html#team (a[href])" source="hyperlink.bad-anchor"/>
+  </file>
+</checkstyle>
+"#
+        )
+    );
+
+    site.close().unwrap();
+}
+
+#[test]
+fn test_format_csv() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("public/index.html")
+        .write_str("<p>See <a href='/missing.html'>missing</a>.</p><a href='/a,b.html'>a</a>")
+        .unwrap();
+    site.child("src/index.md")
+        .write_str("# Home\n\nSee [missing](/missing.html).\n")
+        .unwrap();
+
+    let mut cmd = hyperlink();
+    cmd.current_dir(site.path())
+        .args(["public/", "--sources", "src/", "--format", "csv"]);
+    let output = cmd.output().unwrap();
+    assert_eq!(output.status.code(), Some(1));
+
+    let path = |path: &str| std::path::Path::new(path).display().to_string();
+    let (page, source) = (path("public/index.html"), path("src/index.md"));
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        format!(
+            r#"kind,level,href,file,source,line,message
+bad-link,error,"/a,b.html",{page},,,"bad link /a,b.html (a[href])"
+bad-link,error,/missing.html,{page},{source},3,bad link /missing.html (a[href])
+"#
+        )
+    );
+
+    let mut cmd = hyperlink();
+    cmd.current_dir(site.path())
+        .args(["public/", "--format", "tsv"]);
+    cmd.assert()
+        .failure()
+        .code(1)
+        .stdout(predicate::str::starts_with(
+            "kind\tlevel\thref\tfile\tsource\tline\tmessage\n",
+        ))
+        .stdout(predicate::str::contains(format!(
+            "bad-link\terror\t/a,b.html\t{page}\t\t\tbad link /a,b.html (a[href])\n"
+        )));
+
+    site.close().unwrap();
+}
+
+#[test]
+fn test_format_rdjson() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("public/index.html")
+        .write_str("<p>See <a href='/missing.html'>missing</a>.</p><a href='/gone.html'>gone</a>")
+        .unwrap();
+    site.child("src/index.md")
+        .write_str("# Home\n\nSee [missing](/missing.html).\n")
+        .unwrap();
+
+    let mut cmd = hyperlink();
+    cmd.current_dir(site.path())
+        .args(["public/", "--sources", "src/", "--format", "rdjson"]);
+    let output = cmd.output().unwrap();
+    assert_eq!(output.status.code(), Some(1));
+
+    let result: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(result["source"]["name"], "hyperlink");
+    assert_eq!(
+        result["diagnostics"],
+        serde_json::json!([
+            {
+                "message": "bad link /gone.html (a[href])",
+                "location": { "path": "public/index.html" },
+                "severity": "ERROR",
+                "code": { "value": "bad-link" },
+            },
+            {
+                "message": "bad link /missing.html (a[href])",
+                "location": {
+                    "path": "src/index.md",
+                    "range": { "start": { "line": 3 } },
+                },
+                "severity": "ERROR",
+                "code": { "value": "bad-link" },
+            },
+        ])
+    );
+
+    site.close().unwrap();
+}
+
+#[test]
+fn test_batch_size() {
+    let site = assert_fs::TempDir::new().unwrap();
+    for i in 0..40 {
+        site.child(format!("{i}.html"))
+            .write_str(&format!("<a href='/{}.html'>next</a>", i + 1))
+            .unwrap();
+    }
+
+    for batch_size in ["1", "7", "1000"] {
+        let mut cmd = hyperlink();
+        cmd.current_dir(site.path())
+            .args(["--batch-size", batch_size, "."]);
+        cmd.assert()
+            .failure()
+            .code(1)
+            .stdout(predicate::str::contains("Checking 40 links from 40 files"))
+            .stdout(predicate::str::contains("error: bad link /40.html"))
+            .stdout(predicate::str::contains("Found 1 bad links"));
+    }
+
+    site.close().unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn test_stat_jobs() {
+    let shared = assert_fs::TempDir::new().unwrap();
+    shared.child("guide.html").touch().unwrap();
+    shared.child("faq.html").touch().unwrap();
+
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("index.html")
+        .write_str("<a href='/guide.html'>guide</a><a href='/faq.html'>faq</a>")
+        .unwrap();
+    for name in ["guide.html", "faq.html"] {
+        std::os::unix::fs::symlink(shared.child(name).path(), site.child(name).path()).unwrap();
+    }
+
+    let mut cmd = hyperlink();
+    cmd.current_dir(site.path()).args(["--stat-jobs", "2", "."]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Checking 2 links from 3 files"));
+
+    let mut cmd = hyperlink();
+    cmd.current_dir(site.path())
+        .args(["--trust-file-types", "."]);
+    cmd.assert()
+        .failure()
+        .code(1)
+        .stdout(predicate::str::contains("Found 2 bad links"));
+
+    site.close().unwrap();
+    shared.close().unwrap();
+}
+
+#[test]
+fn test_strip_path_params() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("index.html")
+        .write_str("<a href='/docs;jsessionid=abc/'>docs</a>")
+        .unwrap();
+    site.child("docs/index.html").touch().unwrap();
+
+    let mut cmd = hyperlink();
+    cmd.current_dir(site.path())
+        .args([".", "--warn", "path-parameters"]);
+    cmd.assert()
+        .failure()
+        .code(1)
+        .stdout(predicate::str::contains(
+            "warning: path-parameters: /docs;jsessionid=abc/ has the path parameter \
+             ;jsessionid=abc, which static hosts do not strip",
+        ))
+        .stdout(predicate::str::contains("Found 1 bad links"));
+
+    let mut cmd = hyperlink();
+    cmd.current_dir(site.path())
+        .args([".", "--strip-path-params"]);
+    cmd.assert().success();
+
+    site.close().unwrap();
+}
+
+#[test]
+fn test_fragment_encoding() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("index.html")
+        .write_str("<h2 id=übersicht>Übersicht</h2><a href='#%C3%BCbersicht'>top</a>")
+        .unwrap();
+
+    let mut cmd = hyperlink();
+    cmd.current_dir(site.path()).args([".", "--check-anchors"]);
+    cmd.assert().success();
+
+    let mut cmd = hyperlink();
+    cmd.current_dir(site.path())
+        .args([".", "--check-anchors", "--fragment-encoding", "raw"]);
+    cmd.assert()
+        .failure()
+        .code(2)
+        .stdout(predicate::str::contains(
+            "error: bad link /#%C3%BCbersicht (a[href])",
+        ))
+        .stdout(predicate::str::contains("Found 1 bad anchors"));
+
+    site.close().unwrap();
+}
+
+#[test]
+fn test_uppercase_and_backup_extensions() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("index.html")
+        .write_str("<a href='/LEGACY.HTML'>legacy</a>")
+        .unwrap();
+    site.child("LEGACY.HTML")
+        .write_str("<a href='/missing/'>missing</a>")
+        .unwrap();
+    site.child("about.html.bak")
+        .write_str("<a href='/draft/'>draft</a>")
+        .unwrap();
+
+    let mut cmd = hyperlink();
+    cmd.current_dir(site.path())
+        .args([".", "--warn", "backup-files"]);
+    cmd.assert()
+        .failure()
+        .code(1)
+        .stdout(predicate::str::contains("error: bad link /missing"))
+        .stdout(predicate::str::contains("/draft").not())
+        .stdout(predicate::str::contains(
+            "warning: backup-files: \"about.html.bak\" looks like a .bak file that should not be \
+             deployed",
+        ))
+        .stdout(predicate::str::contains("Found 1 bad links"));
+
+    site.close().unwrap();
+}
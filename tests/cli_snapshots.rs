@@ -3,7 +3,11 @@ use insta_cmd::{assert_cmd_snapshot, get_cargo_bin};
 use std::process::Command;
 
 fn cli() -> Command {
-    Command::new(get_cargo_bin("hyperlink"))
+    let mut cmd = Command::new(get_cargo_bin("hyperlink"));
+    // the same output on GitHub Actions as locally
+    cmd.env_remove("GITHUB_ACTIONS")
+        .env_remove("GITHUB_STEP_SUMMARY");
+    cmd
 }
 
 #[test]
@@ -14,31 +18,297 @@ fn test_no_args() {
     ----- stdout -----
     A command-line tool to find broken links in your static site.
 
-    Usage: [-j=ARG] (COMMAND ... | [--check-anchors] [--sources=ARG] [--github-actions] [BASE-PATH])
+    Usage: [-j=ARG] [--walk-jobs=N] [--parse-jobs=N] [--sources-jobs=N] [--read-jobs=N] [--stat-jobs=N]
+    [--trust-file-types] [--batch-size=N] (COMMAND ... | [--check-anchors] [--check-aria] [
+    --fragment-encoding=ENCODING] [--link-text] [--check-hreflang] [--check-canonical] [--self-canonical
+    ] [--base-url=URL] [--sister-site=SITE]... [--check-redirects] [--check-integrity] [
+    --downloads-manifest=PATH] [--check-text-files] [--check-page-text] [--data-keys=KEYS]... [
+    --check-feeds] [--check-placeholders] [--placeholder-marker=TEXT]... [--soft-404-marker=TEXT]... [
+    --report-unparseable] [--recover-unparseable] [--warn=LINT]... [--max-path-length=N] [
+    --max-segment-length=N] [--summary-by-directory] [--summary-out=PATH] [--prometheus-out=PATH] [
+    --statsd=ADDRESS] [--timings] [--dedup-documents] [--shard=SHARD] [--shard-out=PATH] [--check-config
+    ] [--ratchet=PATH] [--baseline=PATH] [--deny=CATEGORY]... [--allow=CATEGORY]... [--extract=RULE]...
+    [--xml-extract=RULE]... [--extra-attributes=ATTRIBUTES]... [--rewrite-url=RULE]... [
+    --strip-path-params] [--ignore-url=REGEX]... [--check-url-only=REGEX]... [--skip-rel=REL]... [
+    --warn-rel=REL]... [--ignore-id=REGEX]... [--extra-targets=PATH] [--extra-anchors=PATH] [
+    --anchors-index=PATH] [--deploy-ignore=PATH] [--spa-routes=PATH] [--spa-fragment=REGEX]... [
+    --sources=ARG]... [--map-sources=MAPPING]... [--build-manifest=PATH] [--skip-code-blocks] [
+    --git-blame] [--git-range=RANGE] [--format=FORMAT] [--github-actions] [--no-ci-defaults] [
+    --github-severity=MAPPING]... [--preset=PRESET] [BASE-PATH])
 
     Available positional items:
-        BASE-PATH             the static file path to check
+        BASE-PATH                 the static file path to check
 
     Available options:
-        -V, --version         print version information and exit
-        -j, --jobs=ARG        how many threads to use, default is to try and saturate CPU
-            --check-anchors   whether to check for valid anchor references
-            --sources=ARG     path to directory of markdown files to use for reporting errors
-            --github-actions  enable specialized output for GitHub actions
-        -h, --help            Prints help information
+        -V, --version             print version information and exit
+        -j, --jobs=ARG            how many threads to use, default is to try and saturate CPU
+            --walk-jobs=N         how many threads to use for listing directories, default is --jobs
+            --parse-jobs=N        how many threads to use for parsing HTML, default is --jobs
+            --sources-jobs=N      how many threads to use for reading --sources, default is --jobs
+            --read-jobs=N         read HTML files on N threads of their own, ahead of parsing, e.g. on
+                                  network filesystems
+            --stat-jobs=N         look up file types on N threads of their own, in batches, e.g. on
+                                  network filesystems
+            --trust-file-types    take file types from directory listings, and leave out symlinks
+                                  instead of following them
+            --batch-size=N        hand files to threads N at a time, default 16. Lower it if a few huge
+                                  files dominate a run
+            --check-anchors       whether to check for valid anchor references
+            --check-aria          with --check-anchors, also check that the ids in aria-labelledby and
+                                  aria-describedby exist in the same document
+            --fragment-encoding=ENCODING  with --check-anchors, compare fragments and ids
+                                  percent-decoded (the default) or as written
+            --link-text           show the text of broken links and the sentence around them
+            --check-hreflang      check that hreflang alternates link back to each other and include
+                                  x-default
+            --check-canonical     check that canonical links do not point at redirects
+            --self-canonical      with --check-canonical, also require canonical links to point at the
+                                  page itself
+            --base-url=URL        the URL the site is served from, links starting with it are checked
+                                  like local links
+            --sister-site=SITE    check absolute links to another site against its index, can be
+                                  repeated
+            --check-redirects     report rules in _redirects that nothing links to, or that a file
+                                  shadows
+            --check-integrity     check `integrity` attributes of scripts and stylesheets against the
+                                  local files
+            --downloads-manifest=PATH  check linked files next to the release artifacts listed in PATH
+                                  against their checksums
+            --check-text-files    also check URLs in .txt files, e.g. llms.txt or security.txt
+            --check-page-text     also check site-relative paths in the visible text of HTML pages, e.g.
+                                  changelogs. This is a heuristic, findings are marked as such
+            --data-keys=KEYS      comma-separated keys to check in JSON and YAML files, e.g.
+                                  `url,href,image`
+            --check-feeds         also check links in the HTML of posts embedded in Atom, RSS and JSON
+                                  feeds
+            --check-placeholders  warn about links to files that exist, but are empty or contain a
+                                  --placeholder-marker
+            --placeholder-marker=TEXT  text that marks an HTML page as a placeholder, e.g. `Coming
+                                  soon`, can be repeated. Implies --check-placeholders
+            --soft-404-marker=TEXT  text from the 404 page, e.g. `Page not found`, that marks HTML pages
+                                  as soft 404s. Links to them are bad links. Can be repeated
+            --report-unparseable  report files that fail to parse, e.g. malformed JSON, as errors
+                                  instead of aborting
+            --recover-unparseable  with --report-unparseable, still check the links that can be found in
+                                  those files
+            --warn=LINT           report problems found by LINT as warnings, e.g. `hostile-filenames`,
+                                  can be repeated
+            --max-path-length=N   with --warn long-paths, the longest path in bytes not to warn about,
+                                  default is 2000
+            --max-segment-length=N  with --warn long-paths, the longest file or directory name in bytes,
+                                  default is 255
+            --summary-by-directory  print the number of broken links per top-level directory, e.g. to
+                                  route fixes to teams
+            --summary-out=PATH    write counts and duration of the run to PATH as JSON, also if no links
+                                  are broken
+            --prometheus-out=PATH  write run metrics to PATH in the Prometheus text format, e.g. for
+                                  node_exporter
+            --statsd=ADDRESS      send run metrics to the StatsD server at ADDRESS over UDP, e.g.
+                                  `localhost:8125`
+            --timings             print duration, peak memory usage and arena size of each phase to
+                                  stderr
+            --dedup-documents     parse byte-identical HTML files only once, e.g. copies kept as locale
+                                  fallbacks
+            --shard=SHARD         only parse one part of the files, given as K/N, and write what was
+                                  found to --shard-out
+            --shard-out=PATH      with --shard, file to write the links of the shard to, for `hyperlink
+                                  merge`
+            --check-config        only validate the options and the files they refer to, without
+                                  checking any links
+            --ratchet=PATH        only fail if there are more problems than recorded in PATH, and record
+                                  fewer
+            --baseline=PATH       do not fail because of the broken links listed in PATH, each with a
+                                  reason
+            --deny=CATEGORY       fail the run if there are problems in CATEGORY, or found by a lint,
+                                  can be repeated
+            --allow=CATEGORY      do not fail the run because of problems in CATEGORY, e.g. `redirects`,
+                                  can be repeated
+            --extract=RULE        also check ATTRIBUTE on elements matching SELECTOR, can be repeated
+            --xml-extract=RULE    check ATTRIBUTE on elements matching SELECTOR in .xml, .opml, .rss and
+                                  .atom files
+            --extra-attributes=ATTRIBUTES  comma-separated attributes to check on any element, e.g.
+                                  `data-src,data-href`
+            --rewrite-url=RULE    rewrite hrefs before checking them, like a reverse proxy does, can be
+                                  repeated
+            --strip-path-params   check hrefs like `/doc;jsessionid=abc` without their `;`-delimited
+                                  path parameters
+            --ignore-url=REGEX    do not check links whose href matches REGEX, e.g. `^/api/`, can be
+                                  repeated
+            --check-url-only=REGEX  only check links whose href matches REGEX, e.g. `^/docs/`, can be
+                                  repeated
+            --skip-rel=REL        do not check links on elements with `rel=REL`, e.g. `nofollow`, can be
+                                  repeated
+            --warn-rel=REL        report broken links on elements with `rel=REL` as warnings, e.g.
+                                  `external`, can be repeated
+            --ignore-id=REGEX     with --check-anchors, do not count ids matching REGEX as anchors, e.g.
+                                  `^fnref-`
+            --extra-targets=PATH  file listing paths that exist at serve time but not in BASE-PATH
+            --extra-anchors=PATH  file listing anchors that scripts add to pages in the browser, like
+                                  `/search/#results`
+            --anchors-index=PATH  with --check-anchors, file to keep the anchors of each document in
+                                  between runs
+            --deploy-ignore=PATH  file with patterns of files that are not deployed, like an `.s3ignore`
+                                  or rsync excludes
+            --spa-routes=PATH     file listing the client-side routes of a single-page app, like
+                                  `/users/:id`
+            --spa-fragment=REGEX  with --spa-routes, fragments matching REGEX are routes, default is
+                                  `^!?/`, can be repeated
+            --sources=ARG         path to directory of markdown files to use for reporting errors, can
+                                  be repeated
+            --map-sources=MAPPING  only match up HTML under URL-PREFIX with sources under SOURCE-DIR
+            --build-manifest=PATH  JSON file mapping output paths to source paths, used before matching
+                                  paragraphs
+            --skip-code-blocks    leave code blocks out when matching paragraphs with --sources, e.g. if
+                                  they are highlighted
+            --git-blame           show who last changed the source line of each broken link, using git
+                                  blame
+            --git-range=RANGE     only report broken links in source files changed in RANGE, e.g.
+                                  `origin/main...HEAD`
+            --format=FORMAT       print the report in another format: text (default), json, sarif,
+                                  junit, tap, checkstyle, csv, tsv or rdjson
+            --github-actions      enable specialized output for GitHub actions
+            --no-ci-defaults      do not enable --github-actions by default when running on GitHub
+                                  Actions
+            --github-severity=MAPPING  annotation level for a category of problems with
+                                  --github-actions, can be repeated
+            --preset=PRESET       apply workarounds for a static site generator (supported: mdbook)
+        -h, --help                Prints help information
 
     Available commands:
-        dump-paragraphs       Dump out internal data for markdown or html file.
-        match-all-paragraphs  Attempt to match up all paragraphs from the HTML folder with the Markdown
-                              folder and print
-        dump-external-links   Dump out a list and count of _external_ links.  hyperlink does not check
-                              external links,
+        dump-paragraphs           Dump out internal data for markdown or html file.
+        match-all-paragraphs      Attempt to match up all paragraphs from the HTML folder with the
+                                  Markdown folder and print
+        dump-external-links       Dump out a list and count of _external_ links.  hyperlink does not
+                                  check external links,
+        index                     Print the pages and anchors of a site as JSON, for --sister-site.
+        export-anchors            Print the anchors of every page as JSON, for other tools.
+        permalinks                Report pages of an old build that a new build breaks.
+        completions               Print a script that sets up tab completion of subcommands, flags and
+                                  their values for SHELL.
+        man                       Print a man page in roff format, generated from the same definitions
+                                  as --help.
+        merge                     Report on the files written by every `--shard` of a run, as if it had
+                                  been a single run.
+        self-update               Replace this binary with the latest release from GitHub.
 
 
     ----- stderr -----
     "###);
 }
 
+#[test]
+fn test_completions() {
+    assert_cmd_snapshot!(cli().arg("completions").arg("bash"), @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    _bpaf_dynamic_completion()
+    {
+        line="$1 --bpaf-complete-rev=8 ${COMP_WORDS[@]:1}"
+        if [[ ${COMP_WORDS[-1]} == "" ]]; then
+            line="${line} \"\""
+        fi
+        source <( eval ${line})
+    }
+    complete -o nosort -F _bpaf_dynamic_completion hyperlink
+
+    ----- stderr -----
+    "###);
+
+    assert_cmd_snapshot!(cli().arg("completions").arg("zsh"), @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    #compdef hyperlink
+    local line
+    line="${words[1]} --bpaf-complete-rev=7 ${words[@]:1}"
+    if [[ ${words[-1]} == "" ]]; then
+        line="${line} \"\""
+    fi
+    source <(eval ${line})
+
+
+    ----- stderr -----
+    "###);
+
+    assert_cmd_snapshot!(cli().arg("completions").arg("fish"), @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    function _bpaf_dynamic_completion
+        set -l current (commandline --tokenize --current-process)
+        set -l tmpline --bpaf-complete-rev=9 $current[2..]
+        if test (commandline --current-process) != (string trim (commandline --current-process))
+            set tmpline $tmpline ""
+        end
+        eval $current[1] \"$tmpline\"
+    end
+
+    complete --no-files --command hyperlink --arguments '(_bpaf_dynamic_completion)'
+
+
+    ----- stderr -----
+    "###);
+
+    assert_cmd_snapshot!(cli().arg("completions").arg("elvish"), @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    set edit:completion:arg-completer[hyperlink] = { |@args| var args = $args[1..];
+         var @lines = ( hyperlink --bpaf-complete-rev=1 $@args );
+         use str;
+         for line $lines {
+             var @arg = (str:split "\t" $line)
+             try {
+                 edit:complex-candidate $arg[0] &display=( printf "%-19s %s" $arg[0] $arg[1] )
+             } catch {
+                 edit:complex-candidate $line
+             }
+         }
+    }
+
+    ----- stderr -----
+    "###);
+
+    assert_cmd_snapshot!(
+        cli().arg("--bpaf-complete-rev=0").arg("--warn").arg("hos"),
+        @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    hostile-filenames
+    ----- stderr -----
+    "###);
+
+    assert_cmd_snapshot!(
+        cli().arg("--bpaf-complete-rev=0").arg("--format").arg("t"),
+        @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    	FORMAT		print the report in another format: text (default), json, sarif, junit, tap, checkstyle,
+    text	text		
+    tap	tap		
+    tsv	tsv		
+
+
+    ----- stderr -----
+    "###);
+
+    assert_cmd_snapshot!(
+        cli()
+            .arg("--bpaf-complete-rev=0")
+            .arg("--fragment-encoding")
+            .arg("r"),
+        @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    raw
+    ----- stderr -----
+    "###);
+}
+
 #[test]
 fn test_dump_paragraphs_help() {
     let mut settings = insta::Settings::clone_current();
@@ -64,15 +334,210 @@ fn test_dump_paragraphs_help() {
     paragraph. If there are minor formatting differences in two lines that are supposed to match, you
     found the issue that needs fixing in `src/paragraph.rs`.
 
-    Usage: [hyperlink bin] dump-paragraphs --file=ARG
+    Usage: [hyperlink bin] dump-paragraphs --file=ARG [--links] [--skip-code-blocks]
 
     Available options:
-            --file=ARG  markdown or html file
-        -h, --help      Prints help information
+            --file=ARG          markdown or html file
+            --links             also print the URLs each paragraph links to, with reference-style links
+                                resolved
+            --skip-code-blocks  leave code blocks out of paragraphs
+        -h, --help              Prints help information
+
+
+    ----- stderr -----
+    "###);
+}
+
+#[test]
+fn test_dump_paragraphs_links() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("guide.md")
+        .write_str(
+            "See [the guide][guide] and [Install] or [setup][] for details.
+
+* Also [the FAQ][faq] and ![a diagram](diagram.png).
+
+[guide]: /guide/
+[install]: </install/> \"Install\"
+[setup]: https://example.com/setup
+[faq]: /faq/#top
+",
+        )
+        .unwrap();
+
+    assert_cmd_snapshot!(
+        cli()
+            .arg("dump-paragraphs")
+            .arg("--file")
+            .arg("guide.md")
+            .arg("--links")
+            .current_dir(site.path()),
+        @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    1: SeetheguideandInstallorsetupfordetails.
+      /guide/
+      /install/
+      https://example.com/setup
+    4: AlsotheFAQandadiagram.
+      /faq/#top
+      diagram.png
+
+    ----- stderr -----
+    "###);
+
+    site.close().unwrap();
+}
+
+#[test]
+fn test_dump_paragraphs_footnotes() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("paper.md")
+        .write_str(
+            "As shown in [the study](/study/)[^1], links rot.
+
+[^1]: See [the appendix](/appendix/) for the method.
+",
+        )
+        .unwrap();
+
+    assert_cmd_snapshot!(
+        cli()
+            .arg("dump-paragraphs")
+            .arg("--file")
+            .arg("paper.md")
+            .current_dir(site.path()),
+        @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    3: Seetheappendixforthemethod.
+    1: Asshowninthestudy,linksrot.
+
+    ----- stderr -----
+    "###);
+
+    site.close().unwrap();
+}
+
+#[test]
+fn test_dump_paragraphs_tables() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("compare.md")
+        .write_str(
+            "| Tool | Docs |
+| ---- | ---- |
+| hyperlink | [the guide](/guide/) |
+| other | [their docs](/other/) and more |
+",
+        )
+        .unwrap();
+    site.child("compare.html")
+        .write_str(
+            r#"<table>
+<thead><tr><th>Tool</th><th>Docs</th></tr></thead>
+<tbody>
+<tr><td>hyperlink</td><td><a href="/guide/">the guide</a></td></tr>
+<tr><td>other</td><td><a href="/other/">their docs</a> and more</td></tr>
+</tbody>
+</table>"#,
+        )
+        .unwrap();
+
+    assert_cmd_snapshot!(
+        cli()
+            .arg("dump-paragraphs")
+            .arg("--file")
+            .arg("compare.md")
+            .current_dir(site.path()),
+        @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    3: hyperlink
+    4: other
+    1: Tool
+    1: Docs
+    4: theirdocsandmore
+    3: theguide
+
+    ----- stderr -----
+    "###);
+
+    assert_cmd_snapshot!(
+        cli()
+            .arg("dump-paragraphs")
+            .arg("--file")
+            .arg("compare.html")
+            .current_dir(site.path()),
+        @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    theirdocsandmore
+    theguide
+
+    ----- stderr -----
+    "###);
+
+    site.close().unwrap();
+}
+
+#[test]
+fn test_dump_paragraphs_skip_code_blocks() {
+    let site = assert_fs::TempDir::new().unwrap();
+    site.child("install.md")
+        .write_str(
+            "* Run [the installer](/install/):
+
+  ```sh
+  curl -sSf https://example.com | sh
+  ```
+",
+        )
+        .unwrap();
+    site.child("install.html")
+        .write_str(
+            r#"<ul><li><p>Run <a href="/install/">the installer</a>:</p>
+<pre><code><span class="fn">curl</span> -sSf https://example.com <span class="op">|</span> sh
+</code></pre></li></ul>"#,
+        )
+        .unwrap();
+
+    assert_cmd_snapshot!(
+        cli()
+            .arg("dump-paragraphs")
+            .arg("--file")
+            .arg("install.md")
+            .arg("--skip-code-blocks")
+            .current_dir(site.path()),
+        @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    1: Runtheinstaller:
+
+    ----- stderr -----
+    "###);
 
+    assert_cmd_snapshot!(
+        cli()
+            .arg("dump-paragraphs")
+            .arg("--file")
+            .arg("install.html")
+            .arg("--skip-code-blocks")
+            .current_dir(site.path()),
+        @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    Runtheinstaller:
 
     ----- stderr -----
     "###);
+
+    site.close().unwrap();
 }
 
 #[test]
@@ -133,7 +598,7 @@ fn test_redirects() {
     Reading files
     Checking 4 links from 4 files (4 documents)
     ./_redirects
-      error: bad link /missing-page.html
+      error: bad link /missing-page.html (_redirects)
 
     Found 1 bad links
 
@@ -172,7 +637,160 @@ fn test_redirects_only_at_root() {
     Reading files
     Checking 3 links from 4 files (3 documents)
     ./index.html
-      error: bad link /sub-old
+      error: bad link /sub-old (a[href])
+
+    Found 1 bad links
+
+    ----- stderr -----
+    "###);
+
+    site.close().unwrap();
+}
+
+#[test]
+fn test_preset_mdbook() {
+    let site = assert_fs::TempDir::new().unwrap();
+
+    site.child("index.html")
+        .write_str("<a href='print.html'>print this book</a>")
+        .unwrap();
+
+    // print.html repeats every chapter, including its broken links
+    site.child("print.html")
+        .write_str("<a href='missing.html'>link</a>")
+        .unwrap();
+
+    let mut settings = insta::Settings::clone_current();
+    settings.add_filter(r"[/\\]", "/");
+    let _guard = settings.bind_to_scope();
+
+    assert_cmd_snapshot!(cli().arg(".").arg("--preset=mdbook").current_dir(site.path()), @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    Reading files
+    Checking 1 links from 2 files (1 documents)
+    Found 0 bad links
+
+    ----- stderr -----
+    "###);
+
+    site.close().unwrap();
+}
+
+#[test]
+fn test_multiple_sources() {
+    let site = assert_fs::TempDir::new().unwrap();
+
+    site.child("public/index.html")
+        .write_str(
+            "<p>Read the <a href='missing.html'>guide</a></p>\
+             <p>Read the <a href='gone.html'>blog post</a></p>",
+        )
+        .unwrap();
+
+    site.child("docs/index.md")
+        .write_str("# Docs\n\nRead the [guide](missing.html)\n")
+        .unwrap();
+
+    site.child("blog/post.md")
+        .write_str("Read the [blog post](gone.html)\n")
+        .unwrap();
+
+    let mut settings = insta::Settings::clone_current();
+    settings.add_filter(r"[/\\]", "/");
+    let _guard = settings.bind_to_scope();
+
+    assert_cmd_snapshot!(
+        cli()
+            .arg("public")
+            .arg("--sources=docs")
+            .arg("--sources=blog")
+            .current_dir(site.path()),
+        @r###"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    Reading files
+    Checking 2 links from 1 files (1 documents)
+    Found some broken links, reading source files
+    blog/post.md
+      error: bad link /gone.html (a[href]) at line 1
+
+    docs/index.md
+      error: bad link /missing.html (a[href]) at line 3
+
+    Found 2 bad links
+
+    ----- stderr -----
+    "###);
+
+    site.close().unwrap();
+}
+
+#[test]
+fn test_build_manifest() {
+    let site = assert_fs::TempDir::new().unwrap();
+
+    site.child("public/install/index.html")
+        .write_str("<p>See the <a href='/missing.html'>changelog</a></p>")
+        .unwrap();
+
+    site.child("manifest.json")
+        .write_str(r#"{"/install/index.html": "content/install.md"}"#)
+        .unwrap();
+
+    let mut settings = insta::Settings::clone_current();
+    settings.add_filter(r"[/\\]", "/");
+    let _guard = settings.bind_to_scope();
+
+    assert_cmd_snapshot!(
+        cli()
+            .arg("public")
+            .arg("--build-manifest=manifest.json")
+            .current_dir(site.path()),
+        @r###"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    Reading files
+    Checking 1 links from 1 files (1 documents)
+    Found some broken links, reading source files
+    content/install.md
+      error: bad link /missing.html (a[href])
+
+    Found 1 bad links
+
+    ----- stderr -----
+    "###);
+
+    site.close().unwrap();
+}
+
+#[test]
+fn test_link_text() {
+    let site = assert_fs::TempDir::new().unwrap();
+
+    site.child("index.html")
+        .write_str(
+            "<p>Before upgrading, see the <a href='/old/'>migration guide</a>. It is short.</p>",
+        )
+        .unwrap();
+
+    let mut settings = insta::Settings::clone_current();
+    settings.add_filter(r"[/\\]", "/");
+    let _guard = settings.bind_to_scope();
+
+    assert_cmd_snapshot!(
+        cli().arg(".").arg("--link-text").current_dir(site.path()),
+        @r###"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    Reading files
+    Checking 1 links from 1 files (1 documents)
+    ./index.html
+      error: bad link /old (a[href], "migration guide" in "Before upgrading, see the migration guide.")
 
     Found 1 bad links
 
@@ -181,3 +799,1445 @@ fn test_redirects_only_at_root() {
 
     site.close().unwrap();
 }
+
+/// A PDF with two pages and a named destination `install`.
+#[cfg(feature = "pdf")]
+fn minimal_pdf() -> Vec<u8> {
+    let objects = [
+        "<</Type/Catalog/Pages 2 0 R/Names<</Dests<</Names[(install)[3 0 R/Fit]]>>>>>>",
+        "<</Type/Pages/Kids[3 0 R 4 0 R]/Count 2>>",
+        "<</Type/Page/Parent 2 0 R>>",
+        "<</Type/Page/Parent 2 0 R>>",
+    ];
+
+    let mut pdf = b"%PDF-1.4\n".to_vec();
+    let mut offsets = Vec::new();
+    for (i, object) in objects.iter().enumerate() {
+        offsets.push(pdf.len());
+        pdf.extend(format!("{} 0 obj\n{}\nendobj\n", i + 1, object).as_bytes());
+    }
+
+    let xref_offset = pdf.len();
+    pdf.extend(format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1).as_bytes());
+    for offset in offsets {
+        pdf.extend(format!("{offset:010} 00000 n \n").as_bytes());
+    }
+    pdf.extend(
+        format!(
+            "trailer\n<</Size {}/Root 1 0 R>>\nstartxref\n{xref_offset}\n%%EOF\n",
+            objects.len() + 1
+        )
+        .as_bytes(),
+    );
+    pdf
+}
+
+#[cfg(feature = "pdf")]
+#[test]
+fn test_pdf_anchors() {
+    let site = assert_fs::TempDir::new().unwrap();
+
+    site.child("manual.pdf")
+        .write_binary(&minimal_pdf())
+        .unwrap();
+    site.child("index.html")
+        .write_str(
+            "<a href='manual.pdf#page=2'>ok</a> \
+             <a href='manual.pdf#install'>ok</a> \
+             <a href='manual.pdf#nameddest=install'>ok</a> \
+             <a href='manual.pdf#page=3'>bad</a> \
+             <a href='manual.pdf#uninstall'>bad</a>",
+        )
+        .unwrap();
+
+    let mut settings = insta::Settings::clone_current();
+    settings.add_filter(r"[/\\]", "/");
+    let _guard = settings.bind_to_scope();
+
+    assert_cmd_snapshot!(
+        cli().arg(".").arg("--check-anchors").current_dir(site.path()),
+        @r###"
+    success: false
+    exit_code: 2
+    ----- stdout -----
+    Reading files
+    Checking 5 links from 2 files (2 documents)
+    ./index.html
+      error: bad link /manual.pdf#page=3 (a[href])
+      error: bad link /manual.pdf#uninstall (a[href])
+
+    Found 0 bad links
+    Found 2 bad anchors
+
+    ----- stderr -----
+    "###);
+
+    site.close().unwrap();
+}
+
+#[test]
+fn test_extract() {
+    let site = assert_fs::TempDir::new().unwrap();
+
+    site.child("index.html")
+        .write_str(
+            "<div class='card' data-target='/cards/missing.html'></div>\
+             <div class='banner' data-target='/banners/missing.html'></div>\
+             <img data-src='/img/missing.png'>",
+        )
+        .unwrap();
+
+    let mut settings = insta::Settings::clone_current();
+    settings.add_filter(r"[/\\]", "/");
+    let _guard = settings.bind_to_scope();
+
+    assert_cmd_snapshot!(
+        cli()
+            .arg(".")
+            .arg("--extract=div.card@data-target")
+            .arg("--extract=img@data-src")
+            .current_dir(site.path()),
+        @r###"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    Reading files
+    Checking 2 links from 1 files (1 documents)
+    ./index.html
+      error: bad link /cards/missing.html (div.card[data-target])
+      error: bad link /img/missing.png (img[data-src])
+
+    Found 2 bad links
+
+    ----- stderr -----
+    "###);
+
+    site.close().unwrap();
+}
+
+#[test]
+fn test_extra_attributes() {
+    let site = assert_fs::TempDir::new().unwrap();
+
+    site.child("index.html")
+        .write_str(
+            "<img class='lazy' data-src='/img/missing.png'>\
+             <div data-href='/index.html'></div>\
+             <span data-url='/gone.html'></span>",
+        )
+        .unwrap();
+
+    let mut settings = insta::Settings::clone_current();
+    settings.add_filter(r"[/\\]", "/");
+    let _guard = settings.bind_to_scope();
+
+    assert_cmd_snapshot!(
+        cli()
+            .arg(".")
+            .arg("--extra-attributes=data-href,data-src,data-url")
+            .current_dir(site.path()),
+        @r###"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    Reading files
+    Checking 3 links from 1 files (1 documents)
+    ./index.html
+      error: bad link /gone.html (*[data-url])
+      error: bad link /img/missing.png (*[data-src])
+
+    Found 2 bad links
+
+    ----- stderr -----
+    "###);
+
+    site.close().unwrap();
+}
+
+#[test]
+fn test_ignore_url() {
+    let site = assert_fs::TempDir::new().unwrap();
+
+    site.child("index.html")
+        .write_str(
+            "<a href='/api/users'>API</a>\
+             <a href='/avatars/123'>Avatar</a>\
+             <a href='/avatars/me'>Me</a>",
+        )
+        .unwrap();
+
+    let mut settings = insta::Settings::clone_current();
+    settings.add_filter(r"[/\\]", "/");
+    let _guard = settings.bind_to_scope();
+
+    assert_cmd_snapshot!(
+        cli()
+            .arg(".")
+            .arg("--ignore-url=^/api/")
+            .arg(r"--ignore-url=^/avatars/\d+$")
+            .current_dir(site.path()),
+        @r###"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    Reading files
+    Checking 1 links from 1 files (1 documents)
+    Skipped 2 links (ignored: 2)
+    ./index.html
+      error: bad link /avatars/me (a[href])
+
+    Found 1 bad links
+
+    ----- stderr -----
+    "###);
+
+    site.close().unwrap();
+}
+
+#[test]
+fn test_check_url_only() {
+    let site = assert_fs::TempDir::new().unwrap();
+
+    site.child("docs/index.html")
+        .write_str("<a href='/docs/missing/'>docs</a><a href='/blog/'>blog</a>")
+        .unwrap();
+    site.child("blog/index.html")
+        .write_str("<a href='/docs/'>docs</a><a href='/blog/missing/'>missing</a>")
+        .unwrap();
+
+    let mut settings = insta::Settings::clone_current();
+    settings.add_filter(r"[/\\]", "/");
+    let _guard = settings.bind_to_scope();
+
+    assert_cmd_snapshot!(
+        cli()
+            .arg(".")
+            .arg("--check-url-only=^/docs/")
+            .current_dir(site.path()),
+        @r###"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    Reading files
+    Checking 1 links from 2 files (2 documents)
+    Skipped 3 links (ignored: 3)
+    ./docs/index.html
+      error: bad link /docs/missing (a[href])
+
+    Found 1 bad links
+
+    ----- stderr -----
+    "###);
+
+    site.close().unwrap();
+}
+
+#[test]
+fn test_extra_targets() {
+    let site = assert_fs::TempDir::new().unwrap();
+
+    site.child("public/index.html")
+        .write_str(
+            "<a href='/api/health'>health</a>\
+             <a href='/functions/login'>login</a>\
+             <a href='/app/settings/profile'>profile</a>\
+             <a href='/missing'>missing</a>",
+        )
+        .unwrap();
+    site.child("routes.txt")
+        .write_str("# served by the backend\n/api/health\n\n/functions/*\n/app/**\n")
+        .unwrap();
+
+    let mut settings = insta::Settings::clone_current();
+    settings.add_filter(r"[/\\]", "/");
+    let _guard = settings.bind_to_scope();
+
+    assert_cmd_snapshot!(
+        cli()
+            .arg("public/")
+            .arg("--extra-targets=routes.txt")
+            .current_dir(site.path()),
+        @r###"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    Reading files
+    Checking 2 links from 1 files (1 documents)
+    Skipped 2 links (ignored: 2)
+    public/index.html
+      error: bad link /missing (a[href])
+
+    Found 1 bad links
+
+    ----- stderr -----
+    "###);
+
+    site.close().unwrap();
+}
+
+#[test]
+fn test_check_hreflang() {
+    let site = assert_fs::TempDir::new().unwrap();
+
+    site.child("en/index.html")
+        .write_str(
+            r#"<link rel="alternate" hreflang="en" href="/en/">
+            <link rel="alternate" hreflang="de" href="/de/">
+            <link rel="alternate" hreflang="x-default" href="/en/">"#,
+        )
+        .unwrap();
+    site.child("de/index.html")
+        .write_str(
+            r#"<link rel="alternate" hreflang="de" href="/de/">
+            <link rel="alternate" hreflang="fr" href="/fr/">"#,
+        )
+        .unwrap();
+
+    let mut settings = insta::Settings::clone_current();
+    settings.add_filter(r"[/\\]", "/");
+    let _guard = settings.bind_to_scope();
+
+    assert_cmd_snapshot!(
+        cli()
+            .arg(".")
+            .arg("--check-hreflang")
+            .current_dir(site.path()),
+        @r###"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    Reading files
+    Checking 5 links from 2 files (2 documents)
+    ./de/index.html
+      error: bad link /fr (link[rel=alternate])
+      error: bad hreflang: no hreflang=x-default
+
+    ./en/index.html
+      error: bad hreflang: /de (hreflang=de) does not link back
+
+    Found 1 bad links
+    Found 2 bad hreflang links
+
+    ----- stderr -----
+    "###);
+
+    site.close().unwrap();
+}
+
+#[test]
+fn test_check_canonical() {
+    let site = assert_fs::TempDir::new().unwrap();
+
+    site.child("_redirects").write_str("/old/ /new/\n").unwrap();
+    site.child("new/index.html")
+        .write_str(r#"<link rel="canonical" href="https://example.com/new/">"#)
+        .unwrap();
+    site.child("print/index.html")
+        .write_str(r#"<link rel="canonical" href="https://example.com/new/">"#)
+        .unwrap();
+    site.child("moved/index.html")
+        .write_str(r#"<link rel="canonical" href="https://example.com/old/">"#)
+        .unwrap();
+    site.child("gone/index.html")
+        .write_str(r#"<link rel="canonical" href="https://example.com/missing/">"#)
+        .unwrap();
+
+    let mut settings = insta::Settings::clone_current();
+    settings.add_filter(r"[/\\]", "/");
+    let _guard = settings.bind_to_scope();
+
+    assert_cmd_snapshot!(
+        cli()
+            .arg(".")
+            .arg("--base-url=https://example.com/")
+            .arg("--check-canonical")
+            .current_dir(site.path()),
+        @r###"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    Reading files
+    Checking 5 links from 5 files (5 documents)
+    ./gone/index.html
+      error: bad link /missing (link[rel=canonical])
+
+    ./moved/index.html
+      error: bad canonical: /old is a redirect
+
+    Found 1 bad links
+    Found 1 bad canonical links
+
+    ----- stderr -----
+    "###);
+
+    assert_cmd_snapshot!(
+        cli()
+            .arg(".")
+            .arg("--base-url=https://example.com/")
+            .arg("--self-canonical")
+            .current_dir(site.path()),
+        @r###"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    Reading files
+    Checking 5 links from 5 files (5 documents)
+    ./gone/index.html
+      error: bad link /missing (link[rel=canonical])
+      error: bad canonical: /missing is not this page
+
+    ./moved/index.html
+      error: bad canonical: /old is a redirect
+
+    ./print/index.html
+      error: bad canonical: /new is not this page
+
+    Found 1 bad links
+    Found 3 bad canonical links
+
+    ----- stderr -----
+    "###);
+
+    site.close().unwrap();
+}
+
+#[test]
+fn test_check_redirects() {
+    let site = assert_fs::TempDir::new().unwrap();
+
+    site.child("_redirects")
+        .write_str("/old/ /new/\n\n# no longer linked\n/older/ /new/\n/new/ /\n")
+        .unwrap();
+    site.child("index.html")
+        .write_str("<a href='/old/'>old</a><a href='/new/'>new</a>")
+        .unwrap();
+    site.child("new/index.html").touch().unwrap();
+
+    let mut settings = insta::Settings::clone_current();
+    settings.add_filter(r"[/\\]", "/");
+    let _guard = settings.bind_to_scope();
+
+    assert_cmd_snapshot!(
+        cli()
+            .arg(".")
+            .arg("--check-redirects")
+            .current_dir(site.path()),
+        @r###"
+    success: false
+    exit_code: 2
+    ----- stdout -----
+    Reading files
+    Checking 5 links from 3 files (3 documents)
+    ./_redirects
+      error: shadowed redirect /new at line 5 (a file exists at this path)
+      error: unused redirect /older at line 4
+
+    Found 0 bad links
+    Found 2 bad redirects
+
+    ----- stderr -----
+    "###);
+
+    site.close().unwrap();
+}
+
+#[test]
+fn test_summary_by_directory() {
+    let site = assert_fs::TempDir::new().unwrap();
+
+    site.child("index.html")
+        .write_str("<a href='/missing/'>missing</a>")
+        .unwrap();
+    site.child("docs/index.html")
+        .write_str("<a href='/a/'>a</a>")
+        .unwrap();
+    site.child("blog/2024/post/index.html")
+        .write_str("<a href='/b/'>b</a><a href='/c/'>c</a>")
+        .unwrap();
+
+    let mut settings = insta::Settings::clone_current();
+    settings.add_filter(r"[/\\]", "/");
+    let _guard = settings.bind_to_scope();
+
+    assert_cmd_snapshot!(
+        cli()
+            .arg(".")
+            .arg("--summary-by-directory")
+            .current_dir(site.path()),
+        @r###"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    Reading files
+    Checking 4 links from 3 files (3 documents)
+    ./blog/2024/post/index.html
+      error: bad link /b (a[href])
+      error: bad link /c (a[href])
+
+    ./docs/index.html
+      error: bad link /a (a[href])
+
+    ./index.html
+      error: bad link /missing (a[href])
+
+    Bad links by directory:
+      /blog 2
+      / 1
+      /docs 1
+
+    Found 4 bad links
+
+    ----- stderr -----
+    "###);
+
+    site.close().unwrap();
+}
+
+// these files cannot be created on Windows, which is the point of the lint
+#[cfg(unix)]
+#[test]
+fn test_warn_hostile_filenames() {
+    let site = assert_fs::TempDir::new().unwrap();
+
+    site.child("index.html")
+        .write_str("<a href='/nul.html'>nul</a>")
+        .unwrap();
+    site.child("nul.html").touch().unwrap();
+    site.child("docs/notes.").touch().unwrap();
+    site.child("docs/10:30.html").touch().unwrap();
+
+    let mut settings = insta::Settings::clone_current();
+    settings.add_filter(r"[/\\]", "/");
+    let _guard = settings.bind_to_scope();
+
+    assert_cmd_snapshot!(
+        cli()
+            .arg(".")
+            .arg("--warn")
+            .arg("hostile-filenames")
+            .current_dir(site.path()),
+        @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    Reading files
+    Checking 1 links from 4 files (3 documents)
+    ./docs/10:30.html
+      warning: hostile-filenames: "10:30.html" contains ':', which is not allowed on Windows
+
+    ./docs/notes.
+      warning: hostile-filenames: "notes." ends with a dot
+
+    ./nul.html
+      warning: hostile-filenames: "nul.html" is a reserved device name on Windows
+
+    Found 0 bad links
+    Found 3 lint warnings (hostile-filenames: 3)
+
+    ----- stderr -----
+    "###);
+
+    site.close().unwrap();
+}
+
+#[test]
+fn test_warn_unencoded_characters() {
+    let site = assert_fs::TempDir::new().unwrap();
+
+    site.child("index.html")
+        .write_str(
+            "<a href='/my page.html'>page</a>\
+             <img src='/logo.png?v=\"1\"'>\
+             <a href='mailto:me@example.com?subject=Hello there'>mail</a>",
+        )
+        .unwrap();
+    site.child("my page.html").touch().unwrap();
+    site.child("logo.png").touch().unwrap();
+
+    let mut settings = insta::Settings::clone_current();
+    settings.add_filter(r"[/\\]", "/");
+    let _guard = settings.bind_to_scope();
+
+    assert_cmd_snapshot!(
+        cli()
+            .arg(".")
+            .arg("--warn")
+            .arg("unencoded-characters")
+            .current_dir(site.path()),
+        @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    Reading files
+    Checking 2 links from 3 files (2 documents)
+    Skipped 1 links (mailto: 1)
+    ./index.html
+      warning: unencoded-characters: /logo.png?v="1" contains a quote, which must be percent-encoded (img[src])
+      warning: unencoded-characters: /my page.html contains a space, which must be percent-encoded (a[href])
+
+    Found 0 bad links
+    Found 2 lint warnings (unencoded-characters: 2)
+
+    ----- stderr -----
+    "###);
+
+    site.close().unwrap();
+}
+
+#[test]
+fn test_warn_bad_ids() {
+    let site = assert_fs::TempDir::new().unwrap();
+
+    site.child("index.html")
+        .write_str(
+            "<h2 id='getting-started'>Getting started</h2>\
+             <h2 id=''>Configuration</h2>\
+             <h2 id='next steps'>Next steps</h2>\
+             <a href='#next steps'>next</a>",
+        )
+        .unwrap();
+
+    let mut settings = insta::Settings::clone_current();
+    settings.add_filter(r"[/\\]", "/");
+    let _guard = settings.bind_to_scope();
+
+    assert_cmd_snapshot!(
+        cli()
+            .arg(".")
+            .arg("--check-anchors")
+            .arg("--warn")
+            .arg("bad-ids")
+            .current_dir(site.path()),
+        @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    Reading files
+    Checking 1 links from 1 files (1 documents)
+    ./index.html
+      warning: bad-ids: <h2 id="next steps"> contains whitespace, which no fragment can point at
+      warning: bad-ids: <h2> has an empty id
+
+    Found 0 bad links
+    Found 0 bad anchors
+    Found 2 lint warnings (bad-ids: 2)
+
+    ----- stderr -----
+    "###);
+
+    site.close().unwrap();
+}
+
+#[test]
+fn test_check_integrity() {
+    let site = assert_fs::TempDir::new().unwrap();
+
+    site.child("index.html")
+        .write_str(
+            "<script src='app.js' integrity='sha256-qznLcsROx4GACP2dm0UCKCzCG+HiZ1guq6ZZDob/Tng='></script>\
+             <link rel=stylesheet href='/style.css' integrity='sha384-H8BRh8j48O9oYatfu5AZzq6A9RINhZO5H16dQZngK7T62em8MUt1FLm52t+eX6xO'>\
+             <script src='https://cdn.example.com/lib.js' integrity='sha256-AAAA'></script>",
+        )
+        .unwrap();
+    site.child("app.js")
+        .write_str("alert('Hello, world.');")
+        .unwrap();
+    site.child("style.css").write_str("body {}").unwrap();
+
+    let mut settings = insta::Settings::clone_current();
+    settings.add_filter(r"[/\\]", "/");
+    let _guard = settings.bind_to_scope();
+
+    assert_cmd_snapshot!(
+        cli()
+            .arg(".")
+            .arg("--check-integrity")
+            .current_dir(site.path()),
+        @r###"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    Reading files
+    Checking 2 links from 3 files (1 documents)
+    Skipped 1 links (external: 1)
+    ./index.html
+      error: bad integrity: /style.css does not match, its hash is sha384-JvbluEOKMBmUtNHx346xlZFWqKqtOmexOupPSHRCR0NbwTey4wjq9itKKoSWuGsH
+
+    Found 0 bad links
+    Found 1 bad integrity hashes
+
+    ----- stderr -----
+    "###);
+
+    site.close().unwrap();
+}
+
+#[test]
+fn test_spa_routes() {
+    let site = assert_fs::TempDir::new().unwrap();
+
+    site.child("routes.txt")
+        .write_str("# routes of the app\n/users/:id\n/settings\n")
+        .unwrap();
+    site.child("index.html")
+        .write_str(
+            "<a href='app/#/users/1'>user</a>\
+             <a href='app/#!/settings'>settings</a>\
+             <a href='app/#/billing'>billing</a>\
+             <a href='app/#top'>top</a>",
+        )
+        .unwrap();
+    site.child("app/index.html")
+        .write_str("<div id=top></div>")
+        .unwrap();
+
+    let mut settings = insta::Settings::clone_current();
+    settings.add_filter(r"[/\\]", "/");
+    let _guard = settings.bind_to_scope();
+
+    assert_cmd_snapshot!(
+        cli()
+            .arg(".")
+            .arg("--check-anchors")
+            .arg("--spa-routes")
+            .arg("routes.txt")
+            .current_dir(site.path()),
+        @r###"
+    success: false
+    exit_code: 2
+    ----- stdout -----
+    Reading files
+    Checking 4 links from 3 files (2 documents)
+    ./index.html
+      error: bad link /app#/billing (a[href])
+
+    Found 0 bad links
+    Found 1 bad anchors
+
+    ----- stderr -----
+    "###);
+
+    site.close().unwrap();
+}
+
+#[test]
+fn test_check_text_files() {
+    let site = assert_fs::TempDir::new().unwrap();
+
+    site.child("llms.txt")
+        .write_str(
+            "# Example\n\
+             > Docs for LLMs\n\
+             - [Guide](https://example.com/guide/): how to start.\n\
+             - [API](/api/reference.md)\n\
+             See /missing.html, or https://other.example.com/ and and/or 1/2.\n",
+        )
+        .unwrap();
+    site.child("guide/index.html").touch().unwrap();
+    site.child("index.html").touch().unwrap();
+
+    let mut settings = insta::Settings::clone_current();
+    settings.add_filter(r"[/\\]", "/");
+    let _guard = settings.bind_to_scope();
+
+    assert_cmd_snapshot!(
+        cli()
+            .arg(".")
+            .arg("--check-text-files")
+            .arg("--base-url")
+            .arg("https://example.com")
+            .current_dir(site.path()),
+        @r###"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    Reading files
+    Checking 3 links from 3 files (3 documents)
+    ./llms.txt
+      error: bad link /api/reference.md (text)
+      error: bad link /missing.html (text)
+
+    Found 2 bad links
+
+    ----- stderr -----
+    "###);
+
+    site.close().unwrap();
+}
+
+#[test]
+fn test_data_keys() {
+    let site = assert_fs::TempDir::new().unwrap();
+
+    site.child("search.json")
+        .write_str(r#"[{"title": "Guide", "url": "/guide/"}, {"title": "Gone", "url": "/gone/"}]"#)
+        .unwrap();
+    site.child("data/menu.yaml")
+        .write_str("- name: Blog\n  href: /blog/ # latest posts\n- name: Home\n  href: \"/\"\n")
+        .unwrap();
+    site.child("guide/index.html").touch().unwrap();
+    site.child("index.html").touch().unwrap();
+
+    let mut settings = insta::Settings::clone_current();
+    settings.add_filter(r"[/\\]", "/");
+    let _guard = settings.bind_to_scope();
+
+    assert_cmd_snapshot!(
+        cli()
+            .arg(".")
+            .arg("--data-keys")
+            .arg("url,href")
+            .current_dir(site.path()),
+        @r###"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    Reading files
+    Checking 4 links from 4 files (4 documents)
+    ./data/menu.yaml
+      error: bad link /blog (key href)
+
+    ./search.json
+      error: bad link /gone (key url)
+
+    Found 2 bad links
+
+    ----- stderr -----
+    "###);
+
+    site.close().unwrap();
+}
+
+#[test]
+fn test_report_unparseable() {
+    let site = assert_fs::TempDir::new().unwrap();
+
+    site.child("search.json")
+        .write_str(
+            r#"[
+  {
+    "url": "/guide/"
+  },
+  {
+    "url": "/gone/"
+  }
+  {
+    "url": "/lost/"
+  }
+]"#,
+        )
+        .unwrap();
+    site.child("guide/index.html").touch().unwrap();
+
+    let mut settings = insta::Settings::clone_current();
+    settings.add_filter(r"[/\\]", "/");
+    let _guard = settings.bind_to_scope();
+
+    assert_cmd_snapshot!(
+        cli()
+            .arg(".")
+            .arg("--data-keys")
+            .arg("url")
+            .arg("--report-unparseable")
+            .current_dir(site.path()),
+        @r###"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    Reading files
+    Checking 0 links from 2 files (2 documents)
+    ./search.json
+      error: unparseable document at byte 62: expected `,` or `]` at line 8 column 3
+
+    Found 0 bad links
+    Found 1 unparseable documents
+
+    ----- stderr -----
+    "###);
+
+    assert_cmd_snapshot!(
+        cli()
+            .arg(".")
+            .arg("--data-keys")
+            .arg("url")
+            .arg("--recover-unparseable")
+            .current_dir(site.path()),
+        @r###"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    Reading files
+    Checking 3 links from 2 files (2 documents)
+    ./search.json
+      error: bad link /gone (key url)
+      error: bad link /lost (key url)
+      error: unparseable document at byte 62: expected `,` or `]` at line 8 column 3
+
+    Found 2 bad links
+    Found 1 unparseable documents
+
+    ----- stderr -----
+    "###);
+
+    site.close().unwrap();
+}
+
+#[test]
+fn test_check_config() {
+    let site = assert_fs::TempDir::new().unwrap();
+
+    site.child("public/index.html").touch().unwrap();
+    site.child("routes.txt").write_str("/users/:id\n").unwrap();
+    site.child("ratchet.json")
+        .write_str("bad_links: 12")
+        .unwrap();
+
+    let mut settings = insta::Settings::clone_current();
+    settings.add_filter(r"[/\\]", "/");
+    let _guard = settings.bind_to_scope();
+
+    assert_cmd_snapshot!(
+        cli()
+            .arg("public")
+            .arg("--check-config")
+            .arg("--spa-routes")
+            .arg("routes.txt")
+            .current_dir(site.path()),
+        @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    Found 0 problems in the configuration
+
+    ----- stderr -----
+    "###);
+
+    assert_cmd_snapshot!(
+        cli()
+            .arg("public")
+            .arg("--check-config")
+            .arg("--max-path-length")
+            .arg("100")
+            .arg("--spa-fragment")
+            .arg("^#")
+            .arg("--ratchet")
+            .arg("ratchet.json")
+            .arg("--sources")
+            .arg("src")
+            .current_dir(site.path()),
+        @r###"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    error: --max-path-length or --max-segment-length has no effect without --warn long-paths
+    error: --spa-fragment has no effect without --spa-routes
+    error: src is not a directory
+    error: Failed to read ratchet file ratchet.json: expected value at line 1 column 1
+    Found 4 problems in the configuration
+
+    ----- stderr -----
+    "###);
+
+    site.close().unwrap();
+}
+
+#[test]
+fn test_xml_extract() {
+    let site = assert_fs::TempDir::new().unwrap();
+
+    site.child("blogroll.opml")
+        .write_str(
+            r#"<?xml version="1.0"?>
+<opml version="2.0">
+  <body>
+    <outline text="Friends" htmlUrl="/friends/" xmlUrl="https://example.com/feed.xml"/>
+    <outline text="Gone" htmlUrl="/gone/"/>
+  </body>
+</opml>"#,
+        )
+        .unwrap();
+    // not matched by any rule, so nothing is checked
+    site.child("feed.atom")
+        .write_str(r#"<feed><link href="/missing/"/></feed>"#)
+        .unwrap();
+    site.child("friends/index.html").touch().unwrap();
+
+    let mut settings = insta::Settings::clone_current();
+    settings.add_filter(r"[/\\]", "/");
+    let _guard = settings.bind_to_scope();
+
+    assert_cmd_snapshot!(
+        cli()
+            .arg(".")
+            .arg("--xml-extract")
+            .arg("outline@htmlUrl")
+            .current_dir(site.path()),
+        @r###"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    Reading files
+    Checking 2 links from 3 files (3 documents)
+    ./blogroll.opml
+      error: bad link /gone (outline[htmlurl])
+
+    Found 1 bad links
+
+    ----- stderr -----
+    "###);
+
+    site.close().unwrap();
+}
+
+#[test]
+fn test_dedup_documents() {
+    let site = assert_fs::TempDir::new().unwrap();
+    let page = r##"<a id="top" href="#top">top</a> <a href="missing.html">missing</a> <a href="#nowhere">nowhere</a>"##;
+
+    site.child("en/page.html").write_str(page).unwrap();
+    site.child("de/page.html").write_str(page).unwrap();
+    site.child("en/missing.html").touch().unwrap();
+
+    let mut settings = insta::Settings::clone_current();
+    settings.add_filter(r"[/\\]", "/");
+    let _guard = settings.bind_to_scope();
+
+    assert_cmd_snapshot!(
+        cli()
+            .arg(".")
+            .arg("--check-anchors")
+            .arg("--dedup-documents")
+            .current_dir(site.path()),
+        @r###"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    Reading files
+    Checking 6 links from 3 files (3 documents)
+    Skipped parsing 1 duplicate documents
+    ./de/page.html
+      error: bad link /de/missing.html (a[href])
+      error: bad link /de/page.html#nowhere (a[href])
+
+    ./en/page.html
+      error: bad link /en/page.html#nowhere (a[href])
+
+    Found 1 bad links
+    Found 2 bad anchors
+
+    ----- stderr -----
+    "###);
+}
+
+#[test]
+fn test_anchor_sources() {
+    let site = assert_fs::TempDir::new().unwrap();
+
+    site.child("index.html")
+        .write_str(
+            r##"<a name="legacy"></a> <sup id="fnref-1"></sup>
+            <a href="#legacy">legacy</a> <a href="#fnref-1">footnote</a>
+            <a href="search.html#results">results</a> <a href="search.html#missing">missing</a>"##,
+        )
+        .unwrap();
+    site.child("search.html").touch().unwrap();
+    site.child("anchors.txt")
+        .write_str("# added by the search widget\n/search.html#results\n")
+        .unwrap();
+
+    let mut settings = insta::Settings::clone_current();
+    settings.add_filter(r"[/\\]", "/");
+    let _guard = settings.bind_to_scope();
+
+    assert_cmd_snapshot!(
+        cli()
+            .arg(".")
+            .arg("--check-anchors")
+            .arg("--ignore-id")
+            .arg("^fnref-")
+            .arg("--extra-anchors")
+            .arg("anchors.txt")
+            .current_dir(site.path()),
+        @r###"
+    success: false
+    exit_code: 2
+    ----- stdout -----
+    Reading files
+    Checking 4 links from 3 files (2 documents)
+    ./index.html
+      error: bad link /#fnref-1 (a[href])
+      error: bad link /search.html#missing (a[href])
+
+    Found 0 bad links
+    Found 2 bad anchors
+
+    ----- stderr -----
+    "###);
+}
+
+#[test]
+fn test_anchor_defined_elsewhere() {
+    let site = assert_fs::TempDir::new().unwrap();
+
+    site.child("index.html")
+        .write_str(r##"<a href="install.html#install">install</a>"##)
+        .unwrap();
+    site.child("install.html").touch().unwrap();
+    site.child("setup.html")
+        .write_str(r#"<h2 id="install">Installing</h2>"#)
+        .unwrap();
+
+    assert_cmd_snapshot!(
+        cli().arg(".").arg("--check-anchors").current_dir(site.path()),
+        @r###"
+    success: false
+    exit_code: 2
+    ----- stdout -----
+    Reading files
+    Checking 1 links from 3 files (3 documents)
+    ./index.html
+      error: bad link /install.html#install (a[href])
+        note: #install exists on /setup.html (h2)
+
+    Found 0 bad links
+    Found 1 bad anchors
+
+    ----- stderr -----
+    "###);
+}
+
+#[test]
+fn test_baseline() {
+    let site = assert_fs::TempDir::new().unwrap();
+
+    site.child("index.html")
+        .write_str(r#"<a href="/old/">old</a> <a href="/gone">gone</a> <a href="/new">new</a>"#)
+        .unwrap();
+    site.child("baseline.json")
+        .write_str(
+            r#"[
+                {"href": "/old/", "reason": "removed in v2"},
+                {"href": "/gone", "reason": "moved to the blog", "expires": "2020-01-31"}
+            ]"#,
+        )
+        .unwrap();
+
+    assert_cmd_snapshot!(
+        cli()
+            .arg(".")
+            .arg("--baseline")
+            .arg("baseline.json")
+            .current_dir(site.path()),
+        @r###"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    Reading files
+    Checking 3 links from 2 files (1 documents)
+    ./index.html
+      error: bad link /gone (a[href])
+      error: bad link /new (a[href])
+
+    Found 2 bad links
+    Found 1 bad links accepted by the baseline
+      expired on 2020-01-31: /gone (moved to the blog)
+
+    ----- stderr -----
+    "###);
+}
+
+#[test]
+fn test_deploy_ignore() {
+    let site = assert_fs::TempDir::new().unwrap();
+
+    site.child("index.html")
+        .write_str(
+            r#"<a href="/drafts/">drafts</a> <a href="/app.js.map">map</a> <a href="/x">x</a>"#,
+        )
+        .unwrap();
+    site.child("drafts/index.html")
+        .write_str(r#"<a href="/missing">missing</a>"#)
+        .unwrap();
+    site.child("app.js.map").touch().unwrap();
+    site.child(".s3ignore")
+        .write_str("drafts/\n*.map\n")
+        .unwrap();
+
+    assert_cmd_snapshot!(
+        cli()
+            .arg(".")
+            .arg("--deploy-ignore")
+            .arg(".s3ignore")
+            .current_dir(site.path()),
+        @r###"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    Reading files
+    Checking 3 links from 2 files (1 documents)
+    ./index.html
+      error: bad link /app.js.map (a[href])
+        note: the file exists, but is excluded from deployment
+      error: bad link /drafts (a[href])
+        note: the file exists, but is excluded from deployment
+      error: bad link /x (a[href])
+
+    Found 3 bad links
+
+    ----- stderr -----
+    "###);
+}
+
+#[test]
+fn test_rewrite_url() {
+    let site = assert_fs::TempDir::new().unwrap();
+
+    site.child("index.html")
+        .write_str(r#"<a href="/v2/docs/setup/">setup</a> <a href="/v2/docs/gone/">gone</a>"#)
+        .unwrap();
+    site.child("docs/setup/index.html").touch().unwrap();
+
+    assert_cmd_snapshot!(
+        cli()
+            .arg(".")
+            .arg("--rewrite-url")
+            .arg("^/v2/docs/(.*)=/docs/$1")
+            .current_dir(site.path()),
+        @r###"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    Reading files
+    Checking 2 links from 2 files (2 documents)
+    ./index.html
+      error: bad link /docs/gone (a[href])
+
+    Found 1 bad links
+
+    ----- stderr -----
+    "###);
+}
+
+#[test]
+fn test_downloads_manifest() {
+    let site = assert_fs::TempDir::new().unwrap();
+
+    site.child("index.html")
+        .write_str(
+            r#"<a href="/releases/app-1.1.zip">1.1</a> <a href="/releases/app-1.0.zip">1.0</a>"#,
+        )
+        .unwrap();
+    site.child("releases/app-1.1.zip").touch().unwrap();
+    site.child("releases/app-1.0.zip")
+        .write_str("old release")
+        .unwrap();
+    site.child("SHA256SUMS")
+        .write_str(
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855  releases/app-1.1.zip\n",
+        )
+        .unwrap();
+
+    let mut settings = insta::Settings::clone_current();
+    settings.add_filter(r"[/\\]", "/");
+    let _guard = settings.bind_to_scope();
+
+    assert_cmd_snapshot!(
+        cli()
+            .arg(".")
+            .arg("--downloads-manifest")
+            .arg("SHA256SUMS")
+            .current_dir(site.path()),
+        @r###"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    Reading files
+    Checking 2 links from 4 files (1 documents)
+    ./index.html
+      error: bad download: /releases/app-1.0.zip is not in the manifest
+      error: bad download: /releases/app-1.1.zip is empty
+
+    Found 0 bad links
+    Found 2 bad downloads
+
+    ----- stderr -----
+    "###);
+}
+
+#[test]
+fn test_anchors_through_redirects() {
+    let site = assert_fs::TempDir::new().unwrap();
+
+    site.child("index.html")
+        .write_str(r##"<a href="/old/page/#setup">setup</a> <a href="/old/page/#gone">gone</a>"##)
+        .unwrap();
+    site.child("new/page/index.html")
+        .write_str(r#"<h2 id="setup">Setup</h2>"#)
+        .unwrap();
+    site.child("_redirects")
+        .write_str("/old/page/ /new/page/ 301\n")
+        .unwrap();
+
+    assert_cmd_snapshot!(
+        cli()
+            .arg(".")
+            .arg("--check-anchors")
+            .arg("--check-redirects")
+            .current_dir(site.path()),
+        @r###"
+    success: false
+    exit_code: 2
+    ----- stdout -----
+    Reading files
+    Checking 3 links from 3 files (3 documents)
+    ./index.html
+      error: bad link /old/page#gone (a[href])
+        note: redirects to /new/page#gone, which does not exist
+      warning: anchor through redirect /old/page#setup to /new/page#setup, some redirects drop the fragment
+
+    Found 0 bad links
+    Found 1 bad anchors
+    Found 0 bad redirects
+    Found 1 links to anchors through redirects
+
+    ----- stderr -----
+    "###);
+}
+
+#[test]
+fn test_check_aria() {
+    let site = assert_fs::TempDir::new().unwrap();
+
+    site.child("index.html")
+        .write_str(
+            r#"<h2 id="title">Settings</h2>
+            <p id="help">Changes are saved automatically.</p>
+            <form aria-labelledby="title" aria-describedby="help hint"></form>"#,
+        )
+        .unwrap();
+
+    assert_cmd_snapshot!(
+        cli()
+            .arg(".")
+            .arg("--check-anchors")
+            .arg("--check-aria")
+            .current_dir(site.path()),
+        @r###"
+    success: false
+    exit_code: 2
+    ----- stdout -----
+    Reading files
+    Checking 3 links from 1 files (1 documents)
+    ./index.html
+      error: bad link /#hint (*[aria-describedby])
+
+    Found 0 bad links
+    Found 1 bad anchors
+
+    ----- stderr -----
+    "###);
+}
+
+#[test]
+fn test_link_hints() {
+    let site = assert_fs::TempDir::new().unwrap();
+
+    site.child("index.html")
+        .write_str(
+            r#"<a href="/login" rel="nofollow">log in</a>
+            <a href="/preview/" data-hyperlink="ignore">preview</a>
+            <a href="/partner/" rel="external noopener">partner</a>
+            <img src="/generated.png" data-hyperlink="warn">
+            <a href="/gone">gone</a>"#,
+        )
+        .unwrap();
+
+    assert_cmd_snapshot!(
+        cli()
+            .arg(".")
+            .arg("--skip-rel")
+            .arg("nofollow")
+            .arg("--warn-rel")
+            .arg("external")
+            .current_dir(site.path()),
+        @r###"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    Reading files
+    Checking 3 links from 1 files (1 documents)
+    Skipped 2 links (markup: 2)
+    ./index.html
+      error: bad link /gone (a[href])
+      warning: bad link /generated.png (img[src])
+      warning: bad link /partner (a[href])
+
+    Found 1 bad links
+    Found 2 bad links marked as warnings
+
+    ----- stderr -----
+    "###);
+}
+
+#[test]
+fn test_deny_allow() {
+    let site = assert_fs::TempDir::new().unwrap();
+
+    site.child("index.html")
+        .write_str("<h2 id=''>Configuration</h2> <a href='#setup'>setup</a>")
+        .unwrap();
+
+    assert_cmd_snapshot!(
+        cli()
+            .arg(".")
+            .arg("--check-anchors")
+            .arg("--warn")
+            .arg("bad-ids")
+            .arg("--allow")
+            .arg("anchors")
+            .current_dir(site.path()),
+        @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    Reading files
+    Checking 1 links from 1 files (1 documents)
+    ./index.html
+      error: bad link /#setup (a[href])
+      warning: bad-ids: <h2> has an empty id
+
+    Found 0 bad links
+    Found 1 bad anchors
+    Found 1 lint warnings (bad-ids: 1)
+
+    ----- stderr -----
+    "###);
+
+    assert_cmd_snapshot!(
+        cli()
+            .arg(".")
+            .arg("--check-anchors")
+            .arg("--warn")
+            .arg("bad-ids")
+            .arg("--allow")
+            .arg("anchors")
+            .arg("--deny")
+            .arg("bad-ids")
+            .current_dir(site.path()),
+        @r###"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    Reading files
+    Checking 1 links from 1 files (1 documents)
+    ./index.html
+      error: bad link /#setup (a[href])
+      warning: bad-ids: <h2> has an empty id
+
+    Found 0 bad links
+    Found 1 bad anchors
+    Found 1 lint warnings (bad-ids: 1)
+
+    ----- stderr -----
+    "###);
+}